@@ -0,0 +1,116 @@
+//! # Json path macros
+//! The [json_path!] macro parses the jsonpath query while the crate is compiled,
+//! so the typos in the queries fail `cargo build` and nothing is parsed when the program runs.
+//! The crate using the macro depends on `jsonpath-rust` and `serde_json`.
+
+extern crate proc_macro;
+
+use proc_macro::{TokenStream, TokenTree};
+
+/// parses the query literal at compile time and expands to the [`JsonPath<'static>`] built straight from the syntax tree.
+/// # Examples:
+/// ```
+/// use serde_json::json;
+/// use jsonpath_rust::JsonPath;
+/// use jsonpath_rust_macros::json_path;
+///
+/// let path: JsonPath<'static> = json_path!("$.a[?(@.b > 1)].c");
+/// assert_eq!(path, JsonPath::parse("$.a[?(@.b > 1)].c").unwrap());
+///
+/// let json = json!({"a":[{"b":1,"c":"x"},{"b":2,"c":"y"}]});
+/// assert_eq!(&json >> &path, vec![&json!("y")]);
+/// ```
+/// the malformed queries do not compile:
+/// ```compile_fail
+/// let path = jsonpath_rust_macros::json_path!("$.a[?(@.b >< 1)]");
+/// ```
+///
+/// [`JsonPath<'static>`]: https://docs.rs/jsonpath-rust/latest/jsonpath_rust/enum.JsonPath.html
+#[proc_macro]
+pub fn json_path(input: TokenStream) -> TokenStream {
+    let code = literal(input).and_then(|query| jsonpath_rust::generate_path(&query));
+    match code {
+        Ok(code) => code.parse().unwrap_or_else(|e| error(&format!("the generated code is invalid: {}", e))),
+        Err(e) => error(&e),
+    }
+}
+
+fn error(message: &str) -> TokenStream {
+    format!("compile_error!({:?})", message).parse().expect("the error is valid")
+}
+
+/// the value of the single string literal, the macro takes nothing else
+fn literal(input: TokenStream) -> Result<String, String> {
+    let mut tokens = input.into_iter();
+    let lit = match (tokens.next(), tokens.next()) {
+        (Some(TokenTree::Literal(lit)), None) => lit.to_string(),
+        // the literal passed through macro_rules! comes wrapped into the group without delimiters
+        (Some(TokenTree::Group(group)), None) => return literal(group.stream()),
+        _ => return Err(String::from("json_path! expects the single string literal like json_path!(\"$.a\")")),
+    };
+    unquote(&lit).ok_or_else(|| format!("json_path! expects the string literal, got {}", lit))
+}
+
+/// the text of the literal `"..."` or `r#"..."#` with the escape sequences resolved
+fn unquote(lit: &str) -> Option<String> {
+    if let Some(raw) = lit.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        return raw.get(hashes + 1..raw.len() - hashes - 1)
+            .filter(|_| raw[hashes..].starts_with('"') && raw[..raw.len() - hashes].ends_with('"'))
+            .map(String::from);
+    }
+    let body = lit.strip_prefix('"')?.strip_suffix('"')?;
+    let mut res = String::with_capacity(body.len());
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => res.push('\n'),
+            'r' => res.push('\r'),
+            't' => res.push('\t'),
+            '0' => res.push('\0'),
+            '\\' => res.push('\\'),
+            '\'' => res.push('\''),
+            '"' => res.push('"'),
+            'x' => {
+                let code: String = chars.by_ref().take(2).collect();
+                res.push(char::from(u8::from_str_radix(&code, 16).ok().filter(|c| *c < 0x80)?));
+            }
+            'u' => {
+                if chars.next()? != '{' {
+                    return None;
+                }
+                let code: String = chars.by_ref().take_while(|c| *c != '}').filter(|c| *c != '_').collect();
+                res.push(std::char::from_u32(u32::from_str_radix(&code, 16).ok()?)?);
+            }
+            // the line continuation skips the line break and the indent of the next line
+            '\n' => while chars.next_if(|c| c.is_whitespace()).is_some() {},
+            _ => return None,
+        }
+    }
+    Some(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::unquote;
+
+    #[test]
+    fn unquote_test() {
+        assert_eq!(unquote(r#""$.a""#), Some(String::from("$.a")));
+        assert_eq!(unquote(r#""$['a\'b']\n\t\\""#), Some(String::from("$['a'b']\n\t\\")));
+        assert_eq!(unquote(r#""$.\x61\u{62}\u{1F600}""#), Some(String::from("$.ab\u{1F600}")));
+        assert_eq!(unquote("\"$.a\\\n     .b\""), Some(String::from("$.a.b")));
+        assert_eq!(unquote(r#""$[?(@.a == \"b\")]""#), Some(String::from("$[?(@.a == \"b\")]")));
+        assert_eq!(unquote(r###"r#"$[?(@.a == "b")]"#"###), Some(String::from("$[?(@.a == \"b\")]")));
+        assert_eq!(unquote(r#"r"$.a""#), Some(String::from("$.a")));
+
+        assert_eq!(unquote("12"), None);
+        assert_eq!(unquote("'a'"), None);
+        assert_eq!(unquote(r#"b"$.a""#), None);
+        assert_eq!(unquote(r#""\q""#), None);
+    }
+}
@@ -0,0 +1,65 @@
+use std::future::Future;
+use std::pin::Pin;
+use actix_web::dev::Payload;
+use actix_web::http::StatusCode;
+use actix_web::web::Bytes;
+use actix_web::{Error, FromRequest, HttpRequest, ResponseError};
+use serde_core::de::DeserializeOwned;
+use crate::extract::{ExtractQuery, ExtractRejection, Extracted};
+
+/// The body is read as a whole up to the limit of `PayloadConfig`(256kB by default) and scanned without being deserialized.
+impl<Q, T> FromRequest for Extracted<Q, T>
+    where Q: ExtractQuery + 'static,
+          T: DeserializeOwned + 'static,
+{
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output=Result<Self, Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let body = Bytes::from_request(req, payload);
+        Box::pin(async move { Ok(Extracted::from_body(&body.await?)?) })
+    }
+}
+
+impl ResponseError for ExtractRejection {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ExtractRejection::Query(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ExtractRejection::Body(_) => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+    use actix_web::FromRequest;
+    use crate::extract::{ExtractQuery, Extracted};
+
+    struct ItemIds;
+
+    impl ExtractQuery for ItemIds {
+        const QUERY: &'static str = "$.data.items[*].id";
+    }
+
+    /// the body is in memory, so the extraction is ready at the first poll
+    fn extract(body: &'static str) -> Result<Extracted<ItemIds, u64>, StatusCode> {
+        let (req, mut payload) = TestRequest::post().set_payload(body).to_http_parts();
+        let fut = pin!(Extracted::<ItemIds, u64>::from_request(&req, &mut payload));
+        match fut.poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(res) => res.map_err(|e| e.as_response_error().status_code()),
+            Poll::Pending => panic!("the body is not read"),
+        }
+    }
+
+    #[test]
+    fn actix_extract_test() {
+        assert_eq!(extract(r#"{"data":{"items":[{"id":1},{"id":2}]}}"#).unwrap().values, vec![1, 2]);
+        assert_eq!(extract(r#"{"data":{"items":[{"id":"a"}]}}"#).unwrap_err(), StatusCode::BAD_REQUEST);
+        assert_eq!(extract("{").unwrap_err(), StatusCode::BAD_REQUEST);
+    }
+}
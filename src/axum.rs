@@ -0,0 +1,64 @@
+use bytes::Bytes;
+use axum_core::extract::{FromRequest, Request};
+use axum_core::response::{IntoResponse, Response};
+use http::StatusCode;
+use serde_core::de::DeserializeOwned;
+use crate::extract::{ExtractQuery, ExtractRejection, Extracted};
+
+/// The body is read as a whole(up to the limit of axum) and scanned without being deserialized.
+impl<S, Q, T> FromRequest<S> for Extracted<Q, T>
+    where S: Send + Sync,
+          Q: ExtractQuery,
+          T: DeserializeOwned,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let body = Bytes::from_request(req, state).await.map_err(IntoResponse::into_response)?;
+        Extracted::from_body(&body).map_err(IntoResponse::into_response)
+    }
+}
+
+impl IntoResponse for ExtractRejection {
+    fn into_response(self) -> Response {
+        let status = match self {
+            ExtractRejection::Query(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ExtractRejection::Body(_) => StatusCode::BAD_REQUEST,
+        };
+        (status, self.to_string()).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+    use axum_core::body::Body;
+    use axum_core::extract::{FromRequest, Request};
+    use http::StatusCode;
+    use crate::extract::{ExtractQuery, Extracted};
+
+    struct ItemIds;
+
+    impl ExtractQuery for ItemIds {
+        const QUERY: &'static str = "$.data.items[*].id";
+    }
+
+    /// the body is in memory, so the extraction is ready at the first poll
+    fn extract(body: &'static str) -> Result<Extracted<ItemIds, u64>, StatusCode> {
+        let req = Request::new(Body::from(body));
+        let fut = pin!(Extracted::<ItemIds, u64>::from_request(req, &()));
+        match fut.poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(res) => res.map_err(|e| e.status()),
+            Poll::Pending => panic!("the body is not read"),
+        }
+    }
+
+    #[test]
+    fn axum_extract_test() {
+        assert_eq!(extract(r#"{"data":{"items":[{"id":1},{"id":2}]}}"#).unwrap().values, vec![1, 2]);
+        assert_eq!(extract(r#"{"data":{"items":[{"id":"a"}]}}"#).unwrap_err(), StatusCode::BAD_REQUEST);
+        assert_eq!(extract("{").unwrap_err(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,110 @@
+use ::bson::Bson;
+use serde_json::{json, Map, Value};
+use crate::parser::model::JsonPath;
+use crate::path::Options;
+use crate::path::normalized::{NormalizedPath, PathSegment};
+use crate::tree::JsonTree;
+
+/// The nodes the operands of the filters find are turned into the canonical Extended JSON,
+/// so the dates, the object ids and the typed numbers keep their values to be compared by.
+impl JsonTree for Bson {
+    fn to_json(&self) -> Value {
+        extjson(self)
+    }
+
+    fn child(&self, segment: &PathSegment) -> Option<&Self> {
+        match (self, segment) {
+            (Bson::Document(doc), PathSegment::Key(key)) => doc.get(key),
+            (Bson::Array(elems), PathSegment::Index(idx)) => elems.get(*idx),
+            _ => None
+        }
+    }
+
+    fn children(&self) -> Vec<(PathSegment, &Self)> {
+        match self {
+            Bson::Document(doc) => doc.iter().map(|(key, v)| (PathSegment::Key(key.clone()), v)).collect(),
+            Bson::Array(elems) => elems.iter().enumerate().map(|(idx, v)| (PathSegment::Index(idx), v)).collect(),
+            _ => vec![]
+        }
+    }
+}
+
+/// turns the value into the canonical Extended JSON.
+/// The kinds the filters can not compare(the binaries, the regexes, the code etc.) are kept as their shell strings.
+fn extjson(value: &Bson) -> Value {
+    match value {
+        Bson::Null | Bson::Undefined => Value::Null,
+        Bson::Boolean(b) => Value::Bool(*b),
+        Bson::String(s) => Value::String(s.clone()),
+        Bson::Int32(i) => json!({ "$numberInt": i.to_string() }),
+        Bson::Int64(i) => json!({ "$numberLong": i.to_string() }),
+        Bson::Double(f) if f.is_finite() && f.fract() == 0.0 => json!({ "$numberDouble": format!("{:.1}", f) }),
+        Bson::Double(f) if f.is_finite() => json!({ "$numberDouble": f.to_string() }),
+        Bson::Double(f) if f.is_nan() => json!({ "$numberDouble": "NaN" }),
+        Bson::Double(f) => json!({ "$numberDouble": if *f > 0.0 { "Infinity" } else { "-Infinity" } }),
+        Bson::Decimal128(d) => json!({ "$numberDecimal": d.to_string() }),
+        Bson::ObjectId(oid) => json!({ "$oid": oid.to_hex() }),
+        Bson::DateTime(date) => json!({ "$date": { "$numberLong": date.timestamp_millis().to_string() } }),
+        Bson::Array(elems) => Value::Array(elems.iter().map(extjson).collect()),
+        Bson::Document(doc) => Value::Object(doc.iter().map(|(k, v)| (k.clone(), extjson(v))).collect::<Map<_, _>>()),
+        other => Value::String(other.to_string()),
+    }
+}
+
+impl JsonPath<'_> {
+    /// finds the elements of the BSON document along with their locations without turning it into json.
+    /// The filters compare the dates with the strings of RFC 3339, the object ids with their hex strings
+    /// and the numbers of any type by their values(see [Options::extended_json]).
+    /// The [bson::Document] is queried as `Bson::Document`.
+    /// # Examples:
+    /// ```
+    /// use bson::{doc, oid::ObjectId, Bson, DateTime};
+    /// use jsonpath_rust::JsonPath;
+    ///
+    /// let id = ObjectId::parse_str("65a1b2c3d4e5f60718293a4b").unwrap();
+    /// let orders = Bson::Document(doc! {"orders": [
+    ///     {"_id": id, "at": DateTime::from_millis(1_700_000_000_000_i64), "total": 10_i64},
+    ///     {"_id": ObjectId::new(), "at": DateTime::from_millis(1_600_000_000_000_i64), "total": 7.5},
+    /// ]});
+    /// let path = JsonPath::parse("$.orders[?(@.at > '2022-01-01T00:00:00Z' && @.total >= 10)]._id").unwrap();
+    /// let found = path.find_bson(&orders);
+    /// assert_eq!(found[0].1, &Bson::ObjectId(id));
+    /// assert_eq!(found[0].0.to_string(), "$['orders'][0]['_id']");
+    /// ```
+    pub fn find_bson<'a>(&self, doc: &'a Bson) -> Vec<(NormalizedPath, &'a Bson)> {
+        self.find_tree_with_options(doc, &Options::new().extended_json())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bson::{doc, oid::ObjectId, Bson, DateTime, Decimal128};
+    use crate::parser::parser::parse_json_path;
+
+    #[test]
+    fn find_bson_test() {
+        let id = ObjectId::parse_str("65a1b2c3d4e5f60718293a4b").unwrap();
+        let doc = Bson::Document(doc! {
+            "users": [
+                {"_id": id, "name": "a", "joined": DateTime::from_millis(1_000), "score": 5_i32},
+                {"_id": ObjectId::new(), "name": "b", "joined": DateTime::from_millis(2_000), "score": 7_i64},
+                {"name": "c", "score": 6.5, "balance": "12.5".parse::<Decimal128>().unwrap()},
+            ],
+        });
+        let names = |query: &str| parse_json_path(query).unwrap().find_bson(&doc).into_iter()
+            .map(|(_, v)| v.as_str().unwrap_or_default().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names("$.users[?(@._id == '65a1b2c3d4e5f60718293a4b')].name"), vec!["a"]);
+        assert_eq!(names("$.users[?(@.joined > '1970-01-01T00:00:01Z')].name"), vec!["b"]);
+        assert_eq!(names("$.users[?(@.score > 5)].name"), vec!["b", "c"]);
+        assert_eq!(names("$.users[?(@.score == 5)].name"), vec!["a"]);
+
+        let found = parse_json_path("$.users[*]._id").unwrap().find_bson(&doc);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].1, &Bson::ObjectId(id));
+        assert_eq!(found[1].0.to_string(), "$['users'][1]['_id']");
+        // the values are found as they are rather than their json
+        let found = parse_json_path("$..joined").unwrap().find_bson(&doc);
+        assert_eq!(found[0].1, &Bson::DateTime(DateTime::from_millis(1_000)));
+    }
+}
@@ -0,0 +1,170 @@
+use std::fmt::Write;
+use std::path::Path;
+use serde_json::Value;
+use crate::parser::model::{FilterExpression, JsonPath, JsonPathIndex, Operand};
+use crate::parser::parser::parse_json_path;
+
+/// generates the rust code declaring every query as the `static` [crate::SharedPath]
+/// built straight from the syntax tree, so the queries are parsed and checked in the build script
+/// and nothing is parsed when the service starts.
+/// The queries are given as the pairs of the names of the statics and the query strings.
+/// The generated code refers to `jsonpath_rust` and `serde_json`.
+/// # Examples:
+/// ```
+/// let code = jsonpath_rust::generate_queries(&[("BOOK_TITLES", "$.store.book[*].title")]).unwrap();
+/// assert!(code.contains("pub static BOOK_TITLES:"));
+/// ```
+pub fn generate_queries(queries: &[(&str, &str)]) -> Result<String, String> {
+    let mut code = String::from("// generated from the jsonpath queries, do not edit\n");
+    for (name, query) in queries {
+        if !is_ident(name) {
+            return Err(format!("'{}' is not a valid name of the static", name));
+        }
+        let path = parse_json_path(query).map_err(|e| format!("the query {} is invalid: {}", name, e))?;
+        // the attribute keeps the queries spanning several lines inside the doc comment
+        let _ = writeln!(code, "#[doc = {:?}]", format!(" `{}`", query.replace('`', "'")));
+        let _ = writeln!(code, "pub static {}: ::std::sync::LazyLock<::jsonpath_rust::SharedPath> =\n    \
+                                ::std::sync::LazyLock::new(|| ::jsonpath_rust::SharedPath::from({}));", name, expr(&path));
+    }
+    Ok(code)
+}
+
+/// generates the rust expression building the [crate::JsonPath] of the query straight from the syntax tree,
+/// the `json_path!` macro of `jsonpath-rust-macros` expands to it.
+/// The generated code refers to `jsonpath_rust` and `serde_json`.
+/// # Examples:
+/// ```
+/// let code = jsonpath_rust::generate_path("$.a[0]").unwrap();
+/// assert!(code.starts_with("::jsonpath_rust::JsonPath::Chain(vec![::jsonpath_rust::JsonPath::Root"));
+/// assert!(jsonpath_rust::generate_path("$.a[").is_err());
+/// ```
+pub fn generate_path(query: &str) -> Result<String, String> {
+    parse_json_path(query)
+        .map(|path| expr(&path))
+        .map_err(|e| format!("the query is invalid: {}", e))
+}
+
+/// writes the code of [generate_queries] into the file,
+/// usually the one in `OUT_DIR` included then with `include!(concat!(env!("OUT_DIR"), "/queries.rs"))`
+pub fn write_queries<P: AsRef<Path>>(file: P, queries: &[(&str, &str)]) -> Result<(), String> {
+    let code = generate_queries(queries)?;
+    std::fs::write(file, code).map_err(|e| e.to_string())
+}
+
+fn is_ident(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && name != "_"
+}
+
+/// the rust expression building the path
+fn expr(path: &JsonPath) -> String {
+    const P: &str = "::jsonpath_rust::JsonPath";
+    match path {
+        JsonPath::Root => format!("{}::Root", P),
+        JsonPath::Empty => format!("{}::Empty", P),
+        JsonPath::Wildcard => format!("{}::Wildcard", P),
+        JsonPath::Descendants => format!("{}::Descendants", P),
+        JsonPath::Field(key) => format!("{}::Field(::std::borrow::Cow::Borrowed({:?}))", P, key),
+        JsonPath::Descent(key) => format!("{}::Descent(::std::borrow::Cow::Borrowed({:?}))", P, key),
+        JsonPath::Current(tail) => format!("{}::Current(Box::new({}))", P, expr(tail)),
+        JsonPath::Chain(elems) => format!("{}::Chain(vec![{}])", P, list(elems.iter().map(expr))),
+        JsonPath::Index(idx) => format!("{}::Index({})", P, index(idx)),
+    }
+}
+
+fn index(idx: &JsonPathIndex) -> String {
+    const I: &str = "::jsonpath_rust::JsonPathIndex";
+    match idx {
+        JsonPathIndex::Single(v) => format!("{}::Single({})", I, value(v)),
+        JsonPathIndex::UnionIndex(vs) => format!("{}::UnionIndex(vec![{}])", I, list(vs.iter().map(value))),
+        JsonPathIndex::UnionKeys(keys) =>
+            format!("{}::UnionKeys(vec![{}])", I, list(keys.iter().map(|k| format!("::std::borrow::Cow::Borrowed({:?})", k)))),
+        JsonPathIndex::Slice(s, e, step) => format!("{}::Slice({:?}, {:?}, {})", I, s, e, step),
+        JsonPathIndex::Filter(l, op, r) =>
+            format!("{}::Filter({}, ::jsonpath_rust::FilterSign::{:?}, {})", I, operand(l), op, operand(r)),
+        JsonPathIndex::Logical(e) => format!("{}::Logical({})", I, expression(e)),
+        JsonPathIndex::Union(sels) => format!("{}::Union(vec![{}])", I, list(sels.iter().map(expr))),
+    }
+}
+
+fn expression(expr: &FilterExpression) -> String {
+    const E: &str = "::jsonpath_rust::FilterExpression";
+    match expr {
+        FilterExpression::Atom(l, op, r) =>
+            format!("{}::Atom({}, ::jsonpath_rust::FilterSign::{:?}, {})", E, operand(l), op, operand(r)),
+        FilterExpression::And(l, r) => format!("{}::And(Box::new({}), Box::new({}))", E, expression(l), expression(r)),
+        FilterExpression::Or(l, r) => format!("{}::Or(Box::new({}), Box::new({}))", E, expression(l), expression(r)),
+        FilterExpression::Not(e) => format!("{}::Not(Box::new({}))", E, expression(e)),
+    }
+}
+
+fn operand(op: &Operand) -> String {
+    match op {
+        Operand::Static(v) => format!("::jsonpath_rust::Operand::Static({})", value(v)),
+        Operand::Dynamic(p) => format!("::jsonpath_rust::Operand::Dynamic(Box::new({}))", expr(p)),
+        Operand::Function(name, args) => format!("::jsonpath_rust::Operand::Function(::std::borrow::Cow::Borrowed({:?}), vec![{}])",
+                                                 name, args.iter().map(operand).collect::<Vec<_>>().join(", ")),
+        Operand::Variable(name) => format!("::jsonpath_rust::Operand::Variable(::std::borrow::Cow::Borrowed({:?}))", name),
+    }
+}
+
+fn value(v: &Value) -> String {
+    match v {
+        Value::Null => String::from("::serde_json::Value::Null"),
+        Value::Bool(b) => format!("::serde_json::Value::Bool({})", b),
+        Value::Number(n) => match (n.as_i64(), n.as_u64(), n.as_f64()) {
+            (Some(i), _, _) => format!("::serde_json::Value::from({}i64)", i),
+            (_, Some(u), _) => format!("::serde_json::Value::from({}u64)", u),
+            (_, _, Some(f)) => format!("::serde_json::Value::from({:?}f64)", f),
+            _ => format!("::serde_json::from_str::<::serde_json::Value>({:?}).unwrap()", n.to_string()),
+        },
+        Value::String(s) => format!("::serde_json::Value::String(String::from({:?}))", s),
+        Value::Array(elems) => format!("::serde_json::Value::Array(vec![{}])", list(elems.iter().map(value))),
+        Value::Object(fields) => format!("::serde_json::Value::Object(vec![{}].into_iter().collect())",
+                                         list(fields.iter().map(|(k, v)| format!("(String::from({:?}), {})", k, value(v))))),
+    }
+}
+
+fn list<I: Iterator<Item=String>>(items: I) -> String {
+    items.collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codegen::{expr, generate_queries};
+    use crate::parser::parser::parse_json_path;
+
+    #[test]
+    fn expr_test() {
+        let path = parse_json_path("$.a['b c'][?(@.d > 1.5)]").unwrap();
+        assert_eq!(expr(&path), "::jsonpath_rust::JsonPath::Chain(vec![::jsonpath_rust::JsonPath::Root, \
+            ::jsonpath_rust::JsonPath::Field(::std::borrow::Cow::Borrowed(\"a\")), \
+            ::jsonpath_rust::JsonPath::Field(::std::borrow::Cow::Borrowed(\"b c\")), \
+            ::jsonpath_rust::JsonPath::Index(::jsonpath_rust::JsonPathIndex::Filter(\
+            ::jsonpath_rust::Operand::Dynamic(Box::new(::jsonpath_rust::JsonPath::Chain(vec![\
+            ::jsonpath_rust::JsonPath::Current(Box::new(::jsonpath_rust::JsonPath::Chain(vec![\
+            ::jsonpath_rust::JsonPath::Field(::std::borrow::Cow::Borrowed(\"d\"))])))]))), \
+            ::jsonpath_rust::FilterSign::Greater, ::jsonpath_rust::Operand::Static(::serde_json::Value::from(1.5f64))))])");
+        let path = parse_json_path("$[?(@ == $id)]").unwrap();
+        assert!(expr(&path).ends_with("::jsonpath_rust::Operand::Variable(::std::borrow::Cow::Borrowed(\"id\"))))])"));
+    }
+
+    #[test]
+    fn generate_test() {
+        let code = generate_queries(&[("IDS", "$..id"), ("NAMES", "$.users[0:2].name")]).unwrap();
+        assert!(code.contains("pub static IDS: ::std::sync::LazyLock<::jsonpath_rust::SharedPath>"));
+        assert!(code.contains("::jsonpath_rust::JsonPathIndex::Slice(Some(0), Some(2), 1)"));
+
+        let code = generate_queries(&[("LONG", "$.users[?(length(@.name) > 3)]")]).unwrap();
+        assert!(code.contains("::jsonpath_rust::Operand::Function(::std::borrow::Cow::Borrowed(\"length\"), vec![::jsonpath_rust::Operand::Dynamic("));
+
+        let code = generate_queries(&[("NOTED", "$.a # the \"note\"\n.b"), ("KEY", "$['x\ny']")]).unwrap();
+        assert!(code.contains("#[doc = \" `$.a # the \\\"note\\\"\\n.b`\"]\npub static NOTED:"));
+        assert!(code.lines().all(|l| l.starts_with("//") || l.starts_with("#[doc") || l.starts_with("pub static") || l.starts_with("    ::")));
+
+        assert!(generate_queries(&[("1ids", "$..id")]).is_err());
+        assert!(generate_queries(&[("IDS", "$..[")]).is_err());
+    }
+}
@@ -0,0 +1,322 @@
+//! The subset of JMESPath expressions: identifiers, indexes, slices, projections
+//! (`[*]`, `*`, `[]`), filters with the comparisons(`[?price > `10`]`) and pipes.
+//! The filters are translated into the filter expressions of the engine.
+//! Unlike the json path, the result is a single json value:
+//! the projections produce (possibly nested) arrays and the missing elements turn into null.
+use std::str::FromStr;
+use pest::iterators::Pair;
+use pest::Parser;
+use serde_json::Value;
+use crate::parser::model::{JsonPath, JsonPathIndex, Operand, FilterSign};
+use crate::parser::parser::unescape;
+use crate::path::index::Filter;
+use crate::path::json_path_instance;
+
+#[derive(Parser)]
+#[grammar = "dialect/grammar/jmespath.pest"]
+struct JmesPathParser;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Field(String),
+    Current,
+    Index(i64),
+    Slice(Option<i64>, Option<i64>, Option<i64>),
+    ListWildcard,
+    ObjectWildcard,
+    Flatten,
+    Filter(Operand<'static>, FilterSign, Operand<'static>),
+    Truthy(Operand<'static>),
+}
+
+impl Segment {
+    fn is_projection(&self) -> bool {
+        matches!(self, Segment::Slice(..) | Segment::ListWildcard | Segment::ObjectWildcard
+            | Segment::Filter(..) | Segment::Truthy(_))
+    }
+}
+
+/// The parsed JMESPath expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JmesPath {
+    pipes: Vec<Vec<Segment>>,
+}
+
+impl FromStr for JmesPath {
+    type Err = String;
+
+    fn from_str(expr: &str) -> Result<Self, Self::Err> {
+        let root = JmesPathParser::parse(Rule::jmespath, expr).map_err(|e| e.to_string())?.next().unwrap();
+        let pipes = root.into_inner()
+            .filter(|p| p.as_rule() == Rule::expression)
+            .map(|e| e.into_inner().map(parse_segment).collect())
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(JmesPath { pipes })
+    }
+}
+
+fn parse_segment(pair: Pair<Rule>) -> Result<Segment, String> {
+    match pair.as_rule() {
+        Rule::identifier => Ok(Segment::Field(parse_identifier(pair))),
+        Rule::current => Ok(Segment::Current),
+        Rule::object_wildcard => Ok(Segment::ObjectWildcard),
+        Rule::list_wildcard => Ok(Segment::ListWildcard),
+        Rule::flatten => Ok(Segment::Flatten),
+        Rule::index => parse_int(down(pair)).map(Segment::Index),
+        Rule::slice => {
+            let (mut start, mut end, mut step) = (None, None, None);
+            for p in pair.into_inner() {
+                match p.as_rule() {
+                    Rule::start => start = Some(parse_int(down(p))?),
+                    Rule::end => end = Some(parse_int(down(p))?),
+                    _ => step = Some(parse_int(down(p))?),
+                }
+            }
+            if step == Some(0) {
+                return Err(String::from("the slice step can not be 0"));
+            }
+            Ok(Segment::Slice(start, end, step))
+        }
+        Rule::filter => {
+            let mut pairs = pair.into_inner();
+            let left = parse_operand(pairs.next().unwrap())?;
+            match (pairs.next(), pairs.next()) {
+                (Some(sign), Some(right)) => Ok(Segment::Filter(left, FilterSign::new(sign.as_str()), parse_operand(right)?)),
+                _ => Ok(Segment::Truthy(left))
+            }
+        }
+        r => Err(format!("unexpected rule {:?}", r))
+    }
+}
+
+fn parse_identifier(pair: Pair<Rule>) -> String {
+    let pair = down(pair);
+    match pair.as_rule() {
+        Rule::quoted => unescape(down(pair).as_str()),
+        _ => String::from(pair.as_str())
+    }
+}
+
+fn parse_int(pair: Pair<Rule>) -> Result<i64, String> {
+    pair.as_str().parse::<i64>().map_err(|e| e.to_string())
+}
+
+/// translates the operand of the filter into the operand of the engine
+fn parse_operand(pair: Pair<Rule>) -> Result<Operand<'static>, String> {
+    match pair.as_rule() {
+        Rule::literal => serde_json::from_str(&down(pair).as_str().replace("\\`", "`"))
+            .map(Operand::Static)
+            .map_err(|e| e.to_string()),
+        Rule::raw => Ok(Operand::Static(Value::from(down(pair).as_str().replace("\\'", "'")))),
+        Rule::relative => {
+            let mut tail = vec![];
+            for p in pair.into_inner() {
+                match p.as_rule() {
+                    Rule::identifier => tail.push(JsonPath::field(parse_identifier(p))),
+                    Rule::index => match parse_int(down(p))? {
+                        idx if idx >= 0 => tail.push(JsonPath::Index(JsonPathIndex::Single(Value::from(idx)))),
+                        _ => return Err(String::from("the negative indexes are not supported inside of the filters")),
+                    },
+                    _ => ()
+                }
+            }
+            let tail = if tail.is_empty() { JsonPath::Empty } else { JsonPath::Chain(tail) };
+            Ok(Operand::Dynamic(Box::new(JsonPath::Chain(vec![JsonPath::Current(Box::new(tail))]))))
+        }
+        r => Err(format!("unexpected rule {:?}", r))
+    }
+}
+
+fn down(pair: Pair<Rule>) -> Pair<Rule> {
+    pair.into_inner().next().unwrap()
+}
+
+/// false, null and the empty strings, arrays and objects are falsy
+fn truthy(v: &Value) -> bool {
+    match v {
+        Value::Null | Value::Bool(false) => false,
+        Value::String(s) => !s.is_empty(),
+        Value::Array(elems) => !elems.is_empty(),
+        Value::Object(fields) => !fields.is_empty(),
+        _ => true
+    }
+}
+
+impl JmesPath {
+    /// evaluates the expression against the document
+    pub fn search(&self, doc: &Value) -> Value {
+        let mut pipes = self.pipes.iter();
+        let first = pipes.next().map(|segments| evaluate(segments, doc)).unwrap_or(Value::Null);
+        pipes.fold(first, |v, segments| evaluate(segments, &v))
+    }
+}
+
+fn evaluate(segments: &[Segment], value: &Value) -> Value {
+    // the flattening stops the projection on the left side and projects the right side over the flattened result
+    if let Some(pos) = segments.iter().rposition(|s| *s == Segment::Flatten) {
+        let flattened: Vec<Value> = match evaluate(&segments[..pos], value) {
+            Value::Array(elems) => elems.into_iter().flat_map(|e| match e {
+                Value::Array(inner) => inner,
+                e => vec![e]
+            }).collect(),
+            _ => return Value::Null
+        };
+        return project(flattened.iter().collect(), &segments[pos + 1..]);
+    }
+
+    let mut current = value;
+    for (idx, segment) in segments.iter().enumerate() {
+        if segment.is_projection() {
+            return match select(segment, current) {
+                Some(elems) => project(elems, &segments[idx + 1..]),
+                None => Value::Null,
+            };
+        }
+        match step(segment, current) {
+            Some(v) => current = v,
+            None => return Value::Null,
+        }
+    }
+    current.clone()
+}
+
+/// evaluates the rest of the expression against every element dropping the nulls
+fn project(elems: Vec<&Value>, rest: &[Segment]) -> Value {
+    Value::Array(elems.into_iter().map(|e| evaluate(rest, e)).filter(|v| !v.is_null()).collect())
+}
+
+fn step<'a>(segment: &Segment, value: &'a Value) -> Option<&'a Value> {
+    match segment {
+        Segment::Current => Some(value),
+        Segment::Field(key) => value.get(key),
+        Segment::Index(idx) => value.as_array().and_then(|elems| {
+            let idx = if *idx < 0 { elems.len() as i64 + idx } else { *idx };
+            if idx < 0 { None } else { elems.get(idx as usize) }
+        }),
+        _ => None
+    }
+}
+
+/// collects the elements the projection is applied to or None if the projection is not applicable
+fn select<'a>(segment: &Segment, value: &'a Value) -> Option<Vec<&'a Value>> {
+    match (segment, value) {
+        (Segment::ObjectWildcard, Value::Object(fields)) => Some(fields.values().collect()),
+        (Segment::ListWildcard, Value::Array(elems)) => Some(elems.iter().collect()),
+        (Segment::Slice(start, end, step), Value::Array(elems)) => Some(slice(elems, *start, *end, step.unwrap_or(1))),
+        (Segment::Filter(left, sign, right), Value::Array(elems)) => {
+            let filter = Filter::new(left, right, sign, value);
+            Some(elems.iter().filter(|e| filter.check(e)).collect())
+        }
+        (Segment::Truthy(Operand::Static(v)), Value::Array(elems)) =>
+            Some(if truthy(v) { elems.iter().collect() } else { vec![] }),
+        (Segment::Truthy(Operand::Dynamic(path)), Value::Array(elems)) => {
+            let instance = json_path_instance(path, value);
+            Some(elems.iter().filter(|e| instance.find(e).into_iter().any(truthy)).collect())
+        }
+        _ => None
+    }
+}
+
+fn slice(elems: &[Value], start: Option<i64>, end: Option<i64>, step: i64) -> Vec<&Value> {
+    let len = elems.len() as i64;
+    let bound = |v: i64, low: i64, high: i64| {
+        let v = if v < 0 { v + len } else { v };
+        v.max(low).min(high)
+    };
+    let mut res = vec![];
+    if step > 0 {
+        let mut i = start.map(|s| bound(s, 0, len)).unwrap_or(0);
+        let end = end.map(|e| bound(e, 0, len)).unwrap_or(len);
+        while i < end {
+            res.push(&elems[i as usize]);
+            i = match i.checked_add(step) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+    } else {
+        let mut i = start.map(|s| bound(s, -1, len - 1)).unwrap_or(len - 1);
+        let end = end.map(|e| bound(e, -1, len - 1)).unwrap_or(-1);
+        while i > end {
+            res.push(&elems[i as usize]);
+            i = match i.checked_add(step) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use crate::dialect::jmespath::JmesPath;
+
+    fn test(json: &Value, expr: &str, expected: Value) {
+        let expr: JmesPath = expr.parse().unwrap();
+        assert_eq!(expr.search(json), expected)
+    }
+
+    fn json() -> Value {
+        json!({
+            "people": [
+                {"name": "a", "age": 20, "tags": ["x", "y"], "active": true},
+                {"name": "b", "age": 30, "tags": [], "active": false},
+                {"name": "c", "age": 40, "tags": ["z"]}
+            ],
+            "ops": {"f": {"n": 1}, "g": {"n": 2}, "h": {}},
+            "nested": [[0, 1], 2, [3], [4, [5, 6]]]
+        })
+    }
+
+    #[test]
+    fn basic_test() {
+        test(&json(), "people[0].name", json!("a"));
+        test(&json(), "people[-1].name", json!("c"));
+        test(&json(), "people[5].name", Value::Null);
+        test(&json(), "missing.field", Value::Null);
+        test(&json(), "\"people\"[1].age", json!(30));
+        test(&json(), "@.ops.f", json!({"n": 1}));
+    }
+
+    #[test]
+    fn projection_test() {
+        test(&json(), "people[*].name", json!(["a", "b", "c"]));
+        test(&json(), "people[*].tags[0]", json!(["x", "z"]));
+        test(&json(), "people[*].tags[*]", json!([["x", "y"], [], ["z"]]));
+        test(&json(), "people[].tags[]", json!(["x", "y", "z"]));
+        test(&json(), "ops.*.n", json!([1, 2]));
+        test(&json(), "nested[]", json!([0, 1, 2, 3, 4, [5, 6]]));
+        test(&json(), "people[:2].name", json!(["a", "b"]));
+        test(&json(), "people[::-1].name", json!(["c", "b", "a"]));
+        test(&json(), "people[-2:].age", json!([30, 40]));
+    }
+
+    #[test]
+    fn filter_test() {
+        test(&json(), "people[?age > `25`].name", json!(["b", "c"]));
+        test(&json(), "people[?name == 'a'].age", json!([20]));
+        test(&json(), "people[?tags[0] == 'z'].name", json!(["c"]));
+        test(&json(), "people[?active].name", json!(["a"]));
+        test(&json(), "people[?tags].name", json!(["a", "c"]));
+        test(&json(), "people[?@.age <= `20`].name", json!(["a"]));
+        test(&json(), "people[?`true`].age", json!([20, 30, 40]));
+        test(&json(), "people[?`[]`].age", json!([]));
+    }
+
+    #[test]
+    fn pipe_test() {
+        test(&json(), "people[*].tags[*] | [0]", json!(["x", "y"]));
+        test(&json(), "people[*].name | [-1]", json!("c"));
+        test(&json(), "people | [?age < `30`] | [0].name", json!("a"));
+    }
+
+    #[test]
+    fn parse_failed_test() {
+        assert!("people[?age > ]".parse::<JmesPath>().is_err());
+        assert!("people[::0]".parse::<JmesPath>().is_err());
+        assert!("people.".parse::<JmesPath>().is_err());
+        assert!("people[?age > `{`]".parse::<JmesPath>().is_err());
+    }
+}
@@ -0,0 +1,7 @@
+//! The other dialects of the json path evaluated by the same engine.
+
+/// The module provides the SQL:2016 json path expressions
+pub(crate) mod sql;
+/// The module provides the subset of JMESPath expressions
+#[cfg(feature = "jmespath")]
+pub(crate) mod jmespath;
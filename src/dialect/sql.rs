@@ -0,0 +1,333 @@
+//! The SQL:2016 json path expressions like `lax $.a[*] ? (@.b > 1)`.
+//! The filters are translated into the filter expressions of the engine
+//! whereas the accessors are evaluated according to the mode:
+//! - `lax`(by default) unwraps the arrays for the member accessors and filters,
+//! wraps the non-array elements for the element accessors and ignores the missing elements.
+//! - `strict` reports the structural errors like the missing member or the index out of bounds.
+use std::str::FromStr;
+use pest::iterators::Pair;
+use pest::Parser;
+use crate::path::pattern::escape;
+use serde_json::Value;
+use crate::parser::model::{JsonPath, JsonPathIndex, Operand, FilterSign};
+use crate::parser::parser::unescape;
+use crate::path::index::Filter;
+use crate::path::json::type_name;
+
+#[derive(Parser)]
+#[grammar = "dialect/grammar/sql_path.pest"]
+struct SqlPathParser;
+
+/// The index inside of the element accessor
+#[derive(Debug, Clone, PartialEq)]
+enum Position {
+    Index(usize),
+    /// `last - n`
+    Last(usize),
+}
+
+impl Position {
+    fn resolve(&self, len: usize) -> Option<usize> {
+        match self {
+            Position::Index(idx) => Some(*idx),
+            Position::Last(shift) => len.checked_sub(shift + 1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Member(String),
+    MemberWildcard,
+    Elements(Vec<(Position, Option<Position>)>),
+    ElementWildcard,
+    Filter(Operand<'static>, FilterSign, Operand<'static>),
+}
+
+/// The parsed SQL/JSON path expression.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqlJsonPath {
+    strict: bool,
+    steps: Vec<Step>,
+}
+
+impl FromStr for SqlJsonPath {
+    type Err = String;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let mut strict = false;
+        let mut steps = vec![];
+        let pairs = SqlPathParser::parse(Rule::sql_path, path).map_err(|e| e.to_string())?;
+        for pair in pairs.flatten() {
+            match pair.as_rule() {
+                Rule::mode => strict = pair.as_str().eq_ignore_ascii_case("strict"),
+                Rule::absolute => {
+                    for acc in pair.into_inner().skip(1) {
+                        steps.push(parse_step(acc)?)
+                    }
+                    break;
+                }
+                _ => ()
+            }
+        }
+        Ok(SqlJsonPath { strict, steps })
+    }
+}
+
+fn parse_step(pair: Pair<Rule>) -> Result<Step, String> {
+    match pair.as_rule() {
+        Rule::member => Ok(Step::Member(parse_name(down(pair)))),
+        Rule::member_wildcard => Ok(Step::MemberWildcard),
+        Rule::element_wildcard => Ok(Step::ElementWildcard),
+        Rule::elements => pair.into_inner().map(|sub| {
+            let mut bounds = sub.into_inner().map(parse_position);
+            let from = bounds.next().unwrap()?;
+            let to = bounds.next().transpose()?;
+            Ok((from, to))
+        }).collect::<Result<Vec<_>, String>>().map(Step::Elements),
+        Rule::filter => parse_predicate(down(pair)),
+        r => Err(format!("unexpected rule {:?}", r))
+    }
+}
+
+fn parse_name(pair: Pair<Rule>) -> String {
+    match pair.as_rule() {
+        Rule::string => unescape(down(pair).as_str()),
+        _ => String::from(pair.as_str())
+    }
+}
+
+fn parse_position(pair: Pair<Rule>) -> Result<Position, String> {
+    let pair = down(pair);
+    match pair.as_rule() {
+        Rule::last => Ok(Position::Last(0)),
+        Rule::last_minus => {
+            let shift = pair.into_inner().nth(1).unwrap().as_str();
+            shift.parse().map(Position::Last).map_err(|e| e.to_string())
+        }
+        _ => pair.as_str().parse().map(Position::Index).map_err(|e| e.to_string())
+    }
+}
+
+fn parse_predicate(pair: Pair<Rule>) -> Result<Step, String> {
+    match pair.as_rule() {
+        Rule::exists => Ok(Step::Filter(parse_operand(down(pair))?, FilterSign::Exists, Operand::Dynamic(Box::new(JsonPath::Empty)))),
+        Rule::comparison => {
+            let mut pairs = pair.into_inner();
+            let left = parse_operand(pairs.next().unwrap())?;
+            let sign = match pairs.next().unwrap().as_str() {
+                "<>" => FilterSign::Unequal,
+                s => FilterSign::new(s)
+            };
+            let right = parse_operand(pairs.next().unwrap())?;
+            Ok(Step::Filter(left, sign, right))
+        }
+        Rule::like_regex => {
+            let mut pairs = pair.into_inner();
+            let left = parse_operand(pairs.next().unwrap())?;
+            let pattern = parse_name(pairs.next().unwrap());
+            let pattern = match pairs.next().map(parse_name) {
+                Some(flags) if !flags.is_empty() => format!("(?{}){}", flags, pattern),
+                _ => pattern
+            };
+            Ok(Step::Filter(left, FilterSign::Regex, Operand::Static(Value::from(pattern))))
+        }
+        Rule::starts_with => {
+            let mut pairs = pair.into_inner();
+            let left = parse_operand(pairs.next().unwrap())?;
+            let prefix = format!("^{}", escape(&parse_name(pairs.next().unwrap())));
+            Ok(Step::Filter(left, FilterSign::Regex, Operand::Static(Value::from(prefix))))
+        }
+        r => Err(format!("unexpected rule {:?}", r))
+    }
+}
+
+/// translates the path inside of the filter into the path of the engine
+fn parse_operand(pair: Pair<Rule>) -> Result<Operand<'static>, String> {
+    match pair.as_rule() {
+        Rule::number => pair.as_str().parse::<i64>().map(Value::from)
+            .or_else(|_| pair.as_str().parse::<f64>().map(Value::from))
+            .map(Operand::Static)
+            .map_err(|e| e.to_string()),
+        Rule::string => Ok(Operand::Static(Value::from(parse_name(pair)))),
+        Rule::bool => Ok(Operand::Static(Value::from(pair.as_str().eq_ignore_ascii_case("true")))),
+        Rule::null => Ok(Operand::Static(Value::Null)),
+        Rule::relative | Rule::absolute => {
+            let relative = pair.as_rule() == Rule::relative;
+            let mut tail = vec![];
+            for acc in pair.into_inner().skip(1) {
+                tail.push(match parse_step(acc)? {
+                    Step::Member(key) => JsonPath::Field(key.into()),
+                    Step::MemberWildcard | Step::ElementWildcard => JsonPath::Wildcard,
+                    Step::Elements(positions) => match positions.as_slice() {
+                        [(Position::Index(idx), None)] => JsonPath::Index(JsonPathIndex::Single(Value::from(*idx))),
+                        _ => return Err(String::from("only the single index is supported inside of the filters")),
+                    },
+                    Step::Filter(l, s, r) => JsonPath::Index(JsonPathIndex::Filter(l, s, r)),
+                })
+            }
+            let path = if relative {
+                let tail = if tail.is_empty() { JsonPath::Empty } else { JsonPath::Chain(tail) };
+                JsonPath::Chain(vec![JsonPath::Current(Box::new(tail))])
+            } else {
+                tail.insert(0, JsonPath::Root);
+                JsonPath::Chain(tail)
+            };
+            Ok(Operand::Dynamic(Box::new(path)))
+        }
+        r => Err(format!("unexpected rule {:?}", r))
+    }
+}
+
+fn down(pair: Pair<Rule>) -> Pair<Rule> {
+    pair.into_inner().next().unwrap()
+}
+
+impl SqlJsonPath {
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// evaluates the expression against the document.
+    /// The error can appear only in the strict mode.
+    pub fn find<'a>(&self, doc: &'a Value) -> Result<Vec<&'a Value>, String> {
+        let mut current = vec![doc];
+        for step in self.steps.iter() {
+            let mut next = vec![];
+            for node in current.into_iter() {
+                self.step(step, node, doc, &mut next)?
+            }
+            current = next;
+        }
+        Ok(current)
+    }
+
+    fn step<'a>(&self, step: &Step, node: &'a Value, doc: &'a Value, res: &mut Vec<&'a Value>) -> Result<(), String> {
+        match (step, node) {
+            (Step::Member(_), Value::Array(elems))
+            | (Step::MemberWildcard, Value::Array(elems)) if !self.strict => {
+                for el in elems.iter().filter(|el| el.is_object()) {
+                    self.step(step, el, doc, res)?
+                }
+            }
+            (Step::Member(key), Value::Object(fields)) => match fields.get(key) {
+                Some(v) => res.push(v),
+                None if self.strict => return Err(format!("the member '{}' is not found", key)),
+                None => ()
+            },
+            (Step::MemberWildcard, Value::Object(fields)) => res.extend(fields.values()),
+            (Step::Member(_), v) | (Step::MemberWildcard, v) => if self.strict {
+                return Err(format!("the member accessor is applied to {}", type_name(v)));
+            },
+            (Step::ElementWildcard, Value::Array(elems)) => res.extend(elems.iter()),
+            (Step::Elements(positions), Value::Array(elems)) => {
+                for (from, to) in positions.iter() {
+                    let from = from.resolve(elems.len());
+                    let to = to.as_ref().map(|p| p.resolve(elems.len())).unwrap_or(from);
+                    match (from, to) {
+                        (Some(from), Some(to)) if from <= to && to < elems.len() => res.extend(&elems[from..=to]),
+                        (Some(from), Some(to)) if !self.strict && from <= to => res.extend(elems.iter().skip(from).take(to + 1 - from)),
+                        _ if self.strict => return Err(String::from("the index is out of bounds")),
+                        _ => ()
+                    }
+                }
+            }
+            (_, v) if self.strict && !matches!(step, Step::Filter(..)) =>
+                return Err(format!("the element accessor is applied to {}", type_name(v))),
+            (Step::ElementWildcard, v) => res.push(v),
+            (Step::Elements(positions), v) => {
+                // the element is treated as the array of the one element
+                for (from, to) in positions.iter() {
+                    let from = from.resolve(1);
+                    let to = to.as_ref().map(|p| p.resolve(1)).unwrap_or(from);
+                    if from == Some(0) && to.is_some() {
+                        res.push(v)
+                    }
+                }
+            }
+            (Step::Filter(left, sign, right), v) => {
+                let filter = Filter::new(left, right, sign, doc);
+                match v {
+                    Value::Array(elems) if !self.strict => res.extend(elems.iter().filter(|el| filter.check(el))),
+                    v => if filter.check(v) { res.push(v) },
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use crate::dialect::sql::SqlJsonPath;
+
+    fn test(json: &Value, path: &str, expected: Vec<&Value>) {
+        let path: SqlJsonPath = path.parse().unwrap();
+        assert_eq!(path.find(json), Ok(expected))
+    }
+
+    fn test_failed(json: &Value, path: &str) {
+        let path: SqlJsonPath = path.parse().unwrap();
+        assert!(path.find(json).is_err(), "the strict path should fail")
+    }
+
+    #[test]
+    fn parse_test() {
+        assert!(!"$.a".parse::<SqlJsonPath>().unwrap().is_strict());
+        assert!(!"lax $.a".parse::<SqlJsonPath>().unwrap().is_strict());
+        assert!("strict $.a".parse::<SqlJsonPath>().unwrap().is_strict());
+        assert!("$.a ? (@.b > 1 &&".parse::<SqlJsonPath>().is_err());
+        assert!("a.b".parse::<SqlJsonPath>().is_err());
+        assert!("$.a ? (@.b[1 to 2] > 1)".parse::<SqlJsonPath>().is_err());
+    }
+
+    #[test]
+    fn accessors_test() {
+        let json = json!({"a":[{"b":1},{"b":2},{"b":3}],"c":{"d":"x","e y":"z"},"s":5});
+        test(&json, "$.a[*].b", vec![&json!(1), &json!(2), &json!(3)]);
+        test(&json, "$.a[0].b", vec![&json!(1)]);
+        test(&json, "$.a[last].b", vec![&json!(3)]);
+        test(&json, "$.a[last - 1].b", vec![&json!(2)]);
+        test(&json, "$.a[0 to 1, last].b", vec![&json!(1), &json!(2), &json!(3)]);
+        test(&json, "$.c.*", vec![&json!("x"), &json!("z")]);
+        test(&json, "$.c.\"e y\"", vec![&json!("z")]);
+    }
+
+    #[test]
+    fn lax_test() {
+        let json = json!({"a":[{"b":1},{"b":2},{"c":3}],"s":5});
+        test(&json, "lax $.a.b", vec![&json!(1), &json!(2)]);
+        test(&json, "lax $.s[0]", vec![&json!(5)]);
+        test(&json, "lax $.s[*]", vec![&json!(5)]);
+        test(&json, "lax $.a[10]", vec![]);
+        test(&json, "lax $.x", vec![]);
+        test(&json, "lax $.a[1 to 10].b", vec![&json!(2)]);
+    }
+
+    #[test]
+    fn strict_test() {
+        let json = json!({"a":[{"b":1},{"b":2},{"c":3}],"s":5});
+        test(&json, "strict $.a[0 to 1].b", vec![&json!(1), &json!(2)]);
+        test_failed(&json, "strict $.a.b");
+        test_failed(&json, "strict $.a[*].b");
+        test_failed(&json, "strict $.s[0]");
+        test_failed(&json, "strict $.a[10]");
+        test_failed(&json, "strict $.x");
+    }
+
+    #[test]
+    fn filter_test() {
+        let json = json!({"limit":2,"a":[{"b":1,"n":"apple"},{"b":2,"n":"Banana"},{"b":3,"n":"avocado"}]});
+        test(&json, "$.a ? (@.b > 1).b", vec![&json!(2), &json!(3)]);
+        test(&json, "$.a[*] ? (@.b <> 2).b", vec![&json!(1), &json!(3)]);
+        test(&json, "$.a ? (@.b >= $.limit).b", vec![&json!(2), &json!(3)]);
+        test(&json, "$.a ? (exists(@.n)).b", vec![&json!(1), &json!(2), &json!(3)]);
+        #[cfg(feature = "regex")]
+        test(&json, "$.a ? (@.n like_regex \"^b\" flag \"i\").b", vec![&json!(2)]);
+        #[cfg(feature = "regex")]
+        test(&json, "$.a ? (@.n starts with \"a\").b", vec![&json!(1), &json!(3)]);
+        test(&json, "$.a.b ? (@ == 3)", vec![&json!(3)]);
+        test(&json, "strict $.a ? (@.b > 1)", vec![]);
+    }
+}
@@ -0,0 +1,178 @@
+use std::fmt::{Debug, Display, Formatter};
+use std::marker::PhantomData;
+use serde_core::de::DeserializeOwned;
+use crate::parser::model::{JsonPath, SharedPath};
+
+/// Pulls the typed values out of the json bodies, e.g. the requests coming to the web handlers.
+///
+/// The body is scanned as the text(see [JsonPath::find_raw]) and only the found elements are deserialized into `T`,
+/// so the giant payloads are never turned into the structs or [serde_json::Value] as a whole.
+/// The extractors of the web frameworks can wrap it keeping the compiled query in their state.
+/// # Examples:
+/// ```
+/// use jsonpath_rust::PathExtract;
+///
+/// let ids: PathExtract<u64> = PathExtract::new("$.data.items[*].id").unwrap();
+/// let body = br#"{"data":{"items":[{"id":1,"payload":"..."},{"id":2}]}}"#;
+/// assert_eq!(ids.extract(body), Ok(vec![1, 2]));
+/// ```
+pub struct PathExtract<T> {
+    path: SharedPath,
+    _target: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> PathExtract<T> {
+    pub fn new(query: &str) -> Result<Self, String> {
+        SharedPath::parse(query).map(PathExtract::from)
+    }
+
+    pub fn path(&self) -> &JsonPath<'static> {
+        &self.path
+    }
+
+    /// finds the elements in the body and deserializes every one of them
+    pub fn extract(&self, body: &[u8]) -> Result<Vec<T>, String> {
+        self.path.find_raw_bytes(body)?
+            .into_iter()
+            .map(|m| serde_json::from_str(m.raw).map_err(|e| format!("the element at {} is not valid: {}", m.path, e)))
+            .collect()
+    }
+}
+
+impl<T, P: Into<SharedPath>> From<P> for PathExtract<T> {
+    fn from(path: P) -> Self {
+        PathExtract { path: path.into(), _target: PhantomData }
+    }
+}
+
+impl<T> Clone for PathExtract<T> {
+    fn clone(&self) -> Self {
+        PathExtract { path: self.path.clone(), _target: PhantomData }
+    }
+}
+
+impl<T> Debug for PathExtract<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PathExtract").field("path", &*self.path).finish()
+    }
+}
+
+/// The query of [Extracted] given by the type, since the strings can not be the generic parameters.
+/// # Examples:
+/// ```
+/// use jsonpath_rust::ExtractQuery;
+///
+/// struct ItemIds;
+///
+/// impl ExtractQuery for ItemIds {
+///     const QUERY: &'static str = "$.data.items[*].id";
+/// }
+/// ```
+pub trait ExtractQuery {
+    const QUERY: &'static str;
+}
+
+/// The typed values the query `Q` finds in the json body of the request.
+///
+/// With the feature `axum` it is the extractor of axum and with the feature `actix` the one of actix-web,
+/// so the handler taking `Extracted<ItemIds, u64>` gets the ids without deserializing the whole payload(see [PathExtract]).
+/// The malformed body or the found element of the other type is rejected with `400 Bad Request`,
+/// the malformed query with `500 Internal Server Error`.
+/// # Examples:
+/// ```
+/// use jsonpath_rust::{ExtractQuery, Extracted};
+///
+/// struct ItemIds;
+///
+/// impl ExtractQuery for ItemIds {
+///     const QUERY: &'static str = "$.data.items[*].id";
+/// }
+///
+/// let body = br#"{"data":{"items":[{"id":1},{"id":2}]}}"#;
+/// let ids = Extracted::<ItemIds, u64>::from_body(body).unwrap();
+/// assert_eq!(ids.values, vec![1, 2]);
+/// ```
+pub struct Extracted<Q, T> {
+    pub values: Vec<T>,
+    _query: PhantomData<fn() -> Q>,
+}
+
+impl<Q: ExtractQuery, T: DeserializeOwned> Extracted<Q, T> {
+    /// finds the elements in the body and deserializes every one of them
+    pub fn from_body(body: &[u8]) -> Result<Self, ExtractRejection> {
+        let extract = PathExtract::<T>::new(Q::QUERY).map_err(ExtractRejection::Query)?;
+        let values = extract.extract(body).map_err(ExtractRejection::Body)?;
+        Ok(Extracted { values, _query: PhantomData })
+    }
+}
+
+impl<Q, T: Debug> Debug for Extracted<Q, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Extracted").field("values", &self.values).finish()
+    }
+}
+
+/// The reason [Extracted] fails
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExtractRejection {
+    /// the query of [ExtractQuery] can not be parsed
+    Query(String),
+    /// the body is not json or the found element is not of the type
+    Body(String),
+}
+
+impl Display for ExtractRejection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtractRejection::Query(e) => write!(f, "the query of the extractor is not valid: {}", e),
+            ExtractRejection::Body(e) => write!(f, "the body can not be extracted: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ExtractRejection {}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use crate::extract::{ExtractQuery, ExtractRejection, Extracted, PathExtract};
+
+    #[test]
+    fn extract_test() {
+        let body = br#"{"data":{"total":3,"items":[{"id":1,"tags":{"a":"x"}},{"id":2,"tags":{}},{"id":"three"}]}}"#;
+
+        let tags: PathExtract<BTreeMap<String, String>> = PathExtract::new("$.data.items[*].tags").unwrap();
+        assert_eq!(tags.extract(body).unwrap().len(), 2);
+
+        let ids: PathExtract<u64> = PathExtract::new("$.data.items[?(@.id < 3)].id").unwrap();
+        assert_eq!(ids.clone().extract(body), Ok(vec![1, 2]));
+
+        let ids: PathExtract<u64> = PathExtract::new("$.data.items[*].id").unwrap();
+        let err = ids.extract(body).unwrap_err();
+        assert!(err.starts_with("the element at $['data']['items'][2]['id'] is not valid"), "{}", err);
+
+        assert!(ids.extract(b"{\"data\":").is_err());
+        assert!(PathExtract::<u64>::new("$.[").is_err());
+    }
+
+    struct ItemIds;
+
+    impl ExtractQuery for ItemIds {
+        const QUERY: &'static str = "$.data.items[*].id";
+    }
+
+    struct Broken;
+
+    impl ExtractQuery for Broken {
+        const QUERY: &'static str = "$.[";
+    }
+
+    #[test]
+    fn extracted_test() {
+        let body = br#"{"data":{"items":[{"id":1},{"id":2}]}}"#;
+        assert_eq!(Extracted::<ItemIds, u64>::from_body(body).unwrap().values, vec![1, 2]);
+        assert!(matches!(Extracted::<ItemIds, String>::from_body(body), Err(ExtractRejection::Body(_))));
+        assert!(matches!(Extracted::<ItemIds, u64>::from_body(b"{"), Err(ExtractRejection::Body(_))));
+        assert!(matches!(Extracted::<Broken, u64>::from_body(body), Err(ExtractRejection::Query(_))));
+    }
+}
@@ -0,0 +1,270 @@
+use serde_json::Value;
+
+/// parses the JSON5 document: the comments, trailing commas, unquoted keys, single quoted strings,
+/// hexadecimal numbers and the numbers with the leading or trailing point or the plus sign are allowed.
+/// `Infinity` and `NaN` become the strings `"Infinity"` and `"NaN"`, the same way the documents
+/// carry them usually(see [crate::NonFinite]).
+///
+/// The document is translated into json and parsed with serde, so there is no extra dependency.
+pub fn from_json5(text: &str) -> Result<Value, String> {
+    serde_json::from_str(&to_json(text)?).map_err(|e| e.to_string())
+}
+
+/// translates JSON5 into json
+fn to_json(text: &str) -> Result<String, String> {
+    let mut t = Translator { chars: text.chars().collect(), pos: 0, out: String::with_capacity(text.len()) };
+    t.run()?;
+    Ok(t.out)
+}
+
+struct Translator {
+    chars: Vec<char>,
+    pos: usize,
+    out: String,
+}
+
+impl Translator {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn err<T>(&self, msg: &str) -> Result<T, String> {
+        Err(format!("{} at {}", msg, self.pos))
+    }
+
+    fn run(&mut self) -> Result<(), String> {
+        while let Some(c) = self.peek() {
+            match c {
+                '/' => self.comment()?,
+                ',' => {
+                    self.pos += 1;
+                    let after = self.out.len();
+                    self.skip_blank()?;
+                    if !matches!(self.peek(), Some(']' | '}') | None) {
+                        self.out.insert(after, ',');
+                    }
+                }
+                '"' | '\'' => self.string(c)?,
+                '+' | '-' | '.' | '0'..='9' => self.number()?,
+                c if c == '_' || c == '$' || c.is_alphabetic() => self.word()?,
+                c => {
+                    self.out.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// copies the spaces and drops the comments
+    fn skip_blank(&mut self) -> Result<(), String> {
+        while let Some(c) = self.peek() {
+            match c {
+                '/' => self.comment()?,
+                c if c.is_whitespace() => {
+                    self.out.push(c);
+                    self.pos += 1;
+                }
+                _ => break
+            }
+        }
+        Ok(())
+    }
+
+    fn comment(&mut self) -> Result<(), String> {
+        match self.peek_at(1) {
+            Some('/') => {
+                while !matches!(self.peek(), Some('\n') | None) {
+                    self.pos += 1;
+                }
+                Ok(())
+            }
+            Some('*') => {
+                let start = self.pos;
+                self.pos += 2;
+                loop {
+                    match (self.peek(), self.peek_at(1)) {
+                        (Some('*'), Some('/')) => {
+                            self.pos += 2;
+                            self.out.push(' ');
+                            return Ok(());
+                        }
+                        (Some(_), _) => self.pos += 1,
+                        (None, _) => return Err(format!("the comment is not closed at {}", start)),
+                    }
+                }
+            }
+            _ => self.err("unexpected '/'"),
+        }
+    }
+
+    fn string(&mut self, quote: char) -> Result<(), String> {
+        let start = self.pos;
+        self.pos += 1;
+        self.out.push('"');
+        loop {
+            match self.peek() {
+                None => return Err(format!("the string is not closed at {}", start)),
+                Some(c) if c == quote => {
+                    self.pos += 1;
+                    self.out.push('"');
+                    return Ok(());
+                }
+                Some('"') => {
+                    self.pos += 1;
+                    self.out.push_str("\\\"");
+                }
+                Some('\\') => {
+                    self.pos += 2;
+                    match self.chars.get(self.pos - 1).copied() {
+                        // the line continuation
+                        Some('\n') => (),
+                        Some('\r') => {
+                            if self.peek() == Some('\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        Some('\'') => self.out.push('\''),
+                        Some('x') => {
+                            let hex: String = self.chars.iter().skip(self.pos).take(2).collect();
+                            self.pos += 2;
+                            self.out.push_str("\\u00");
+                            self.out.push_str(&hex);
+                        }
+                        Some(c) => {
+                            self.out.push('\\');
+                            self.out.push(c);
+                        }
+                        None => return Err(format!("the string is not closed at {}", start)),
+                    }
+                }
+                Some(c) => {
+                    self.pos += 1;
+                    self.out.push(c);
+                }
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<(), String> {
+        let negative = match self.peek() {
+            Some(s @ ('+' | '-')) => {
+                self.pos += 1;
+                s == '-'
+            }
+            _ => false
+        };
+        if matches!(self.peek(), Some('I' | 'N')) {
+            let word = self.ident();
+            return match word.as_str() {
+                "Infinity" => {
+                    self.out.push_str(if negative { "\"-Infinity\"" } else { "\"Infinity\"" });
+                    Ok(())
+                }
+                "NaN" => {
+                    self.out.push_str("\"NaN\"");
+                    Ok(())
+                }
+                w => self.err(&format!("unexpected '{}'", w)),
+            };
+        }
+        let start = self.pos;
+        while matches!(self.peek(), Some('0'..='9' | 'a'..='f' | 'A'..='F' | 'x' | 'X' | '.' | '+' | '-')) {
+            // the sign belongs to the exponent only
+            if matches!(self.peek(), Some('+' | '-')) && !matches!(self.chars.get(self.pos - 1), Some('e' | 'E')) {
+                break;
+            }
+            self.pos += 1;
+        }
+        let body: String = self.chars[start..self.pos].iter().collect();
+        if negative {
+            self.out.push('-');
+        }
+        if let Some(hex) = body.strip_prefix("0x").or_else(|| body.strip_prefix("0X")) {
+            let n = u64::from_str_radix(hex, 16).map_err(|e| format!("the number '{}' is malformed at {}: {}", body, start, e))?;
+            self.out.push_str(&n.to_string());
+            return Ok(());
+        }
+        let (mantissa, exp) = match body.find(['e', 'E']) {
+            Some(i) => body.split_at(i),
+            None => (body.as_str(), ""),
+        };
+        if mantissa.starts_with('.') {
+            self.out.push('0');
+        }
+        self.out.push_str(mantissa);
+        if mantissa.ends_with('.') {
+            self.out.push('0');
+        }
+        self.out.push_str(exp);
+        Ok(())
+    }
+
+    fn ident(&mut self) -> String {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c == '_' || c == '$' || c.is_alphanumeric()) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    /// the unquoted key or one of the literals
+    fn word(&mut self) -> Result<(), String> {
+        let word = self.ident();
+        let mut ahead = self.pos;
+        while matches!(self.chars.get(ahead), Some(c) if c.is_whitespace()) {
+            ahead += 1;
+        }
+        if self.chars.get(ahead) == Some(&':') {
+            self.out.push('"');
+            self.out.push_str(&word);
+            self.out.push('"');
+            return Ok(());
+        }
+        match word.as_str() {
+            "true" | "false" | "null" => self.out.push_str(&word),
+            "Infinity" => self.out.push_str("\"Infinity\""),
+            "NaN" => self.out.push_str("\"NaN\""),
+            w => return self.err(&format!("unexpected '{}'", w)),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::json5::from_json5;
+    use crate::path::json::same;
+
+    #[test]
+    fn json5_test() {
+        let doc = from_json5(r#"
+        // the config
+        {
+            name: 'app "one"',  /* the name */
+            $port: 0x1F90,
+            ratio: .5, scale: +2., big: 1.5e+3,
+            tags: ['a', "b",],
+            limits: {max: Infinity, min: -Infinity, none: NaN,},
+            line: 'one \
+two',
+            esc: 'it\'s \x41',
+        }"#).unwrap();
+        assert!(same(&doc, &json!({
+            "name": "app \"one\"", "$port": 8080, "ratio": 0.5, "scale": 2.0, "big": 1500.0,
+            "tags": ["a", "b"], "limits": {"max": "Infinity", "min": "-Infinity", "none": "NaN"},
+            "line": "one two", "esc": "it's A"
+        })), "{}", doc);
+        assert_eq!(from_json5("[1, 2, /* three */ 3, ]").unwrap(), json!([1, 2, 3]));
+        assert!(same(&from_json5(r#"{"a": [-1, -0.5e-1, "x//y"]}"#).unwrap(), &json!({"a": [-1, -0.05, "x//y"]})));
+
+        assert!(from_json5("{a: 'x}").is_err());
+        assert!(from_json5("{a: undefined}").is_err());
+        assert!(from_json5("[1 /* 2]").is_err());
+    }
+}
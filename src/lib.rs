@@ -62,6 +62,8 @@
 //! - ``` $.store.book[*].author ``` : the authors of all books in the store
 //! - ``` $..book[?(@.isbn)]``` : filter all books with isbn number
 //! - ``` $..book[?(@.price<10)]``` : filter all books cheapier than 10
+//! - ``` $..book[?(@.price<10 && !@.isbn)]``` : filter all books cheapier than 10 without isbn number
+//! - ``` $..book[?(length(@.title) > 20)]``` : filter all books with the titles longer than 20 characters
 //! - ``` $..*``` : all Elements in XML document. All members of JSON structure
 //! - ``` $..book[0,1]``` : The first two books
 //! - ``` $..book[:2]``` : The first two books
@@ -72,11 +74,11 @@
 //! - `@`Pointer to the current element inside the filter operations.It is used inside the filter operations to iterate the collection.
 //! - `*` or `[*]`Wildcard. It brings to the list all objects and elements regardless their names.It is analogue a flatmap operation.
 //! - `<..>`| Descent operation. It brings to the list all objects, children of that objects and etc It is analogue a flatmap operation.
-//! - `.<name>` or `.['<name>']`the key pointing to the field of the objectIt is used to obtain the specific field.
+//! - `.<name>`, `.['<name>']` or `.["<name>"]`the key pointing to the field of the objectIt is used to obtain the specific field. The quoted keys can contain any characters like spaces, dots or brackets.
 //! - `['<name>' (, '<name>')]`the list of keysthe same usage as for a single key but for list
 //! - `[<number>]`the filter getting the element by its index.
 //! - `[<number> (, <number>)]`the list if elements of array according to their indexes representing these numbers. |
-//! - `[<start>:<end>:<step>]`slice operator to get a list of element operating with their indexes. By default step = 1, start = 0, end = array len. The elements can be omitted ```[:]```
+//! - `[<start>:<end>:<step>]`slice operator to get a list of element operating with their indexes. By default step = 1, start = 0, end = array len. The elements can be omitted ```[:]```, the negative step walks the array backwards ```[::-1]```
 //! - `[?(<expression>)]`the logical expression to filter elements in the list.It is used with arrays preliminary.
 //!
 //! # Examples
@@ -106,85 +108,204 @@
 //! [`there`]: https://goessner.net/articles/JsonPath/
 
 
+use std::borrow::Cow;
 use serde_json::{Value};
+use serde_core::de::DeserializeOwned;
 use crate::parser::parser::parse_json_path;
-use crate::path::{json_path_instance, PathInstance};
-use crate::parser::model::JsonPath;
+use crate::path::{json_path_instance, functions, PathInstance};
+pub use crate::dialect::sql::SqlJsonPath;
+pub use crate::pipeline::{NdjsonPipeline, Record};
+pub use crate::stream::{MatchStream, MatchSender, Next, SendDoc};
+pub use crate::shared::{SharedValue, PersistentValue};
+pub use crate::raw::{RawMatch, RawKind};
+pub use crate::push::PushEvaluator;
+pub use crate::tree::JsonTree;
+pub use crate::extract::{PathExtract, ExtractQuery, Extracted, ExtractRejection};
+pub use crate::codegen::{generate_queries, generate_path, write_queries};
+pub use crate::stages::{StagedQuery, Stage};
+#[cfg(feature = "json5")]
+pub use crate::json5::from_json5;
+#[cfg(feature = "jmespath")]
+pub use crate::dialect::jmespath::JmesPath;
+pub use crate::path::{BoundPrefix, NormalizedPath, PathSegment, escape_member_name, EmptyReport, NodeSummary, SchemaIssue, LintWarning, VisitFlow, NodeList, Cursor, Page, NonFinite, Options, Collation, FilterFunction, MatchHandle, Profiler, SegmentStats, FilterStats, QueryPolicy, JsonPathInst, PatchOp, PatchRecorder, merge_patch, deep_merge, ArrayMerge, MergeOptions, diff, Change, RelativePointer};
+pub use crate::parser::model::{JsonPath, JsonPathIndex, Operand, FilterSign, FilterExpression, SharedPath};
+pub use crate::parser::errors::{JsonPathParserError, JsonPathError};
+pub use crate::parser::builder::PathBuilder;
 
 mod parser;
 mod path;
+mod dialect;
+mod pipeline;
+mod stream;
+mod shared;
+mod raw;
+mod push;
+mod tree;
+mod extract;
+mod codegen;
+mod stages;
+#[cfg(feature = "json5")]
+mod json5;
+#[cfg(feature = "bson")]
+mod bson;
+#[cfg(feature = "prost-types")]
+mod prost;
+#[cfg(feature = "axum")]
+mod axum;
+#[cfg(feature = "actix")]
+mod actix;
 
 
 #[macro_use]
 extern crate pest_derive;
 extern crate pest;
 
+/// builds the bracket segments of the query from the keys and indexes
+/// quoting the keys safely(see [escape_member_name]).
+/// # Examples:
+/// ```
+/// use jsonpath_rust::segment;
+/// let key = "it's a key";
+/// assert_eq!(segment!(key), "['it\\'s a key']");
+/// assert_eq!(format!("${}", segment!("items", 0, key)), "$['items'][0]['it\\'s a key']");
+/// ```
+#[macro_export]
+macro_rules! segment {
+    ($($s:expr),+ $(,)?) => {{
+        let mut res = String::new();
+        $(res.push_str(&$crate::PathSegment::from($s).to_string());)+
+        res
+    }};
+}
+
 /// the trait allows to mix the method path to the value of [Value]
 /// and thus the using can be shortened to the following one:
 /// # Examples:
 /// ```
 /// use serde_json::{json,Value};
-/// use jsonpath_rust::JsonPathQuery;
-///fn test(){
-///         let json: Value = serde_json::from_str("{}").expect("to get json");
-///         let v = json.path("$..book[?(@.author size 10)].title").expect("the path is correct");
-///         assert_eq!(v, json!([]));
+/// use jsonpath_rust::{JsonPathQuery, JsonPathError};
+///fn test() -> Result<(), JsonPathError> {
+///         let json: Value = json!({"a":{"b":[1,2]}});
+///         let v = json.path("$.a.b[0]")?;
+///         assert_eq!(v, vec![&json!(1)]);
+///         Ok(())
 /// }
 /// ```
 /// #Note:
-/// the result is going to be cloned and therefore it can be significant for the huge queries
+/// the found elements are borrowed from the value, clone them to keep them longer than the value.
 pub trait JsonPathQuery {
-    fn path(self, query: &str) -> Result<Value, String>;
+    fn path(&self, query: &str) -> Result<Vec<&Value>, JsonPathError>;
 }
 
 impl JsonPathQuery for Value {
-    fn path(self, query: &str) -> Result<Value, String> {
-        let p = parse_json_path(query).map_err(|e| e.to_string())?;
-        Ok(JsonPathFinder::new(self, p).find())
+    fn path(&self, query: &str) -> Result<Vec<&Value>, JsonPathError> {
+        let p = JsonPath::parse(query)?;
+        p.validate().map_err(JsonPathError::Invalid)?;
+        if let Some(name) = functions::unknown(&p, &Options::new()) {
+            return Err(JsonPathError::Invalid(format!("the function '{}' is unknown", name)));
+        }
+        Ok(self >> &p)
+    }
+}
+
+/// the shorthand for finding the elements of the value by the parsed path:
+/// `&value >> &path` gives the same elements as [JsonPathFinder::find_slice] does.
+/// # Examples:
+/// ```
+/// use serde_json::json;
+/// use jsonpath_rust::{JsonPath, NormalizedPath};
+///
+/// let json = json!({"a":{"b":[1,2]}});
+/// let path = JsonPath::from("$['a']['b'][1]".parse::<NormalizedPath>().unwrap());
+/// assert_eq!(&json >> &path, vec![&json!(2)]);
+/// ```
+impl<'a, 'p> std::ops::Shr<&JsonPath<'p>> for &'a Value {
+    type Output = Vec<&'a Value>;
+
+    fn shr(self, path: &JsonPath<'p>) -> Self::Output {
+        let mut res = vec![];
+        path.find_values_with(self, |v| res.push(v));
+        res
     }
 }
 
 
-/// The base structure conjuncting the json instance and jsonpath instance
-pub struct JsonPathFinder {
-    json: Value,
-    path: JsonPath,
+/// The base structure conjuncting the json instance and jsonpath instance.
+/// The json is either owned or borrowed.
+pub struct JsonPathFinder<'a> {
+    json: Cow<'a, Value>,
+    path: JsonPath<'a>,
 }
 
-impl JsonPathFinder {
+impl<'a> JsonPathFinder<'a> {
     /// creates a new instance of [JsonPathFinder]
-    pub fn new(json: Value, path: JsonPath) -> Self {
-        JsonPathFinder { json, path }
+    pub fn new(json: Value, path: JsonPath<'a>) -> Self {
+        JsonPathFinder { json: Cow::Owned(json), path }
+    }
+
+    /// creates a new instance of [JsonPathFinder] borrowing the json
+    pub fn borrowed(json: &'a Value, path: JsonPath<'a>) -> Self {
+        JsonPathFinder { json: Cow::Borrowed(json), path }
+    }
+
+    pub fn json(&self) -> &Value {
+        &self.json
+    }
+
+    pub fn path(&self) -> &JsonPath<'a> {
+        &self.path
     }
 
     /// updates a path with a new one
-    pub fn set_path(&mut self, path: JsonPath) {
+    pub fn set_path(&mut self, path: JsonPath<'a>) {
         self.path = path
     }
     /// updates a json with a new one
     pub fn set_json(&mut self, json: Value) {
-        self.json = json
+        self.json = Cow::Owned(json)
     }
     /// updates a json from string and therefore can be some parsing errors
     pub fn set_json_str(&mut self, json: &str) -> Result<(), String> {
-        self.json = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        self.json = Cow::Owned(serde_json::from_str(json).map_err(|e| e.to_string())?);
+        Ok(())
+    }
+    /// updates a json from the JSON5 string
+    #[cfg(feature = "json5")]
+    pub fn set_json5_str(&mut self, json: &str) -> Result<(), String> {
+        self.json = Cow::Owned(from_json5(json)?);
         Ok(())
     }
+    /// replaces the json with a new one giving the previous one back
+    pub fn reset_json(&mut self, json: Value) -> Cow<'a, Value> {
+        std::mem::replace(&mut self.json, Cow::Owned(json))
+    }
+    /// replaces the path with a new one giving the previous one back
+    pub fn reset_path(&mut self, path: JsonPath<'a>) -> JsonPath<'a> {
+        std::mem::replace(&mut self.path, path)
+    }
     /// updates a path from string and therefore can be some parsing errors
     pub fn set_path_str(&mut self, path: &str) -> Result<(), String> {
-        self.path = parse_json_path(path).map_err(|e| e.to_string())?;
+        self.path = parse_json_path(path).map_err(|e| e.to_string())?.into_owned();
         Ok(())
     }
 
     /// create a new instance from string and therefore can be some parsing errors
     pub fn from_str(json: &str, path: &str) -> Result<Self, String> {
         let json = serde_json::from_str(json).map_err(|e| e.to_string())?;
-        let path = parse_json_path(path).map_err(|e| e.to_string())?;
+        let path = parse_json_path(path).map_err(|e| e.to_string())?.into_owned();
+        Ok(JsonPathFinder::new(json, path))
+    }
+
+    /// create a new instance from the JSON5 string
+    #[cfg(feature = "json5")]
+    pub fn from_json5_str(json: &str, path: &str) -> Result<Self, String> {
+        let json = from_json5(json)?;
+        let path = parse_json_path(path).map_err(|e| e.to_string())?.into_owned();
         Ok(JsonPathFinder::new(json, path))
     }
 
     /// creates an instance to find a json slice from the json
-    pub fn instance(&self) -> PathInstance {
+    pub fn instance(&self) -> PathInstance<'_> {
         json_path_instance(&self.path, &self.json)
     }
     /// finds a slice of data in the set json.
@@ -195,7 +316,59 @@ impl JsonPathFinder {
 
     /// finds a slice of data and wrap it with Value::Array by cloning the data.
     pub fn find(&self) -> Value {
-        Value::Array(self.find_slice().into_iter().map(|x| x.clone()).collect())
+        Value::Array(self.find_slice().into_iter().cloned().collect())
+    }
+
+    /// finds the copies of the elements so they outlive the finder and the json
+    pub fn find_owned(&self) -> Vec<Value> {
+        self.find_slice().into_iter().cloned().collect()
+    }
+
+    /// finds the data like [JsonPathFinder::find] and deserializes it into `T`, e.g. `Vec<String>` or `Vec<Book>`
+    pub fn find_as<T: DeserializeOwned>(&self) -> Result<T, String> {
+        serde_json::from_value(self.find()).map_err(|e| format!("the found elements are not valid: {}", e))
+    }
+
+    /// finds a slice of data failing on the invalid path(see [JsonPath::validate])
+    pub fn try_find_slice(&self) -> Result<Vec<&Value>, String> {
+        self.path.try_find(&self.json)
+    }
+
+    /// finds the data like [JsonPathFinder::find] failing on the invalid path
+    pub fn try_find(&self) -> Result<Value, String> {
+        self.try_find_slice().map(|res| Value::Array(res.into_iter().cloned().collect()))
+    }
+
+    /// finds the elements along with their locations like `$['store']['book'][2]`
+    pub fn find_nodes(&self) -> NodeList<'_> {
+        self.path.find_nodes(&self.json)
+    }
+
+    /// finds the first element and stops the traversal right there
+    pub fn find_first(&self) -> Option<&Value> {
+        self.path.find_first(&self.json)
+    }
+
+    /// checks whether the path finds anything stopping at the first found element
+    pub fn exists(&self) -> bool {
+        self.path.exists(&self.json)
+    }
+
+    /// counts the found elements without collecting them
+    pub fn count(&self) -> usize {
+        self.path.count(&self.json)
+    }
+
+    /// finds the locations of the elements as the json pointers like `/store/book/2/title`
+    pub fn find_pointers(&self) -> Vec<String> {
+        self.path.find_pointers(&self.json)
+    }
+
+    /// finds the locations of the elements instead of the elements themselves
+    pub fn find_paths(&self) -> Vec<NormalizedPath> {
+        let mut paths = vec![];
+        self.path.find_with(&self.json, |p, _| paths.push(p.clone()));
+        paths
     }
 }
 
@@ -204,6 +377,7 @@ mod tests {
     use serde_json::{json, Value};
     use crate::JsonPathFinder;
     use crate::JsonPathQuery;
+    use crate::PushEvaluator;
 
     fn test(json: &str, path: &str, expected: Vec<&Value>) {
         match JsonPathFinder::from_str(json, path) {
@@ -212,6 +386,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn outlive_query_test() {
+        let mut finder = {
+            let query = String::from("$.a");
+            JsonPathFinder::from_str(r#"{"a":1,"b":2}"#, &query).unwrap()
+        };
+        finder.set_path_str(&String::from("$.b")).unwrap();
+        assert_eq!(finder.find(), json!([2]));
+    }
+
     fn template_json<'a>() -> &'a str {
         r#" {"store": { "book": [
              {
@@ -309,6 +493,12 @@ mod tests {
                  &json!("J. R. R. Tolkien"),
              ],
         );
+        let json = r#"{"store":{"bicycle":{"color":"red"},"items":[{"id":1},{"id":2}]}}"#;
+        test(json, "$.store.*", vec![&json!({"color":"red"}), &json!([{"id":1},{"id":2}])]);
+        test(json, "$['store'][*]", vec![&json!({"color":"red"}), &json!([{"id":1},{"id":2}])]);
+        test(json, "$.store.items[*].id", vec![&json!(1), &json!(2)]);
+        test(json, "$.store.items.*.id", vec![&json!(1), &json!(2)]);
+        test(json, "$.store.bicycle.color.*", vec![]);
     }
 
     #[test]
@@ -326,6 +516,35 @@ mod tests {
              ]);
     }
 
+    #[test]
+    fn negative_index_test() {
+        let json = r#"{"a":["x","y","z"]}"#;
+        test(json, "$.a[-1]", vec![&json!("z")]);
+        test(json, "$.a[-3]", vec![&json!("x")]);
+        test(json, "$.a[-4]", vec![]);
+        test(json, "$.a[-1,0,-2]", vec![&json!("z"), &json!("x"), &json!("y")]);
+
+        let doc: Value = serde_json::from_str(json).unwrap();
+        let path = crate::parser::parser::parse_json_path("$.a[-1]").unwrap();
+        assert_eq!(path.find_one(&doc), Some(&json!("z")));
+        assert_eq!(path.find_strict(&doc), Ok(&json!("z")));
+        assert_eq!(path.compile()(&doc), vec![&json!("z")]);
+        assert_eq!(path.find_nodes(&doc).into_iter().map(|(p, _)| p.to_string()).collect::<Vec<_>>(), vec!["$['a'][2]"]);
+        let raw = path.find_raw(json).unwrap();
+        assert_eq!((raw[0].raw, raw[0].path.to_string()), ("\"z\"", String::from("$['a'][2]")));
+
+        let mut found = vec![];
+        let mut push = PushEvaluator::new(&path, |p, v| found.push((p.to_string(), v)));
+        push.start_object().unwrap();
+        push.key("a").unwrap();
+        push.start_array().unwrap();
+        ["x", "y", "z"].iter().for_each(|v| push.value(*v).unwrap());
+        push.end_array().unwrap();
+        push.end_object().unwrap();
+        push.finish().unwrap();
+        assert_eq!(found, vec![(String::from("$['a'][2]"), json!("z"))]);
+    }
+
     #[test]
     fn index_unit_index_test() {
         test(template_json(), "$..book[2,4].isbn",
@@ -337,6 +556,9 @@ mod tests {
                  &json!("0-553-21311-3"),
                  &json!("0-395-19395-8"),
              ]);
+        test(r#"{"a":["x","y","z"]}"#, "$.a[2,0,1,0]", vec![&json!("z"), &json!("x"), &json!("y"), &json!("x")]);
+        test(r#"{"a":["x","y","z"]}"#, "$.a[5,1]", vec![&json!("y")]);
+        test(r#"{"a":{"0":"x"}}"#, "$.a[0,1]", vec![]);
     }
 
     #[test]
@@ -348,6 +570,10 @@ mod tests {
                  &json!("The Lord of the Rings"),
                  &json!(22.99),
              ]);
+        let json = r#"{"book":{"title":"t","author":"a","price":1}}"#;
+        test(json, "$.book['price','missing','title']", vec![&json!(1), &json!("t")]);
+        test(json, "$.book['author','author']", vec![&json!("a"), &json!("a")]);
+        test(r#"{"book":["title","price"]}"#, "$.book['title','price']", vec![]);
     }
 
     #[test]
@@ -390,6 +616,31 @@ mod tests {
              vec![
                  &json!(8),
              ]);
+        let json = r#"{"a":[0,1,2,3,4]}"#;
+        test(json, "$.a[:2]", vec![&json!(0), &json!(1)]);
+        test(json, "$.a[3:]", vec![&json!(3), &json!(4)]);
+        test(json, "$.a[:-3]", vec![&json!(0), &json!(1)]);
+        test(json, "$.a[::2]", vec![&json!(0), &json!(2), &json!(4)]);
+        test(json, "$.a[0:0]", vec![]);
+        test(json, "$.a[:0]", vec![]);
+        test(json, "$.a[2:2]", vec![]);
+    }
+
+    #[test]
+    fn reverse_slice_test() {
+        let json = r#"{"a":[0,1,2,3,4,5,6]}"#;
+        test(json, "$.a[::-1]", vec![&json!(6), &json!(5), &json!(4), &json!(3), &json!(2), &json!(1), &json!(0)]);
+        test(json, "$.a[5:1:-2]", vec![&json!(5), &json!(3)]);
+        test(json, "$.a[1:5:-1]", vec![]);
+        test(json, "$.a[-1:-3:-1]", vec![&json!(6), &json!(5)]);
+        test(json, "$.a[:4:-1]", vec![&json!(6), &json!(5)]);
+        test(json, "$.a[10:-10:-3]", vec![&json!(6), &json!(3), &json!(0)]);
+
+        let doc: Value = serde_json::from_str(json).unwrap();
+        let path = crate::parser::parser::parse_json_path("$.a[5:1:-2]").unwrap();
+        assert_eq!(path.compile()(&doc), vec![&json!(5), &json!(3)]);
+        assert_eq!(path.find_nodes(&doc).into_iter().map(|(p, _)| p.to_string()).collect::<Vec<_>>(), vec!["$['a'][5]", "$['a'][3]"]);
+        assert_eq!(path.find_raw(json).unwrap().iter().map(|m| m.raw).collect::<Vec<_>>(), vec!["5", "3"]);
     }
 
     #[test]
@@ -412,6 +663,7 @@ mod tests {
              vec![
                  &json!("Sayings of the Century"),
              ]);
+        #[cfg(feature = "regex")]
         test(template_json(),
              "$..book[?(@.author ~= '.*Rees')].price",
              vec![
@@ -472,6 +724,155 @@ mod tests {
              ]);
     }
 
+    #[test]
+    fn index_filter_literals_test() {
+        let json = r#"{"items":[{"id":1,"active":true,"note":null},{"id":2,"active":false,"note":"x"},{"id":3}]}"#;
+        test(json, "$.items[?(@.active == true)].id", vec![&json!(1)]);
+        test(json, "$.items[?(@.active == false)].id", vec![&json!(2)]);
+        test(json, "$.items[?(@.active != true)].id", vec![&json!(2), &json!(3)]);
+        test(json, "$.items[?(@.note == null)].id", vec![&json!(1)]);
+        test(json, "$.items[?(@.id < 3)].id", vec![&json!(1), &json!(2)]);
+        test(json, "$.items[?(@.note == 'x')].id", vec![&json!(2)]);
+    }
+
+    #[test]
+    fn index_filter_membership_test() {
+        let json = r#"[{"c":"fiction","v":1},{"c":"poetry","v":2},{"c":"essay","v":3},{"c":2,"v":4},{"c":true,"v":5},{"c":null,"v":6},{"v":7}]"#;
+        test(json, "$[?(@.c in ['fiction','poetry'])].v", vec![&json!(1), &json!(2)]);
+        test(json, "$[?(@.c in ['fiction', 2.0, true])].v", vec![&json!(1), &json!(4), &json!(5)]);
+        test(json, "$[?(@.c nin ['fiction','poetry', 2])].v", vec![&json!(3), &json!(5), &json!(6), &json!(7)]);
+        test(json, "$[?(@.c in [null])].v", vec![&json!(6)]);
+        test(json, "$[?(@.c in [])].v", vec![]);
+        test(r#"{"allowed":[2,3],"items":[1,2,3]}"#, "$.items[?(@ in $.allowed)]", vec![&json!(2), &json!(3)]);
+    }
+
+    #[test]
+    fn index_filter_logical_test() {
+        let json = r#"[{"id":1,"price":5},{"id":2,"price":20,"onSale":true},{"id":3,"price":8,"hidden":true},
+            {"id":4,"price":30},{"id":5,"price":20,"onSale":false,"hidden":false}]"#;
+        let query = "$[?((@.price < 10 || @.onSale) && !@.hidden)].id";
+        test(json, query, vec![&json!(1), &json!(2)]);
+        test(json, "$[?(@.price > 10 && @.price < 25)].id", vec![&json!(2), &json!(5)]);
+        test(json, "$[?(@.price < 6 || @.price > 25 || @.hidden)].id", vec![&json!(1), &json!(3), &json!(4), &json!(5)]);
+        test(json, "$[?(!(@.price >= 10))].id", vec![&json!(1), &json!(3)]);
+        test(json, "$[?(@.id == 1 || @.id == 4 && @.price > 100)].id", vec![&json!(1)]);
+        test(json, "$[?(@.onSale == true || @.onSale == false)].id", vec![&json!(2), &json!(5)]);
+
+        let doc: Value = serde_json::from_str(json).unwrap();
+        let path = crate::parser::parser::parse_json_path(query).unwrap();
+        assert_eq!(path.compile()(&doc), vec![&json!(1), &json!(2)]);
+        assert_eq!(path.find_raw(json).unwrap().iter().map(|m| m.raw).collect::<Vec<_>>(), vec!["1", "2"]);
+        assert_eq!(path.find_nodes(&doc).len(), 2);
+    }
+
+    #[test]
+    fn index_filter_root_test() {
+        let json = r#"{"limits":{"max":10,"min":2,"tags":["a","b"]},"items":[{"id":1,"price":5,"tag":"a"},
+            {"id":2,"price":15,"tag":"c"},{"id":3,"price":1},{"id":4,"price":10,"tag":"b"}]}"#;
+        test(json, "$.items[?(@.price > $.limits.max)].id", vec![&json!(2)]);
+        test(json, "$.items[?(@.price <= $.limits.max && @.price >= $.limits.min)].id", vec![&json!(1), &json!(4)]);
+        test(json, "$.items[?(@.tag in $.limits.tags)].id", vec![&json!(1), &json!(4)]);
+        test(json, "$.items[?(@.price > $.limits.missing)].id", vec![]);
+        test(json, "$.items[?(@.price == $.items[-1].price)].id", vec![&json!(4)]);
+
+        let doc: Value = serde_json::from_str(json).unwrap();
+        let path = crate::parser::parser::parse_json_path("$.items[?(@.price > $.limits.max)].id").unwrap();
+        assert_eq!(path.compile()(&doc), vec![&json!(2)]);
+        assert_eq!(path.find_raw(json).unwrap().iter().map(|m| m.raw).collect::<Vec<_>>(), vec!["2"]);
+    }
+
+    #[test]
+    fn index_filter_exists_test() {
+        let json = r#"[{"id":1,"isbn":"0-553-21311-3"},{"id":2,"isbn":null},{"id":3,"isbn":false},{"id":4,"isbn":0},
+            {"id":5,"isbn":""},{"id":6,"isbn":[]},{"id":7,"isbn":{}},{"id":8},{"id":9,"ISBN":"x"},{"id":10,"meta":{"isbn":"y"}}]"#;
+        test(json, "$[?(@.isbn)].id", vec![&json!(1), &json!(2), &json!(3), &json!(4), &json!(5), &json!(6), &json!(7)]);
+        test(json, "$[?(!@.isbn)].id", vec![&json!(8), &json!(9), &json!(10)]);
+        test(json, "$[?(@.meta.isbn)].id", vec![&json!(10)]);
+        test(json, "$[?(@..isbn)].id", vec![&json!(1), &json!(2), &json!(3), &json!(4), &json!(5), &json!(6), &json!(7), &json!(10)]);
+        test(json, "$[?(@.isbn[0])].id", vec![]);
+        test(json, "$[?(@.*)].id", vec![&json!(1), &json!(2), &json!(3), &json!(4), &json!(5), &json!(6), &json!(7), &json!(8), &json!(9), &json!(10)]);
+
+        let doc: Value = serde_json::from_str(json).unwrap();
+        let path = crate::parser::parser::parse_json_path("$[?(@.isbn)].id").unwrap();
+        assert_eq!(path.compile()(&doc).len(), 7);
+        assert_eq!(path.find_raw(json).unwrap().len(), 7);
+    }
+
+    #[test]
+    fn index_filter_length_function_test() {
+        let json = r#"[{"id":1,"authors":["a","b","c"],"title":"Blåbær","meta":{"a":1,"b":2}},{"id":2,"authors":["a"],"title":"short"},
+            {"id":3,"authors":{"length":5},"title":7},{"id":4}]"#;
+        test(json, "$[?(length(@.authors) >= 2)].id", vec![&json!(1)]);
+        test(json, "$[?(length(@.authors) == 1)].id", vec![&json!(2), &json!(3)]);
+        test(json, "$[?(length(@.title) == 6)].id", vec![&json!(1)]);
+        test(json, "$[?(length(@.meta) == 2)].id", vec![&json!(1)]);
+        test(json, "$[?(length(@) == 3)].id", vec![&json!(2), &json!(3)]);
+        test(json, "$[?(length(@.title) == length('short'))].id", vec![&json!(2)]);
+        test(json, "$[?(length(@.title) != 6)].id", vec![&json!(2), &json!(3), &json!(4)]);
+        test(json, "$[?(length(@.missing) == 0)].id", vec![]);
+        test(json, "$[?(length(@.authors[0]) == length($[0].authors[1]))].id", vec![&json!(1), &json!(2)]);
+
+        let doc: Value = serde_json::from_str(json).unwrap();
+        let path = crate::parser::parser::parse_json_path("$[?(length(@.authors) >= 2)].id").unwrap();
+        assert_eq!(path.compile()(&doc), vec![&json!(1)]);
+        assert_eq!(path.find_raw(json).unwrap().iter().map(|m| m.raw).collect::<Vec<_>>(), vec!["1"]);
+        assert_eq!(path.find_nodes(&doc).len(), 1);
+    }
+
+    #[test]
+    fn index_filter_count_test() {
+        let json = r#"{"limit":2,"books":[{"id":1,"authors":["a","b","c"]},{"id":2,"authors":["a"]},
+            {"id":3,"authors":[]},{"id":4,"authors":{"x":"a","y":"b"}},{"id":5}]}"#;
+        test(json, "$.books[?(count(@.authors[*]) >= 2)].id", vec![&json!(1), &json!(4)]);
+        test(json, "$.books[?(count(@.authors[*]) == 0)].id", vec![&json!(3), &json!(5)]);
+        test(json, "$.books[?(count(@.authors) == 1)].id", vec![&json!(1), &json!(2), &json!(3), &json!(4)]);
+        test(json, "$.books[?(count(@..x) == 1)].id", vec![&json!(4)]);
+        test(json, "$.books[?(count(@.authors[*]) == $.limit)].id", vec![&json!(4)]);
+        test(json, "$.books[?(count(@.authors[?(@ == 'a')]) == 1 && count(@.*) == 2)].id", vec![&json!(1), &json!(2), &json!(4)]);
+        test(json, "$.books[?(count($.books[*]) > 4 && @.id > 3)].id", vec![&json!(4), &json!(5)]);
+
+        let doc: Value = serde_json::from_str(json).unwrap();
+        let path = crate::parser::parser::parse_json_path("$.books[?(count(@.authors[*]) >= 2)].id").unwrap();
+        assert_eq!(path.compile()(&doc), vec![&json!(1), &json!(4)]);
+        assert_eq!(path.find_raw(json).unwrap().iter().map(|m| m.raw).collect::<Vec<_>>(), vec!["1", "4"]);
+    }
+
+    #[test]
+    fn index_filter_value_test() {
+        let json = r#"[{"id":1,"a":{"color":"red"}},{"id":2,"color":"red","b":{"color":"red"}},{"id":3,"color":"blue"},
+            {"id":4,"a":{"b":{"color":"red"}}},{"id":5,"tags":["x","y"]},{"id":6}]"#;
+        test(json, "$[?(value(@..color) == \"red\")].id", vec![&json!(1), &json!(4)]);
+        test(json, "$[?(value(@..color) != 'red')].id", vec![&json!(2), &json!(3), &json!(5), &json!(6)]);
+        test(json, "$[?(value(@.tags[*]) == 'x')].id", vec![]);
+        test(json, "$[?(value(@.tags) == ['x','y'])].id", vec![&json!(5)]);
+        test(json, "$[?(length(value(@.tags[?(@ == 'y')])) == 1)].id", vec![&json!(5)]);
+        test(json, "$[?(value(@.*) == 6)].id", vec![&json!(6)]);
+
+        let doc: Value = serde_json::from_str(json).unwrap();
+        let path = crate::parser::parser::parse_json_path("$[?(value(@..color) == 'red')].id").unwrap();
+        assert_eq!(path.compile()(&doc), vec![&json!(1), &json!(4)]);
+        assert_eq!(path.find_raw(json).unwrap().iter().map(|m| m.raw).collect::<Vec<_>>(), vec!["1", "4"]);
+    }
+
+    #[test]
+    fn index_filter_length_test() {
+        let json = r#"[{"id":1,"tags":["a","b","c"],"title":"Blåbær"},{"id":2,"tags":["a"],"title":"short"},
+            {"id":3,"tags":{"length":5},"title":["x","y","z","w","v","u"]},{"id":4}]"#;
+        test(json, "$[?(@.tags.length > 2)].id", vec![&json!(1), &json!(3)]);
+        test(json, "$[?(@.tags.length == 1)].id", vec![&json!(2)]);
+        test(json, "$[?(@.title.length == 6)].id", vec![&json!(1), &json!(3)]);
+        test(json, "$[?(@.title size 6)].id", vec![&json!(1), &json!(3)]);
+        test(json, "$[?(@.title size 5)].id", vec![&json!(2)]);
+        test(json, "$[?(@.missing size 0)].id", vec![]);
+        test(json, "$[?(@.tags.length < $[0].tags.length)].id", vec![&json!(2)]);
+        test(json, "$[0].tags[?(@.length == 1)]", vec![&json!("a"), &json!("b"), &json!("c")]);
+
+        let doc: Value = serde_json::from_str(json).unwrap();
+        let path = crate::parser::parser::parse_json_path("$[?(@.tags.length > 2)].id").unwrap();
+        assert_eq!(path.compile()(&doc), vec![&json!(1), &json!(3)]);
+        assert_eq!(path.find_raw(json).unwrap().iter().map(|m| m.raw).collect::<Vec<_>>(), vec!["1", "3"]);
+    }
+
     #[test]
     fn index_filter_sets_test() {
         test(template_json(),
@@ -498,6 +899,109 @@ mod tests {
         let v = json.path("$..book[?(@.author size 10)].title")
             .expect("the path is correct");
 
-        assert_eq!(v, json!(["Sayings of the Century"]))
+        assert_eq!(v, vec![&json!("Sayings of the Century")]);
+        assert_eq!(json.path("$.store.bicycle.color"), Ok(vec![&json!("red")]));
+        assert_eq!(json.path("$.store.missing"), Ok(vec![]));
+
+        let e = json.path("$.store[").unwrap_err();
+        assert!(matches!(&e, crate::JsonPathError::Parse(p) if p.offset() == 8));
+        assert!(std::error::Error::source(&e).is_some());
+        assert_eq!(json.path("$[?(twice(@.a) > 1)]"),
+                   Err(crate::JsonPathError::Invalid(String::from("the function 'twice' is unknown"))));
+    }
+
+    #[test]
+    fn finder_test() {
+        let json = json!({"a":[{"b":1},{"b":2}],"c":{"b":3}});
+        let mut finder = JsonPathFinder::borrowed(&json, crate::parser::parser::parse_json_path("$..b").unwrap());
+        assert_eq!(finder.find(), json!([1, 2, 3]));
+        assert_eq!(finder.find_paths().iter().map(|p| p.to_string()).collect::<Vec<_>>(),
+                   vec!["$['a'][0]['b']", "$['a'][1]['b']", "$['c']['b']"]);
+        let nodes: Vec<(String, &Value)> = finder.find_nodes().into_iter().map(|(p, v)| (p.to_string(), v)).collect();
+        assert_eq!(nodes, vec![(String::from("$['a'][0]['b']"), &json!(1)), (String::from("$['a'][1]['b']"), &json!(2)),
+                               (String::from("$['c']['b']"), &json!(3))]);
+        assert_eq!(finder.find_pointers(), vec!["/a/0/b", "/a/1/b", "/c/b"]);
+        assert_eq!(finder.find_first(), Some(&json!(1)));
+        assert!(finder.exists());
+        assert_eq!(finder.count(), 3);
+        assert_eq!(finder.find_owned(), vec![json!(1), json!(2), json!(3)]);
+
+        let prev = finder.reset_path(crate::parser::parser::parse_json_path("$.c").unwrap());
+        assert_eq!(prev, crate::parser::parser::parse_json_path("$..b").unwrap());
+        assert_eq!(finder.find_paths().iter().map(|p| p.to_string()).collect::<Vec<_>>(), vec!["$['c']"]);
+
+        let prev = finder.reset_json(json!({"c":"new"}));
+        assert_eq!(prev.as_ref(), &json);
+        assert_eq!(finder.find(), json!(["new"]));
+        assert_eq!(finder.json(), &json!({"c":"new"}));
+    }
+
+    #[test]
+    fn finder_as_test() {
+        let json = json!({"a":[{"b":1,"n":"x"},{"b":2,"n":"y"}]});
+        let finder = JsonPathFinder::borrowed(&json, crate::parser::parser::parse_json_path("$.a[*].b").unwrap());
+        assert_eq!(finder.find_as::<Vec<u64>>(), Ok(vec![1, 2]));
+
+        let finder = JsonPathFinder::borrowed(&json, crate::parser::parser::parse_json_path("$.a[*].n").unwrap());
+        assert_eq!(finder.find_as::<Vec<String>>(), Ok(vec![String::from("x"), String::from("y")]));
+
+        let finder = JsonPathFinder::borrowed(&json, crate::parser::parser::parse_json_path("$.a[0]").unwrap());
+        let found = finder.find_as::<Vec<std::collections::BTreeMap<String, Value>>>().unwrap();
+        assert_eq!(found[0].get("n"), Some(&json!("x")));
+
+        let finder = JsonPathFinder::borrowed(&json, crate::parser::parser::parse_json_path("$.a[*].n").unwrap());
+        assert!(finder.find_as::<Vec<u64>>().unwrap_err().starts_with("the found elements are not valid"));
+    }
+
+    #[test]
+    fn shr_test() {
+        let json = json!({"a":[{"b":1},{"b":2},{"c":3}]});
+        let path = crate::parser::parser::parse_json_path("$.a[*].b").unwrap();
+        assert_eq!(&json >> &path, vec![&json!(1), &json!(2)]);
+        let path = crate::parser::parser::parse_json_path("$.a[?(@.c)]").unwrap();
+        assert_eq!(&json >> &path, vec![&json!({"c":3})]);
+    }
+
+    #[test]
+    fn escaped_key_test() {
+        let key = "a 'strange' \\ key\n\"with\" [brackets].";
+        let json = json!({"a":[{ key: 42 }]});
+        let path = format!("$.a[0]{}", segment!(key));
+        let finder = JsonPathFinder::new(json, crate::parser::parser::parse_json_path(&path).unwrap());
+        assert_eq!(finder.find_slice(), vec![&json!(42)]);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex_literal_test() {
+        let json = r#"[{"name":"Foobar","v":1},{"name":"bar/foo","v":2},{"name":"food","v":3},{"v":4}]"#;
+        test(json, "$[?(@.name =~ /^foo.*/i)].v", vec![&json!(1), &json!(3)]);
+        test(json, "$[?(@.name =~ /^foo/)].v", vec![&json!(3)]);
+        test(json, "$[?(@.name =~ /r\\/f/)].v", vec![&json!(2)]);
+        test(json, "$[?(@.name ~= '^FOO')].v", vec![]);
+
+        let doc: Value = serde_json::from_str(json).unwrap();
+        let path = crate::parser::parser::parse_json_path("$[?(@.name =~ /^foo.*/i)].v").unwrap();
+        assert_eq!(path.compile()(&doc), vec![&json!(1), &json!(3)]);
+        assert_eq!(path.find_raw(json).unwrap().iter().map(|m| m.raw).collect::<Vec<_>>(), vec!["1", "3"]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn bracket_key_test() {
+        let json = r#"{"key with spaces":1,"weird-key!":2,"a.b":{"[c]":3},"a":{"b":4}}"#;
+        test(json, "$['key with spaces']", vec![&json!(1)]);
+        test(json, "$[\"weird-key!\"]", vec![&json!(2)]);
+        test(json, "$[\"a.b\"]['[c]']", vec![&json!(3)]);
+        test(json, "$.a.b", vec![&json!(4)]);
+        test(json, "$[\"key with spaces\",'weird-key!']", vec![&json!(1), &json!(2)]);
+        test(json, "$..[\"[c]\"]", vec![&json!(3)]);
+        test(r#"[{"k":"a b"},{"k":"c"}]"#, "$[?(@.k == \"a b\")].k", vec![&json!("a b")]);
+    }
+
+    #[cfg(feature = "json5")]
+    #[test]
+    fn finder_json5_test() {
+        let finder = JsonPathFinder::from_json5_str("{a: [1, 2,], // the list\n}", "$.a[1]").unwrap();
+        assert_eq!(finder.find(), json!([2]));
+    }
+}
@@ -0,0 +1,470 @@
+//! The compact binary form of the parsed [JsonPath].
+//! It allows to share the precompiled queries without shipping the text and reparsing it.
+//! The format is the version byte followed by the tagged nodes where
+//! the numbers are stored as varints and the json values are stored as the length-prefixed json text.
+use std::convert::TryFrom;
+use serde_json::Value;
+use crate::parser::model::{JsonPath, JsonPathIndex, Operand, FilterSign, FilterExpression};
+
+const VERSION: u8 = 3;
+
+const SIGNS: [FilterSign; 14] = [
+    FilterSign::Equal,
+    FilterSign::Unequal,
+    FilterSign::Less,
+    FilterSign::Greater,
+    FilterSign::LeOrEq,
+    FilterSign::GrOrEq,
+    FilterSign::Regex,
+    FilterSign::In,
+    FilterSign::Nin,
+    FilterSign::Size,
+    FilterSign::NoneOf,
+    FilterSign::AnyOf,
+    FilterSign::SubSetOf,
+    FilterSign::Exists,
+];
+
+impl JsonPath<'_> {
+    /// serializes the path into the compact binary form.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer { buf: vec![VERSION] };
+        w.path(self);
+        w.buf
+    }
+
+    /// restores the path from the binary form obtained from [JsonPath::to_bytes]
+    pub fn from_bytes(bytes: &[u8]) -> Result<JsonPath<'static>, String> {
+        let mut r = Reader { bytes, pos: 0, version: VERSION, depth: 0 };
+        let version = r.byte()?;
+        r.version = version;
+        if version == 0 || version > VERSION {
+            return Err(format!("unsupported version of the binary path: {}", version));
+        }
+        let path = r.path()?;
+        if r.pos != bytes.len() {
+            return Err(format!("unexpected trailing bytes at {}", r.pos));
+        }
+        Ok(path)
+    }
+}
+
+struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    fn uint(&mut self, mut v: u64) {
+        while v >= 0x80 {
+            self.buf.push((v as u8) | 0x80);
+            v >>= 7;
+        }
+        self.buf.push(v as u8);
+    }
+
+    fn int(&mut self, v: i64) {
+        self.uint(((v << 1) ^ (v >> 63)) as u64)
+    }
+
+    fn str(&mut self, v: &str) {
+        self.uint(v.len() as u64);
+        self.buf.extend_from_slice(v.as_bytes());
+    }
+
+    fn value(&mut self, v: &Value) {
+        self.str(&v.to_string())
+    }
+
+    fn path(&mut self, path: &JsonPath) {
+        match path {
+            JsonPath::Root => self.buf.push(0),
+            JsonPath::Field(key) => {
+                self.buf.push(1);
+                self.str(key)
+            }
+            JsonPath::Chain(elems) => {
+                self.buf.push(2);
+                self.uint(elems.len() as u64);
+                elems.iter().for_each(|p| self.path(p))
+            }
+            JsonPath::Descent(key) => {
+                self.buf.push(3);
+                self.str(key)
+            }
+            JsonPath::Index(index) => {
+                self.buf.push(4);
+                self.index(index)
+            }
+            JsonPath::Current(tail) => {
+                self.buf.push(5);
+                self.path(tail)
+            }
+            JsonPath::Wildcard => self.buf.push(6),
+            JsonPath::Empty => self.buf.push(7),
+            JsonPath::Descendants => self.buf.push(8),
+        }
+    }
+
+    fn index(&mut self, index: &JsonPathIndex) {
+        match index {
+            JsonPathIndex::Single(v) => {
+                self.buf.push(0);
+                self.value(v)
+            }
+            JsonPathIndex::UnionIndex(elems) => {
+                self.buf.push(1);
+                self.uint(elems.len() as u64);
+                elems.iter().for_each(|v| self.value(v))
+            }
+            JsonPathIndex::UnionKeys(keys) => {
+                self.buf.push(2);
+                self.uint(keys.len() as u64);
+                keys.iter().for_each(|k| self.str(k))
+            }
+            JsonPathIndex::Slice(start, end, step) => {
+                self.buf.push(3);
+                self.buf.push(start.is_some() as u8 | (end.is_some() as u8) << 1);
+                start.iter().chain(end.iter()).for_each(|b| self.int(*b));
+                self.int(*step)
+            }
+            JsonPathIndex::Filter(left, sign, right) => {
+                self.buf.push(4);
+                self.atom(left, sign, right)
+            }
+            JsonPathIndex::Logical(expr) => {
+                self.buf.push(5);
+                self.expression(expr)
+            }
+            JsonPathIndex::Union(sels) => {
+                self.buf.push(6);
+                self.uint(sels.len() as u64);
+                sels.iter().for_each(|s| self.path(s))
+            }
+        }
+    }
+
+    fn atom(&mut self, left: &Operand, sign: &FilterSign, right: &Operand) {
+        self.operand(left);
+        self.buf.push(SIGNS.iter().position(|s| s == sign).unwrap_or(0) as u8);
+        self.operand(right)
+    }
+
+    fn expression(&mut self, expr: &FilterExpression) {
+        match expr {
+            FilterExpression::Atom(left, sign, right) => {
+                self.buf.push(0);
+                self.atom(left, sign, right)
+            }
+            FilterExpression::And(left, right) => {
+                self.buf.push(1);
+                self.expression(left);
+                self.expression(right)
+            }
+            FilterExpression::Or(left, right) => {
+                self.buf.push(2);
+                self.expression(left);
+                self.expression(right)
+            }
+            FilterExpression::Not(expr) => {
+                self.buf.push(3);
+                self.expression(expr)
+            }
+        }
+    }
+
+    fn operand(&mut self, op: &Operand) {
+        match op {
+            Operand::Static(v) => {
+                self.buf.push(0);
+                self.value(v)
+            }
+            Operand::Dynamic(path) => {
+                self.buf.push(1);
+                self.path(path)
+            }
+            Operand::Function(name, args) => {
+                self.buf.push(2);
+                self.str(name);
+                self.uint(args.len() as u64);
+                args.iter().for_each(|a| self.operand(a))
+            }
+            Operand::Variable(name) => {
+                self.buf.push(3);
+                self.str(name)
+            }
+        }
+    }
+}
+
+/// how deep the paths, the filter expressions and the function arguments can be nested into each other
+const MAX_DEPTH: usize = 128;
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    version: u8,
+    /// how many paths, expressions and operands are being decoded now
+    depth: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, String> {
+        let b = self.bytes.get(self.pos).copied()
+            .ok_or_else(|| String::from("unexpected end of the binary path"))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn uint(&mut self) -> Result<u64, String> {
+        let mut res: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let b = self.byte()?;
+            if shift > 63 {
+                return Err(format!("the number is too long at {}", self.pos));
+            }
+            res |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(res);
+            }
+            shift += 7;
+        }
+    }
+
+    fn int(&mut self) -> Result<i64, String> {
+        let v = self.uint()?;
+        Ok(((v >> 1) as i64) ^ -((v & 1) as i64))
+    }
+
+    fn len(&mut self) -> Result<usize, String> {
+        let len = self.uint()? as usize;
+        if len > self.bytes.len() - self.pos {
+            Err(format!("the length {} is out of bounds at {}", len, self.pos))
+        } else {
+            Ok(len)
+        }
+    }
+
+    fn str(&mut self) -> Result<String, String> {
+        let len = self.len()?;
+        let s = std::str::from_utf8(&self.bytes[self.pos..self.pos + len]).map_err(|e| e.to_string())?;
+        self.pos += len;
+        Ok(String::from(s))
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        serde_json::from_str(&self.str()?).map_err(|e| e.to_string())
+    }
+
+    /// decodes the nested part keeping track of the depth, so the crafted input can not overflow the stack
+    fn nested<T>(&mut self, read: fn(&mut Self) -> Result<T, String>) -> Result<T, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err(format!("the path is nested deeper than {} levels at {}", MAX_DEPTH, self.pos));
+        }
+        self.depth += 1;
+        let res = read(self);
+        self.depth -= 1;
+        res
+    }
+
+    fn path(&mut self) -> Result<JsonPath<'static>, String> {
+        self.nested(Self::read_path)
+    }
+
+    fn read_path(&mut self) -> Result<JsonPath<'static>, String> {
+        match self.byte()? {
+            0 => Ok(JsonPath::Root),
+            1 => Ok(JsonPath::Field(self.str()?.into())),
+            2 => {
+                let len = self.len()?;
+                let mut elems = Vec::with_capacity(len);
+                for _ in 0..len {
+                    elems.push(self.path()?)
+                }
+                Ok(JsonPath::Chain(elems))
+            }
+            3 => Ok(JsonPath::Descent(self.str()?.into())),
+            4 => Ok(JsonPath::Index(self.index()?)),
+            5 => Ok(JsonPath::Current(Box::new(self.path()?))),
+            6 => Ok(JsonPath::Wildcard),
+            7 => Ok(JsonPath::Empty),
+            8 => Ok(JsonPath::Descendants),
+            tag => Err(format!("unknown path tag {} at {}", tag, self.pos - 1))
+        }
+    }
+
+    fn index(&mut self) -> Result<JsonPathIndex<'static>, String> {
+        match self.byte()? {
+            0 => Ok(JsonPathIndex::Single(self.value()?)),
+            1 => {
+                let len = self.len()?;
+                let mut elems = Vec::with_capacity(len);
+                for _ in 0..len {
+                    elems.push(self.value()?)
+                }
+                Ok(JsonPathIndex::UnionIndex(elems))
+            }
+            2 => {
+                let len = self.len()?;
+                let mut keys = Vec::with_capacity(len);
+                for _ in 0..len {
+                    keys.push(self.str()?.into())
+                }
+                Ok(JsonPathIndex::UnionKeys(keys))
+            }
+            3 => {
+                // the first version had no omitted bounds and the end 0 stood for the end of the array
+                let (start, end) = if self.version == 1 {
+                    let (start, end) = (self.int()?, self.int()?);
+                    (Some(start), if end == 0 { None } else { Some(end) })
+                } else {
+                    let present = self.byte()?;
+                    let start = if present & 1 != 0 { Some(self.int()?) } else { None };
+                    let end = if present & 2 != 0 { Some(self.int()?) } else { None };
+                    (start, end)
+                };
+                // the steps were unsigned before the third version
+                let step = if self.version < 3 {
+                    i64::try_from(self.uint()?).unwrap_or(i64::MAX)
+                } else {
+                    self.int()?
+                };
+                Ok(JsonPathIndex::Slice(start, end, step))
+            }
+            4 => {
+                let (left, sign, right) = self.atom()?;
+                Ok(JsonPathIndex::Filter(left, sign, right))
+            }
+            5 => Ok(JsonPathIndex::Logical(self.expression()?)),
+            6 => {
+                let len = self.len()?;
+                let mut sels = Vec::with_capacity(len);
+                for _ in 0..len {
+                    sels.push(self.path()?)
+                }
+                Ok(JsonPathIndex::Union(sels))
+            }
+            tag => Err(format!("unknown index tag {} at {}", tag, self.pos - 1))
+        }
+    }
+
+    fn atom(&mut self) -> Result<(Operand<'static>, FilterSign, Operand<'static>), String> {
+        let left = self.operand()?;
+        let sign = self.byte()?;
+        let sign = SIGNS.get(sign as usize).cloned()
+            .ok_or_else(|| format!("unknown filter sign {} at {}", sign, self.pos - 1))?;
+        let right = self.operand()?;
+        Ok((left, sign, right))
+    }
+
+    fn expression(&mut self) -> Result<FilterExpression<'static>, String> {
+        self.nested(Self::read_expression)
+    }
+
+    fn read_expression(&mut self) -> Result<FilterExpression<'static>, String> {
+        match self.byte()? {
+            0 => {
+                let (left, sign, right) = self.atom()?;
+                Ok(FilterExpression::Atom(left, sign, right))
+            }
+            1 => Ok(FilterExpression::and(self.expression()?, self.expression()?)),
+            2 => Ok(FilterExpression::or(self.expression()?, self.expression()?)),
+            3 => Ok(!self.expression()?),
+            tag => Err(format!("unknown filter expression tag {} at {}", tag, self.pos - 1))
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand<'static>, String> {
+        self.nested(Self::read_operand)
+    }
+
+    fn read_operand(&mut self) -> Result<Operand<'static>, String> {
+        match self.byte()? {
+            0 => Ok(Operand::Static(self.value()?)),
+            1 => Ok(Operand::Dynamic(Box::new(self.path()?))),
+            2 => {
+                let name = self.str()?;
+                let len = self.len()?;
+                let mut args = Vec::with_capacity(len);
+                for _ in 0..len {
+                    args.push(self.operand()?)
+                }
+                Ok(Operand::Function(name.into(), args))
+            }
+            3 => Ok(Operand::Variable(self.str()?.into())),
+            tag => Err(format!("unknown operand tag {} at {}", tag, self.pos - 1))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parser::parse_json_path;
+    use crate::parser::model::{JsonPath, JsonPathIndex};
+
+    fn test(path: &str) {
+        let path = parse_json_path(path).unwrap();
+        assert_eq!(JsonPath::from_bytes(&path.to_bytes()), Ok(path));
+    }
+
+    #[test]
+    fn round_trip_test() {
+        test("$.k.['k']['k']..k..['k'].*.[*][*][1][1,2]['k','k'][:][10:][:10][10:10:10][-1:-2][::-1][5:1:-2]");
+        test("$..book[?(@.isbn)].title");
+        test("$..book[?(@.price <= $.expensive)].price");
+        test("$..book[?(@.title nin ['Moby Dick','Shmoby Dick'])].title");
+        test("$.orders[?(@.ref subsetOf [1,2,3,4])].id");
+        test("$..book[?(@.author ~= '.*Rees')].price");
+        test("$..book[?((@.price < 10 || @.onSale) && !@.hidden)].price");
+        test("$..book[?(length(@.title) > length('Moby Dick'))].price");
+        test("$[-3000000000:5000000000:4000000000][-99999999999999999999:]");
+        test("$.items[?(@.id == $id || $flag)]");
+        test("$.a['b',1,2:4,*,?(@.c)]");
+    }
+
+    #[test]
+    fn first_version_test() {
+        // `[2:]` and `[-1:3]` written by the first version
+        assert_eq!(JsonPath::from_bytes(&[1, 4, 3, 4, 0, 1]), Ok(JsonPath::Index(JsonPathIndex::Slice(Some(2), None, 1))));
+        assert_eq!(JsonPath::from_bytes(&[1, 4, 3, 1, 6, 1]), Ok(JsonPath::Index(JsonPathIndex::Slice(Some(-1), Some(3), 1))));
+    }
+
+    #[test]
+    fn second_version_test() {
+        // `[1::2]` written by the second version
+        assert_eq!(JsonPath::from_bytes(&[2, 4, 3, 1, 2, 2]), Ok(JsonPath::Index(JsonPathIndex::Slice(Some(1), None, 2))));
+    }
+
+    #[test]
+    fn broken_bytes_test() {
+        let bytes = parse_json_path("$.a[?(@.b > 1)]").unwrap().to_bytes();
+        assert!(JsonPath::from_bytes(&[]).is_err());
+        assert!(JsonPath::from_bytes(&[42]).is_err());
+        assert!(JsonPath::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(JsonPath::from_bytes(&trailing).is_err());
+
+        let mut huge_len = bytes;
+        huge_len.truncate(2);
+        huge_len.extend_from_slice(&[0xff, 0xff, 0xff, 0x0f]);
+        assert!(JsonPath::from_bytes(&huge_len).is_err());
+    }
+
+    #[test]
+    fn depth_test() {
+        let mut nested = vec![3];
+        nested.extend(std::iter::repeat_n(5, 1_000_000));
+        nested.push(0);
+        assert!(JsonPath::from_bytes(&nested).unwrap_err().starts_with("the path is nested deeper than 128 levels"));
+
+        // the negations nested into each other
+        let mut negated = vec![3, 4, 5];
+        negated.extend(std::iter::repeat_n(3, 1_000_000));
+        assert!(JsonPath::from_bytes(&negated).unwrap_err().starts_with("the path is nested deeper than 128 levels"));
+
+        let path = parse_json_path("$.a[?(!!!(@.b[?(@.c[?(@.d > 1)])]))]").unwrap();
+        assert_eq!(JsonPath::from_bytes(&path.to_bytes()), Ok(path.into_owned()));
+    }
+}
@@ -0,0 +1,138 @@
+use std::borrow::Cow;
+use serde_json::Value;
+use crate::parser::model::{FilterExpression, JsonPath, JsonPathIndex};
+
+/// Builds the path step by step, so the paths made of the user data need no escaping and no parsing.
+/// The built path is the same as the parser gives for the equivalent query.
+/// # Examples:
+/// ```
+/// use serde_json::json;
+/// use jsonpath_rust::{FilterExpression, FilterSign, JsonPath, Operand};
+///
+/// let path = JsonPath::root().field("store").index(3).wildcard().build();
+/// assert_eq!(path, JsonPath::parse("$.store[3][*]").unwrap());
+///
+/// let cheap = FilterExpression::Atom(
+///     Operand::path(JsonPath::current().field("price").build()), FilterSign::Less, Operand::val(json!(10)),
+/// );
+/// let path = JsonPath::root().descent("book").filter(cheap).field("it's").build();
+/// assert_eq!(path.to_string(), "$..book[?(@.price < 10)]['it\\'s']");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathBuilder<'a> {
+    elems: Vec<JsonPath<'a>>,
+    current: bool,
+}
+
+impl<'a> JsonPath<'a> {
+    /// starts the path from the root `$`
+    pub fn root() -> PathBuilder<'a> {
+        PathBuilder { elems: vec![JsonPath::Root], current: false }
+    }
+
+    /// starts the path from the current element `@` to use it in the filters
+    pub fn current() -> PathBuilder<'a> {
+        PathBuilder { elems: vec![], current: true }
+    }
+}
+
+impl<'a> PathBuilder<'a> {
+    fn push(mut self, elem: JsonPath<'a>) -> Self {
+        self.elems.push(elem);
+        self
+    }
+
+    /// the member of the object `['key']`
+    pub fn field<K: Into<Cow<'a, str>>>(self, key: K) -> Self {
+        self.push(JsonPath::Field(key.into()))
+    }
+
+    /// the members with the name at any depth `..key`
+    pub fn descent<K: Into<Cow<'a, str>>>(self, key: K) -> Self {
+        self.push(JsonPath::Descent(key.into()))
+    }
+
+    /// the element of the array, the negative index counts from the end
+    pub fn index(self, idx: i64) -> Self {
+        self.push(JsonPath::Index(JsonPathIndex::Single(Value::from(idx))))
+    }
+
+    /// the members `['a','b']`, the single one is the plain field
+    pub fn keys<K: Into<Cow<'a, str>>, I: IntoIterator<Item=K>>(self, keys: I) -> Self {
+        let mut keys: Vec<Cow<'a, str>> = keys.into_iter().map(Into::into).collect();
+        match keys.len() {
+            1 => self.push(JsonPath::Field(keys.remove(0))),
+            _ => self.push(JsonPath::Index(JsonPathIndex::UnionKeys(keys))),
+        }
+    }
+
+    /// the elements `[1,2]`, the single one is the plain index
+    pub fn indexes<I: IntoIterator<Item=i64>>(self, indexes: I) -> Self {
+        let indexes: Vec<Value> = indexes.into_iter().map(Value::from).collect();
+        match indexes.as_slice() {
+            [idx] => self.push(JsonPath::Index(JsonPathIndex::Single(idx.clone()))),
+            _ => self.push(JsonPath::Index(JsonPathIndex::UnionIndex(indexes))),
+        }
+    }
+
+    /// the slice `[start:end:step]`
+    pub fn slice(self, start: Option<i64>, end: Option<i64>, step: i64) -> Self {
+        self.push(JsonPath::Index(JsonPathIndex::Slice(start, end, step)))
+    }
+
+    /// all members or elements `[*]`
+    pub fn wildcard(self) -> Self {
+        self.push(JsonPath::Wildcard)
+    }
+
+    /// the elements satisfying the expression `[?(...)]`
+    pub fn filter(self, expr: FilterExpression<'a>) -> Self {
+        match expr {
+            FilterExpression::Atom(l, sign, r) => self.push(JsonPath::Index(JsonPathIndex::Filter(l, sign, r))),
+            expr => self.push(JsonPath::Index(JsonPathIndex::Logical(expr))),
+        }
+    }
+
+    pub fn build(self) -> JsonPath<'a> {
+        if !self.current {
+            return JsonPath::Chain(self.elems);
+        }
+        let tail = if self.elems.is_empty() { JsonPath::Empty } else { JsonPath::Chain(self.elems) };
+        JsonPath::Chain(vec![JsonPath::Current(Box::new(tail))])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::parser::model::{FilterExpression, FilterSign, JsonPath, Operand};
+    use crate::parser::parser::parse_json_path;
+
+    #[test]
+    fn builder_test() {
+        let cases = [
+            (JsonPath::root().build(), "$"),
+            (JsonPath::root().field("a").field("b c").index(-1).build(), "$.a['b c'][-1]"),
+            (JsonPath::root().descent("a").wildcard().slice(Some(1), None, 2).slice(None, None, 1).build(), "$..a[*][1::2][:]"),
+            (JsonPath::root().keys(["a", "b"]).keys(["c"]).indexes([1, 2]).indexes([3]).build(), "$['a','b'].c[1,2][3]"),
+        ];
+        for (path, query) in cases {
+            assert_eq!(path, parse_json_path(query).unwrap(), "{}", query);
+        }
+
+        let at = |key: &'static str| Operand::path(JsonPath::current().field(key).build());
+        let expr = FilterExpression::or(
+            FilterExpression::Atom(at("a"), FilterSign::Equal, Operand::str("x")),
+            !FilterExpression::Atom(Operand::path(JsonPath::current().build()), FilterSign::Exists, Operand::path(JsonPath::Empty)),
+        );
+        let path = JsonPath::root().field("items").filter(expr)
+            .filter(FilterExpression::Atom(at("b"), FilterSign::GrOrEq, Operand::val(json!(1)))).build();
+        assert_eq!(path, parse_json_path("$.items[?(@.a == 'x' || !@)][?(@.b >= 1)]").unwrap());
+
+        let key = String::from("it's \\ new");
+        let path = JsonPath::root().field(key.as_str()).build();
+        assert_eq!(parse_json_path(&path.to_string()).unwrap(), path);
+        let doc = json!({"it's \\ new": 1});
+        assert_eq!(path.find_owned(&doc), vec![json!(1)]);
+    }
+}
@@ -0,0 +1,190 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use pest::error::{ErrorVariant, InputLocation, LineColLocation};
+use crate::parser::parser::Rule;
+
+/// The error of parsing the query, see [crate::JsonPath::parse].
+/// It points out where the query goes wrong so the applications can show it to the users.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPathParserError {
+    message: String,
+    offset: usize,
+    line: usize,
+    column: usize,
+    token: Option<String>,
+    expected: Vec<String>,
+}
+
+impl JsonPathParserError {
+    pub(crate) fn new(e: pest::error::Error<Rule>, query: &str) -> Self {
+        let (offset, end) = match e.location {
+            InputLocation::Pos(p) => (p, None),
+            InputLocation::Span((start, end)) => (start, Some(end)),
+        };
+        let (line, column) = match e.line_col {
+            LineColLocation::Pos(p) | LineColLocation::Span(p, _) => p,
+        };
+        let mut expected: Vec<String> = vec![];
+        if let ErrorVariant::ParsingError { positives, .. } = &e.variant {
+            for rule in positives {
+                let rule = describe(rule);
+                if !expected.contains(&rule) {
+                    expected.push(rule)
+                }
+            }
+        }
+        JsonPathParserError {
+            message: e.to_string(),
+            offset,
+            line,
+            column,
+            token: token(query, offset, end),
+            expected,
+        }
+    }
+
+    /// what is wrong with the query along with the place in it
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// the position in bytes the query goes wrong at
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// the line the query goes wrong at starting from 1
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// the column in characters the query goes wrong at starting from 1
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// the part of the query that can not be parsed, none at the end of the query
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// what could go at the place instead of the token like `number` or `key`,
+    /// empty if the query is parsed but is not valid like the function getting the wrong arguments
+    pub fn expected(&self) -> &[String] {
+        &self.expected
+    }
+}
+
+/// the word or the single symbol the error points out to, or the whole span of the error
+fn token(query: &str, offset: usize, end: Option<usize>) -> Option<String> {
+    let rest = query.get(offset..)?;
+    let len = match end {
+        Some(end) => end.saturating_sub(offset),
+        None => match rest.find(|c: char| !(c.is_alphanumeric() || c == '_')) {
+            Some(0) => rest.chars().next().map(char::len_utf8).unwrap_or(0),
+            Some(len) => len,
+            None => rest.len(),
+        }
+    };
+    rest.get(..len).filter(|t| !t.is_empty()).map(String::from)
+}
+
+fn describe(rule: &Rule) -> String {
+    let name = match rule {
+        Rule::chain | Rule::strict_path => "path",
+        Rule::number | Rule::unsigned | Rule::signed => "number",
+        Rule::string_qt => "string",
+        Rule::boolean => "boolean",
+        Rule::function_name => "function",
+        Rule::key | Rule::key_lim | Rule::key_unlim => "key",
+        Rule::logic_or | Rule::logic_and | Rule::logic_not | Rule::atom => "filter expression",
+        Rule::sign => "comparison operator",
+        Rule::EOI => "end of the query",
+        Rule::root => "$",
+        Rule::current => "@",
+        Rule::descent => "..",
+        Rule::wildcard | Rule::star => "*",
+        Rule::index => "[",
+        Rule::step_slice => ":",
+        rule => return format!("{:?}", rule).replace('_', " "),
+    };
+    String::from(name)
+}
+
+impl Display for JsonPathParserError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for JsonPathParserError {}
+
+/// The error of running the query, see [crate::JsonPathQuery].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JsonPathError {
+    /// the query is malformed
+    Parse(JsonPathParserError),
+    /// the query is parsed but can not be run like the index out of the range or the unknown function
+    Invalid(String),
+}
+
+impl From<JsonPathParserError> for JsonPathError {
+    fn from(e: JsonPathParserError) -> Self {
+        JsonPathError::Parse(e)
+    }
+}
+
+impl Display for JsonPathError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonPathError::Parse(e) => Display::fmt(e, f),
+            JsonPathError::Invalid(e) => write!(f, "the path is invalid: {}", e),
+        }
+    }
+}
+
+impl Error for JsonPathError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            JsonPathError::Parse(e) => Some(e),
+            JsonPathError::Invalid(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parser::parse_json_path;
+
+    fn error(query: &str) -> (usize, usize, usize, Option<String>, Vec<String>) {
+        let e = parse_json_path(query).unwrap_err();
+        (e.offset(), e.line(), e.column(), e.token().map(String::from), e.expected().to_vec())
+    }
+
+    #[test]
+    fn position_test() {
+        assert_eq!(error("$.a["), (4, 1, 5, None, vec![String::from("number"), String::from("string"), String::from("filter"), String::from("*")]));
+        assert_eq!(error("$.a.."), (5, 1, 6, None, vec![String::from("key")]));
+        assert_eq!(error("$[1,x]"), (4, 1, 5, Some(String::from("x")), vec![String::from("number"), String::from("string"), String::from("filter"), String::from("*")]));
+        assert_eq!(error("$[?(@.a && )]"), (11, 1, 12, Some(String::from(")")), vec![String::from("filter expression")]));
+        assert_eq!(error("foo"), (0, 1, 1, Some(String::from("foo")), vec![String::from("path")]));
+
+        let (offset, line, column, token, expected) = error("$[?(@.a >< 1)]");
+        assert_eq!((offset, line, column, token), (9, 1, 10, Some(String::from("<"))));
+        assert!(expected.contains(&String::from("function")) && expected.contains(&String::from("path")));
+
+        let (offset, line, column, token, expected) = error("$.a b");
+        assert_eq!((offset, line, column, token), (4, 1, 5, Some(String::from("b"))));
+        assert_eq!(expected.first(), Some(&String::from("end of the query")));
+
+        assert_eq!(error("$['böö'][?(@.a >< 1)]").0, 18);
+        assert_eq!(error("$['böö'][?(@.a >< 1)]").2, 17);
+        assert_eq!(error("$['böö']\n  [?(@.a\n  >< 1)]"), (23, 3, 4, Some(String::from("<")), error("$[?(@.a >< 1)]").4));
+    }
+
+    #[test]
+    fn invalid_function_test() {
+        assert_eq!(error("$[?(length(@.*) > 1)]"), (11, 1, 12, Some(String::from("@.*")), vec![]));
+        assert_eq!(error("$[?(count(@.a))]"), (4, 1, 5, Some(String::from("count(@.a)")), vec![]));
+    }
+}
@@ -2,5 +2,10 @@
 //! The module grammar denotes the structure of the parsing grammar
 
 pub(crate) mod model;
+pub(crate) mod binary;
+pub(crate) mod errors;
+pub(crate) mod builder;
+mod serialize;
+#[allow(clippy::module_inception)]
 pub(crate) mod parser;
 
@@ -1,75 +1,245 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::mem::discriminant;
+use std::ops::{Deref, Not};
+use std::str::FromStr;
+use std::sync::Arc;
 use serde_json::Value;
+use crate::path::json::cmp_numbers;
+use crate::path::normalized::escape_member_name;
+use crate::parser::errors::JsonPathParserError;
 
 /// The basic structures for parsing json paths.
 /// The common logic of the structures pursues to correspond the internal parsing structure.
+/// The keys borrow from the parsed query whenever they contain no escape sequences.
 #[derive(Debug, Clone)]
-pub enum JsonPath {
+pub enum JsonPath<'a> {
     /// The $ operator
     Root,
     /// Field represents key
-    Field(String),
+    Field(Cow<'a, str>),
     /// The whole chain of the path.
-    Chain(Vec<JsonPath>),
+    Chain(Vec<JsonPath<'a>>),
     /// The .. operator
-    Descent(String),
+    Descent(Cow<'a, str>),
     /// The indexes for array
-    Index(JsonPathIndex),
+    Index(JsonPathIndex<'a>),
     /// The @ operator
-    Current(Box<JsonPath>),
+    Current(Box<JsonPath<'a>>),
     /// The * operator
     Wildcard,
+    /// The element along with all its descendants, `..*` and `..[0]` are this followed by the selector
+    Descendants,
     /// The item uses to define the unresolved state
     Empty,
 }
 
-impl JsonPath {
-    pub fn descent(key: &str) -> Self {
-        JsonPath::Descent(String::from(key))
+impl<'a> JsonPath<'a> {
+    /// parses the query, the keys borrow from it
+    pub fn parse(query: &'a str) -> Result<Self, JsonPathParserError> {
+        crate::parser::parser::parse_json_path(query)
     }
-    pub fn field(key: &str) -> Self {
-        JsonPath::Field(String::from(key))
+
+    /// parses the query following RFC 9535 to the letter, see [crate::parser::parser::parse_json_path_strict]
+    pub fn parse_strict(query: &'a str) -> Result<Self, JsonPathParserError> {
+        crate::parser::parser::parse_json_path_strict(query)
+    }
+
+    pub fn descent<K: Into<Cow<'a, str>>>(key: K) -> Self {
+        JsonPath::Descent(key.into())
+    }
+    pub fn field<K: Into<Cow<'a, str>>>(key: K) -> Self {
+        JsonPath::Field(key.into())
+    }
+
+    /// detaches the path from the query it has been parsed from
+    pub fn into_owned(self) -> JsonPath<'static> {
+        match self {
+            JsonPath::Root => JsonPath::Root,
+            JsonPath::Field(key) => JsonPath::Field(Cow::Owned(key.into_owned())),
+            JsonPath::Chain(elems) => JsonPath::Chain(elems.into_iter().map(JsonPath::into_owned).collect()),
+            JsonPath::Descent(key) => JsonPath::Descent(Cow::Owned(key.into_owned())),
+            JsonPath::Index(idx) => JsonPath::Index(idx.into_owned()),
+            JsonPath::Current(tail) => JsonPath::Current(Box::new(tail.into_owned())),
+            JsonPath::Wildcard => JsonPath::Wildcard,
+            JsonPath::Descendants => JsonPath::Descendants,
+            JsonPath::Empty => JsonPath::Empty,
+        }
+    }
+}
+
+/// The compiled query that is cheap to clone: the clones share the same path behind [Arc],
+/// so one query can serve many request handlers and worker threads.
+/// It dereferences to [JsonPath], so everything the path does is at hand.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SharedPath {
+    path: Arc<JsonPath<'static>>,
+}
+
+impl SharedPath {
+    pub fn new(path: JsonPath<'_>) -> Self {
+        SharedPath { path: Arc::new(path.into_owned()) }
+    }
+
+    /// parses the query
+    pub fn parse(query: &str) -> Result<Self, String> {
+        crate::parser::parser::parse_json_path(query).map(SharedPath::new).map_err(|e| e.to_string())
+    }
+
+    /// checks whether both are the clones of the same query
+    pub fn ptr_eq(&self, other: &SharedPath) -> bool {
+        Arc::ptr_eq(&self.path, &other.path)
+    }
+}
+
+/// parses the query into the path owning its keys
+impl FromStr for JsonPath<'static> {
+    type Err = JsonPathParserError;
+
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        JsonPath::parse(query).map(JsonPath::into_owned)
+    }
+}
+
+impl Deref for SharedPath {
+    type Target = JsonPath<'static>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.path
+    }
+}
+
+impl From<JsonPath<'_>> for SharedPath {
+    fn from(path: JsonPath<'_>) -> Self {
+        SharedPath::new(path)
     }
 }
 
 #[derive(Debug, Clone)]
-pub enum JsonPathIndex {
+pub enum JsonPathIndex<'a> {
     /// The single element in array
     Single(Value),
     /// Union represents a several indexes
     UnionIndex(Vec<Value>),
     /// Union represents a several keys
-    UnionKeys(Vec<String>),
-    /// DEfault slice where the items are start/end/step respectively
-    Slice(i32, i32, usize),
+    UnionKeys(Vec<Cow<'a, str>>),
+    /// DEfault slice where the items are start/end/step respectively,
+    /// the omitted start and end stand for the beginning and the end of the array
+    /// in the direction of the step, the negative step walks the array backwards
+    Slice(Option<i64>, Option<i64>, i64),
     /// Filter ?()
-    Filter(Operand, FilterSign, Operand),
+    Filter(Operand<'a>, FilterSign, Operand<'a>),
+    /// Filter ?() combining the comparisons with `&&`, `||` and `!`,
+    /// the single comparison is always [JsonPathIndex::Filter]
+    Logical(FilterExpression<'a>),
+    /// The selectors of the different kinds like `['a',1,2:4,*,?@.b]` selecting one after another,
+    /// every selector is the field, the wildcard or the index
+    Union(Vec<JsonPath<'a>>),
 }
 
-impl JsonPathIndex {
-    pub fn exists(op: Operand) -> Self {
+impl<'a> JsonPathIndex<'a> {
+    pub fn exists(op: Operand<'a>) -> Self {
         JsonPathIndex::Filter(op, FilterSign::Exists, Operand::Dynamic(Box::new(JsonPath::Empty)))
     }
+
+    pub fn into_owned(self) -> JsonPathIndex<'static> {
+        match self {
+            JsonPathIndex::Single(v) => JsonPathIndex::Single(v),
+            JsonPathIndex::UnionIndex(elems) => JsonPathIndex::UnionIndex(elems),
+            JsonPathIndex::UnionKeys(keys) =>
+                JsonPathIndex::UnionKeys(keys.into_iter().map(|k| Cow::Owned(k.into_owned())).collect()),
+            JsonPathIndex::Slice(s, e, st) => JsonPathIndex::Slice(s, e, st),
+            JsonPathIndex::Filter(l, op, r) => JsonPathIndex::Filter(l.into_owned(), op, r.into_owned()),
+            JsonPathIndex::Logical(expr) => JsonPathIndex::Logical(expr.into_owned()),
+            JsonPathIndex::Union(sels) => JsonPathIndex::Union(sels.into_iter().map(JsonPath::into_owned).collect()),
+        }
+    }
+}
+
+/// The logical expression of the filter, `!` binds tighter than `&&` and `&&` binds tighter than `||`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum FilterExpression<'a> {
+    /// the single comparison like `@.a > 1`
+    Atom(Operand<'a>, FilterSign, Operand<'a>),
+    And(Box<FilterExpression<'a>>, Box<FilterExpression<'a>>),
+    Or(Box<FilterExpression<'a>>, Box<FilterExpression<'a>>),
+    Not(Box<FilterExpression<'a>>),
+}
+
+impl<'a> FilterExpression<'a> {
+    pub fn and(left: FilterExpression<'a>, right: FilterExpression<'a>) -> Self {
+        FilterExpression::And(Box::new(left), Box::new(right))
+    }
+    pub fn or(left: FilterExpression<'a>, right: FilterExpression<'a>) -> Self {
+        FilterExpression::Or(Box::new(left), Box::new(right))
+    }
+
+    /// the comparisons of the expression from the left to the right
+    pub fn atoms(&self) -> Vec<(&Operand<'a>, &FilterSign, &Operand<'a>)> {
+        match self {
+            FilterExpression::Atom(l, op, r) => vec![(l, op, r)],
+            FilterExpression::And(l, r) | FilterExpression::Or(l, r) => {
+                let mut atoms = l.atoms();
+                atoms.extend(r.atoms());
+                atoms
+            }
+            FilterExpression::Not(expr) => expr.atoms(),
+        }
+    }
+
+    pub fn into_owned(self) -> FilterExpression<'static> {
+        match self {
+            FilterExpression::Atom(l, op, r) => FilterExpression::Atom(l.into_owned(), op, r.into_owned()),
+            FilterExpression::And(l, r) => FilterExpression::and(l.into_owned(), r.into_owned()),
+            FilterExpression::Or(l, r) => FilterExpression::or(l.into_owned(), r.into_owned()),
+            FilterExpression::Not(expr) => !expr.into_owned(),
+        }
+    }
+}
+
+impl<'a> Not for FilterExpression<'a> {
+    type Output = FilterExpression<'a>;
+
+    fn not(self) -> Self::Output {
+        FilterExpression::Not(Box::new(self))
+    }
 }
 
 /// Operand for filtering expressions
 #[derive(Debug, Clone)]
-pub enum Operand {
+pub enum Operand<'a> {
     Static(Value),
-    Dynamic(Box<JsonPath>),
+    Dynamic(Box<JsonPath<'a>>),
+    /// the function extension like `length(@.authors)` with its arguments
+    Function(Cow<'a, str>, Vec<Operand<'a>>),
+    /// the placeholder like `$id` getting the value by [JsonPath::bind_var], the unbound one gives nothing
+    Variable(Cow<'a, str>),
 }
 
-impl Operand {
+impl<'a> Operand<'a> {
     pub fn str(v: &str) -> Self {
         Operand::Static(Value::from(v))
     }
     pub fn val(v: Value) -> Self { Operand::Static(v) }
-    pub fn path(p: JsonPath) -> Self {
+    pub fn path(p: JsonPath<'a>) -> Self {
         Operand::Dynamic(Box::new(p))
     }
+
+    pub fn into_owned(self) -> Operand<'static> {
+        match self {
+            Operand::Static(v) => Operand::Static(v),
+            Operand::Dynamic(jp) => Operand::Dynamic(Box::new(jp.into_owned())),
+            Operand::Function(name, args) =>
+                Operand::Function(Cow::Owned(name.into_owned()), args.into_iter().map(Operand::into_owned).collect()),
+            Operand::Variable(name) => Operand::Variable(Cow::Owned(name.into_owned())),
+        }
+    }
 }
 
 /// The operators for filtering functions
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum FilterSign {
     Equal,
     Unequal,
@@ -96,7 +266,7 @@ impl FilterSign {
             ">" => FilterSign::Greater,
             "<=" => FilterSign::LeOrEq,
             ">=" => FilterSign::GrOrEq,
-            "~=" => FilterSign::Regex,
+            "~=" | "=~" => FilterSign::Regex,
             "in" => FilterSign::In,
             "nin" => FilterSign::Nin,
             "size" => FilterSign::Size,
@@ -108,13 +278,14 @@ impl FilterSign {
     }
 }
 
-impl PartialEq for JsonPath {
+impl PartialEq for JsonPath<'_> {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (JsonPath::Root, JsonPath::Root) => true,
             (JsonPath::Descent(k1), JsonPath::Descent(k2)) => k1 == k2,
             (JsonPath::Field(k1), JsonPath::Field(k2)) => k1 == k2,
             (JsonPath::Wildcard, JsonPath::Wildcard) => true,
+            (JsonPath::Descendants, JsonPath::Descendants) => true,
             (JsonPath::Empty, JsonPath::Empty) => true,
             (JsonPath::Current(jp1), JsonPath::Current(jp2)) => jp1 == jp2,
             (JsonPath::Chain(ch1), JsonPath::Chain(ch2)) => ch1 == ch2,
@@ -124,7 +295,7 @@ impl PartialEq for JsonPath {
     }
 }
 
-impl PartialEq for JsonPathIndex {
+impl PartialEq for JsonPathIndex<'_> {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (JsonPathIndex::Slice(s1, e1, st1),
@@ -134,18 +305,457 @@ impl PartialEq for JsonPathIndex {
             (JsonPathIndex::UnionKeys(elems1), JsonPathIndex::UnionKeys(elems2)) => elems1 == elems2,
             (JsonPathIndex::Filter(l1, s1, r1),
                 JsonPathIndex::Filter(l2, s2, r2)) => l1 == l2 && s1 == s2 && r1 == r2,
+            (JsonPathIndex::Logical(e1), JsonPathIndex::Logical(e2)) => e1 == e2,
+            (JsonPathIndex::Union(s1), JsonPathIndex::Union(s2)) => s1 == s2,
             (_, _) => false
         }
     }
 }
 
-impl PartialEq for Operand {
+impl PartialEq for Operand<'_> {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Operand::Static(v1), Operand::Static(v2)) => v1 == v2,
             (Operand::Dynamic(jp1), Operand::Dynamic(jp2)) => jp1 == jp2,
+            (Operand::Function(n1, a1), Operand::Function(n2, a2)) => n1 == n2 && a1 == a2,
+            (Operand::Variable(n1), Operand::Variable(n2)) => n1 == n2,
             (_, _) => false
         }
     }
 }
 
+
+impl Eq for JsonPath<'_> {}
+
+impl Eq for JsonPathIndex<'_> {}
+
+impl Eq for Operand<'_> {}
+
+impl Hash for JsonPath<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        discriminant(self).hash(state);
+        match self {
+            JsonPath::Field(key) | JsonPath::Descent(key) => key.hash(state),
+            JsonPath::Chain(elems) => elems.hash(state),
+            JsonPath::Index(idx) => idx.hash(state),
+            JsonPath::Current(tail) => tail.hash(state),
+            JsonPath::Root | JsonPath::Wildcard | JsonPath::Descendants | JsonPath::Empty => (),
+        }
+    }
+}
+
+impl Hash for JsonPathIndex<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        discriminant(self).hash(state);
+        match self {
+            JsonPathIndex::Single(v) => v.hash(state),
+            JsonPathIndex::UnionIndex(elems) => elems.hash(state),
+            JsonPathIndex::UnionKeys(keys) => keys.hash(state),
+            JsonPathIndex::Slice(s, e, st) => (s, e, st).hash(state),
+            JsonPathIndex::Filter(l, op, r) => (l, op, r).hash(state),
+            JsonPathIndex::Logical(expr) => expr.hash(state),
+            JsonPathIndex::Union(sels) => sels.hash(state),
+        }
+    }
+}
+
+impl Hash for Operand<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        discriminant(self).hash(state);
+        match self {
+            Operand::Static(v) => v.hash(state),
+            Operand::Dynamic(jp) => jp.hash(state),
+            Operand::Function(name, args) => (name, args).hash(state),
+            Operand::Variable(name) => name.hash(state),
+        }
+    }
+}
+
+/// The structural order of the paths, so they can be the keys of the ordered maps.
+/// The variants go in the order they are declared.
+impl Ord for JsonPath<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        fn rank(p: &JsonPath) -> u8 {
+            match p {
+                JsonPath::Root => 0,
+                JsonPath::Field(_) => 1,
+                JsonPath::Chain(_) => 2,
+                JsonPath::Descent(_) => 3,
+                JsonPath::Index(_) => 4,
+                JsonPath::Current(_) => 5,
+                JsonPath::Wildcard => 6,
+                JsonPath::Descendants => 7,
+                JsonPath::Empty => 8,
+            }
+        }
+        match (self, other) {
+            (JsonPath::Field(k1), JsonPath::Field(k2)) | (JsonPath::Descent(k1), JsonPath::Descent(k2)) => k1.cmp(k2),
+            (JsonPath::Chain(ch1), JsonPath::Chain(ch2)) => ch1.cmp(ch2),
+            (JsonPath::Index(idx1), JsonPath::Index(idx2)) => idx1.cmp(idx2),
+            (JsonPath::Current(jp1), JsonPath::Current(jp2)) => jp1.cmp(jp2),
+            (l, r) => rank(l).cmp(&rank(r)),
+        }
+    }
+}
+
+impl PartialOrd for JsonPath<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for JsonPathIndex<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        fn rank(idx: &JsonPathIndex) -> u8 {
+            match idx {
+                JsonPathIndex::Single(_) => 0,
+                JsonPathIndex::UnionIndex(_) => 1,
+                JsonPathIndex::UnionKeys(_) => 2,
+                JsonPathIndex::Slice(..) => 3,
+                JsonPathIndex::Filter(..) => 4,
+                JsonPathIndex::Logical(_) => 5,
+                JsonPathIndex::Union(_) => 6,
+            }
+        }
+        match (self, other) {
+            (JsonPathIndex::Single(v1), JsonPathIndex::Single(v2)) => cmp_values(v1, v2),
+            (JsonPathIndex::UnionIndex(e1), JsonPathIndex::UnionIndex(e2)) => cmp_lists(e1, e2),
+            (JsonPathIndex::UnionKeys(k1), JsonPathIndex::UnionKeys(k2)) => k1.cmp(k2),
+            (JsonPathIndex::Slice(s1, e1, st1), JsonPathIndex::Slice(s2, e2, st2)) => (s1, e1, st1).cmp(&(s2, e2, st2)),
+            (JsonPathIndex::Filter(l1, op1, r1), JsonPathIndex::Filter(l2, op2, r2)) =>
+                l1.cmp(l2).then_with(|| op1.cmp(op2)).then_with(|| r1.cmp(r2)),
+            (JsonPathIndex::Logical(e1), JsonPathIndex::Logical(e2)) => e1.cmp(e2),
+            (JsonPathIndex::Union(s1), JsonPathIndex::Union(s2)) => s1.cmp(s2),
+            (l, r) => rank(l).cmp(&rank(r)),
+        }
+    }
+}
+
+impl PartialOrd for JsonPathIndex<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Operand<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        fn rank(op: &Operand) -> u8 {
+            match op {
+                Operand::Static(_) => 0,
+                Operand::Dynamic(_) => 1,
+                Operand::Function(..) => 2,
+                Operand::Variable(_) => 3,
+            }
+        }
+        match (self, other) {
+            (Operand::Static(v1), Operand::Static(v2)) => cmp_values(v1, v2),
+            (Operand::Dynamic(jp1), Operand::Dynamic(jp2)) => jp1.cmp(jp2),
+            (Operand::Function(n1, a1), Operand::Function(n2, a2)) => n1.cmp(n2).then_with(|| a1.cmp(a2)),
+            (Operand::Variable(n1), Operand::Variable(n2)) => n1.cmp(n2),
+            (l, r) => rank(l).cmp(&rank(r)),
+        }
+    }
+}
+
+impl PartialOrd for Operand<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// the total order of the json values agreeing with their equality:
+/// null, booleans, numbers, strings, arrays and objects go one after another
+/// and the numbers equal by value but written differently(`1` and `1.0`) are ordered by the text
+pub(crate) fn cmp_values(left: &Value, right: &Value) -> Ordering {
+    fn rank(v: &Value) -> u8 {
+        match v {
+            Value::Null => 0,
+            Value::Bool(_) => 1,
+            Value::Number(_) => 2,
+            Value::String(_) => 3,
+            Value::Array(_) => 4,
+            Value::Object(_) => 5,
+        }
+    }
+    match (left, right) {
+        (Value::Bool(b1), Value::Bool(b2)) => b1.cmp(b2),
+        (Value::Number(n1), Value::Number(n2)) => cmp_numbers(n1, n2).unwrap_or(Ordering::Equal)
+            .then_with(|| if n1 == n2 { Ordering::Equal } else { n1.to_string().cmp(&n2.to_string()) }),
+        (Value::String(s1), Value::String(s2)) => s1.cmp(s2),
+        (Value::Array(e1), Value::Array(e2)) => cmp_lists(e1, e2),
+        (Value::Object(f1), Value::Object(f2)) => f1.iter().zip(f2.iter())
+            .map(|((k1, v1), (k2, v2))| k1.cmp(k2).then_with(|| cmp_values(v1, v2)))
+            .find(|o| o.is_ne()).unwrap_or_else(|| f1.len().cmp(&f2.len())),
+        (l, r) => rank(l).cmp(&rank(r)),
+    }
+}
+
+fn cmp_lists(left: &[Value], right: &[Value]) -> Ordering {
+    left.iter().zip(right).map(|(l, r)| cmp_values(l, r)).find(|o| o.is_ne()).unwrap_or_else(|| left.len().cmp(&right.len()))
+}
+
+/// Renders the path back into the query, parsing the text gives the equal path.
+/// The form is canonical: the plain names go after the dot and the others in the brackets like `['a b']`,
+/// the strings are single-quoted, the regexes are written as the strings
+/// and the filters have the parentheses only where the precedence needs them.
+impl Display for JsonPath<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonPath::Root => write!(f, "$"),
+            JsonPath::Field(key) => write_key(f, ".", "", key),
+            JsonPath::Descent(key) => write_key(f, "..", "..", key),
+            JsonPath::Chain(elems) => elems.iter().try_for_each(|p| write!(f, "{}", p)),
+            JsonPath::Index(idx) => write!(f, "[{}]", idx),
+            JsonPath::Current(tail) => write!(f, "@{}", tail),
+            JsonPath::Wildcard => write!(f, "[*]"),
+            JsonPath::Descendants => write!(f, ".."),
+            JsonPath::Empty => Ok(()),
+        }
+    }
+}
+
+fn write_key(f: &mut Formatter<'_>, plain: &str, quoted: &str, key: &str) -> std::fmt::Result {
+    if key.starts_with(|c: char| !c.is_ascii_digit()) && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        write!(f, "{}{}", plain, key)
+    } else {
+        write!(f, "{}['{}']", quoted, escape_member_name(key))
+    }
+}
+
+/// the comparison, the existence check is the operand alone
+fn write_atom(f: &mut Formatter<'_>, left: &Operand, sign: &FilterSign, right: &Operand) -> std::fmt::Result {
+    match sign {
+        FilterSign::Exists => write!(f, "{}", left),
+        sign => write!(f, "{} {} {}", left, sign, right),
+    }
+}
+
+/// the literal as it is written in the query, the objects can not be written
+fn write_value(f: &mut Formatter<'_>, v: &Value) -> std::fmt::Result {
+    match v {
+        Value::String(s) => write!(f, "'{}'", escape_member_name(s)),
+        Value::Array(elems) => {
+            write!(f, "[")?;
+            for (i, el) in elems.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ",")?;
+                }
+                write_value(f, el)?;
+            }
+            write!(f, "]")
+        }
+        v => write!(f, "{}", v),
+    }
+}
+
+impl Display for JsonPathIndex<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        fn list<T, W>(f: &mut Formatter<'_>, elems: &[T], mut write: W) -> std::fmt::Result
+            where W: FnMut(&mut Formatter<'_>, &T) -> std::fmt::Result {
+            for (i, el) in elems.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ",")?;
+                }
+                write(f, el)?;
+            }
+            Ok(())
+        }
+        match self {
+            JsonPathIndex::Single(v) => write_value(f, v),
+            JsonPathIndex::UnionIndex(elems) => list(f, elems, write_value),
+            JsonPathIndex::UnionKeys(keys) => list(f, keys, |f, k| write!(f, "'{}'", escape_member_name(k))),
+            JsonPathIndex::Slice(start, end, step) => {
+                if let Some(start) = start {
+                    write!(f, "{}", start)?;
+                }
+                write!(f, ":")?;
+                if let Some(end) = end {
+                    write!(f, "{}", end)?;
+                }
+                if *step != 1 {
+                    write!(f, ":{}", step)?;
+                }
+                Ok(())
+            }
+            JsonPathIndex::Filter(l, sign, r) => {
+                write!(f, "?(")?;
+                write_atom(f, l, sign, r)?;
+                write!(f, ")")
+            }
+            JsonPathIndex::Logical(expr) => write!(f, "?({})", expr),
+            JsonPathIndex::Union(sels) => list(f, sels, |f, sel| match sel {
+                JsonPath::Field(key) => write!(f, "'{}'", escape_member_name(key)),
+                JsonPath::Wildcard => write!(f, "*"),
+                JsonPath::Index(idx) => write!(f, "{}", idx),
+                sel => write!(f, "{}", sel),
+            }),
+        }
+    }
+}
+
+impl Display for FilterExpression<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let grouped = |f: &mut Formatter<'_>, expr: &FilterExpression, parens: bool| {
+            if parens { write!(f, "({})", expr) } else { write!(f, "{}", expr) }
+        };
+        match self {
+            FilterExpression::Atom(l, sign, r) => write_atom(f, l, sign, r),
+            FilterExpression::And(l, r) => {
+                grouped(f, l, matches!(**l, FilterExpression::Or(..)))?;
+                write!(f, " && ")?;
+                grouped(f, r, matches!(**r, FilterExpression::Or(..) | FilterExpression::And(..)))
+            }
+            FilterExpression::Or(l, r) => {
+                write!(f, "{} || ", l)?;
+                grouped(f, r, matches!(**r, FilterExpression::Or(..)))
+            }
+            FilterExpression::Not(expr) => {
+                write!(f, "!")?;
+                grouped(f, expr, matches!(**expr, FilterExpression::And(..) | FilterExpression::Or(..)))
+            }
+        }
+    }
+}
+
+impl Display for Operand<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operand::Static(v) => write_value(f, v),
+            Operand::Dynamic(path) => write!(f, "{}", path),
+            Operand::Function(name, args) => {
+                write!(f, "{}(", name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, ")")
+            }
+            Operand::Variable(name) => write!(f, "${}", name),
+        }
+    }
+}
+
+impl Display for FilterSign {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let sign = match self {
+            FilterSign::Equal => "==",
+            FilterSign::Unequal => "!=",
+            FilterSign::Less => "<",
+            FilterSign::Greater => ">",
+            FilterSign::LeOrEq => "<=",
+            FilterSign::GrOrEq => ">=",
+            FilterSign::Regex => "~=",
+            FilterSign::In => "in",
+            FilterSign::Nin => "nin",
+            FilterSign::Size => "size",
+            FilterSign::NoneOf => "noneOf",
+            FilterSign::AnyOf => "anyOf",
+            FilterSign::SubSetOf => "subsetOf",
+            FilterSign::Exists => "exists",
+        };
+        write!(f, "{}", sign)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+    use serde_json::json;
+    use crate::parser::parser::parse_json_path;
+    use crate::parser::model::{cmp_values, JsonPath, SharedPath};
+
+    #[test]
+    fn keys_test() {
+        let queries = ["$.a", "$.b", "$['a']", "$.a[0]", "$.a[1]", "$.a[?(@.b == 1)]", "$.a[?(@.b == 1.0)]", "$..a", "$.a[*]", "$.a[0:2]"];
+        let paths: Vec<JsonPath> = queries.iter().map(|q| parse_json_path(q).unwrap()).collect();
+
+        let routes: HashMap<&JsonPath, usize> = paths.iter().enumerate().map(|(i, p)| (p, i)).collect();
+        assert_eq!(routes.len(), queries.len() - 1);
+        assert_eq!(routes[&parse_json_path("$.a").unwrap()], 2);
+        assert_eq!(routes[&parse_json_path("$.a[?(@.b == 1.0)]").unwrap()], 6);
+
+        let ordered: BTreeMap<&JsonPath, usize> = paths.iter().enumerate().map(|(i, p)| (p, i)).collect();
+        assert_eq!(ordered.len(), queries.len() - 1);
+        for (p1, p2) in paths.iter().zip(paths.iter().skip(1)) {
+            assert_eq!(p1 == p2, p1.cmp(p2).is_eq());
+            assert_eq!(p1.cmp(p2), p2.cmp(p1).reverse());
+        }
+        assert!(parse_json_path("$.a").unwrap() < parse_json_path("$.b").unwrap());
+    }
+
+    #[test]
+    fn values_order_test() {
+        let values = [json!(null), json!(false), json!(true), json!(-1), json!(1), json!(1.0), json!(2),
+            json!("a"), json!("b"), json!([1]), json!([1, 2]), json!({"a": 1}), json!({"a": 2}), json!({"b": 0})];
+        for (i, l) in values.iter().enumerate() {
+            for (j, r) in values.iter().enumerate() {
+                assert_eq!(cmp_values(l, r), i.cmp(&j), "{} {}", l, r);
+            }
+        }
+    }
+
+    #[test]
+    fn shared_path_test() {
+        let path = SharedPath::parse("$.items[?(@.v > 1)].v").unwrap();
+        let docs = [json!({"items":[{"v":1},{"v":2}]}), json!({"items":[{"v":3}]})];
+        let found: Vec<Vec<serde_json::Value>> = std::thread::scope(|s| {
+            let handles: Vec<_> = docs.iter().map(|doc| {
+                let path = path.clone();
+                assert!(path.ptr_eq(&path.clone()));
+                s.spawn(move || path.find_nodes(doc).values().into_iter().cloned().collect())
+            }).collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(found, vec![vec![json!(2)], vec![json!(3)]]);
+        assert_eq!(*path, parse_json_path("$.items[?(@.v > 1)].v").unwrap());
+        assert!(!path.ptr_eq(&SharedPath::from(parse_json_path("$.items[?(@.v > 1)].v").unwrap())));
+        assert!(SharedPath::parse("$.[").is_err());
+    }
+
+    #[test]
+    fn display_test() {
+        let queries = [
+            "$", "$.a.b_1", "$['a b']['it\\'s']['']", "$..a..['b.c']", "$[*].a[*]", "$.a[0][-1]", "$.a[1,2,-3]", "$['a','b\\n']",
+            "$.a[:]", "$.a[1:]", "$.a[:-2]", "$.a[1:5:2]", "$.a[::-1]", "$.a[?(@)]", "$.a[?(@.b)]", "$.a[?(!@.b)]",
+            "$.a[?(@.b == 'x\\'y')]", "$.a[?(@.b != 1.5)]", "$.a[?(@ > -1e20)]", "$.a[?(@.b == true && @.c == null)]",
+            "$.a[?(@.b in ['x','y'])]", "$.a[?(@.b nin [1,2])]", "$.a[?(@.b size 2)]", "$.a[?(@.b anyOf [])]",
+            "$.a[?(@.b subsetOf ['x'])]", "$.a[?(@.b noneOf [true])]", "$.a[?(@.b ~= '^x')]", "$.a[?(@.b =~ /^x\\/y/i)]",
+            "$.a[?(@.b || @.c && @.d)]", "$.a[?((@.b || @.c) && @.d)]", "$.a[?(@.b || (@.c || @.d))]", "$.a[?(@.b && (@.c && @.d))]",
+            "$.a[?(!(@.b && @.c) || !!@.d)]", "$.a[?(@.b == $.c[0].d)]", "$.a[?(@[0] == @['x y'])]",
+            "$.a[?(length(@.b) > 1 && count(@.*) == match(@.c, 'x'))]", "$.a[?(value(@..c) == 1)]", "$..a[?(@.b)][0].c",
+            "$..[*]", "$.a..[0]..['b','c']", "$..[?(@.b)]", "$['1']['a☺']", "$.a['b',1,2:4,*,?(@.c)]",
+        ];
+        for query in queries {
+            let path = parse_json_path(query).unwrap();
+            let text = path.to_string();
+            assert_eq!(parse_json_path(&text).unwrap(), path, "{} -> {}", query, text);
+            assert_eq!(parse_json_path(&text).unwrap().to_string(), text);
+        }
+        let canonical = |q: &str| parse_json_path(q).unwrap().to_string();
+        assert_eq!(canonical("$.['a'].*..['b'][ 1 ]"), "$.a[*]..b[1]");
+        assert_eq!(canonical("$[?( @.a=~/x/ && !( @.b||@.c ) )]"), "$[?(@.a ~= 'x' && !(@.b || @.c))]");
+        assert_eq!(canonical("$.a[::1]['x-y']"), "$.a[:]['x-y']");
+        assert_eq!(canonical("$..*[?@.a == 1]"), "$..[*][?(@.a == 1)]");
+    }
+
+    #[test]
+    fn from_str_test() -> Result<(), Box<dyn std::error::Error>> {
+        let path: JsonPath = "$.a['b'][?(@.c > 1)]".parse()?;
+        assert_eq!(path, parse_json_path("$.a['b'][?(@.c > 1)]")?);
+        let query = String::from("$.a.b");
+        let borrowed = JsonPath::parse(&query)?;
+        assert!(matches!(&borrowed, JsonPath::Chain(elems) if matches!(&elems[1], JsonPath::Field(std::borrow::Cow::Borrowed("a")))));
+
+        for query in ["", "$.a[", "$[?(@.a >< 1)]", "$[?(length(@.*) > 1)]"] {
+            let e = query.parse::<JsonPath>().unwrap_err();
+            assert!(!e.message().is_empty());
+            assert_eq!(e.to_string(), e.message());
+            assert_eq!(Err(e), JsonPath::parse(query));
+        }
+        let e: Box<dyn std::error::Error> = Box::new(JsonPath::parse("$.[").unwrap_err());
+        assert!(e.to_string().contains("$.["));
+        Ok(())
+    }
+}
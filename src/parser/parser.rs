@@ -1,8 +1,11 @@
+use std::borrow::Cow;
 use pest::iterators::{Pair, Pairs};
 use pest::{Parser};
-use serde_json::{Value,json};
-use crate::parser::model::{JsonPath, JsonPathIndex, Operand, FilterSign};
-use pest::error::{Error};
+use serde_json::Value;
+use crate::parser::model::{JsonPath, JsonPathIndex, Operand, FilterSign, FilterExpression};
+use pest::error::{Error, ErrorVariant};
+use crate::path::functions::{signature, FnType};
+use crate::parser::errors::JsonPathParserError;
 
 #[derive(Parser)]
 #[grammar = "parser/grammar/json_path.pest"]
@@ -10,69 +13,191 @@ struct JsonPathParser;
 
 /// the parsing function.
 /// Since the parsing can finish with error the result is [[Result]]
-pub fn parse_json_path(jp_str: &str) -> Result<JsonPath, Error<Rule>> {
-    Ok(parse_internal(JsonPathParser::parse(Rule::path, jp_str)?.next().unwrap()))
+pub fn parse_json_path(jp_str: &str) -> Result<JsonPath<'_>, JsonPathParserError> {
+    parse(jp_str, false)
+}
+
+/// parses the query following RFC 9535 to the letter:
+/// the query can not be surrounded by the whitespace, the names after the dot can not start with the digits,
+/// the literals are compared rather than standing alone in the filters
+/// and the comparisons take the paths to the single elements
+pub fn parse_json_path_strict(jp_str: &str) -> Result<JsonPath<'_>, JsonPathParserError> {
+    parse(jp_str, true)
+}
+
+fn parse(jp_str: &str, strict: bool) -> Result<JsonPath<'_>, JsonPathParserError> {
+    let rule = if strict { Rule::strict_path } else { Rule::path };
+    let path = JsonPathParser::parse(rule, jp_str)
+        .and_then(|mut pairs| {
+            let path = pairs.next().unwrap();
+            check_functions(path.clone(), strict).map(|_| path)
+        })
+        .map_err(|e| JsonPathParserError::new(e, jp_str))?;
+    Ok(parse_internal(path))
+}
+
+/// checks the function extensions get the arguments of the right types
+/// and the functions giving the values are compared rather than standing alone in the filters,
+/// the strict parsing checks the rest of the rules of RFC 9535 as well
+fn check_functions(rule: Pair<'_, Rule>, strict: bool) -> Result<(), Error<Rule>> {
+    let error = |message: String, span| Error::new_from_span(ErrorVariant::CustomError { message }, span);
+    for pair in rule.into_inner().flatten() {
+        match pair.as_rule() {
+            Rule::function => {
+                let mut pairs = pair.clone().into_inner();
+                let name = pairs.next().unwrap().as_str();
+                // the functions added by the application are resolved when the query is evaluated
+                let sig = match signature(name) {
+                    Some(sig) => sig,
+                    None => continue,
+                };
+                let args: Vec<Pair<'_, Rule>> = pairs.collect();
+                if args.len() != sig.args.len() {
+                    return Err(error(format!("the function '{}' takes {} argument(s), got {}", name, sig.args.len(), args.len()), pair.as_span()));
+                }
+                for (arg, tp) in args.iter().zip(sig.args) {
+                    if !fits(arg, *tp) {
+                        return Err(error(format!("the argument of the function '{}' is not {}", name, describe(*tp)), arg.as_span()));
+                    }
+                }
+            }
+            // the number out of the range of f64 would become null, `arbitrary_precision` keeps it as the text
+            Rule::number if number_to_value(pair.as_str()).is_null() =>
+                return Err(error(String::from("the number is out of the range"), pair.as_span())),
+            Rule::key_lim if strict && pair.as_str().starts_with(|c: char| c.is_ascii_digit()) =>
+                return Err(error(String::from("the name can not start with the digit"), pair.as_span())),
+            Rule::atom => match pair.clone().into_inner().collect::<Vec<_>>().as_slice() {
+                [op] if result_type(op) == Some(FnType::Value) =>
+                    return Err(error(String::from("the function gives the value, it needs to be compared with something"), op.as_span())),
+                [op] if strict && matches!(op.as_rule(), Rule::string_qt | Rule::number | Rule::boolean | Rule::null | Rule::array | Rule::regex) =>
+                    return Err(error(String::from("the literal needs to be compared with something"), op.as_span())),
+                [left, sign, right] if strict && matches!(sign.as_str(), "==" | "!=" | "<" | "<=" | ">" | ">=") => {
+                    if let Some(op) = [left, right].iter().find(|op| op.as_rule() == Rule::chain && !is_singular(op)) {
+                        return Err(error(String::from("the comparison needs the path to the single element"), op.as_span()));
+                    }
+                }
+                _ => ()
+            },
+            _ => ()
+        }
+    }
+    Ok(())
+}
+
+fn describe(tp: FnType) -> &'static str {
+    match tp {
+        FnType::Value => "the single value(the literal, the path to the single element or the function giving the value)",
+        FnType::Nodes => "the path",
+    }
+}
+
+/// the type the function gives if the operand is the function
+fn result_type(op: &Pair<'_, Rule>) -> Option<FnType> {
+    match op.as_rule() {
+        Rule::function => signature(op.clone().into_inner().next()?.as_str()).map(|s| s.result),
+        _ => None
+    }
+}
+
+/// checks whether the operand can be the argument of the given type
+fn fits(op: &Pair<'_, Rule>, tp: FnType) -> bool {
+    match (op.as_rule(), tp) {
+        (Rule::function, _) => result_type(op) == Some(tp),
+        (Rule::chain, FnType::Value) => is_singular(op),
+        (Rule::chain, FnType::Nodes) => true,
+        (_, FnType::Value) => true,
+        (_, FnType::Nodes) => false,
+    }
+}
+
+/// checks whether the path can find one element at most, i.e. it consists of the fields and the single indexes
+fn is_singular(chain: &Pair<'_, Rule>) -> bool {
+    chain.clone().into_inner().all(|segment| match segment.as_rule() {
+        Rule::root | Rule::field => true,
+        Rule::current => segment.into_inner().next().map(|chain| is_singular(&chain)).unwrap_or(true),
+        Rule::index => {
+            let mut selectors = segment.into_inner();
+            matches!((selectors.next().map(|s| s.as_rule()), selectors.next()), (Some(Rule::signed), None))
+        }
+        _ => false
+    })
 }
 
 /// Internal function takes care of the logic by parsing the operators and unrolling the string into the final result.
-fn parse_internal(rule: Pair<Rule>) -> JsonPath {
+fn parse_internal(rule: Pair<'_, Rule>) -> JsonPath<'_> {
     match rule.as_rule() {
-        Rule::path => rule.into_inner().next().map(parse_internal).unwrap_or(JsonPath::Empty),
+        Rule::path | Rule::strict_path => rule.into_inner().next().map(parse_internal).unwrap_or(JsonPath::Empty),
         Rule::current => JsonPath::Current(Box::new(rule.into_inner().next().map(parse_internal).unwrap_or(JsonPath::Empty))),
-        Rule::chain => JsonPath::Chain(rule.into_inner().map(parse_internal).collect()),
+        Rule::chain => JsonPath::Chain(rule.into_inner().flat_map(parse_segment).collect()),
         Rule::root => JsonPath::Root,
         Rule::wildcard => JsonPath::Wildcard,
+        Rule::descendants => JsonPath::Descendants,
         Rule::descent => parse_key(down(rule)).map(JsonPath::Descent).unwrap_or(JsonPath::Empty),
         Rule::field => parse_key(down(rule)).map(JsonPath::Field).unwrap_or(JsonPath::Empty),
-        Rule::index => JsonPath::Index(parse_index(rule)),
+        Rule::index => parse_index(rule),
         _ => JsonPath::Empty
     }
 }
 
+/// the segment of the chain, `..*` turns into the descendants followed by the wildcard
+fn parse_segment(rule: Pair<'_, Rule>) -> Vec<JsonPath<'_>> {
+    match rule.as_rule() {
+        Rule::descendants if rule.as_str().ends_with('*') => vec![JsonPath::Descendants, JsonPath::Wildcard],
+        _ => vec![parse_internal(rule)],
+    }
+}
+
 /// parsing the rule 'key' with the structures either .key or .]'key'[
-fn parse_key(rule: Pair<Rule>) -> Option<String> {
+fn parse_key(rule: Pair<'_, Rule>) -> Option<Cow<'_, str>> {
     match rule.as_rule() {
         Rule::key
         | Rule::key_unlim
         | Rule::string_qt => parse_key(down(rule)),
-        Rule::key_lim
-        | Rule::inner => Some(String::from(rule.as_str())),
+        Rule::key_lim => Some(Cow::Borrowed(rule.as_str())),
+        Rule::inner | Rule::inner_dq => Some(unescape_cow(rule.as_str())),
         _ => None
     }
 }
 
-fn parse_slice(mut pairs: Pairs<Rule>) -> JsonPathIndex {
-    let mut start = 0;
-    let mut end = 0;
+fn parse_slice(mut pairs: Pairs<'_, Rule>) -> JsonPathIndex<'static> {
+    let mut start = None;
+    let mut end = None;
     let mut step = 1;
     while pairs.peek().is_some() {
         let in_pair = pairs.next().unwrap();
         match in_pair.as_rule() {
-            Rule::start_slice => start = in_pair.as_str().parse::<i32>().unwrap_or(start),
-            Rule::end_slice => end = in_pair.as_str().parse::<i32>().unwrap_or(end),
-            Rule::step_slice => step = down(in_pair).as_str().parse::<usize>().unwrap_or(step),
+            Rule::start_slice => start = Some(slice_bound(in_pair.as_str())),
+            Rule::end_slice => end = Some(slice_bound(in_pair.as_str())),
+            Rule::step_slice => step = slice_bound(down(in_pair).as_str()),
             _ => ()
         }
     }
     JsonPathIndex::Slice(start, end, step)
 }
 
-fn parse_unit_keys(mut pairs: Pairs<Rule>) -> JsonPathIndex {
+/// the bounds and steps beyond the range are clamped to it selecting the same elements
+fn slice_bound(bound: &str) -> i64 {
+    bound.parse::<i64>().unwrap_or(if bound.starts_with('-') { i64::MIN } else { i64::MAX })
+}
+
+fn parse_unit_keys(mut pairs: Pairs<'_, Rule>) -> JsonPathIndex<'_> {
     let mut keys = vec![];
 
     while pairs.peek().is_some() {
-        keys.push(String::from(down(pairs.next().unwrap()).as_str()));
+        keys.push(unescape_cow(down(pairs.next().unwrap()).as_str()));
     }
     JsonPathIndex::UnionKeys(keys)
 }
 
 fn number_to_value(number: &str) -> Value {
+    // serde keeps the big numbers exactly if `arbitrary_precision` is on
     number.parse::<i64>().ok().map(Value::from)
+        .or_else(|| serde_json::from_str(number).ok())
         .or_else(|| number.parse::<f64>().ok().map(Value::from))
         .unwrap()
 }
 
-fn parse_unit_indexes(mut pairs: Pairs<Rule>) -> JsonPathIndex {
+fn parse_unit_indexes(mut pairs: Pairs<'_, Rule>) -> JsonPathIndex<'static> {
     let mut keys = vec![];
 
     while pairs.peek().is_some() {
@@ -81,14 +206,14 @@ fn parse_unit_indexes(mut pairs: Pairs<Rule>) -> JsonPathIndex {
     JsonPathIndex::UnionIndex(keys)
 }
 
-fn parse_chain_in_operand(rule: Pair<Rule>) -> Operand {
+fn parse_chain_in_operand(rule: Pair<'_, Rule>) -> Operand<'_> {
     match parse_internal(rule) {
         JsonPath::Chain(elems) => {
             if elems.len() == 1 {
                 match elems.first() {
-                    Some(JsonPath::Index(JsonPathIndex::UnionKeys(keys))) => Operand::val(Value::from(keys.clone())),
+                    Some(JsonPath::Index(JsonPathIndex::UnionKeys(keys))) => Operand::val(keys.iter().map(|k| Value::from(k.as_ref())).collect()),
                     Some(JsonPath::Index(JsonPathIndex::UnionIndex(keys))) => Operand::val(Value::from(keys.clone())),
-                    Some(JsonPath::Field(f)) => Operand::val(Value::Array(vec![Value::from(f.clone())])),
+                    Some(JsonPath::Field(f)) => Operand::val(Value::Array(vec![Value::from(f.as_ref())])),
                     _ => Operand::Dynamic(Box::new(JsonPath::Chain(elems)))
                 }
             } else {
@@ -99,53 +224,160 @@ fn parse_chain_in_operand(rule: Pair<Rule>) -> Operand {
     }
 }
 
-fn parse_operand(rule: Pair<Rule>) -> Operand {
+fn parse_operand(rule: Pair<'_, Rule>) -> Operand<'_> {
     match rule.as_rule() {
         Rule::number => Operand::Static(number_to_value(rule.as_str())),
-        Rule::string_qt => Operand::Static(Value::from(down(rule).as_str())),
+        Rule::string_qt => Operand::Static(Value::from(unescape(down(rule).as_str()))),
+        Rule::boolean => Operand::Static(Value::Bool(rule.as_str() == "true")),
+        Rule::null => Operand::Static(Value::Null),
+        Rule::regex => Operand::Static(Value::from(parse_regex(rule.into_inner()))),
+        Rule::array => Operand::Static(Value::Array(rule.into_inner().map(|el| match parse_operand(el) {
+            Operand::Static(v) => v,
+            _ => Value::Null,
+        }).collect())),
         Rule::chain => parse_chain_in_operand(rule),
+        Rule::variable => Operand::Variable(Cow::Borrowed(&rule.as_str()[1..])),
+        Rule::function => {
+            let mut pairs = rule.into_inner();
+            let name = pairs.next().map(|p| p.as_str()).unwrap_or_default();
+            Operand::Function(Cow::Borrowed(name), pairs.map(parse_operand).collect())
+        }
         _ => Operand::Static(Value::Null)
     }
 }
 
-fn parse_filter_index(mut pairs: Pairs<Rule>) -> JsonPathIndex {
-    let left: Operand = parse_operand(pairs.next().unwrap());
-    if pairs.peek().is_none() {
-        JsonPathIndex::exists(left)
+/// turns the regex literal like `/^a.*/i` into the pattern, the flags go inline like `(?i)^a.*`
+/// and `\/` stands for the slash
+fn parse_regex(mut pairs: Pairs<'_, Rule>) -> String {
+    let body = pairs.next().map(|p| p.as_str().replace("\\/", "/")).unwrap_or_default();
+    match pairs.next().map(|p| p.as_str()) {
+        Some(flags) if !flags.is_empty() => format!("(?{}){}", flags, body),
+        _ => body
+    }
+}
+
+fn parse_filter_index(mut pairs: Pairs<'_, Rule>) -> JsonPathIndex<'_> {
+    match pairs.next().map(parse_logic) {
+        Some(FilterExpression::Atom(left, sign, right)) => JsonPathIndex::Filter(left, sign, right),
+        Some(expr) => JsonPathIndex::Logical(expr),
+        None => JsonPathIndex::exists(Operand::Dynamic(Box::new(JsonPath::Empty))),
+    }
+}
+
+/// parsing the logical expression of the filter, the single comparison turns into [FilterExpression::Atom]
+fn parse_logic(rule: Pair<'_, Rule>) -> FilterExpression<'_> {
+    match rule.as_rule() {
+        Rule::logic_or => rule.into_inner().map(parse_logic).reduce(FilterExpression::or).unwrap(),
+        Rule::logic_and => rule.into_inner().map(parse_logic).reduce(FilterExpression::and).unwrap(),
+        Rule::logic_not => {
+            let mut pairs: Vec<Pair<'_, Rule>> = rule.into_inner().collect();
+            let expr = parse_logic(pairs.pop().unwrap());
+            pairs.iter().fold(expr, |expr, _| !expr)
+        }
+        _ => {
+            let mut pairs = rule.into_inner();
+            let left = parse_operand(pairs.next().unwrap());
+            match pairs.next() {
+                Some(sign) => FilterExpression::Atom(left, FilterSign::new(sign.as_str()), parse_operand(pairs.next().unwrap())),
+                None => FilterExpression::Atom(left, FilterSign::Exists, Operand::Dynamic(Box::new(JsonPath::Empty))),
+            }
+        }
+    }
+}
+
+/// the brackets with the selectors, the several selectors of the different kinds turn into [JsonPathIndex::Union]
+fn parse_index(rule: Pair<'_, Rule>) -> JsonPath<'_> {
+    let mut selectors: Vec<JsonPath<'_>> = rule.into_inner().map(parse_selector).collect();
+    if selectors.len() == 1 {
+        selectors.remove(0)
     } else {
-        let sign: FilterSign = FilterSign::new(pairs.next().unwrap().as_str());
-        let right: Operand = parse_operand(pairs.next().unwrap());
-        JsonPathIndex::Filter(left, sign, right)
+        JsonPath::Index(JsonPathIndex::Union(selectors))
     }
 }
 
-fn parse_index(rule: Pair<Rule>) -> JsonPathIndex {
-    let next = down(rule);
-    match next.as_rule() {
-        Rule::unsigned => JsonPathIndex::Single(number_to_value(next.as_str())),
-        Rule::slice => parse_slice(next.into_inner()),
-        Rule::unit_indexes => parse_unit_indexes(next.into_inner()),
-        Rule::unit_keys => parse_unit_keys(next.into_inner()),
-        Rule::filter => parse_filter_index(next.into_inner()),
-        _ => JsonPathIndex::Single(number_to_value(next.as_str()))
+fn parse_selector(rule: Pair<'_, Rule>) -> JsonPath<'_> {
+    match rule.as_rule() {
+        Rule::string_qt => parse_key(rule).map(JsonPath::Field).unwrap_or(JsonPath::Empty),
+        Rule::star => JsonPath::Wildcard,
+        Rule::slice => JsonPath::Index(parse_slice(rule.into_inner())),
+        Rule::unit_indexes => JsonPath::Index(parse_unit_indexes(rule.into_inner())),
+        Rule::unit_keys => JsonPath::Index(parse_unit_keys(rule.into_inner())),
+        Rule::filter => JsonPath::Index(parse_filter_index(rule.into_inner())),
+        _ => JsonPath::Index(JsonPathIndex::Single(number_to_value(rule.as_str())))
     }
 }
 
-fn down(rule: Pair<Rule>) -> Pair<Rule> {
+fn down(rule: Pair<'_, Rule>) -> Pair<'_, Rule> {
     rule.into_inner().next().unwrap()
 }
 
+/// unescapes the content of the quoted string.
+/// The invalid escape sequences(like the lone surrogates) are replaced with U+FFFD.
+pub(crate) fn unescape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    let mut chars = s.char_indices();
+    while let Some((_, c)) = chars.next() {
+        if c == '\\' {
+            res.push(unescape_char(&mut chars).unwrap_or(char::REPLACEMENT_CHARACTER))
+        } else {
+            res.push(c)
+        }
+    }
+    res
+}
+
+/// unescapes the content of the quoted string borrowing it if there is nothing to unescape
+fn unescape_cow(s: &str) -> Cow<'_, str> {
+    if s.contains('\\') { Cow::Owned(unescape(s)) } else { Cow::Borrowed(s) }
+}
+
+/// unescapes the character following the backslash taking the surrogate pairs into account
+pub(crate) fn unescape_char<I: Iterator<Item=(usize, char)>>(chars: &mut I) -> Option<char> {
+    fn hex<I: Iterator<Item=(usize, char)>>(chars: &mut I) -> Option<u32> {
+        (0..4).try_fold(0, |acc, _| chars.next().and_then(|(_, c)| c.to_digit(16)).map(|d| acc * 16 + d))
+    }
+    match chars.next()?.1 {
+        'b' => Some('\u{8}'),
+        'f' => Some('\u{c}'),
+        'n' => Some('\n'),
+        'r' => Some('\r'),
+        't' => Some('\t'),
+        c @ ('/' | '\\' | '\'' | '"') => Some(c),
+        'u' => {
+            let high = hex(chars)?;
+            if (0xD800..0xDC00).contains(&high) {
+                match (chars.next(), chars.next()) {
+                    (Some((_, '\\')), Some((_, 'u'))) => {
+                        let low = hex(chars)?;
+                        if (0xDC00..0xE000).contains(&low) {
+                            char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+                        } else {
+                            None
+                        }
+                    }
+                    _ => None
+                }
+            } else {
+                char::from_u32(high)
+            }
+        }
+        _ => None
+    }
+}
+
+
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
     use std::panic;
     use crate::parser::model::JsonPath::{Chain, Current, Field, Descent, Wildcard};
 
     fn test_failed(input: &str) {
         match parse_json_path(input) {
             Ok(elem) => panic!("should be false but got {:?}", elem),
-            Err(e) => println!("{}", e.to_string())
+            Err(e) => println!("{}", e)
         }
     }
 
@@ -154,7 +386,7 @@ mod tests {
             Ok(JsonPath::Chain(elems)) => assert_eq!(elems, expected),
             Ok(e) => panic!("unexpected value {:?}", e),
             Err(e) => {
-                println!("{}", e.to_string());
+                println!("{}", e);
                 panic!("parsing error");
             }
         }
@@ -175,20 +407,65 @@ mod tests {
                  JsonPath::Wildcard,
                  JsonPath::Index(JsonPathIndex::Single(json!(1))),
                  JsonPath::Index(JsonPathIndex::UnionIndex(vec![json!(1), json!(2)])),
-                 JsonPath::Index(JsonPathIndex::UnionKeys(vec!["k".to_string(), "k".to_string()])),
-                 JsonPath::Index(JsonPathIndex::Slice(0, 0, 1)),
-                 JsonPath::Index(JsonPathIndex::Slice(10, 0, 1)),
-                 JsonPath::Index(JsonPathIndex::Slice(0, 10, 1)),
-                 JsonPath::Index(JsonPathIndex::Slice(10, 10, 10)),
+                 JsonPath::Index(JsonPathIndex::UnionKeys(vec!["k".into(), "k".into()])),
+                 JsonPath::Index(JsonPathIndex::Slice(None, None, 1)),
+                 JsonPath::Index(JsonPathIndex::Slice(Some(10), None, 1)),
+                 JsonPath::Index(JsonPathIndex::Slice(None, Some(10), 1)),
+                 JsonPath::Index(JsonPathIndex::Slice(Some(10), Some(10), 10)),
                  JsonPath::Index(JsonPathIndex::Filter(Operand::path(Chain(vec![JsonPath::Current(Box::new(JsonPath::Empty))])), FilterSign::Exists, Operand::path(JsonPath::Empty))),
                  JsonPath::Index(JsonPathIndex::Filter(Operand::path(Chain(vec![JsonPath::Current(Box::new(Chain(vec![JsonPath::field("abc")])))])), FilterSign::GrOrEq, Operand::val(json!(10)))),
              ])
     }
 
+    #[test]
+    fn borrowed_keys_test() {
+        let query = String::from("$.a['b']..c[?(@.d == 1)]['e','f\\'g']");
+        let borrowed = |k: &Cow<str>| matches!(k, Cow::Borrowed(_));
+        match parse_json_path(&query).unwrap() {
+            Chain(elems) => {
+                assert!(matches!(&elems[1], Field(k) if borrowed(k)));
+                assert!(matches!(&elems[2], Field(k) if borrowed(k)));
+                assert!(matches!(&elems[3], Descent(k) if borrowed(k)));
+                match &elems[5] {
+                    JsonPath::Index(JsonPathIndex::UnionKeys(keys)) => {
+                        assert!(borrowed(&keys[0]));
+                        assert!(!borrowed(&keys[1]));
+                        assert_eq!(keys[1], "f'g");
+                    }
+                    other => panic!("unexpected {:?}", other)
+                }
+            }
+            other => panic!("unexpected {:?}", other)
+        }
+    }
+
+    #[test]
+    fn comments_test() {
+        test("$.a # the comment\n\t.b // the other comment\r\n[1]",
+             vec![JsonPath::Root, JsonPath::field("a"), JsonPath::field("b"), JsonPath::Index(JsonPathIndex::Single(json!(1)))]);
+        test("// the header\n$.['a#b'].c#d",
+             vec![JsonPath::Root, JsonPath::field("a#b"), JsonPath::field("c#d")]);
+        test("$.a[?(\n  @.b >= 10 # lower bound\n)]",
+             vec![JsonPath::Root, JsonPath::field("a"),
+                  JsonPath::Index(JsonPathIndex::Filter(Operand::path(Chain(vec![JsonPath::Current(Box::new(Chain(vec![JsonPath::field("b")])))])), FilterSign::GrOrEq, Operand::val(json!(10))))]);
+        test("$.a # the comment .b", vec![JsonPath::Root, JsonPath::field("a")]);
+        test("$.a # the comment\n", vec![JsonPath::Root, JsonPath::field("a")]);
+        test(" $.a\n", vec![JsonPath::Root, JsonPath::field("a")]);
+        test("$.a ", vec![JsonPath::Root, JsonPath::field("a")]);
+        // RFC 9535 does not allow the whitespace around the query
+        assert!(parse_json_path_strict(" $.a").is_err());
+        assert!(parse_json_path_strict("$.a\n").is_err());
+        assert!(parse_json_path_strict("$.a # the comment\n").is_err());
+        assert!(parse_json_path_strict("$.a # the comment\n.b").is_ok());
+        test_failed("$.a[1 # the comment ]");
+    }
+
     #[test]
     fn descent_test() {
         test("..abc", vec![JsonPath::descent("abc")]);
         test("..['abc']", vec![JsonPath::descent("abc")]);
+        test("..[0]", vec![JsonPath::Descendants, JsonPath::Index(JsonPathIndex::Single(json!(0)))]);
+        test("..['a','b']", vec![JsonPath::Descendants, JsonPath::Index(JsonPathIndex::UnionKeys(vec!["a".into(), "b".into()]))]);
         test_failed("...['abc']");
         test_failed("...abc");
     }
@@ -198,7 +475,21 @@ mod tests {
         test(".abc", vec![JsonPath::field("abc")]);
         test(".['abc']", vec![JsonPath::field("abc")]);
         test("['abc']", vec![JsonPath::field("abc")]);
-        test(".['abc\\\"abc']", vec![JsonPath::field("abc\\\"abc")]);
+        test(".['abc\\\"abc']", vec![JsonPath::field("abc\"abc")]);
+        test(".['abc\"abc']", vec![JsonPath::field("abc\"abc")]);
+        test(".['it\\'s\\\\\\n\\u00e9\\ud83d\\ude00']", vec![JsonPath::field("it's\\\n\u{e9}\u{1F600}")]);
+        test("['a','\\'b']", vec![JsonPath::Index(JsonPathIndex::UnionKeys(vec!["a".into(), "'b".into()]))]);
+        test("[\"weird-key!\"]", vec![JsonPath::field("weird-key!")]);
+        test("..[\"a.b[c]\"]", vec![JsonPath::descent("a.b[c]")]);
+        test("[\"it's \\\"quoted\\\"\"]", vec![JsonPath::field("it's \"quoted\"")]);
+        test(".['key with spaces']", vec![JsonPath::field("key with spaces")]);
+        test(".☺.a1", vec![JsonPath::field("☺"), JsonPath::field("a1")]);
+        test(".1", vec![JsonPath::field("1")]);
+        test("..1a", vec![JsonPath::descent("1a")]);
+        assert!(parse_json_path_strict("$.1").unwrap_err().to_string().contains("the name can not start with the digit"));
+        assert!(parse_json_path_strict("$..1a").is_err());
+        assert!(parse_json_path_strict("$.a1").is_ok());
+        test_failed("[\"abc']");
         test_failed(".abc()abc");
         test_failed("..[abc]");
         test_failed(".'abc'");
@@ -211,36 +502,70 @@ mod tests {
         test(".abc.*", vec![JsonPath::field("abc"), JsonPath::Wildcard]);
         test(".abc.[*]", vec![JsonPath::field("abc"), JsonPath::Wildcard]);
         test(".abc[*]", vec![JsonPath::field("abc"), JsonPath::Wildcard]);
-        test_failed("..*");
+        test("..*", vec![JsonPath::Descendants, JsonPath::Wildcard]);
+        test("..[*]", vec![JsonPath::Descendants, JsonPath::Wildcard]);
         test_failed("abc*");
     }
 
     #[test]
     fn index_single_test() {
         test("[1]", vec![JsonPath::Index(JsonPathIndex::Single(json!(1)))]);
-        test_failed("[-1]");
+        test("[-1]", vec![JsonPath::Index(JsonPathIndex::Single(json!(-1)))]);
+        test_failed("[-]");
         test_failed("[1a]");
     }
 
     #[test]
     fn index_slice_test() {
-        test("[1:1000:10]", vec![JsonPath::Index(JsonPathIndex::Slice(1, 1000, 10))]);
-        test("[:1000:10]", vec![JsonPath::Index(JsonPathIndex::Slice(0, 1000, 10))]);
-        test("[:1000]", vec![JsonPath::Index(JsonPathIndex::Slice(0, 1000, 1))]);
-        test("[:]", vec![JsonPath::Index(JsonPathIndex::Slice(0, 0, 1))]);
-        test("[::10]", vec![JsonPath::Index(JsonPathIndex::Slice(0, 0, 10))]);
-        test_failed("[::-1]");
+        test("[1:1000:10]", vec![JsonPath::Index(JsonPathIndex::Slice(Some(1), Some(1000), 10))]);
+        test("[:1000:10]", vec![JsonPath::Index(JsonPathIndex::Slice(None, Some(1000), 10))]);
+        test("[:1000]", vec![JsonPath::Index(JsonPathIndex::Slice(None, Some(1000), 1))]);
+        test("[:]", vec![JsonPath::Index(JsonPathIndex::Slice(None, None, 1))]);
+        test("[::10]", vec![JsonPath::Index(JsonPathIndex::Slice(None, None, 10))]);
+        test("[2:]", vec![JsonPath::Index(JsonPathIndex::Slice(Some(2), None, 1))]);
+        test("[0:0]", vec![JsonPath::Index(JsonPathIndex::Slice(Some(0), Some(0), 1))]);
+        test("[-3000000000:5000000000]", vec![JsonPath::Index(JsonPathIndex::Slice(Some(-3000000000), Some(5000000000), 1))]);
+        test("[-99999999999999999999:99999999999999999999]", vec![JsonPath::Index(JsonPathIndex::Slice(Some(i64::MIN), Some(i64::MAX), 1))]);
+        test("[::-1]", vec![JsonPath::Index(JsonPathIndex::Slice(None, None, -1))]);
+        test("[5:1:-2]", vec![JsonPath::Index(JsonPathIndex::Slice(Some(5), Some(1), -2))]);
+        test("[::-99999999999999999999]", vec![JsonPath::Index(JsonPathIndex::Slice(None, None, i64::MIN))]);
         test_failed("[:::0]");
+        test_failed("[::-]");
     }
 
     #[test]
     fn index_union_test() {
         test("[1,2,3]", vec![JsonPath::Index(JsonPathIndex::UnionIndex(vec![json!(1), json!(2), json!(3)]))]);
-        test("['abc','bcd']", vec![JsonPath::Index(JsonPathIndex::UnionKeys(vec![String::from("abc"), String::from("bcd")]))]);
+        test("['abc','bcd']", vec![JsonPath::Index(JsonPathIndex::UnionKeys(vec!["abc".into(), "bcd".into()]))]);
         test_failed("[]");
         test("[-1,-2]", vec![JsonPath::Index(JsonPathIndex::UnionIndex(vec![json!(-1), json!(-2)]))]);
         test_failed("[abc,bcd]");
-        test_failed("[\"abc\",\"bcd\"]");
+        test("[\"abc\",'bcd']", vec![JsonPath::Index(JsonPathIndex::UnionKeys(vec!["abc".into(), "bcd".into()]))]);
+        test("['a',1,2:,*,?@.b]", vec![JsonPath::Index(JsonPathIndex::Union(vec![
+            JsonPath::field("a"),
+            JsonPath::Index(JsonPathIndex::Single(json!(1))),
+            JsonPath::Index(JsonPathIndex::Slice(Some(2), None, 1)),
+            JsonPath::Wildcard,
+            JsonPath::Index(JsonPathIndex::exists(Operand::path(Chain(vec![Current(Box::new(Chain(vec![JsonPath::field("b")])))])))),
+        ]))]);
+        test("[1, 2, 3:]", vec![JsonPath::Index(JsonPathIndex::Union(vec![
+            JsonPath::Index(JsonPathIndex::Single(json!(1))),
+            JsonPath::Index(JsonPathIndex::Single(json!(2))),
+            JsonPath::Index(JsonPathIndex::Slice(Some(3), None, 1)),
+        ]))]);
+        test_failed("[1,]");
+        test_failed("[*,]");
+    }
+
+    #[test]
+    #[cfg(not(feature = "arbitrary_precision"))]
+    fn number_out_of_range_test() {
+        test_failed("[1,1e400]");
+        let error = |q| parse_json_path(q).unwrap_err().to_string();
+        assert!(error("$[?(@.a == 1e400)]").contains("the number is out of the range"));
+        assert!(error("$[?(@.a > -1e400)]").contains("the number is out of the range"));
+        assert!(parse_json_path("$[?(@.a == 1e308)]").is_ok());
+        assert!(parse_json_path("$[?(@.a == 1e-400)]").is_ok());
     }
 
     #[test]
@@ -255,19 +580,34 @@ mod tests {
             FilterSign::Equal,
             Operand::val(json!(1)),
         ))]);
+        test("[?(@.a != true)]", vec![JsonPath::Index(JsonPathIndex::Filter(
+            Operand::Dynamic(Box::new(Chain(vec![Current(Box::new(Chain(vec![Field("a".into())])))]))),
+            FilterSign::Unequal,
+            Operand::val(json!(true)),
+        ))]);
+        test("[?@.a != true]", vec![JsonPath::Index(JsonPathIndex::Filter(
+            Operand::Dynamic(Box::new(Chain(vec![Current(Box::new(Chain(vec![Field("a".into())])))]))),
+            FilterSign::Unequal,
+            Operand::val(json!(true)),
+        ))]);
+        test("[?(null == false)]", vec![JsonPath::Index(JsonPathIndex::Filter(
+            Operand::val(json!(null)),
+            FilterSign::Equal,
+            Operand::val(json!(false)),
+        ))]);
 
         test("[?(@.abc in ['abc','bcd'])]", vec![JsonPath::Index(JsonPathIndex::Filter(
-            Operand::Dynamic(Box::new(Chain(vec![Current(Box::new(Chain(vec![Field(String::from("abc"))])))]))),
+            Operand::Dynamic(Box::new(Chain(vec![Current(Box::new(Chain(vec![Field("abc".into())])))]))),
             FilterSign::In,
             Operand::val(json!(["abc","bcd"])),
         ))]);
         test("[?(@.abc.[*] in ['abc','bcd'])]", vec![JsonPath::Index(JsonPathIndex::Filter(
-            Operand::Dynamic(Box::new(Chain(vec![Current(Box::new(Chain(vec![Field(String::from("abc")), Wildcard])))]))),
+            Operand::Dynamic(Box::new(Chain(vec![Current(Box::new(Chain(vec![Field("abc".into()), Wildcard])))]))),
             FilterSign::In,
             Operand::val(json!(["abc","bcd"])),
         ))]);
         test("[?(@.[*]..next in ['abc','bcd'])]", vec![JsonPath::Index(JsonPathIndex::Filter(
-            Operand::Dynamic(Box::new(Chain(vec![Current(Box::new(Chain(vec![Wildcard, Descent(String::from("next"))])))]))),
+            Operand::Dynamic(Box::new(Chain(vec![Current(Box::new(Chain(vec![Wildcard, Descent("next".into())])))]))),
             FilterSign::In,
             Operand::val(json!(["abc","bcd"])),
         ))]);
@@ -298,8 +638,152 @@ mod tests {
             Operand::val(json!([1,2,3])),
         ))]);
 
+        test("[?(@.name =~ /^foo.*/i)]", vec![JsonPath::Index(JsonPathIndex::Filter(
+            Operand::Dynamic(Box::new(Chain(vec![Current(Box::new(Chain(vec![Field("name".into())])))]))),
+            FilterSign::Regex,
+            Operand::str("(?i)^foo.*"),
+        ))]);
+        test("[?(@ ~= /a\\/b\\d+/)]", vec![JsonPath::Index(JsonPathIndex::Filter(
+            Operand::Dynamic(Box::new(Chain(vec![Current(Box::new(JsonPath::Empty))]))),
+            FilterSign::Regex,
+            Operand::str("a/b\\d+"),
+        ))]);
+        test("[?(@.category nin ['fiction', 2, -1.5, true, null])]", vec![JsonPath::Index(JsonPathIndex::Filter(
+            Operand::Dynamic(Box::new(Chain(vec![Current(Box::new(Chain(vec![Field("category".into())])))]))),
+            FilterSign::Nin,
+            Operand::val(json!(["fiction", 2, -1.5, true, null])),
+        ))]);
+        test("[?(@ in [])]", vec![JsonPath::Index(JsonPathIndex::Filter(
+            Operand::Dynamic(Box::new(Chain(vec![Current(Box::new(JsonPath::Empty))]))),
+            FilterSign::In,
+            Operand::val(json!([])),
+        ))]);
+        test("[?(@ in [1])]", vec![JsonPath::Index(JsonPathIndex::Filter(
+            Operand::Dynamic(Box::new(Chain(vec![Current(Box::new(JsonPath::Empty))]))),
+            FilterSign::In,
+            Operand::val(json!([1])),
+        ))]);
+        test_failed("[?(@ in [1,])]");
+        test_failed("[?(@ =~ /a/q)]");
+        test_failed("[?(@ =~ /a)]");
         test_failed("[?(@[1] subsetof ['abc','abc'])]");
         test_failed("[?(@ >< ['abc','abc'])]");
         test_failed("[?(@ in {\"abc\":1})]");
+
+        // the strict parsing follows RFC 9535, the default one accepts these queries as before
+        for query in ["$[?(@.a[*] == 1)]", "$[?(@.a == $..b)]", "$[?(true)]", "$[?(@.a == .1)]"].iter() {
+            assert!(parse_json_path(query).is_ok(), "{}", query);
+        }
+        let error = |q| parse_json_path_strict(q).unwrap_err().to_string();
+        assert!(error("$[?(@.a == .1)]").contains("the name can not start with the digit"));
+        assert!(error("$[?(@.a[*] == 1)]").contains("the comparison needs the path to the single element"));
+        assert!(error("$[?($..a <= @.b)]").contains("needs the path to the single element"));
+        assert!(error("$[?(@.a == @[0,1])]").contains("needs the path to the single element"));
+        assert!(error("$[?(true)]").contains("the literal needs to be compared"));
+        assert!(error("$[?(@.a && 'x')]").contains("the literal needs to be compared"));
+        assert!(parse_json_path_strict("$[?(@.a[*] in [1,2])]").is_ok());
+        assert!(parse_json_path_strict("$[?(@.a[0]['b'] == $.c)]").is_ok());
+    }
+
+    #[test]
+    fn index_filter_logical_test() {
+        let field = |f: &'static str| Operand::Dynamic(Box::new(Chain(vec![Current(Box::new(Chain(vec![Field(f.into())])))])));
+        let exists = |f| FilterExpression::Atom(field(f), FilterSign::Exists, Operand::Dynamic(Box::new(JsonPath::Empty)));
+        test("[?((@.price < 10 || @.onSale) && !@.hidden)]", vec![JsonPath::Index(JsonPathIndex::Logical(
+            FilterExpression::and(
+                FilterExpression::or(
+                    FilterExpression::Atom(field("price"), FilterSign::Less, Operand::val(json!(10))),
+                    exists("onSale"),
+                ),
+                !exists("hidden"),
+            )
+        ))]);
+        test("[?(@.a || @.b && @.c)]", vec![JsonPath::Index(JsonPathIndex::Logical(
+            FilterExpression::or(exists("a"), FilterExpression::and(exists("b"), exists("c")))
+        ))]);
+        test("[?(@.a && @.b || @.c)]", vec![JsonPath::Index(JsonPathIndex::Logical(
+            FilterExpression::or(FilterExpression::and(exists("a"), exists("b")), exists("c"))
+        ))]);
+        test("[?(!!@.a)]", vec![JsonPath::Index(JsonPathIndex::Logical(
+            !!exists("a")
+        ))]);
+        test("[?(!(@.a != 1))]", vec![JsonPath::Index(JsonPathIndex::Logical(
+            !FilterExpression::Atom(field("a"), FilterSign::Unequal, Operand::val(json!(1)))
+        ))]);
+        test("[?((@.a == 1))]", vec![JsonPath::Index(JsonPathIndex::Filter(
+            field("a"), FilterSign::Equal, Operand::val(json!(1)),
+        ))]);
+        test("[?(@.a)]", vec![JsonPath::Index(JsonPathIndex::exists(field("a")))]);
+        test_failed("[?(@.a &&)]");
+        test_failed("[?((@.a || @.b)]");
+        test_failed("[?(@.a & @.b)]");
+    }
+
+    #[test]
+    fn index_filter_function_test() {
+        let field = |f: &'static str| Operand::Dynamic(Box::new(Chain(vec![Current(Box::new(Chain(vec![Field(f.into())])))])));
+        test("[?(length(@.authors) >= 2)]", vec![JsonPath::Index(JsonPathIndex::Filter(
+            Operand::Function("length".into(), vec![field("authors")]),
+            FilterSign::GrOrEq,
+            Operand::val(json!(2)),
+        ))]);
+        test("[?(length('abc') == length(length($.a[0])))]", vec![JsonPath::Index(JsonPathIndex::Filter(
+            Operand::Function("length".into(), vec![Operand::str("abc")]),
+            FilterSign::Equal,
+            Operand::Function("length".into(), vec![Operand::Function("length".into(), vec![
+                Operand::Dynamic(Box::new(Chain(vec![JsonPath::Root, Field("a".into()), JsonPath::Index(JsonPathIndex::Single(json!(0)))])))
+            ])]),
+        ))]);
+        test("[?(length(@) > 1 && !@.a)]", vec![JsonPath::Index(JsonPathIndex::Logical(FilterExpression::and(
+            FilterExpression::Atom(
+                Operand::Function("length".into(), vec![Operand::Dynamic(Box::new(Chain(vec![Current(Box::new(JsonPath::Empty))])))]),
+                FilterSign::Greater,
+                Operand::val(json!(1)),
+            ),
+            !FilterExpression::Atom(field("a"), FilterSign::Exists, Operand::Dynamic(Box::new(JsonPath::Empty))),
+        )))]);
+
+        test_failed("[?(length (@.a) > 1)]");
+        test_failed("[?(length(@.a) > 1 > 2)]");
+        let error = |q| parse_json_path(q).unwrap_err().to_string();
+        test("[?(foo_2(@.*, 'a', foo(), 1))]", vec![JsonPath::Index(JsonPathIndex::exists(Operand::Function("foo_2".into(), vec![
+            Operand::Dynamic(Box::new(Chain(vec![Current(Box::new(Chain(vec![Wildcard])))]))),
+            Operand::str("a"),
+            Operand::Function("foo".into(), vec![]),
+            Operand::val(json!(1)),
+        ])))]);
+        test_failed("[?(Foo(@.a))]");
+        test_failed("[?(_foo(@.a))]");
+        assert!(error("$[?(length(@.a, @.b) > 1)]").contains("the function 'length' takes 1 argument(s), got 2"));
+        assert!(error("$[?(length() > 1)]").contains("takes 1 argument(s), got 0"));
+        assert!(error("$[?(length(@.*) > 1)]").contains("the argument of the function 'length' is not the single value"));
+        assert!(error("$[?(length(@..a) > 1)]").contains("is not the single value"));
+        assert!(error("$[?(length(@.a[1:]) > 1)]").contains("is not the single value"));
+        assert!(error("$[?(length(@.a))]").contains("the function gives the value, it needs to be compared"));
+        assert!(error("$[?(@.b || !length(@.a))]").contains("it needs to be compared"));
+        assert!(parse_json_path("$[?(length(@.a[-1]['b']) > 1)]").is_ok());
+
+        test("[?(count(@.*) > count($..a[?(@.b)]))]", vec![JsonPath::Index(JsonPathIndex::Filter(
+            Operand::Function("count".into(), vec![Operand::Dynamic(Box::new(Chain(vec![Current(Box::new(Chain(vec![Wildcard])))])))]),
+            FilterSign::Greater,
+            Operand::Function("count".into(), vec![Operand::Dynamic(Box::new(Chain(vec![
+                JsonPath::Root,
+                Descent("a".into()),
+                JsonPath::Index(JsonPathIndex::exists(field("b"))),
+            ])))]),
+        ))]);
+        assert!(error("$[?(count('abc') > 1)]").contains("the argument of the function 'count' is not the path"));
+        assert!(error("$[?(count(length(@.a)) > 1)]").contains("is not the path"));
+        assert!(parse_json_path("$[?(length(count(@.a)) > 1)]").is_ok());
+        assert!(error("$[?(count(@.a))]").contains("it needs to be compared"));
+
+        test("[?(value(@..color) == \"red\")]", vec![JsonPath::Index(JsonPathIndex::Filter(
+            Operand::Function("value".into(), vec![Operand::Dynamic(Box::new(Chain(vec![Current(Box::new(Chain(vec![Descent("color".into())])))])))]),
+            FilterSign::Equal,
+            Operand::str("red"),
+        ))]);
+        assert!(error("$[?(value('red') == 'red')]").contains("the argument of the function 'value' is not the path"));
+        assert!(error("$[?(value(@.a))]").contains("it needs to be compared"));
+        assert!(parse_json_path("$[?(length(value(@..a)) == 2)]").is_ok());
     }
 }
\ No newline at end of file
@@ -0,0 +1,101 @@
+use std::fmt::Formatter;
+use serde_core::de::{Error, Visitor};
+use serde_core::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::parser::model::{JsonPath, SharedPath};
+use crate::path::JsonPathInst;
+
+/// the path goes as its canonical query(see the [std::fmt::Display] of [JsonPath])
+impl Serialize for JsonPath<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// the query is parsed and validated(see [JsonPath::validate]) when the configuration is loaded,
+/// the functions added by the applications are resolved when the query is evaluated
+impl<'de> Deserialize<'de> for JsonPath<'_> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(QueryVisitor)
+    }
+}
+
+struct QueryVisitor;
+
+impl Visitor<'_> for QueryVisitor {
+    type Value = JsonPath<'static>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the jsonpath query")
+    }
+
+    fn visit_str<E: Error>(self, query: &str) -> Result<Self::Value, E> {
+        let path = JsonPath::parse(query).map_err(|e| E::custom(format!("the query '{}' is invalid: {}", query, e)))?;
+        // the variables are bound once the query is loaded
+        path.check_states().map_err(|e| E::custom(format!("the query '{}' is invalid: {}", query, e)))?;
+        Ok(path.into_owned())
+    }
+}
+
+impl Serialize for SharedPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SharedPath {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        JsonPath::deserialize(deserializer).map(SharedPath::new)
+    }
+}
+
+impl Serialize for JsonPathInst {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.path().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonPathInst {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        JsonPath::deserialize(deserializer).map(JsonPathInst::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use serde_json::json;
+    use crate::parser::model::{JsonPath, SharedPath};
+    use crate::parser::parser::parse_json_path;
+    use crate::path::JsonPathInst;
+
+    #[test]
+    fn serde_test() {
+        let config = r#"{"title": "$.books[?(@.price < 10)].title", "authors": "$..author"}"#;
+        let paths: BTreeMap<String, JsonPath> = serde_json::from_str(config).unwrap();
+        assert_eq!(paths["authors"], parse_json_path("$..author").unwrap());
+        assert_eq!(serde_json::to_value(&paths).unwrap(), json!({
+            "authors": "$..author",
+            "title": "$.books[?(@.price < 10)].title"
+        }));
+
+        let shared: SharedPath = serde_json::from_value(json!("$.a['b c']")).unwrap();
+        assert_eq!(serde_json::to_value(&shared).unwrap(), json!("$.a['b c']"));
+        let inst: JsonPathInst = serde_json::from_value(json!("$.a[0]")).unwrap();
+        assert_eq!(inst.find(&json!({"a":[1]})), vec![&json!(1)]);
+        assert_eq!(serde_json::to_string(&inst).unwrap(), r#""$.a[0]""#);
+    }
+
+    #[test]
+    fn invalid_test() {
+        let e = serde_json::from_value::<JsonPath>(json!("$.a[")).unwrap_err();
+        assert!(e.to_string().starts_with("the query '$.a[' is invalid"), "{}", e);
+        let e = serde_json::from_value::<JsonPathInst>(json!("$[?(@.a ~= '(')]")).unwrap_err();
+        assert!(e.to_string().starts_with("the query '$[?(@.a ~= '(')]' is invalid: the regex '(' "), "{}", e);
+        let inst: JsonPathInst = serde_json::from_value(json!("$.a[::0]")).unwrap();
+        assert!(inst.find(&json!({"a":[1]})).is_empty());
+        let path: JsonPath = serde_json::from_value(json!("$.a[?(@.id == $id)]")).unwrap();
+        assert_eq!(path.variables(), vec![String::from("id")]);
+        let e = serde_json::from_value::<SharedPath>(json!(true)).unwrap_err();
+        assert_eq!(e.to_string(), "invalid type: boolean `true`, expected the jsonpath query");
+    }
+}
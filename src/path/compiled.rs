@@ -0,0 +1,247 @@
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use serde_json::Value;
+use serde_core::de::DeserializeOwned;
+use crate::parser::errors::JsonPathParserError;
+use crate::parser::model::JsonPath;
+use crate::path::{NodeList, Options};
+use crate::path::visit::{Ctx, Matches, Plan};
+
+/// The query compiled once and applied to any number of documents.
+/// Unlike [crate::path::PathInstance] it is not bound to the document:
+/// it owns the path along with the segments, and the found elements borrow only the document,
+/// so they outlive the instance.
+/// The instance holds neither the document nor the non-thread-safe parts,
+/// thus it is `Send + Sync` and one instance serves many threads behind [std::sync::Arc].
+/// # Examples:
+/// ```
+/// use serde_json::json;
+/// use jsonpath_rust::JsonPathInst;
+///
+/// let inst: JsonPathInst = "$.a[?(@.b > 1)].b".parse().unwrap();
+/// let (doc1, doc2) = (json!({"a":[{"b":1},{"b":2}]}), json!({"a":[{"b":3}]}));
+/// assert_eq!(inst.find(&doc1), vec![&json!(2)]);
+/// assert_eq!(inst.find(&doc2), vec![&json!(3)]);
+/// ```
+#[derive(Debug)]
+pub struct JsonPathInst {
+    path: JsonPath<'static>,
+    /// the segments along with the filters prepared once for all the documents
+    plan: Plan<'static>,
+}
+
+impl JsonPathInst {
+    pub fn new(path: JsonPath<'_>) -> Self {
+        let plan = Plan::new(&path).into_owned();
+        JsonPathInst { path: path.into_owned(), plan }
+    }
+
+    /// the path the instance is compiled from
+    pub fn path(&self) -> &JsonPath<'static> {
+        &self.path
+    }
+
+    /// finds the elements of the document, `$` in the filters stands for the root of this document
+    pub fn find<'a>(&self, doc: &'a Value) -> Vec<&'a Value> {
+        self.iter(doc).collect()
+    }
+
+    /// finds the elements along with their locations like `$['store']['book'][2]`
+    pub fn find_nodes<'a>(&self, doc: &'a Value) -> NodeList<'a> {
+        self.path.find_nodes(doc)
+    }
+
+    /// finds the locations of the elements as the json pointers like `/store/book/2/title`
+    pub fn find_pointers(&self, doc: &Value) -> Vec<String> {
+        self.path.find_pointers(doc)
+    }
+
+    /// finds the first element and stops the traversal right there
+    pub fn find_first<'a>(&self, doc: &'a Value) -> Option<&'a Value> {
+        self.iter(doc).next()
+    }
+
+    /// checks whether the path finds anything stopping the traversal at the first found element
+    pub fn exists(&self, doc: &Value) -> bool {
+        self.find_first(doc).is_some()
+    }
+
+    /// finds the copies of the elements so they outlive the document
+    pub fn find_owned(&self, doc: &Value) -> Vec<Value> {
+        self.iter(doc).cloned().collect()
+    }
+
+    /// deserializes every found element into `T`(see [JsonPath::find_as])
+    pub fn find_as<T: DeserializeOwned>(&self, doc: &Value) -> Result<Vec<T>, String> {
+        self.path.find_as(doc)
+    }
+
+    /// finds the elements as the mutable references to change them in place(see [JsonPath::find_mut])
+    pub fn find_mut<'a>(&self, doc: &'a mut Value) -> Vec<&'a mut Value> {
+        self.path.find_mut(doc)
+    }
+
+    /// counts the found elements without collecting them
+    pub fn count(&self, doc: &Value) -> usize {
+        self.iter(doc).count()
+    }
+
+    /// finds the only element failing when there is none or there are several ones(see [JsonPath::find_unique])
+    pub fn find_unique<'a>(&self, doc: &'a Value) -> Result<&'a Value, String> {
+        self.path.find_unique(doc)
+    }
+
+    /// finds the elements one by one in the same order [JsonPathInst::find] gives them,
+    /// the document is walked only as far as the taken elements need.
+    pub fn iter<'a: 'f, 'f>(&'f self, doc: &'a Value) -> impl Iterator<Item=&'a Value> + 'f {
+        let ctx = Ctx::new(doc, &self.plan, Cow::Owned(Options::default()), None);
+        Matches::new(&self.plan.steps, ctx, doc, false)
+    }
+}
+
+impl From<JsonPath<'_>> for JsonPathInst {
+    fn from(path: JsonPath<'_>) -> Self {
+        JsonPathInst::new(path)
+    }
+}
+
+/// parses and compiles the query
+impl FromStr for JsonPathInst {
+    type Err = JsonPathParserError;
+
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        JsonPath::parse(query).map(JsonPathInst::new)
+    }
+}
+
+/// the query the instance is compiled from, see the [Display] of [JsonPath]
+impl Display for JsonPathInst {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path)
+    }
+}
+
+/// `&value >> &inst` gives the same elements as [JsonPathInst::find] does
+impl<'a> std::ops::Shr<&JsonPathInst> for &'a Value {
+    type Output = Vec<&'a Value>;
+
+    fn shr(self, inst: &JsonPathInst) -> Self::Output {
+        inst.find(self)
+    }
+}
+
+impl JsonPath<'_> {
+    /// turns the query into the closure for the hot paths evaluating the same query over and over.
+    /// The segments and the filters along with their literals and regexes are prepared once,
+    /// so every call only walks the document; the operands starting from `$` are evaluated once per document.
+    /// The closure owns the query(see [JsonPathInst]), so it outlives the path and can be shared between the threads.
+    pub fn compile(&self) -> impl Fn(&Value) -> Vec<&Value> + Send + Sync + 'static {
+        let inst = JsonPathInst::new(self.clone());
+        move |doc| inst.find(doc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use serde_json::json;
+    use crate::parser::parser::parse_json_path;
+    use crate::path::json_path_instance;
+    use crate::path::compiled::JsonPathInst;
+
+    #[test]
+    fn compile_test() {
+        let doc = json!({"limit":10,"store":{"book":[
+            {"title":"a","price":8,"tags":["x","y"]},
+            {"title":"b","price":12,"isbn":"1"},
+            {"title":"c","price":9}
+        ],"bicycle":{"price":19}}});
+        for q in ["$.store.book[*].title", "$..price", "$.store.book[1:].title", "$.store.book[-1:]",
+            "$.store.book[0,2].price", "$.store['book','bicycle']", "$.store.book[?(@.price < $.limit)].title",
+            "$.store.book[?(@.isbn)]", "$..book[0].tags[*]", "$.store.*", "$.store.book[5]",
+            "$.store.book[?(@.price > 8 && !@.isbn || @.title == 'b')].title"] {
+            let path = parse_json_path(q).unwrap();
+            let find = path.compile();
+            assert_eq!(find(&doc), json_path_instance(&path, &doc).find(&doc), "{}", q);
+            assert_eq!(JsonPathInst::new(path.clone()).iter(&doc).collect::<Vec<_>>(), find(&doc), "{}", q);
+        }
+
+        let find = parse_json_path("$.a[?(@ > $.min)]").unwrap().compile();
+        assert_eq!(find(&json!({"min":1,"a":[1,2,3]})), vec![&json!(2), &json!(3)]);
+        assert_eq!(find(&json!({"min":2,"a":[1,2,3]})), vec![&json!(3)]);
+
+        let find = parse_json_path("$.a[?(@ > 1)]").unwrap().compile();
+        let handle = std::thread::spawn(move || find(&json!({"a":[1,2,3]})).len());
+        assert_eq!(handle.join().unwrap(), 2);
+    }
+
+    #[test]
+    fn inst_test() {
+        let inst: JsonPathInst = "$.books[?(@.price < $.limit)].title".parse().unwrap();
+        let docs = [
+            json!({"limit":10,"books":[{"title":"a","price":8},{"title":"b","price":12}]}),
+            json!({"limit":20,"books":[{"title":"a","price":8},{"title":"b","price":12}]}),
+            json!({"limit":0,"books":[{"title":"a","price":8}]}),
+        ];
+        let found: Vec<Vec<_>> = docs.iter().map(|doc| inst.find(doc)).collect();
+        drop(inst);
+        assert_eq!(found, vec![vec![&json!("a")], vec![&json!("a"), &json!("b")], vec![]]);
+
+        let inst = JsonPathInst::from(parse_json_path("$.books[-1].title").unwrap());
+        assert_eq!(inst.path(), &parse_json_path("$.books[-1].title").unwrap());
+        assert_eq!(inst.to_string(), "$.books[-1].title");
+        assert_eq!(&docs[0] >> &inst, vec![&json!("b")]);
+        let nodes = inst.find_nodes(&docs[0]);
+        assert_eq!(nodes.paths().iter().map(|p| p.to_string()).collect::<Vec<_>>(), vec!["$['books'][1]['title']"]);
+        assert_eq!(nodes.values(), inst.find(&docs[0]));
+        assert_eq!(nodes.pointers(), vec!["/books/1/title"]);
+        assert_eq!(inst.find_pointers(&docs[0]), nodes.pointers());
+        assert!("$.books[".parse::<JsonPathInst>().is_err());
+    }
+
+    #[test]
+    fn iter_test() {
+        let doc = json!({"a":{"b":1,"c":{"b":2,"d":[{"b":3},{"e":{"b":4}}]}},"b":5});
+        let inst: JsonPathInst = "$..b".parse().unwrap();
+        assert_eq!(inst.iter(&doc).collect::<Vec<_>>(), inst.find(&doc));
+        assert_eq!(inst.iter(&doc).take(2).collect::<Vec<_>>(), vec![&json!(5), &json!(1)]);
+
+        let inst: JsonPathInst = "$..d[?(@.b >= 2 || @.e)]..b".parse().unwrap();
+        assert_eq!(inst.iter(&doc).collect::<Vec<_>>(), inst.find(&doc));
+
+        let inst: JsonPathInst = "$[*]".parse().unwrap();
+        let huge = serde_json::Value::Array((0..100_000).map(|i| json!({"id": i})).collect());
+        let mut iter = inst.iter(&huge);
+        assert_eq!(iter.next(), Some(&json!({"id":0})));
+        assert_eq!(iter.nth(10), Some(&json!({"id":11})));
+        assert_eq!(inst.iter(&json!(1)).next(), None);
+        assert_eq!(inst.find_first(&huge), Some(&json!({"id":0})));
+        assert!(inst.find_unique(&huge).is_err());
+        assert_eq!(inst.find_unique(&json!([7])), Ok(&json!(7)));
+        assert!(inst.exists(&huge));
+        assert!(!inst.exists(&json!([])));
+        assert_eq!(inst.count(&huge), 100_000);
+        assert_eq!(inst.find_owned(&json!([1, {"a":2}])), vec![json!(1), json!({"a":2})]);
+        assert!(inst.find_as::<u64>(&json!([1, {"a":2}])).unwrap_err().starts_with("the element at $[1] is not valid"));
+        let mut doc = json!([1, 2]);
+        inst.find_mut(&mut doc).into_iter().for_each(|v| *v = json!(0));
+        assert_eq!(doc, json!([0, 0]));
+    }
+
+    #[test]
+    fn shared_inst_test() {
+        fn thread_safe<T: Send + Sync + 'static>() {}
+        thread_safe::<JsonPathInst>();
+        thread_safe::<crate::JsonPath<'static>>();
+        thread_safe::<crate::SharedPath>();
+
+        let inst: Arc<JsonPathInst> = Arc::new("$.a[?(@ > $.min)]".parse().unwrap());
+        let handles: Vec<_> = (0..4).map(|min| {
+            let inst = inst.clone();
+            std::thread::spawn(move || inst.find(&json!({"min":min,"a":[1,2,3,4]})).len())
+        }).collect();
+        let found: Vec<usize> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert_eq!(found, vec![4, 3, 2, 1]);
+    }
+}
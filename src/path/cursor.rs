@@ -0,0 +1,183 @@
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use serde_json::Value;
+use crate::parser::model::JsonPath;
+use crate::path::Env;
+use crate::path::nodes::NodeList;
+use crate::path::normalized::NormalizedPath;
+use crate::path::visit::{Ctx, Matches, Plan};
+
+/// The position in the results of the query where the next page starts.
+///
+/// The cursor remembers how many elements have been returned and the location of the last one,
+/// so it can be handed over to the client as a string(see [Display] and [FromStr])
+/// and the evaluation resumed later. Resuming fails if the last returned element
+/// is not at the same place anymore, i.e. the document has been changed in the meantime.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cursor {
+    offset: usize,
+    last: NormalizedPath,
+}
+
+impl Cursor {
+    /// the number of the elements returned before the cursor
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+    /// the location of the last returned element
+    pub fn last(&self) -> &NormalizedPath {
+        &self.last
+    }
+}
+
+/// The page of the results and the cursor pointing to the next page if there is any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<'a> {
+    pub nodes: NodeList<'a>,
+    pub next: Option<Cursor>,
+}
+
+impl JsonPath<'_> {
+    /// finds at most `limit` elements starting from the cursor or from the beginning if there is no cursor.
+    /// The traversal resumes right at the location of the last returned element skipping the parts of the document
+    /// before it, unless the query can find the same location several times(like `$..a..b` or `$[0,0]`),
+    /// then the elements before the cursor are counted again.
+    pub fn page<'a>(&self, doc: &'a Value, cursor: Option<&Cursor>, limit: usize) -> Result<Page<'a>, String> {
+        if limit == 0 {
+            return Err(String::from("the limit of the page should be positive"));
+        }
+        let env = Env::new(doc);
+        let plan = Plan::new(self);
+        let ctx = Ctx::new(env.root, &plan, Cow::Borrowed(&env.options), None);
+        let mut matches = Matches::new(&plan.steps, ctx, doc, true);
+        let skip = cursor.map(|c| c.offset).unwrap_or(0);
+        if let Some(c) = cursor {
+            let found = if plan.seekable() {
+                matches.seek(&c.last)
+            } else {
+                let seen = matches.by_ref().take(skip).count();
+                if seen < skip {
+                    return Err(format!("the cursor points out to the element #{} but the query finds {}", skip, seen));
+                }
+                matches.location() == &c.last
+            };
+            if !found {
+                return Err(format!("the cursor {} is no longer valid, the document has been changed", c));
+            }
+        }
+        let mut nodes = vec![];
+        let mut more = false;
+        while let Some(v) = matches.next() {
+            if nodes.len() == limit {
+                more = true;
+                break;
+            }
+            nodes.push((matches.location().clone(), v));
+        }
+        let next = match nodes.last() {
+            Some((last, _)) if more => Some(Cursor { offset: skip + nodes.len(), last: last.clone() }),
+            _ => None
+        };
+        Ok(Page { nodes: NodeList::new(nodes), next })
+    }
+}
+
+impl Display for Cursor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.offset, self.last)
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (offset, last) = s.split_once(':').ok_or_else(|| format!("the cursor '{}' is malformed", s))?;
+        let offset = offset.parse::<usize>().map_err(|e| format!("the cursor '{}' is malformed: {}", s, e))?;
+        if offset == 0 {
+            return Err(format!("the cursor '{}' is malformed: the offset is zero", s));
+        }
+        Ok(Cursor { offset, last: last.parse()? })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::parser::parser::parse_json_path;
+    use crate::path::cursor::Cursor;
+
+    #[test]
+    fn pagination_test() {
+        let doc = json!({"items":[0,1,2,3,4,5,6]});
+        let path = parse_json_path("$.items[*]").unwrap();
+        let mut cursor: Option<Cursor> = None;
+        let mut pages = vec![];
+        loop {
+            let page = path.page(&doc, cursor.as_ref(), 3).unwrap();
+            pages.push(page.nodes.values().into_iter().cloned().collect::<Vec<_>>());
+            match page.next {
+                // the cursor travels through the client as the string
+                Some(c) => cursor = Some(c.to_string().parse().unwrap()),
+                None => break
+            }
+        }
+        assert_eq!(pages, vec![vec![json!(0), json!(1), json!(2)], vec![json!(3), json!(4), json!(5)], vec![json!(6)]]);
+
+        let page = path.page(&doc, None, 7).unwrap();
+        assert_eq!(page.nodes.len(), 7);
+        assert_eq!(page.next, None);
+    }
+
+    #[test]
+    fn seek_test() {
+        let doc = json!({"a":[{"b":1},{"c":{"b":2}},{"b":3}],"d":{"b":4}});
+        // the queries finding the locations once resume at the cursor, the other ones count the elements again
+        for query in ["$..b", "$.a[*].b", "$..[?(@.b)].b", "$.a[2,0].b", "$..b..*", "$[*,'a'][*]", "$.a[0,0]"] {
+            let path = parse_json_path(query).unwrap();
+            let all: Vec<_> = path.page(&doc, None, 100).unwrap().nodes.values().into_iter().cloned().collect();
+            let mut cursor: Option<Cursor> = None;
+            let mut paged = vec![];
+            loop {
+                let page = path.page(&doc, cursor.as_ref(), 1).unwrap();
+                paged.extend(page.nodes.values().into_iter().cloned());
+                match page.next {
+                    Some(c) => cursor = Some(c),
+                    None => break
+                }
+            }
+            assert_eq!(paged, all, "{}", query);
+        }
+        let path = parse_json_path("$..b").unwrap();
+        let cursor = path.page(&doc, None, 2).unwrap().next.unwrap();
+        assert_eq!(cursor.last().to_string(), "$['a'][1]['c']['b']");
+        // the elements before the last returned one do not matter anymore
+        let changed = json!({"a":[{"x":0},{"c":{"b":2}},{"b":3}],"d":{"b":4}});
+        let page = path.page(&changed, Some(&cursor), 2).unwrap();
+        assert_eq!(page.nodes.values(), vec![&json!(3), &json!(4)]);
+    }
+
+    #[test]
+    fn cursor_test() {
+        let doc = json!({"a":[{"b":1},{"b":2},{"b":3}]});
+        let path = parse_json_path("$..b").unwrap();
+        let cursor = path.page(&doc, None, 2).unwrap().next.unwrap();
+        assert_eq!(cursor.to_string(), "2:$['a'][1]['b']");
+
+        let changed = json!({"a":[{"b":1},{"c":2},{"b":3}]});
+        assert!(path.page(&changed, Some(&cursor), 2).is_err());
+        let shrunk = json!({"a":[{"b":1}]});
+        assert!(path.page(&shrunk, Some(&cursor), 2).is_err());
+        let path = parse_json_path("$.a[0,0,1].b").unwrap();
+        let cursor = path.page(&doc, None, 2).unwrap().next.unwrap();
+        assert!(path.page(&json!({"a":[{"c":1},{"b":2}]}), Some(&cursor), 2).is_err());
+        assert!(path.page(&json!({"a":[{"b":1},{"c":2}]}), Some(&cursor), 2).unwrap().nodes.is_empty());
+
+        assert!(path.page(&doc, None, 0).is_err());
+
+        assert!("x".parse::<Cursor>().is_err());
+        assert!("0:$".parse::<Cursor>().is_err());
+        assert!("1:a".parse::<Cursor>().is_err());
+    }
+}
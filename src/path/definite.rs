@@ -0,0 +1,273 @@
+use serde_json::Value;
+use crate::parser::model::{JsonPath, JsonPathIndex};
+use crate::path::{json_path_instance, Env};
+use std::convert::TryFrom;
+use crate::path::json::{as_index, position, type_name};
+use crate::path::normalized::NormalizedPath;
+use crate::path::visit::visit_at;
+use std::ops::ControlFlow;
+
+impl JsonPath<'_> {
+    /// checks whether the path can point out to the one element at most,
+    /// namely it consists only of the root, fields and single indexes.
+    pub fn is_definite(&self) -> bool {
+        match self {
+            JsonPath::Root | JsonPath::Field(_) | JsonPath::Empty => true,
+            JsonPath::Index(JsonPathIndex::Single(_)) => true,
+            JsonPath::Current(tail) => tail.is_definite(),
+            JsonPath::Chain(elems) => elems.iter().all(JsonPath::is_definite),
+            _ => false
+        }
+    }
+
+    /// finds the element in the json walking straight through it without collecting the intermediate results.
+    /// It works only for definite paths(see [JsonPath::is_definite]) and returns None for the others.
+    pub fn find_one<'a>(&self, doc: &'a Value) -> Option<&'a Value> {
+        if self.is_definite() { walk(self, doc, doc) } else { None }
+    }
+
+    /// finds the element like [JsonPath::find_one] but explains why the element is not found.
+    /// When the member is missing, the error suggests the closest names of the existing members.
+    pub fn find_strict<'a>(&self, doc: &'a Value) -> Result<&'a Value, String> {
+        if self.is_definite() {
+            walk_strict(self, doc, doc, &mut NormalizedPath::root())
+        } else {
+            Err(String::from("the strict lookup is possible only for the definite paths"))
+        }
+    }
+
+    /// resolves the definite path(the prefix) once and binds the found subtree
+    /// to evaluate the different suffixes against it afterwards.
+    pub fn bind<'a>(&self, doc: &'a Value) -> Option<BoundPrefix<'a>> {
+        self.find_one(doc).map(|node| BoundPrefix { root: doc, node })
+    }
+}
+
+/// The subtree found by the definite prefix of the queries.
+/// The suffixes are evaluated starting from the subtree but `$` inside of them still points out to the document root.
+#[derive(Debug, Clone, Copy)]
+pub struct BoundPrefix<'a> {
+    root: &'a Value,
+    node: &'a Value,
+}
+
+impl<'a> BoundPrefix<'a> {
+    /// the subtree the prefix is resolved to
+    pub fn node(&self) -> &'a Value {
+        self.node
+    }
+
+    /// evaluates the suffix like `.b[*].c` against the bound subtree
+    pub fn find<'b>(&self, suffix: &'b JsonPath) -> Vec<&'b Value> where 'a: 'b {
+        json_path_instance(suffix, self.root).find(self.node)
+    }
+}
+
+/// the location the definite path points out to regardless of the document
+pub(crate) fn location(path: &JsonPath, current: &mut NormalizedPath) -> Option<()> {
+    match path {
+        JsonPath::Root => *current = NormalizedPath::root(),
+        JsonPath::Empty => (),
+        JsonPath::Field(key) => current.push(key.as_ref()),
+        JsonPath::Index(JsonPathIndex::Single(idx)) => current.push(usize::try_from(as_index(idx)?).ok()?),
+        JsonPath::Current(tail) => location(tail, current)?,
+        JsonPath::Chain(elems) => elems.iter().try_for_each(|p| location(p, current))?,
+        _ => return None
+    }
+    Some(())
+}
+
+fn walk<'a>(path: &JsonPath, root: &'a Value, current: &'a Value) -> Option<&'a Value> {
+    match path {
+        JsonPath::Root => Some(root),
+        JsonPath::Empty => Some(current),
+        JsonPath::Field(key) => current.as_object().and_then(|fields| fields.get(key.as_ref())),
+        JsonPath::Index(JsonPathIndex::Single(idx)) =>
+            as_index(idx).and_then(|idx| current.as_array().and_then(|elems| elems.get(position(idx, elems.len())?))),
+        JsonPath::Current(tail) => walk(tail, root, current),
+        JsonPath::Chain(elems) => elems.iter().try_fold(current, |cur, p| walk(p, root, cur)),
+        _ => None
+    }
+}
+
+fn walk_strict<'a>(path: &JsonPath, root: &'a Value, current: &'a Value, location: &mut NormalizedPath) -> Result<&'a Value, String> {
+    match path {
+        JsonPath::Root => {
+            *location = NormalizedPath::root();
+            Ok(root)
+        }
+        JsonPath::Field(key) => match current {
+            Value::Object(fields) => fields.get(key.as_ref()).inspect(|_| location.push(key.as_ref())).ok_or_else(|| {
+                let msg = format!("the member '{}' is not found at {}", key, location);
+                match suggest(key, fields.keys()).as_slice() {
+                    [] => msg,
+                    names => format!("{}, did you mean {}?", msg, names.iter().map(|n| format!("'{}'", n)).collect::<Vec<_>>().join(" or "))
+                }
+            }),
+            v => Err(format!("the member '{}' is requested at {} but the element is {}", key, location, type_name(v)))
+        },
+        JsonPath::Index(JsonPathIndex::Single(idx)) => match (current, as_index(idx)) {
+            (Value::Array(elems), Some(i)) => position(i, elems.len()).map(|pos| {
+                location.push(pos);
+                &elems[pos]
+            }).ok_or_else(|| format!("the index {} is out of bounds at {} with the length {}", idx, location, elems.len())),
+            (Value::Array(_), None) => Err(format!("the index {} at {} is not the integer", idx, location)),
+            (v, _) => Err(format!("the index {} is requested at {} but the element is {}", idx, location, type_name(v)))
+        },
+        JsonPath::Current(tail) => walk_strict(tail, root, current, location),
+        JsonPath::Chain(elems) => elems.iter().try_fold(current, |cur, p| walk_strict(p, root, cur, location)),
+        _ => Ok(current)
+    }
+}
+
+/// checks the segments of the query stage by stage the way the strict mode of [crate::Options] does:
+/// the members and indexes the query goes through should exist in every element they are applied to,
+/// the other segments like filters, wildcards or slices select the elements as usual, following the options of the env.
+pub(crate) fn check_strict(path: &JsonPath, env: &Env) -> Result<(), String> {
+    let doc = env.root;
+    let elems = match path {
+        JsonPath::Chain(elems) => elems.as_slice(),
+        p => std::slice::from_ref(p),
+    };
+    let mut nodes = vec![(NormalizedPath::root(), doc)];
+    for elem in elems {
+        let mut next = vec![];
+        for (mut location, node) in nodes {
+            match elem {
+                JsonPath::Root => next.push((NormalizedPath::root(), doc)),
+                JsonPath::Field(_) | JsonPath::Index(JsonPathIndex::Single(_)) => {
+                    let found = walk_strict(elem, doc, node, &mut location)?;
+                    next.push((location, found));
+                }
+                _ => {
+                    let _ = visit_at(elem, env, node, |rel, found| {
+                        let mut loc = location.clone();
+                        rel.segments().iter().cloned().for_each(|s| loc.push(s));
+                        next.push((loc, found));
+                        ControlFlow::Continue(())
+                    });
+                }
+            }
+        }
+        nodes = next;
+    }
+    Ok(())
+}
+
+/// picks up the names being the closest to the key by the edit distance
+fn suggest<'a, I: Iterator<Item=&'a String>>(key: &str, names: I) -> Vec<&'a String> {
+    let limit = std::cmp::max(1, key.chars().count() / 3);
+    let mut candidates: Vec<(usize, &String)> = names
+        .map(|n| (distance(key, n), n))
+        .filter(|(d, _)| *d <= limit)
+        .collect();
+    candidates.sort();
+    candidates.into_iter().take(3).map(|(_, n)| n).collect()
+}
+
+/// the edit distance between the strings where the transposition of two adjacent chars costs as one edit
+fn distance(left: &str, right: &str) -> usize {
+    let left: Vec<char> = left.chars().collect();
+    let right: Vec<char> = right.chars().collect();
+    let mut d = vec![vec![0; right.len() + 1]; left.len() + 1];
+    (0..=left.len()).for_each(|i| d[i][0] = i);
+    (0..=right.len()).for_each(|j| d[0][j] = j);
+    for i in 1..=left.len() {
+        for j in 1..=right.len() {
+            let cost = if left[i - 1] == right[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j - 1] + cost).min(d[i - 1][j] + 1).min(d[i][j - 1] + 1);
+            if i > 1 && j > 1 && left[i - 1] == right[j - 2] && left[i - 2] == right[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[left.len()][right.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::parser::parser::parse_json_path;
+    use crate::path::definite::{distance, suggest};
+
+    #[test]
+    fn definite_test() {
+        assert!(parse_json_path("$.a.b[1]").unwrap().is_definite());
+        assert!(parse_json_path("$['a'][0]").unwrap().is_definite());
+        assert!(!parse_json_path("$.a[*]").unwrap().is_definite());
+        assert!(!parse_json_path("$..a").unwrap().is_definite());
+        assert!(!parse_json_path("$.a[?(@.b)]").unwrap().is_definite());
+        assert!(!parse_json_path("$.a[1:2]").unwrap().is_definite());
+    }
+
+    #[test]
+    fn find_one_test() {
+        let json = json!({"a":{"b":[1,{"c":42}]}});
+        let path = parse_json_path("$.a.b[1].c").unwrap();
+        assert_eq!(path.find_one(&json), Some(&json!(42)));
+
+        let path = parse_json_path("$.a.b[2]").unwrap();
+        assert_eq!(path.find_one(&json), None);
+
+        let path = parse_json_path("$.a.c").unwrap();
+        assert_eq!(path.find_one(&json), None);
+
+        let path = parse_json_path("$").unwrap();
+        assert_eq!(path.find_one(&json), Some(&json));
+
+        let path = parse_json_path("$.a.b[*]").unwrap();
+        assert_eq!(path.find_one(&json), None);
+    }
+
+    #[test]
+    fn find_strict_test() {
+        let json = json!({"config":{"timeout":10,"retries":3,"items":[1,2]}});
+        let path = parse_json_path("$.config.timeout").unwrap();
+        assert_eq!(path.find_strict(&json), Ok(&json!(10)));
+
+        let path = parse_json_path("$.config.timeuot").unwrap();
+        assert_eq!(path.find_strict(&json),
+                   Err(String::from("the member 'timeuot' is not found at $['config'], did you mean 'timeout'?")));
+
+        let path = parse_json_path("$.config.abc").unwrap();
+        assert_eq!(path.find_strict(&json), Err(String::from("the member 'abc' is not found at $['config']")));
+
+        let path = parse_json_path("$.config.items[2]").unwrap();
+        assert_eq!(path.find_strict(&json),
+                   Err(String::from("the index 2 is out of bounds at $['config']['items'] with the length 2")));
+
+        let path = parse_json_path("$.config.retries.count").unwrap();
+        assert_eq!(path.find_strict(&json),
+                   Err(String::from("the member 'count' is requested at $['config']['retries'] but the element is number")));
+
+        assert!(parse_json_path("$.config[*]").unwrap().find_strict(&json).is_err());
+    }
+
+    #[test]
+    fn distance_test() {
+        assert_eq!(distance("", ""), 0);
+        assert_eq!(distance("abc", ""), 3);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("nmae", "name"), 1);
+        let names = [String::from("game"), String::from("name"), String::from("id")];
+        assert_eq!(suggest("nmae", names.iter()), vec![&String::from("name")]);
+        assert_eq!(suggest("ame", names.iter()), vec![&String::from("game"), &String::from("name")]);
+    }
+
+    #[test]
+    fn bind_test() {
+        let json = json!({"limit":2,"a":{"b":[{"c":1},{"c":2},{"c":3}]}});
+        let prefix = parse_json_path("$.a").unwrap();
+        let bound = prefix.bind(&json).unwrap();
+        assert_eq!(bound.node(), &json!({"b":[{"c":1},{"c":2},{"c":3}]}));
+
+        let suffix = parse_json_path(".b[0].c").unwrap();
+        assert_eq!(bound.find(&suffix), vec![&json!(1)]);
+
+        let suffix = parse_json_path(".b[?(@.c >= $.limit)].c").unwrap();
+        assert_eq!(bound.find(&suffix), vec![&json!(2), &json!(3)]);
+
+        assert!(parse_json_path("$.x").unwrap().bind(&json).is_none());
+        assert!(parse_json_path("$.a[*]").unwrap().bind(&json).is_none());
+    }
+}
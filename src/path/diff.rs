@@ -0,0 +1,122 @@
+use serde_json::Value;
+use crate::path::normalized::NormalizedPath;
+
+/// The difference between two documents at the single location.
+/// The member or the element that is missing on the one side has the value `None` there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    /// the location like `$['server']['ports'][1]`
+    pub path: NormalizedPath,
+    /// the value in the first document
+    pub old: Option<Value>,
+    /// the value in the second document
+    pub new: Option<Value>,
+}
+
+impl Change {
+    /// the location is absent in the first document
+    pub fn is_added(&self) -> bool {
+        self.old.is_none()
+    }
+
+    /// the location is absent in the second document
+    pub fn is_removed(&self) -> bool {
+        self.new.is_none()
+    }
+}
+
+/// compares the documents structurally and gives the changes turning the first one into the second one.
+/// The objects are compared member by member and the arrays element by element at the same indexes,
+/// the values of the different types or the different scalars are reported as replaced as a whole.
+/// # Examples:
+/// ```
+/// use serde_json::json;
+/// use jsonpath_rust::diff;
+///
+/// let changes = diff(&json!({"port":80,"hosts":["a"]}), &json!({"port":8080,"hosts":["a","b"]}));
+/// let changes: Vec<String> = changes.iter().map(|c| c.path.to_string()).collect();
+/// assert_eq!(changes, vec!["$['hosts'][1]", "$['port']"]);
+/// ```
+pub fn diff(a: &Value, b: &Value) -> Vec<Change> {
+    let mut changes = vec![];
+    walk(a, b, &mut NormalizedPath::root(), &mut changes);
+    changes
+}
+
+fn walk(a: &Value, b: &Value, path: &mut NormalizedPath, changes: &mut Vec<Change>) {
+    match (a, b) {
+        (Value::Object(left), Value::Object(right)) => {
+            for (key, v) in left {
+                path.push(key.as_str());
+                match right.get(key) {
+                    Some(other) => walk(v, other, path, changes),
+                    None => changes.push(change(path, Some(v), None)),
+                }
+                path.pop();
+            }
+            for (key, v) in right.iter().filter(|(key, _)| !left.contains_key(*key)) {
+                changes.push(change(&path.join(key.as_str()), None, Some(v)));
+            }
+        }
+        (Value::Array(left), Value::Array(right)) => {
+            for idx in 0..left.len().max(right.len()) {
+                path.push(idx);
+                match (left.get(idx), right.get(idx)) {
+                    (Some(v), Some(other)) => walk(v, other, path, changes),
+                    (old, new) => changes.push(change(path, old, new)),
+                }
+                path.pop();
+            }
+        }
+        _ if a != b => changes.push(change(path, Some(a), Some(b))),
+        _ => (),
+    }
+}
+
+fn change(path: &NormalizedPath, old: Option<&Value>, new: Option<&Value>) -> Change {
+    Change { path: path.clone(), old: old.cloned(), new: new.cloned() }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::path::diff::{diff, Change};
+    use crate::path::normalized::NormalizedPath;
+
+    fn at(path: &str) -> NormalizedPath {
+        path.parse().unwrap()
+    }
+
+    #[test]
+    fn diff_test() {
+        let a = json!({"a":1,"b":{"c":[1,2,3],"d":"x"},"e":null,"f":[]});
+        let b = json!({"a":1,"b":{"c":[1,5],"d":{"x":1}},"f":[true],"g":"y"});
+        let changes = diff(&a, &b);
+        assert_eq!(changes, vec![
+            Change { path: at("$['b']['c'][1]"), old: Some(json!(2)), new: Some(json!(5)) },
+            Change { path: at("$['b']['c'][2]"), old: Some(json!(3)), new: None },
+            Change { path: at("$['b']['d']"), old: Some(json!("x")), new: Some(json!({"x":1})) },
+            Change { path: at("$['e']"), old: Some(json!(null)), new: None },
+            Change { path: at("$['f'][0]"), old: None, new: Some(json!(true)) },
+            Change { path: at("$['g']"), old: None, new: Some(json!("y")) },
+        ]);
+        assert!(changes[1].is_removed() && !changes[1].is_added());
+        assert!(changes[4].is_added());
+
+        assert_eq!(diff(&a, &a), vec![]);
+        assert_eq!(diff(&json!(1), &json!([1])), vec![
+            Change { path: NormalizedPath::root(), old: Some(json!(1)), new: Some(json!([1])) }
+        ]);
+    }
+
+    #[test]
+    fn locations_test() {
+        let a = json!({"servers":[{"name":"a","port":80},{"name":"b","port":80}]});
+        let b = json!({"servers":[{"name":"a","port":80},{"name":"b","port":8080}]});
+        for change in diff(&a, &b) {
+            let path = crate::JsonPath::from(&change.path);
+            assert_eq!(path.find_owned(&a), change.old.into_iter().collect::<Vec<_>>());
+            assert_eq!(path.find_owned(&b), change.new.into_iter().collect::<Vec<_>>());
+        }
+    }
+}
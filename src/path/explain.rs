@@ -0,0 +1,117 @@
+use std::fmt::{Display, Formatter};
+use serde_json::Value;
+use crate::parser::model::JsonPath;
+use crate::path::json_path_instance;
+use crate::path::json::type_name;
+
+/// The short description of the element the segment has been applied to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeSummary {
+    /// the json type of the element
+    pub type_name: &'static str,
+    /// the member names if the element is an object
+    pub keys: Vec<String>,
+    /// the number of elements or members if the element is an array or an object
+    pub len: Option<usize>,
+}
+
+impl NodeSummary {
+    fn new(v: &Value) -> Self {
+        match v {
+            Value::Object(fields) => NodeSummary {
+                type_name: type_name(v),
+                keys: fields.keys().cloned().collect(),
+                len: Some(fields.len()),
+            },
+            Value::Array(elems) => NodeSummary { type_name: type_name(v), keys: vec![], len: Some(elems.len()) },
+            _ => NodeSummary { type_name: type_name(v), keys: vec![], len: None }
+        }
+    }
+}
+
+/// The report explaining why the query yields nothing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmptyReport {
+    /// the number of the leading segments that still had matches
+    pub matched_segments: usize,
+    /// the segment that produced no results
+    pub segment: JsonPath<'static>,
+    /// the elements the segment has been applied to
+    pub candidates: Vec<NodeSummary>,
+}
+
+impl Display for EmptyReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "the segment `{}` (#{}) found nothing among {} candidate(s):",
+                 self.segment, self.matched_segments + 1, self.candidates.len())?;
+        for c in self.candidates.iter() {
+            match (c.type_name, c.len) {
+                ("object", _) => writeln!(f, " - object with the keys [{}]", c.keys.join(", "))?,
+                (tp, Some(len)) => writeln!(f, " - {} of {} elements", tp, len)?,
+                (tp, None) => writeln!(f, " - {}", tp)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl JsonPath<'_> {
+    /// evaluates the query segment by segment and, if it yields nothing,
+    /// reports the first segment that has lost all matches and the elements it has been applied to.
+    /// Returns None if the query finds something.
+    pub fn explain_empty<'a>(&'a self, doc: &'a Value) -> Option<EmptyReport> {
+        let segments = match self {
+            JsonPath::Chain(elems) => elems.as_slice(),
+            single => std::slice::from_ref(single),
+        };
+        let mut current = vec![doc];
+        for (idx, segment) in segments.iter().enumerate() {
+            let instance = json_path_instance(segment, doc);
+            let next: Vec<&Value> = current.iter().flat_map(|v| instance.find(v)).collect();
+            if next.is_empty() {
+                return Some(EmptyReport {
+                    matched_segments: idx,
+                    segment: segment.clone().into_owned(),
+                    candidates: current.into_iter().map(NodeSummary::new).collect(),
+                });
+            }
+            current = next;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::parser::parser::parse_json_path;
+    use crate::parser::model::JsonPath;
+
+    #[test]
+    fn explain_empty_test() {
+        let json = json!({"store":{"book":[{"title":"a","price":10},{"title":"b"}],"bicycle":{"color":"red"}}});
+
+        let path = parse_json_path("$.store.book[*].price").unwrap();
+        assert_eq!(path.explain_empty(&json), None);
+
+        let path = parse_json_path("$.store.book[*].isbn").unwrap();
+        let report = path.explain_empty(&json).unwrap();
+        assert_eq!(report.matched_segments, 4);
+        assert_eq!(report.segment, JsonPath::field("isbn"));
+        assert_eq!(report.candidates.len(), 2);
+        assert_eq!(report.candidates[0].keys, vec![String::from("price"), String::from("title")]);
+        assert_eq!(report.to_string(),
+                   "the segment `.isbn` (#5) found nothing among 2 candidate(s):\n \
+                   - object with the keys [price, title]\n \
+                   - object with the keys [title]\n");
+
+        let path = parse_json_path("$.store.bicycle[0]").unwrap();
+        let report = path.explain_empty(&json).unwrap();
+        assert_eq!(report.matched_segments, 3);
+        assert_eq!(report.candidates[0].type_name, "object");
+
+        let path = parse_json_path("$.store.book[0].title[0]").unwrap();
+        let report = path.explain_empty(&json).unwrap();
+        assert_eq!(report.to_string(), "the segment `[0]` (#6) found nothing among 1 candidate(s):\n - string\n");
+    }
+}
@@ -0,0 +1,148 @@
+use serde_json::Value;
+use crate::parser::model::FilterSign;
+use crate::path::json::{eq, less};
+
+/// The value of the MongoDB Extended JSON wrapper the filters compare by.
+enum Ext {
+    /// `$numberInt`, `$numberLong`, `$numberDouble` and `$numberDecimal`
+    Number(Value),
+    /// `$date` as the milliseconds since the epoch
+    Date(i64),
+    /// `$oid` as the hex string
+    Oid(String),
+}
+
+fn unwrap(v: &Value) -> Option<Ext> {
+    let fields = v.as_object().filter(|f| f.len() == 1)?;
+    let (key, inner) = fields.iter().next()?;
+    match (key.as_str(), inner) {
+        ("$numberInt" | "$numberLong" | "$numberDouble" | "$numberDecimal", Value::String(s)) =>
+            serde_json::from_str::<Value>(s).ok().filter(Value::is_number).map(Ext::Number),
+        ("$oid", Value::String(s)) => Some(Ext::Oid(s.clone())),
+        ("$date", Value::Number(n)) => n.as_i64().map(Ext::Date),
+        ("$date", Value::String(s)) => parse_date(s).map(Ext::Date),
+        ("$date", v) => match unwrap(v) {
+            Some(Ext::Number(n)) => n.as_i64().map(Ext::Date),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+/// compares the operands if any of them is the Extended JSON wrapper:
+/// the numbers by value, the dates by time(the string on the other side is read as the date)
+/// and the object ids by their hex strings.
+pub(crate) fn compare(op: &FilterSign, left: &[&Value], right: &[&Value]) -> Option<bool> {
+    let (l, r) = match (left, right) {
+        ([l], [r]) => (*l, *r),
+        _ => return None
+    };
+    let (le, re) = (unwrap(l), unwrap(r));
+    if le.is_none() && re.is_none() {
+        return None;
+    }
+    let plain = |side: Option<Ext>, v: &Value, other: &Option<Ext>| match (side, other) {
+        (Some(Ext::Number(n)), _) => Some(n),
+        (Some(Ext::Date(ms)), _) => Some(Value::from(ms)),
+        (Some(Ext::Oid(id)), _) => Some(Value::from(id)),
+        (None, Some(Ext::Date(_))) => match v {
+            Value::String(s) => parse_date(s).map(Value::from),
+            v => Some(v.clone()),
+        },
+        (None, _) => Some(v.clone()),
+    };
+    let (lp, rp) = match (plain(le, l, &unwrap(r)), plain(re, r, &unwrap(l))) {
+        (Some(lp), Some(rp)) => (lp, rp),
+        _ => return Some(*op == FilterSign::Unequal),
+    };
+    let (lv, rv) = (vec![&lp], vec![&rp]);
+    match op {
+        FilterSign::Equal => Some(eq(lv, rv)),
+        FilterSign::Unequal => Some(!eq(lv, rv)),
+        FilterSign::Less => Some(less(lv, rv)),
+        FilterSign::LeOrEq => Some(less(lv.clone(), rv.clone()) || eq(lv, rv)),
+        FilterSign::Greater => Some(less(rv, lv)),
+        FilterSign::GrOrEq => Some(less(rv.clone(), lv.clone()) || eq(lv, rv)),
+        _ => None
+    }
+}
+
+/// parses the date like `2020-01-31T10:20:30.123Z` or with the offset `+02:00` into the milliseconds since the epoch
+fn parse_date(s: &str) -> Option<i64> {
+    let num = |from: usize, len: usize| s.get(from..from + len).filter(|d| d.bytes().all(|b| b.is_ascii_digit()))?.parse::<i64>().ok();
+    let b = s.as_bytes();
+    if b.len() < 20 || b[4] != b'-' || b[7] != b'-' || !matches!(b[10], b'T' | b't' | b' ') || b[13] != b':' || b[16] != b':' {
+        return None;
+    }
+    let (year, month, day) = (num(0, 4)?, num(5, 2)?, num(8, 2)?);
+    let (hour, min, sec) = (num(11, 2)?, num(14, 2)?, num(17, 2)?);
+    let mut pos = 19;
+    let mut millis = 0;
+    if b.get(pos) == Some(&b'.') {
+        let digits = b[pos + 1..].iter().take_while(|c| c.is_ascii_digit()).count();
+        let frac = num(pos + 1, digits.min(3))?;
+        millis = frac * 10i64.pow(3 - digits.min(3) as u32);
+        pos += 1 + digits;
+    }
+    let offset = match b.get(pos)? {
+        b'Z' | b'z' if pos + 1 == b.len() => 0,
+        sign @ (b'+' | b'-') if pos + 6 == b.len() && b[pos + 3] == b':' => {
+            let minutes = num(pos + 1, 2)? * 60 + num(pos + 4, 2)?;
+            if *sign == b'+' { minutes } else { -minutes }
+        }
+        _ => return None
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || min > 59 || sec > 60 {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    Some((((days * 24 + hour) * 60 + min - offset) * 60 + sec) * 1000 + millis)
+}
+
+/// the number of days since 1970-01-01 for the date of the proleptic gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::parser::parser::parse_json_path;
+    use crate::path::extjson::parse_date;
+    use crate::path::Options;
+
+    #[test]
+    fn date_test() {
+        assert_eq!(parse_date("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_date("2020-02-29T12:30:15.5Z"), Some(1582979415500));
+        assert_eq!(parse_date("2020-02-29T14:30:15.500+02:00"), Some(1582979415500));
+        assert_eq!(parse_date("1969-12-31T23:59:59Z"), Some(-1000));
+        assert_eq!(parse_date("2020-13-01T00:00:00Z"), None);
+        assert_eq!(parse_date("yesterday"), None);
+    }
+
+    #[test]
+    fn extended_json_test() {
+        let doc = json!({"orders":[
+            {"_id":{"$oid":"5f1a00000000000000000001"},"qty":{"$numberLong":"9007199254740993"},"at":{"$date":{"$numberLong":"1582979415500"}}},
+            {"_id":{"$oid":"5f1a00000000000000000002"},"qty":{"$numberInt":"5"},"at":{"$date":"2021-06-01T00:00:00Z"}}
+        ]});
+        let options = Options::new().extended_json();
+        let find = |q: &str, o: &Options| parse_json_path(q).unwrap().find_with_options(&doc, o).unwrap().len();
+
+        assert_eq!(find("$.orders[?(@._id == '5f1a00000000000000000002')]", &options), 1);
+        assert_eq!(find("$.orders[?(@.qty > 10)]", &options), 1);
+        assert_eq!(find("$.orders[?(@.qty >= 5)]", &options), 2);
+        assert_eq!(find("$.orders[?(@.at < '2021-01-01T00:00:00Z')]", &options), 1);
+        assert_eq!(find("$.orders[?(@.at > $.orders[0].at)]", &options), 1);
+        assert_eq!(find("$.orders[?(@.at != 'never')]", &options), 2);
+
+        assert_eq!(find("$.orders[?(@.qty > 10)]", &Options::new()), 0);
+    }
+}
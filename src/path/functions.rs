@@ -0,0 +1,143 @@
+use serde_json::Value;
+use crate::parser::model::{JsonPath, JsonPathIndex, Operand};
+use crate::path::Options;
+
+/// the types of the arguments and the results of the function extensions(RFC 9535)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FnType {
+    /// the single json value or nothing when there is no such one
+    Value,
+    /// the list of the elements found by the path
+    Nodes,
+}
+
+/// the types the function takes and gives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Signature {
+    pub(crate) args: &'static [FnType],
+    pub(crate) result: FnType,
+}
+
+/// the signature of the built-in function
+pub(crate) fn signature(name: &str) -> Option<Signature> {
+    match name {
+        "length" => Some(Signature { args: &[FnType::Value], result: FnType::Value }),
+        "count" => Some(Signature { args: &[FnType::Nodes], result: FnType::Value }),
+        "value" => Some(Signature { args: &[FnType::Nodes], result: FnType::Value }),
+        _ => None
+    }
+}
+
+/// the elements the operand of the filter gives,
+/// either found in the document or computed like the lengths
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Found<'a> {
+    Nodes(Vec<&'a Value>),
+    Values(Vec<Value>),
+}
+
+impl<'a> Found<'a> {
+    pub(crate) fn values(&self) -> Vec<&Value> {
+        match self {
+            Found::Nodes(nodes) => nodes.clone(),
+            Found::Values(values) => values.iter().collect(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Found::Nodes(nodes) => nodes.len(),
+            Found::Values(values) => values.len(),
+        }
+    }
+
+    /// the single value, the empty and the several ones give nothing
+    fn single(&self) -> Option<&Value> {
+        match self {
+            Found::Nodes(nodes) if nodes.len() == 1 => Some(nodes[0]),
+            Found::Values(values) if values.len() == 1 => Some(&values[0]),
+            _ => None
+        }
+    }
+}
+
+/// calls the built-in function, the unknown ones give nothing
+pub(crate) fn call<'a>(name: &str, args: Vec<Found<'a>>) -> Found<'a> {
+    let computed = |res: Option<Value>| Found::Values(res.into_iter().collect());
+    match name {
+        "length" => computed(args.first().and_then(Found::single).and_then(length)),
+        "count" => computed(args.first().map(|nodes| Value::from(nodes.len()))),
+        // the found element itself, the several ones give nothing
+        "value" => args.into_iter().next().filter(|nodes| nodes.len() == 1).unwrap_or(Found::Nodes(vec![])),
+        _ => Found::Nodes(vec![])
+    }
+}
+
+/// the first function of the query that is neither built in nor added by the options
+pub(crate) fn unknown<'p>(path: &'p JsonPath, options: &Options) -> Option<&'p str> {
+    fn operand<'p>(op: &'p Operand, options: &Options) -> Option<&'p str> {
+        match op {
+            Operand::Static(_) | Operand::Variable(_) => None,
+            Operand::Dynamic(path) => unknown(path, options),
+            Operand::Function(name, _) if signature(name).is_none() && !options.functions.contains_key(name.as_ref()) => Some(name),
+            Operand::Function(_, args) => args.iter().find_map(|a| operand(a, options)),
+        }
+    }
+    match path {
+        JsonPath::Chain(elems) => elems.iter().find_map(|p| unknown(p, options)),
+        JsonPath::Current(tail) => unknown(tail, options),
+        JsonPath::Index(JsonPathIndex::Filter(l, _, r)) => operand(l, options).or_else(|| operand(r, options)),
+        JsonPath::Index(JsonPathIndex::Logical(expr)) =>
+            expr.atoms().into_iter().find_map(|(l, _, r)| operand(l, options).or_else(|| operand(r, options))),
+        JsonPath::Index(JsonPathIndex::Union(sels)) => sels.iter().find_map(|p| unknown(p, options)),
+        _ => None
+    }
+}
+
+/// the number of the characters of the string, the elements of the array or the members of the object
+fn length(v: &Value) -> Option<Value> {
+    match v {
+        Value::String(s) => Some(Value::from(s.chars().count())),
+        Value::Array(elems) => Some(Value::from(elems.len())),
+        Value::Object(fields) => Some(Value::from(fields.len())),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::path::functions::{call, Found};
+
+    #[test]
+    fn length_test() {
+        let length = |v| call("length", vec![Found::Values(vec![v])]);
+        assert_eq!(length(json!("Blåbær")), Found::Values(vec![json!(6)]));
+        assert_eq!(length(json!([1, [2, 3]])), Found::Values(vec![json!(2)]));
+        assert_eq!(length(json!({"a": 1, "b": 2, "c": 3})), Found::Values(vec![json!(3)]));
+        assert_eq!(length(json!(12)), Found::Values(vec![]));
+        assert_eq!(length(json!(null)), Found::Values(vec![]));
+
+        let (a, b) = (json!("a"), json!("bb"));
+        assert_eq!(call("length", vec![Found::Nodes(vec![&a, &b])]), Found::Values(vec![]));
+        assert_eq!(call("length", vec![Found::Nodes(vec![])]), Found::Values(vec![]));
+        assert_eq!(call("length", vec![Found::Nodes(vec![&b])]), Found::Values(vec![json!(2)]));
+    }
+
+    #[test]
+    fn count_test() {
+        let (a, b) = (json!("a"), json!(["b", "c"]));
+        assert_eq!(call("count", vec![Found::Nodes(vec![&a, &b])]), Found::Values(vec![json!(2)]));
+        assert_eq!(call("count", vec![Found::Nodes(vec![&b])]), Found::Values(vec![json!(1)]));
+        assert_eq!(call("count", vec![Found::Nodes(vec![])]), Found::Values(vec![json!(0)]));
+    }
+
+    #[test]
+    fn value_test() {
+        let (a, b) = (json!("a"), json!(["b", "c"]));
+        assert_eq!(call("value", vec![Found::Nodes(vec![&b])]), Found::Nodes(vec![&b]));
+        assert_eq!(call("value", vec![Found::Nodes(vec![&a, &b])]), Found::Nodes(vec![]));
+        assert_eq!(call("value", vec![Found::Nodes(vec![])]), Found::Nodes(vec![]));
+        assert_eq!(call("value", vec![Found::Values(vec![json!(1)])]), Found::Values(vec![json!(1)]));
+    }
+}
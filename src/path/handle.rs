@@ -0,0 +1,85 @@
+use serde_json::Value;
+use crate::parser::model::JsonPath;
+use crate::path::normalized::NormalizedPath;
+
+/// The found element referred to by the id of the document and the location inside of it.
+///
+/// The handle owns everything it holds, so it is `Send + 'static` as long as the id is
+/// and can travel over the channels to the other threads, where it is resolved against the same document
+/// without cloning the found value.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MatchHandle<Id> {
+    id: Id,
+    path: NormalizedPath,
+}
+
+impl<Id> MatchHandle<Id> {
+    pub fn new(id: Id, path: NormalizedPath) -> Self {
+        MatchHandle { id, path }
+    }
+
+    /// the id of the document the element has been found in
+    pub fn id(&self) -> &Id {
+        &self.id
+    }
+
+    /// the location of the element in the document
+    pub fn path(&self) -> &NormalizedPath {
+        &self.path
+    }
+
+    /// finds the element in the document, None if the document does not have it(anymore)
+    pub fn resolve<'a>(&self, doc: &'a Value) -> Option<&'a Value> {
+        self.path.resolve(doc)
+    }
+
+    pub fn into_parts(self) -> (Id, NormalizedPath) {
+        (self.id, self.path)
+    }
+}
+
+impl JsonPath<'_> {
+    /// finds the elements as the handles carrying the id of the document and the locations of the elements
+    pub fn find_handles<Id: Clone>(&self, id: Id, doc: &Value) -> Vec<MatchHandle<Id>> {
+        let mut res = vec![];
+        self.find_with(doc, |path, _| res.push(MatchHandle::new(id.clone(), path.clone())));
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::mpsc::channel;
+    use std::sync::Arc;
+    use serde_json::json;
+    use crate::parser::parser::parse_json_path;
+    use crate::path::handle::MatchHandle;
+
+    #[test]
+    fn handles_test() {
+        let docs: Arc<HashMap<&str, serde_json::Value>> = Arc::new(vec![
+            ("a", json!({"items":[{"id":1},{"id":2}]})),
+            ("b", json!({"items":[{"id":3}]})),
+        ].into_iter().collect());
+
+        let (tx, rx) = channel::<MatchHandle<&'static str>>();
+        let path = parse_json_path("$.items[*].id").unwrap();
+        for id in ["a", "b"] {
+            path.find_handles(id, &docs[id]).into_iter().for_each(|h| tx.send(h).unwrap());
+        }
+        drop(tx);
+
+        let resolver = {
+            let docs = docs.clone();
+            std::thread::spawn(move || rx.iter().map(|h| (*h.id(), h.resolve(&docs[h.id()]).cloned())).collect::<Vec<_>>())
+        };
+        assert_eq!(resolver.join().unwrap(),
+                   vec![("a", Some(json!(1))), ("a", Some(json!(2))), ("b", Some(json!(3)))]);
+
+        let handle = path.find_handles(7u64, &docs["a"]).pop().unwrap();
+        assert_eq!(handle.path().to_string(), "$['items'][1]['id']");
+        assert_eq!(handle.resolve(&json!({"items":[]})), None);
+        assert_eq!(handle.into_parts().0, 7);
+    }
+}
@@ -1,56 +1,72 @@
-use crate::path::{Path, PathInstance, json_path_instance, process_operand};
+use crate::path::{Path, PathInstance, Env, Options, instance, process_operand};
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::cell::OnceCell;
 use serde_json::Value;
-use crate::parser::model::{JsonPath, FilterSign, Operand};
+use crate::parser::model::{JsonPath, FilterExpression, FilterSign, Operand};
 use crate::path::json::*;
 use serde_json::value::Value::{Array, Object};
-use crate::path::top::ObjectField;
+use crate::path::top::{Chain, ObjectField};
+use crate::path::{extjson, functions, non_finite, options, pattern};
+use crate::path::functions::Found;
+use crate::path::options::FilterFunction;
+use std::sync::Arc;
+use crate::path::pattern::Pattern;
+use crate::path::profile::FilterStats;
+use std::time::Instant;
 
 /// process the slice like [start:end:step]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct ArraySlice {
-    start_index: i32,
-    end_index: i32,
-    step: usize,
+    start_index: Option<i64>,
+    end_index: Option<i64>,
+    step: i64,
 }
 
 impl ArraySlice {
-    pub(crate) fn new(start_index: i32,
-                      end_index: i32,
-                      step: usize, ) -> ArraySlice {
+    pub(crate) fn new(start_index: Option<i64>,
+                      end_index: Option<i64>,
+                      step: i64, ) -> ArraySlice {
         ArraySlice { start_index, end_index, step }
     }
 
-    fn end(&self, len: i32) -> Option<usize> {
-        if self.end_index >= 0 {
-            if self.end_index > len { None } else { Some(self.end_index as usize) }
+    /// the position the bound points out to clamped to the range, the negative ones count from the end
+    fn bound(idx: i64, len: i64, low: i64, high: i64) -> i64 {
+        let idx = if idx >= 0 { idx } else { len + idx };
+        idx.max(low).min(high)
+    }
+
+    /// the first position and the position to stop before for the direction of the step
+    fn bounds(&self, len: i64) -> (i64, i64) {
+        if self.step > 0 {
+            (self.start_index.map(|s| ArraySlice::bound(s, len, 0, len)).unwrap_or(0),
+             self.end_index.map(|e| ArraySlice::bound(e, len, 0, len)).unwrap_or(len))
         } else {
-            if self.end_index < -len { None } else { Some((len - (-self.end_index)) as usize) }
+            (self.start_index.map(|s| ArraySlice::bound(s, len, -1, len - 1)).unwrap_or(len - 1),
+             self.end_index.map(|e| ArraySlice::bound(e, len, -1, len - 1)).unwrap_or(-1))
         }
     }
 
-    fn start(&self, len: i32) -> Option<usize> {
-        if self.start_index >= 0 {
-            if self.start_index > len { None } else { Some(self.start_index as usize) }
-        } else {
-            if self.start_index < -len { None } else { Some((len - -self.start_index) as usize) }
+    /// the positions of the selected elements in the array of the given length.
+    /// The negative step walks the array backwards, the zero step selects nothing.
+    pub(crate) fn indexes(&self, len: usize) -> Vec<usize> {
+        if self.step == 0 {
+            return vec![];
         }
+        let (mut i, end) = self.bounds(i64::try_from(len).unwrap_or(i64::MAX));
+        let mut res = vec![];
+        while (self.step > 0 && i < end) || (self.step < 0 && i > end) {
+            res.extend(usize::try_from(i).ok().filter(|i| *i < len));
+            i = match i.checked_add(self.step) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+        res
     }
 
     fn process<'a, T>(&self, elements: &'a [T]) -> Vec<&'a T> {
-        let len = elements.len() as i32;
-        let mut filtered_elems: Vec<&T> = vec![];
-        match (self.start(len), self.end(len)) {
-            (Some(start_idx), Some(end_idx)) => {
-                let end_idx = if end_idx == 0 { elements.len() } else { end_idx };
-                for idx in (start_idx..end_idx).step_by(self.step) {
-                    if let Some(v) = elements.get(idx) {
-                        filtered_elems.push(v)
-                    }
-                }
-                filtered_elems
-            }
-            _ => filtered_elems
-        }
+        self.indexes(elements.len()).into_iter().filter_map(|idx| elements.get(idx)).collect()
     }
 }
 
@@ -64,13 +80,13 @@ impl<'a> Path<'a> for ArraySlice {
     }
 }
 
-/// process the simple index like [index]
+/// process the simple index like [index], the negative one counts from the end
 pub(crate) struct ArrayIndex {
-    index: usize
+    index: i64
 }
 
 impl ArrayIndex {
-    pub(crate) fn new(index: usize) -> Self {
+    pub(crate) fn new(index: i64) -> Self {
         ArrayIndex { index }
     }
 }
@@ -80,7 +96,7 @@ impl<'a> Path<'a> for ArrayIndex {
 
     fn find(&self, data: &'a Self::Data) -> Vec<&'a Self::Data> {
         data.as_array()
-            .and_then(|elems| elems.get(self.index))
+            .and_then(|elems| elems.get(position(self.index, elems.len())?))
             .map(|e| vec![e])
             .unwrap_or_default()
     }
@@ -92,10 +108,10 @@ pub(crate) struct Current<'a> {
 }
 
 impl<'a> Current<'a> {
-    pub(crate) fn from(jp: &'a JsonPath, root: &'a Value) -> Self {
+    pub(crate) fn from(jp: &'a JsonPath, env: &Env<'a>) -> Self {
         match jp {
             JsonPath::Empty => Current::none(),
-            tail => Current::new(json_path_instance(tail, root))
+            tail => Current::new(instance(tail, env))
         }
     }
     pub(crate) fn new(tail: PathInstance<'a>) -> Self {
@@ -123,13 +139,13 @@ impl<'a> UnionIndex<'a> {
     pub fn from_indexes(elems: &'a [Value]) -> Self {
         let mut indexes: Vec<PathInstance<'a>> = vec![];
 
-        for idx in elems.iter() {
-            indexes.push(Box::new(ArrayIndex::new(idx.as_u64().unwrap() as usize)))
+        for idx in elems.iter().filter_map(as_index) {
+            indexes.push(Box::new(ArrayIndex::new(idx)))
         }
 
         UnionIndex::new(indexes)
     }
-    pub fn from_keys(elems: &'a [String]) -> Self {
+    pub fn from_keys(elems: &'a [Cow<'_, str>]) -> Self {
         let mut indexes: Vec<PathInstance<'a>> = vec![];
 
         for key in elems.iter() {
@@ -152,44 +168,208 @@ impl<'a> Path<'a> for UnionIndex<'a> {
     }
 }
 
+/// the operand of the filter.
+/// The operands not depending on the current element(the literals and the paths starting from the root)
+/// are evaluated once for all the elements.
+/// The operand ending with `.length` like `@.tags.length` gives the lengths of the arrays and strings it finds.
+struct FilterOperand<'a> {
+    source: Source<'a>,
+    constant: bool,
+    cache: OnceCell<Vec<&'a Value>>,
+}
+
+enum Source<'a> {
+    /// the literal or the path
+    Path(PathInstance<'a>),
+    /// the path without the trailing `.length`
+    Length(PathInstance<'a>),
+    /// the function extension over the operands
+    Function(&'a str, Vec<FilterOperand<'a>>),
+    /// the function added by the options
+    Custom(Arc<dyn FilterFunction>, Vec<FilterOperand<'a>>),
+}
+
+impl<'a> FilterOperand<'a> {
+    fn new(op: &'a Operand, env: &Env<'a>) -> Self {
+        let constant = match op {
+            Operand::Static(_) | Operand::Variable(_) => true,
+            Operand::Dynamic(jp) => matches!(jp.as_ref(), JsonPath::Root)
+                || matches!(jp.as_ref(), JsonPath::Chain(elems) if matches!(elems.first(), Some(JsonPath::Root))),
+            Operand::Function(..) => false,
+        };
+        let source = match op {
+            Operand::Dynamic(jp) => match without_length(jp, env) {
+                Some(path) => Source::Length(path),
+                None => Source::Path(process_operand(op, env)),
+            },
+            Operand::Static(_) | Operand::Variable(_) => Source::Path(process_operand(op, env)),
+            Operand::Function(name, args) => {
+                let args = args.iter().map(|a| FilterOperand::new(a, env)).collect();
+                match env.options.functions.get(name.as_ref()) {
+                    Some(f) if functions::signature(name).is_none() => Source::Custom(f.clone(), args),
+                    _ => Source::Function(name, args),
+                }
+            }
+        };
+        FilterOperand { source, constant, cache: OnceCell::new() }
+    }
+
+    fn find(&self, el: &'a Value) -> Found<'a> {
+        match &self.source {
+            Source::Path(path) => Found::Nodes(self.nodes(path, el)),
+            Source::Length(path) => Found::Values(lengths(&self.nodes(path, el))),
+            Source::Function(name, args) => functions::call(name, args.iter().map(|a| a.find(el)).collect()),
+            Source::Custom(f, args) => {
+                let args: Vec<Found<'a>> = args.iter().map(|a| a.find(el)).collect();
+                let args: Vec<Vec<&Value>> = args.iter().map(Found::values).collect();
+                Found::Values(f.call(&args).into_iter().collect())
+            }
+        }
+    }
+
+    fn nodes(&self, path: &PathInstance<'a>, el: &'a Value) -> Vec<&'a Value> {
+        if self.constant {
+            self.cache.get_or_init(|| path.find(el)).clone()
+        } else {
+            path.find(el)
+        }
+    }
+}
+
+/// the lengths of the found elements, the objects give their member `length` instead
+pub(crate) fn lengths(found: &[&Value]) -> Vec<Value> {
+    found.iter().filter_map(|v| match v {
+        Value::String(s) => Some(Value::from(s.chars().count())),
+        Array(elems) => Some(Value::from(elems.len())),
+        Object(fields) => fields.get("length").cloned(),
+        _ => None
+    }).collect()
+}
+
+/// the instance of the path without the trailing `.length` if the path has got it
+fn without_length<'a>(jp: &'a JsonPath, env: &Env<'a>) -> Option<PathInstance<'a>> {
+    match jp {
+        JsonPath::Chain(elems) => match elems.split_last()? {
+            (JsonPath::Field(key), prefix) if key == "length" => Some(Box::new(Chain::from(prefix, env))),
+            (last, prefix) => {
+                let last = without_length(last, env)?;
+                let mut chain: Vec<PathInstance<'a>> = prefix.iter().map(|p| instance(p, env)).collect();
+                chain.push(last);
+                Some(Box::new(Chain::new(chain)))
+            }
+        },
+        JsonPath::Current(tail) => Some(Box::new(Current::new(without_length(tail, env)?))),
+        _ => None
+    }
+}
+
 /// process filter element like [?(op sign op)]
 pub(crate) struct Filter<'a> {
-    left: PathInstance<'a>,
-    right: PathInstance<'a>,
+    left: FilterOperand<'a>,
+    right: FilterOperand<'a>,
     op: &'a FilterSign,
+    env: Env<'a>,
+    /// the regex given by the literal compiled before checking the elements
+    pattern: Option<Pattern>,
+    /// the function standing alone like `[?(has(@.tags, 'a'))]` matches unless it gives nothing or `false`
+    test: bool,
 }
 
 impl<'a> Filter<'a> {
     pub(crate) fn new(left: &'a Operand, right: &'a Operand, op: &'a FilterSign, root: &'a Value) -> Self {
+        Filter::with_env(left, right, op, Env::new(root))
+    }
+
+    pub(crate) fn with_env(left: &'a Operand, right: &'a Operand, op: &'a FilterSign, env: Env<'a>) -> Self {
+        let pattern = match (op, right) {
+            (FilterSign::Regex, Operand::Static(Value::String(p))) => pattern::compile(p).ok(),
+            _ => None
+        };
         Filter {
-            left: process_operand(left, root),
-            right: process_operand(right, root),
+            left: FilterOperand::new(left, &env),
+            right: FilterOperand::new(right, &env),
+            test: matches!((op, left), (FilterSign::Exists, Operand::Function(..))),
             op,
+            env,
+            pattern,
         }
     }
 
-    fn or(one: &'a FilterSign, two: &'a FilterSign, left: Vec<&'a Value>, right: Vec<&'a Value>) -> bool {
-        Filter::process(one, left.clone(), right.clone())
-            || Filter::process(two, left.clone(), right.clone())
-    }
-
-    fn process(op: &'a FilterSign, left: Vec<&'a Value>, right: Vec<&'a Value>) -> bool {
-        match op {
-            FilterSign::Equal => eq(left, right),
-            FilterSign::Unequal => !Filter::process(&FilterSign::Equal, left, right),
-            FilterSign::Less => less(left, right),
-            FilterSign::LeOrEq => Filter::or(&FilterSign::Less, &FilterSign::Equal, left, right),
-            FilterSign::Greater => !Filter::process(&FilterSign::LeOrEq, left, right),
-            FilterSign::GrOrEq => !Filter::process(&FilterSign::Less, left, right),
-            FilterSign::Regex => regex(left, right),
-            FilterSign::In => inside(left, right),
-            FilterSign::Nin => !Filter::process(&FilterSign::In, left, right),
-            FilterSign::NoneOf => !Filter::process(&FilterSign::AnyOf, left, right),
-            FilterSign::AnyOf => any_of(left, right),
-            FilterSign::SubSetOf => sub_set_of(left, right),
-            FilterSign::Exists => !left.is_empty(),
-            FilterSign::Size => size(left, right)
+    /// checks whether the element satisfies the filter
+    pub(crate) fn check(&self, el: &'a Value) -> bool {
+        let (left, right) = (self.left.find(el), self.right.find(el));
+        self.compare(left.values(), right.values())
+    }
+
+    fn compare<'v>(&self, left: Vec<&'v Value>, right: Vec<&'v Value>) -> bool {
+        compare(&self.env.options, self.env.error.as_deref(), self.op, self.pattern.as_ref(), self.test, left, right)
+    }
+
+    /// the same as [Filter::check] measuring the time of every part of the expression
+    pub(crate) fn check_timed(&self, el: &'a Value, stats: &mut FilterStats) -> bool {
+        let start = Instant::now();
+        let left = self.left.find(el);
+        let after_left = Instant::now();
+        let right = self.right.find(el);
+        let after_right = Instant::now();
+        stats.checks += 1;
+        stats.left_nodes += left.len();
+        stats.right_nodes += right.len();
+        stats.left += after_left - start;
+        stats.right += after_right - after_left;
+        let res = self.compare(left.values(), right.values());
+        stats.compare += after_right.elapsed();
+        res
+    }
+
+}
+
+/// compares the operands of the filter the way the sign says following the options,
+/// the error the options run into goes to the cell if anyone listens
+pub(crate) fn compare<'v>(options: &Options, error: Option<&OnceCell<String>>, op: &FilterSign, pattern: Option<&Pattern>,
+                          test: bool, left: Vec<&'v Value>, right: Vec<&'v Value>) -> bool {
+    if test {
+        return left.iter().any(|v| **v != Value::Bool(false));
+    }
+    let compared = non_finite::compare(options.non_finite, op, &left, &right)
+        .or_else(|| options::collate(options, op, &left, &right).map(Ok))
+        .or_else(|| options.extended_json.then(|| extjson::compare(op, &left, &right)).flatten().map(Ok));
+    match compared {
+        Some(Ok(res)) => res,
+        Some(Err(e)) => {
+            if let Some(error) = error {
+                let _ = error.set(e);
+            }
+            false
         }
+        None => match pattern {
+            Some(p) => matches(&left, p),
+            None => process(op, left, right),
+        },
+    }
+}
+
+fn or<'v>(one: &FilterSign, two: &FilterSign, left: Vec<&'v Value>, right: Vec<&'v Value>) -> bool {
+    process(one, left.clone(), right.clone())
+        || process(two, left.clone(), right.clone())
+}
+
+fn process<'v>(op: &FilterSign, left: Vec<&'v Value>, right: Vec<&'v Value>) -> bool {
+    match op {
+        FilterSign::Equal => eq(left, right),
+        FilterSign::Unequal => !process(&FilterSign::Equal, left, right),
+        FilterSign::Less => less(left, right),
+        FilterSign::LeOrEq => or(&FilterSign::Less, &FilterSign::Equal, left, right),
+        FilterSign::Greater => less(right, left),
+        FilterSign::GrOrEq => or(&FilterSign::Greater, &FilterSign::Equal, left, right),
+        FilterSign::Regex => regex(left, right),
+        FilterSign::In => inside(left, right),
+        FilterSign::Nin => !process(&FilterSign::In, left, right),
+        FilterSign::NoneOf => !process(&FilterSign::AnyOf, left, right),
+        FilterSign::AnyOf => any_of(left, right),
+        FilterSign::SubSetOf => sub_set_of(left, right),
+        FilterSign::Exists => !left.is_empty(),
+        FilterSign::Size => size(left, right)
     }
 }
 
@@ -202,7 +382,7 @@ impl<'a> Path<'a> for Filter<'a> {
         match data {
             Array(elems) => {
                 for el in elems.iter() {
-                    if Filter::process(&self.op, self.left.find(el), self.right.find(el)) {
+                    if self.check(el) {
                         res.push(el)
                     }
                 }
@@ -210,7 +390,7 @@ impl<'a> Path<'a> for Filter<'a> {
             }
             Object(pairs) => {
                 for el in pairs.values() {
-                    if Filter::process(&self.op, self.left.find(el), self.right.find(el)) {
+                    if self.check(el) {
                         res.push(el)
                     }
                 }
@@ -221,70 +401,137 @@ impl<'a> Path<'a> for Filter<'a> {
     }
 }
 
+/// process the logical expression of the filter like [?(@.a > 1 && !@.b)]
+pub(crate) enum Logical<'a> {
+    Atom(Box<Filter<'a>>),
+    And(Box<Logical<'a>>, Box<Logical<'a>>),
+    Or(Box<Logical<'a>>, Box<Logical<'a>>),
+    Not(Box<Logical<'a>>),
+}
+
+impl<'a> Logical<'a> {
+    pub(crate) fn new(expr: &'a FilterExpression, env: &Env<'a>) -> Self {
+        match expr {
+            FilterExpression::Atom(l, op, r) => Logical::Atom(Box::new(Filter::with_env(l, r, op, env.clone()))),
+            FilterExpression::And(l, r) => Logical::And(Box::new(Logical::new(l, env)), Box::new(Logical::new(r, env))),
+            FilterExpression::Or(l, r) => Logical::Or(Box::new(Logical::new(l, env)), Box::new(Logical::new(r, env))),
+            FilterExpression::Not(expr) => Logical::Not(Box::new(Logical::new(expr, env))),
+        }
+    }
+
+    /// checks whether the element satisfies the expression evaluating the right side only if it is needed
+    pub(crate) fn check(&self, el: &'a Value) -> bool {
+        match self {
+            Logical::Atom(filter) => filter.check(el),
+            Logical::And(l, r) => l.check(el) && r.check(el),
+            Logical::Or(l, r) => l.check(el) || r.check(el),
+            Logical::Not(expr) => !expr.check(el),
+        }
+    }
+
+    /// the same as [Logical::check] summing up the time of all the comparisons
+    pub(crate) fn check_timed(&self, el: &'a Value, stats: &mut FilterStats) -> bool {
+        match self {
+            Logical::Atom(filter) => filter.check_timed(el, stats),
+            Logical::And(l, r) => l.check_timed(el, stats) && r.check_timed(el, stats),
+            Logical::Or(l, r) => l.check_timed(el, stats) || r.check_timed(el, stats),
+            Logical::Not(expr) => !expr.check_timed(el, stats),
+        }
+    }
+}
+
+impl<'a> Path<'a> for Logical<'a> {
+    type Data = Value;
+
+    fn find(&self, data: &'a Self::Data) -> Vec<&'a Self::Data> {
+        match data {
+            Array(elems) => elems.iter().filter(|el| self.check(el)).collect(),
+            Object(pairs) => pairs.values().filter(|el| self.check(el)).collect(),
+            _ => vec![]
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use serde_json::Value;
+use serde_json::Value;
     use serde_json::json;
     use crate::parser::model::{JsonPath, JsonPathIndex, Operand, FilterSign};
     use crate::path::index::{ArraySlice, ArrayIndex};
     use crate::path::{Path, json_path_instance};
 
     #[test]
-    fn array_slice_end_start_test() {
-        let array = vec![0, 1, 2, 3, 4, 5];
-        let len = array.len() as i32;
-        let mut slice = ArraySlice::new(0, 0, 0);
-
-        assert_eq!(slice.start(len).unwrap(), 0);
-        slice.start_index = 1;
-
-        assert_eq!(slice.start(len).unwrap(), 1);
-
-        slice.start_index = 2;
-        assert_eq!(slice.start(len).unwrap(), 2);
-
-        slice.start_index = 5;
-        assert_eq!(slice.start(len).unwrap(), 5);
-
-        slice.start_index = 7;
-        assert_eq!(slice.start(len), None);
-
-        slice.start_index = -1;
-        assert_eq!(slice.start(len).unwrap(), 5);
-
-        slice.start_index = -5;
-        assert_eq!(slice.start(len).unwrap(), 1);
-
-        slice.end_index = 0;
-        assert_eq!(slice.end(len).unwrap(), 0);
-
-        slice.end_index = 5;
-        assert_eq!(slice.end(len).unwrap(), 5);
+    fn array_slice_bounds_test() {
+        let len = 6;
+        let mut slice = ArraySlice::new(None, None, 1);
+        assert_eq!(slice.bounds(len), (0, 6));
+
+        slice.start_index = Some(2);
+        slice.end_index = Some(5);
+        assert_eq!(slice.bounds(len), (2, 5));
+
+        slice.start_index = Some(7);
+        slice.end_index = Some(-1);
+        assert_eq!(slice.bounds(len), (6, 5));
+
+        slice.start_index = Some(-7);
+        slice.end_index = Some(-5);
+        assert_eq!(slice.bounds(len), (0, 1));
+
+        slice.step = -1;
+        slice.start_index = None;
+        slice.end_index = None;
+        assert_eq!(slice.bounds(len), (5, -1));
+
+        slice.start_index = Some(7);
+        slice.end_index = Some(-7);
+        assert_eq!(slice.bounds(len), (5, -1));
+
+        slice.start_index = Some(-2);
+        slice.end_index = Some(1);
+        assert_eq!(slice.bounds(len), (4, 1));
+    }
 
-        slice.end_index = -1;
-        assert_eq!(slice.end(len).unwrap(), 5);
+    #[test]
+    fn reverse_slice_test() {
+        let slice = |s, e, step| ArraySlice::new(s, e, step).indexes(6);
+        assert_eq!(slice(None, None, -1), vec![5, 4, 3, 2, 1, 0]);
+        assert_eq!(slice(Some(5), Some(1), -2), vec![5, 3]);
+        assert_eq!(slice(Some(1), Some(5), -1), Vec::<usize>::new());
+        assert_eq!(slice(Some(-1), None, -3), vec![5, 2]);
+        assert_eq!(slice(Some(100), Some(-100), -4), vec![5, 1]);
+        assert_eq!(slice(None, None, i64::MIN), vec![5]);
+        assert_eq!(slice(Some(1), Some(100), 2), vec![1, 3, 5]);
+        assert_eq!(slice(Some(-100), Some(2), 1), vec![0, 1]);
+    }
 
-        slice.end_index = -5;
-        assert_eq!(slice.end(len).unwrap(), 1);
+    #[test]
+    fn huge_slice_test() {
+        let len = 4_000_000_000;
+        assert_eq!(ArraySlice::new(Some(3_000_000_000), Some(3_000_000_002), 1).indexes(len), vec![3_000_000_000, 3_000_000_001]);
+        assert_eq!(ArraySlice::new(Some(-2), None, 1).indexes(len), vec![3_999_999_998, 3_999_999_999]);
+        assert_eq!(ArraySlice::new(Some(-4_000_000_000), Some(-3_999_999_999), 1).indexes(len), vec![0]);
+        assert_eq!(ArraySlice::new(Some(i64::MIN), Some(i64::MAX), 1).indexes(3), vec![0, 1, 2]);
+        assert_eq!(ArraySlice::new(None, None, i64::MAX).indexes(3), vec![0]);
     }
 
     #[test]
     fn slice_test() {
         let array = json!([0,1,2,3,4,5,6,7,8,9,10]);
 
-        let mut slice = ArraySlice::new(0, 6, 2);
+        let mut slice = ArraySlice::new(Some(0), Some(6), 2);
         assert_eq!(slice.find(&array), vec![&json!(0), &json!(2), &json!(4)]);
 
         slice.step = 3;
         assert_eq!(slice.find(&array), vec![&json!(0), &json!(3)]);
 
-        slice.start_index = -1;
-        slice.end_index = 1;
+        slice.start_index = Some(-1);
+        slice.end_index = Some(1);
 
         assert!(slice.find(&array).is_empty());
 
-        slice.start_index = -10;
-        slice.end_index = 10;
+        slice.start_index = Some(-10);
+        slice.end_index = Some(10);
 
         assert_eq!(slice.find(&array), vec![&json!(1), &json!(4), &json!(7)]);
     }
@@ -300,6 +547,14 @@ mod tests {
         assert_eq!(index.find(&array), vec![&json!(10)]);
         index.index = 100;
         assert!(index.find(&array).is_empty());
+        index.index = -1;
+        assert_eq!(index.find(&array), vec![&json!(10)]);
+        index.index = -11;
+        assert_eq!(index.find(&array), vec![&json!(0)]);
+        index.index = -12;
+        assert!(index.find(&array).is_empty());
+        index.index = i64::MIN;
+        assert!(index.find(&array).is_empty());
     }
 
     #[test]
@@ -315,7 +570,7 @@ mod tests {
         });
 
         let root = JsonPath::Root;
-        let object = JsonPath::Field(String::from("object"));
+        let object = JsonPath::field("object");
         let cur = JsonPath::Current(Box::new(JsonPath::Empty));
 
         let chain = vec![root.clone(), object.clone(), cur.clone()];
@@ -331,8 +586,8 @@ mod tests {
         let expected_res = vec![&res];
         assert_eq!(path_inst.find(&json), expected_res);
 
-        let field_3 = JsonPath::Field(String::from("field_3"));
-        let field_a = JsonPath::Field(String::from("a"));
+        let field_3 = JsonPath::field("field_3");
+        let field_a = JsonPath::field("a");
         let chain_in = vec![field_3, field_a];
         let chain_in = JsonPath::Chain(chain_in);
         let cur = JsonPath::Current(Box::new(chain_in));
@@ -354,14 +609,14 @@ mod tests {
         });
 
 
-        let field = JsonPath::Field(String::from("field"));
+        let field = JsonPath::field("field");
         let cur = JsonPath::Current(Box::new(field.clone()));
         let operand = Operand::Dynamic(Box::new(cur));
         let empty_operand = Operand::Static(Value::Null);
 
 
         let root = JsonPath::Root;
-        let key = JsonPath::Field(String::from("key"));
+        let key = JsonPath::field("key");
         let filter = JsonPathIndex::Filter(operand, FilterSign::Exists, empty_operand);
         let index = JsonPath::Index(filter);
 
@@ -388,14 +643,14 @@ mod tests {
             });
 
 
-        let field = JsonPath::Field(String::from("field"));
+        let field = JsonPath::field("field");
         let cur = JsonPath::Current(Box::new(field));
         let operand = Operand::Dynamic(Box::new(cur));
         let right_operand = Operand::Static(json!(1));
 
 
         let root = JsonPath::Root;
-        let key = JsonPath::Field(String::from("key"));
+        let key = JsonPath::field("key");
         let filter = JsonPathIndex::Filter(operand, FilterSign::Greater, right_operand);
         let index = JsonPath::Index(filter);
 
@@ -410,6 +665,7 @@ mod tests {
         assert_eq!(path_inst.find(&json), expected_res)
     }
 
+    #[cfg(feature = "regex")]
     #[test]
     fn filter_regex_test() {
         let json = json!({
@@ -422,14 +678,14 @@ mod tests {
             });
 
 
-        let field = JsonPath::Field(String::from("field"));
+        let field = JsonPath::field("field");
         let cur = JsonPath::Current(Box::new(field));
         let operand = Operand::Dynamic(Box::new(cur));
         let right_operand = Operand::Static(json!("[a-zA-Z]+[0-9]#[0-9]+"));
 
 
         let root = JsonPath::Root;
-        let key = JsonPath::Field(String::from("key"));
+        let key = JsonPath::field("key");
         let filter = JsonPathIndex::Filter(operand, FilterSign::Regex, right_operand);
         let index = JsonPath::Index(filter);
 
@@ -455,14 +711,14 @@ mod tests {
             });
 
 
-        let field = JsonPath::Field(String::from("field"));
+        let field = JsonPath::field("field");
         let cur = JsonPath::Current(Box::new(field));
         let operand = Operand::Dynamic(Box::new(cur));
         let right_operand = Operand::Static(json!(["a11#","aaa","111"]));
 
 
         let root = JsonPath::Root;
-        let key = JsonPath::Field(String::from("key"));
+        let key = JsonPath::field("key");
         let filter = JsonPathIndex::Filter(operand, FilterSign::AnyOf, right_operand);
         let index = JsonPath::Index(filter);
 
@@ -489,14 +745,14 @@ mod tests {
             });
 
 
-        let field = JsonPath::Field(String::from("field"));
+        let field = JsonPath::field("field");
         let cur = JsonPath::Current(Box::new(field));
         let operand = Operand::Dynamic(Box::new(cur));
         let right_operand = Operand::Static(json!(4));
 
 
         let root = JsonPath::Root;
-        let key = JsonPath::Field(String::from("key"));
+        let key = JsonPath::field("key");
         let filter = JsonPathIndex::Filter(operand, FilterSign::Size, right_operand);
         let index = JsonPath::Index(filter);
 
@@ -511,4 +767,23 @@ mod tests {
         let expected_res = vec![&exp2, &exp3, &exp4];
         assert_eq!(path_inst.find(&json), expected_res)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn root_operand_test() {
+        let json = json!({"limit":2,"items":[{"v":1},{"v":2},{"v":3},{"v":0}]});
+        let path = crate::parser::parser::parse_json_path("$.items[?(@.v >= $.limit)].v").unwrap();
+        let path_inst = json_path_instance(&path, &json);
+        assert_eq!(path_inst.find(&json), vec![&json!(2), &json!(3)]);
+    }
+
+    #[test]
+    fn greater_over_missing_test() {
+        let json = json!([{"a": 2}, {"b": 2}, {"a": 1}, {}]);
+        for (query, expected) in [("$[?(@.a > 1)]", vec![json!({"a": 2})]),
+                                  ("$[?(@.a >= 1)]", vec![json!({"a": 2}), json!({"a": 1})])] {
+            let path = crate::parser::parser::parse_json_path(query).unwrap();
+            let found: Vec<Value> = json_path_instance(&path, &json).find(&json).into_iter().cloned().collect();
+            assert_eq!(found, expected, "{}", query);
+        }
+    }
+}
@@ -1,15 +1,20 @@
-use serde_json::Value;
-use regex::Regex;
+use std::convert::TryFrom;
+use std::cmp::Ordering;
+use serde_json::{Number, Value};
+use crate::path::pattern;
 
 /// compare sizes of json elements
 /// The method expects to get a number on the right side and array or string or object on the left
 /// where the number of characters, elements or fields will be compared respectively.
 pub fn size(left: Vec<&Value>, right: Vec<&Value>) -> bool {
-    if let Some(Value::Number(n)) = right.get(0) {
+    if left.is_empty() {
+        return false;
+    }
+    if let Some(Value::Number(n)) = right.first() {
         if let Some(sz) = n.as_f64() {
             for el in left.iter() {
                 match el {
-                    Value::String(v) if v.len() == sz as usize => true,
+                    Value::String(v) if v.chars().count() == sz as usize => true,
                     Value::Array(elems) if elems.len() == sz as usize => true,
                     Value::Object(fields) if fields.len() == sz as usize => true,
                     _ => return false
@@ -32,7 +37,7 @@ pub fn sub_set_of(left: Vec<&Value>, right: Vec<&Value>) -> bool {
     }
 
     if let Some(elems) = left.first().and_then(|e| e.as_array()) {
-        if let Some(Value::Array(right_elems)) = right.get(0) {
+        if let Some(Value::Array(right_elems)) = right.first() {
             if right_elems.is_empty() {
                 return false;
             }
@@ -41,7 +46,7 @@ pub fn sub_set_of(left: Vec<&Value>, right: Vec<&Value>) -> bool {
                 let mut res = false;
 
                 for r in right_elems.iter() {
-                    if el.eq(r) {
+                    if same(el, r) {
                         res = true
                     }
                 }
@@ -63,7 +68,7 @@ pub fn any_of(left: Vec<&Value>, right: Vec<&Value>) -> bool {
         return false;
     }
 
-    if let Some(Value::Array(elems)) = right.get(0) {
+    if let Some(Value::Array(elems)) = right.first() {
         if elems.is_empty() {
             return false;
         }
@@ -72,14 +77,14 @@ pub fn any_of(left: Vec<&Value>, right: Vec<&Value>) -> bool {
             if let Some(left_elems) = el.as_array() {
                 for l in left_elems.iter() {
                     for r in elems.iter() {
-                        if l.eq(r) {
+                        if same(l, r) {
                             return true;
                         }
                     }
                 }
             } else {
                 for r in elems.iter() {
-                    if el.eq(&r) {
+                    if same(el, r) {
                         return true;
                     }
                 }
@@ -96,33 +101,27 @@ pub fn regex(left: Vec<&Value>, right: Vec<&Value>) -> bool {
         return false;
     }
 
-    match right.get(0) {
-        Some(Value::String(str)) => {
-            if let Ok(regex) = Regex::new(str) {
-                for el in left.iter() {
-                    if let Some(v) = el.as_str() {
-                        if regex.is_match(v) {
-                            return true;
-                        }
-                    }
-                }
-            }
-            false
-        }
+    match right.first() {
+        Some(Value::String(str)) => pattern::compile(str).map(|p| matches(&left, &p)).unwrap_or(false),
         _ => false
     }
 }
 
+/// ensure that one of the elements on the left side is the string matching the compiled regex
+pub(crate) fn matches(left: &[&Value], pattern: &pattern::Pattern) -> bool {
+    left.iter().filter_map(|el| el.as_str()).any(|v| pattern::is_match(pattern, v))
+}
+
 /// ensure that the element on the left side belongs to the array on the right side.
 pub fn inside(left: Vec<&Value>, right: Vec<&Value>) -> bool {
     if left.is_empty() {
         return false;
     }
 
-    match right.get(0) {
+    match right.first() {
         Some(Value::Array(elems)) => {
             for el in left.iter() {
-                if elems.contains(el) {
+                if elems.iter().any(|r| same(el, r)) {
                     return true;
                 }
             }
@@ -131,7 +130,7 @@ pub fn inside(left: Vec<&Value>, right: Vec<&Value>) -> bool {
         Some(Value::Object(elems)) => {
             for el in left.iter() {
                 for r in elems.values() {
-                    if el.eq(&r) {
+                    if same(el, r) {
                         return true;
                     }
                 }
@@ -142,12 +141,13 @@ pub fn inside(left: Vec<&Value>, right: Vec<&Value>) -> bool {
     }
 }
 
-/// ensure the number on the left side is less the number on the right side
+/// ensure the number on the left side is less the number on the right side,
+/// the strings are compared by their code points(RFC 9535)
 pub fn less(left: Vec<&Value>, right: Vec<&Value>) -> bool {
     if left.len() == 1 && right.len() == 1 {
-        match (left.get(0), right.get(0)) {
-            (Some(Value::Number(l)), Some(Value::Number(r))) =>
-                l.as_f64().and_then(|v1| r.as_f64().map(|v2| v1 < v2)).unwrap_or(false),
+        match (left.first(), right.first()) {
+            (Some(Value::Number(l)), Some(Value::Number(r))) => cmp_numbers(l, r) == Some(Ordering::Less),
+            (Some(Value::String(l)), Some(Value::String(r))) => l < r,
             _ => false
         }
     } else {
@@ -155,6 +155,118 @@ pub fn less(left: Vec<&Value>, right: Vec<&Value>) -> bool {
     }
 }
 
+/// compares the elements like `==` does but the numbers are compared by their values, i.e. `1 == 1.0`
+pub fn same(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(l), Value::Number(r)) => cmp_numbers(l, r) == Some(Ordering::Equal),
+        (Value::Array(l), Value::Array(r)) => l.len() == r.len() && l.iter().zip(r).all(|(a, b)| same(a, b)),
+        (Value::Object(l), Value::Object(r)) =>
+            l.len() == r.len() && l.iter().all(|(k, a)| r.get(k).map(|b| same(a, b)).unwrap_or(false)),
+        (l, r) => l == r
+    }
+}
+
+/// compares the numbers exactly: the integers and the floats are compared as they are
+/// and the rest works on the decimal text,
+/// hence the big numbers kept by `arbitrary_precision` do not lose the digits going through f64.
+/// The numbers too big even for the decimal text are not comparable.
+pub fn cmp_numbers(left: &Number, right: &Number) -> Option<Ordering> {
+    if let (Some(l), Some(r)) = (left.as_i64(), right.as_i64()) {
+        return Some(l.cmp(&r));
+    }
+    if let (Some(l), Some(r)) = (left.as_u64(), right.as_u64()) {
+        return Some(l.cmp(&r));
+    }
+    #[cfg(not(feature = "arbitrary_precision"))]
+    if let (Some(l), Some(r)) = (exact_f64(left), exact_f64(right)) {
+        return l.partial_cmp(&r);
+    }
+    let (l, r) = (Decimal::parse(&left.to_string())?, Decimal::parse(&right.to_string())?);
+    let magnitude = || match (l.digits.is_empty(), r.digits.is_empty()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        _ => l.exp.cmp(&r.exp).then_with(|| l.digits.cmp(&r.digits)),
+    };
+    let zero = |d: &Decimal| d.digits.is_empty();
+    Some(match (l.negative && !zero(&l), r.negative && !zero(&r)) {
+        (false, false) => magnitude(),
+        (true, true) => magnitude().reverse(),
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+    })
+}
+
+/// the number as f64 if the conversion loses nothing, i.e. the float or the integer up to 2^53
+#[cfg(not(feature = "arbitrary_precision"))]
+fn exact_f64(n: &Number) -> Option<f64> {
+    const EXACT: u64 = 1 << 53;
+    match (n.as_i64(), n.as_u64()) {
+        (Some(i), _) if i.unsigned_abs() <= EXACT => Some(i as f64),
+        (_, Some(u)) if u <= EXACT => Some(u as f64),
+        (None, None) => n.as_f64(),
+        _ => None,
+    }
+}
+
+/// the number as `0.digits * 10^exp` with no leading or trailing zeros in the digits
+struct Decimal {
+    negative: bool,
+    digits: Vec<u8>,
+    exp: i64,
+}
+
+impl Decimal {
+    fn parse(text: &str) -> Option<Decimal> {
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let (mantissa, exp) = match text.find(['e', 'E']) {
+            Some(i) => (&text[..i], text[i + 1..].trim_start_matches('+').parse::<i64>().ok()?),
+            None => (text, 0),
+        };
+        let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let mut digits: Vec<u8> = int.bytes().chain(frac.bytes()).collect();
+        if !digits.iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        let leading = digits.iter().take_while(|d| **d == b'0').count();
+        digits.drain(..leading);
+        let exp = exp.checked_add(int.len() as i64)?.checked_sub(leading as i64)?;
+        while digits.last() == Some(&b'0') {
+            digits.pop();
+        }
+        Some(Decimal { negative, digits, exp })
+    }
+}
+
+/// the index of the query, None if it is not the integer
+pub fn as_index(v: &Value) -> Option<i64> {
+    v.as_i64()
+}
+
+/// the position in the array of the given length the index points out to, the negative ones count from the end
+pub fn position(idx: i64, len: usize) -> Option<usize> {
+    if idx >= 0 {
+        usize::try_from(idx).ok().filter(|i| *i < len)
+    } else {
+        usize::try_from(idx.unsigned_abs()).ok().and_then(|back| len.checked_sub(back))
+    }
+}
+
+/// the name of the json type used in the messages
+pub fn type_name(v: &Value) -> &'static str {
+    match v {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
 /// compare elements
 pub fn eq(left: Vec<&Value>, right: Vec<&Value>) -> bool {
     if left.len() != right.len() {
@@ -162,8 +274,7 @@ pub fn eq(left: Vec<&Value>, right: Vec<&Value>) -> bool {
     } else {
         left.iter()
             .zip(right)
-            .map(|(a, b)| a.eq(&b))
-            .all(|a| a)
+            .all(|(a, b)| same(a, b))
     }
 }
 
@@ -171,7 +282,7 @@ pub fn eq(left: Vec<&Value>, right: Vec<&Value>) -> bool {
 #[cfg(test)]
 mod tests {
     use serde_json::{json, Value};
-    use crate::path::json::{eq, less, regex, any_of, sub_set_of, size};
+    use crate::path::json::{eq, less, any_of, sub_set_of, size, same, Decimal};
 
     #[test]
     fn value_eq_test() {
@@ -229,8 +340,49 @@ mod tests {
         assert!(!less(vec![&right, &right], vec![&left]));
     }
 
+    #[test]
+    fn numbers_test() {
+        let n = |s: &str| serde_json::from_str::<Value>(s).unwrap();
+        assert!(same(&n("1"), &n("1.0")));
+        assert!(same(&n("100"), &n("1e2")));
+        assert!(same(&n("0"), &n("-0.0")));
+        assert!(same(&json!([1, {"a": 2}]), &json!([1.0, {"a": 2.0}])));
+        assert!(!same(&json!([1]), &json!([1, 2])));
+        assert!(less(vec![&n("-2.5")], vec![&n("-2")]));
+        assert!(less(vec![&n("0.001")], vec![&n("1e-2")]));
+        assert!(less(vec![&n("99")], vec![&n("1e2")]));
+        assert!(!less(vec![&n("1e2")], vec![&n("100")]));
+        assert!(eq(vec![&n("1.50")], vec![&n("1.5")]));
+        assert!(less(vec![&n("9007199254740992.0")], vec![&n("9007199254740993")]));
+        assert!(less(vec![&n("-9223372036854775808")], vec![&n("18446744073709551615")]));
+        assert!(less(vec![&n("18446744073709551615")], vec![&n("1.8446744073709552e19")]));
+        assert!(Decimal::parse("1e9223372036854775807").is_none());
+        assert!(Decimal::parse("0.0001e-9223372036854775807").is_none());
+        assert!(Decimal::parse("-12.5e-3").is_some());
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn big_numbers_test() {
+        let n = |s: &str| serde_json::from_str::<Value>(s).unwrap();
+        assert!(less(vec![&n("18446744073709551617")], vec![&n("18446744073709551618")]));
+        assert!(less(vec![&n("-18446744073709551618")], vec![&n("-18446744073709551617")]));
+        assert!(!eq(vec![&n("0.10000000000000000001")], vec![&n("0.1")]));
+        assert!(eq(vec![&n("123456789012345678901234567890")], vec![&n("1.2345678901234567890123456789e29")]));
+
+        let json = n(r#"{"ids":[{"id":18446744073709551617},{"id":18446744073709551618}]}"#);
+        let path = crate::parser::parser::parse_json_path("$.ids[?(@.id > 18446744073709551617)].id").unwrap();
+        assert_eq!(crate::path::json_path_instance(&path, &json).find(&json), vec![&n("18446744073709551618")]);
+
+        let json = n(r#"[{"a":1e9223372036854775807},{"a":2}]"#);
+        let path = crate::parser::parser::parse_json_path("$[?(@.a > 1)]").unwrap();
+        assert_eq!(crate::path::json_path_instance(&path, &json).find(&json), vec![&json[1]]);
+    }
+
+    #[cfg(feature = "regex")]
     #[test]
     fn regex_test() {
+        use crate::path::json::regex;
         let right = json!("[a-zA-Z]+[0-9]#[0-9]+");
         let left1 = json!("a11#");
         let left2 = json!("a1#1");
@@ -0,0 +1,165 @@
+use serde_json::Value;
+use crate::path::pattern;
+use crate::parser::model::{JsonPath, JsonPathIndex, Operand, FilterSign};
+use crate::path::json_path_instance;
+use crate::path::json::type_name;
+
+/// The statically suspicious construct of the query that most likely finds nothing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintWarning {
+    /// the position of the segment in the query
+    pub position: usize,
+    /// the suspicious segment
+    pub segment: JsonPath<'static>,
+    /// the explanation
+    pub message: String,
+}
+
+impl JsonPath<'_> {
+    /// checks the query for the constructs that always yield nothing, namely
+    /// the empty slices, the filters comparing the incompatible literals
+    /// and, if the sample document is provided, the selectors applied to the scalars of the sample.
+    pub fn lint(&self, sample: Option<&Value>) -> Vec<LintWarning> {
+        let segments = match self {
+            JsonPath::Chain(elems) => elems.as_slice(),
+            single => std::slice::from_ref(single),
+        };
+        let mut warnings = vec![];
+        let mut current: Option<Vec<&Value>> = sample.map(|s| vec![s]);
+        for (position, segment) in segments.iter().enumerate() {
+            let mut warn = |message: String| warnings.push(LintWarning { position, segment: segment.clone().into_owned(), message });
+            if let JsonPath::Index(index) = segment {
+                lint_index(index).into_iter().for_each(&mut warn);
+            }
+            if let (Some(nodes), Some(sample)) = (current.as_ref(), sample) {
+                if let Some(msg) = lint_sample(segment, nodes) {
+                    warn(msg)
+                }
+                let instance = json_path_instance(segment, sample);
+                let next: Vec<&Value> = nodes.iter().flat_map(|n| instance.find(n)).collect();
+                current = if next.is_empty() { None } else { Some(next) };
+            }
+        }
+        warnings
+    }
+}
+
+fn lint_sample(segment: &JsonPath, nodes: &[&Value]) -> Option<String> {
+    let scalar = |v: &&Value| !v.is_array() && !v.is_object();
+    if nodes.is_empty() || !nodes.iter().all(scalar) {
+        return None;
+    }
+    let tp = type_name(nodes[0]);
+    match segment {
+        JsonPath::Field(key) => Some(format!("the member '{}' is requested but the element of the sample is {}", key, tp)),
+        JsonPath::Index(_) | JsonPath::Wildcard =>
+            Some(format!("the selector is applied to the scalar but the element of the sample is {}", tp)),
+        _ => None
+    }
+}
+
+fn lint_index(index: &JsonPathIndex) -> Vec<String> {
+    match index {
+        JsonPathIndex::Slice(_, _, 0) => vec![String::from("the slice with the step 0 selects nothing")],
+        JsonPathIndex::Slice(Some(start), Some(end), step) if (*start >= 0) == (*end >= 0)
+            && ((*step > 0 && start >= end) || (*step < 0 && start <= end)) =>
+            vec![if *step == 1 {
+                format!("the slice [{}:{}] is always empty", start, end)
+            } else {
+                format!("the slice [{}:{}:{}] is always empty", start, end, step)
+            }],
+        JsonPathIndex::Filter(left, sign, right) => lint_filter(left, sign, right),
+        JsonPathIndex::Logical(expr) => expr.atoms().into_iter().flat_map(|(left, sign, right)| lint_filter(left, sign, right)).collect(),
+        JsonPathIndex::Union(sels) => sels.iter().flat_map(|s| match s {
+            JsonPath::Index(idx) => lint_index(idx),
+            _ => vec![]
+        }).collect(),
+        _ => vec![]
+    }
+}
+
+fn lint_filter(left: &Operand, sign: &FilterSign, right: &Operand) -> Vec<String> {
+    let mut warnings = vec![];
+    if let (Operand::Static(_), Operand::Static(_)) = (left, right) {
+        if *sign != FilterSign::Exists {
+            warnings.push(String::from("the filter compares two literals and therefore is constant"));
+        }
+    }
+    let statics = [left, right].iter().filter_map(|op| match op {
+        Operand::Static(v) => Some(v),
+        _ => None
+    }).collect::<Vec<_>>();
+    match sign {
+        FilterSign::Less | FilterSign::Greater | FilterSign::LeOrEq | FilterSign::GrOrEq => {
+            if let Some(v) = statics.iter().find(|v| !v.is_number()) {
+                warnings.push(format!("the numbers are compared with the {} literal", type_name(v)))
+            }
+        }
+        FilterSign::Regex => match right {
+            Operand::Static(Value::String(r)) => if let Err(e) = pattern::compile(r) {
+                warnings.push(e)
+            },
+            Operand::Static(v) => warnings.push(format!("the regex is expected but got the {} literal", type_name(v))),
+            _ => ()
+        },
+        FilterSign::In | FilterSign::Nin | FilterSign::AnyOf | FilterSign::NoneOf | FilterSign::SubSetOf => match right {
+            Operand::Static(v) if !v.is_array() => warnings.push(format!("the array is expected on the right side but got the {} literal", type_name(v))),
+            _ => ()
+        },
+        FilterSign::Size => match right {
+            Operand::Static(v) if !v.is_number() => warnings.push(format!("the size is expected to be a number but got the {} literal", type_name(v))),
+            _ => ()
+        },
+        _ => ()
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::parser::parser::parse_json_path;
+
+    fn lint(path: &str, sample: Option<serde_json::Value>) -> Vec<(usize, String)> {
+        parse_json_path(path).unwrap().lint(sample.as_ref())
+            .into_iter()
+            .map(|w| (w.position, w.message))
+            .collect()
+    }
+
+    #[test]
+    fn slice_test() {
+        assert!(lint("$.a[1:5]", None).is_empty());
+        assert!(lint("$.a[-3:-1]", None).is_empty());
+        assert!(lint("$.a[2:]", None).is_empty());
+        assert_eq!(lint("$.a[5:2]", None), vec![(2, String::from("the slice [5:2] is always empty"))]);
+        assert_eq!(lint("$.a[-1:-3]", None), vec![(2, String::from("the slice [-1:-3] is always empty"))]);
+        assert!(lint("$.a[5:2:-1]", None).is_empty());
+        assert_eq!(lint("$.a[2:5:-1]", None), vec![(2, String::from("the slice [2:5:-1] is always empty"))]);
+        assert_eq!(lint("$.a[::0]", None), vec![(2, String::from("the slice with the step 0 selects nothing"))]);
+    }
+
+    #[test]
+    fn filter_test() {
+        assert!(lint("$.a[?(@.b < 10)]", None).is_empty());
+        #[cfg(feature = "regex")]
+        assert!(lint("$.a[?(@.b ~= '^a.*')]", None).is_empty());
+        assert_eq!(lint("$.a[?(@.b < 'x')]", None), vec![(2, String::from("the numbers are compared with the string literal"))]);
+        assert_eq!(lint("$.a[?(@.b ~= '(')]", None).len(), 1);
+        assert_eq!(lint("$.a[?(@.b ~= 1)]", None), vec![(2, String::from("the regex is expected but got the number literal"))]);
+        assert_eq!(lint("$.a[?(@.b in 1)]", None), vec![(2, String::from("the array is expected on the right side but got the number literal"))]);
+        assert_eq!(lint("$.a[?(@.b size 'a')]", None), vec![(2, String::from("the size is expected to be a number but got the string literal"))]);
+        assert_eq!(lint("$.a[?(1 == 1)]", None), vec![(2, String::from("the filter compares two literals and therefore is constant"))]);
+    }
+
+    #[test]
+    fn sample_test() {
+        let sample = json!({"a":{"b":1,"c":[1,2]}});
+        assert!(lint("$.a.c[0]", Some(sample.clone())).is_empty());
+        assert!(lint("$.a.x[0]", Some(sample.clone())).is_empty());
+        assert_eq!(lint("$.a.b[0]", Some(sample.clone())),
+                   vec![(3, String::from("the selector is applied to the scalar but the element of the sample is number"))]);
+        assert_eq!(lint("$.a.c[0].d", Some(sample)),
+                   vec![(4, String::from("the member 'd' is requested but the element of the sample is number"))]);
+    }
+}
@@ -0,0 +1,226 @@
+use serde_json::{Map, Value};
+use crate::parser::model::JsonPath;
+use crate::path::mutate;
+
+/// applies the RFC 7386 json merge patch to the document:
+/// the members of the patch object are merged into the object recursively,
+/// the members set to null are removed and anything else replaces the target.
+/// # Examples:
+/// ```
+/// use serde_json::json;
+/// let mut doc = json!({"a":"b","c":{"d":"e","f":"g"}});
+/// jsonpath_rust::merge_patch(&mut doc, &json!({"a":"z","c":{"f":null}}));
+/// assert_eq!(doc, json!({"a":"z","c":{"d":"e"}}));
+/// ```
+pub fn merge_patch(doc: &mut Value, patch: &Value) {
+    let members = match patch {
+        Value::Object(members) => members,
+        _ => return *doc = patch.clone(),
+    };
+    if !doc.is_object() {
+        *doc = Value::Object(Map::new());
+    }
+    if let Value::Object(fields) = doc {
+        for (key, v) in members {
+            if v.is_null() {
+                fields.remove(key);
+            } else {
+                merge_patch(fields.entry(key.as_str()).or_insert(Value::Null), v);
+            }
+        }
+    }
+}
+
+/// How [deep_merge] combines the arrays.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ArrayMerge {
+    /// the array of the source replaces the target one
+    #[default]
+    Replace,
+    /// the elements of the source go after the elements of the target
+    Concat,
+    /// the objects having the same value of the member are merged, the other elements are appended
+    ByKey(String),
+}
+
+/// The settings of [deep_merge].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeOptions {
+    arrays: ArrayMerge,
+    null_removes: bool,
+}
+
+impl MergeOptions {
+    pub fn new() -> Self {
+        MergeOptions::default()
+    }
+
+    /// how the arrays are combined, replaced by default
+    pub fn arrays(mut self, arrays: ArrayMerge) -> Self {
+        self.arrays = arrays;
+        self
+    }
+
+    /// the members set to null in the source remove the members of the target like the merge patch does,
+    /// otherwise null is merged as any other value
+    pub fn null_removes(mut self) -> Self {
+        self.null_removes = true;
+        self
+    }
+}
+
+/// merges the source into the document: the objects are merged member by member recursively,
+/// the arrays are combined as the options say and the other values of the source replace the target ones.
+/// # Examples:
+/// ```
+/// use serde_json::json;
+/// use jsonpath_rust::{deep_merge, ArrayMerge, MergeOptions};
+///
+/// let mut doc = json!({"users":[{"id":1,"name":"a"}],"tags":["x"]});
+/// deep_merge(&mut doc, &json!({"users":[{"id":1,"age":3},{"id":2}]}), &MergeOptions::new().arrays(ArrayMerge::ByKey("id".into())));
+/// assert_eq!(doc, json!({"users":[{"id":1,"name":"a","age":3},{"id":2}],"tags":["x"]}));
+/// ```
+pub fn deep_merge(doc: &mut Value, source: &Value, options: &MergeOptions) {
+    match (doc, source) {
+        (Value::Object(fields), Value::Object(members)) => for (key, v) in members {
+            match fields.get_mut(key) {
+                _ if v.is_null() && options.null_removes => {
+                    fields.remove(key);
+                }
+                Some(field) => deep_merge(field, v, options),
+                None => {
+                    fields.insert(key.clone(), v.clone());
+                }
+            }
+        },
+        (Value::Array(elems), Value::Array(others)) => match &options.arrays {
+            ArrayMerge::Replace => *elems = others.clone(),
+            ArrayMerge::Concat => elems.extend(others.iter().cloned()),
+            ArrayMerge::ByKey(key) => for other in others {
+                let id = other.get(key).filter(|_| other.is_object());
+                match elems.iter_mut().find(|e| e.is_object() && id.is_some() && e.get(key) == id) {
+                    Some(elem) => deep_merge(elem, other, options),
+                    None => elems.push(other.clone()),
+                }
+            },
+        },
+        (doc, source) => *doc = source.clone(),
+    }
+}
+
+impl JsonPath<'_> {
+    /// applies the json merge patch(see [merge_patch]) to every found element returning how many are patched,
+    /// e.g. merges the defaults into every `$.services[*].config`.
+    pub fn merge_patch(&self, doc: &mut Value, patch: &Value) -> usize {
+        mutate::replace_with(self, doc, merged(patch), None)
+    }
+
+    /// merges the source into every found element(see [deep_merge]) returning how many are merged
+    pub fn deep_merge(&self, doc: &mut Value, source: &Value, options: &MergeOptions) -> usize {
+        mutate::replace_with(self, doc, deep_merged(source, options), None)
+    }
+}
+
+/// the closure merging the source into the elements for [mutate::replace_with]
+pub(crate) fn deep_merged<'a>(source: &'a Value, options: &'a MergeOptions) -> impl FnMut(Value) -> Value + 'a {
+    move |mut v| {
+        deep_merge(&mut v, source, options);
+        v
+    }
+}
+
+/// the closure patching the elements for [mutate::replace_with]
+pub(crate) fn merged(patch: &Value) -> impl FnMut(Value) -> Value + '_ {
+    move |mut v| {
+        merge_patch(&mut v, patch);
+        v
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::parser::parser::parse_json_path;
+    use crate::path::merge::{deep_merge, merge_patch, ArrayMerge, MergeOptions};
+
+    #[test]
+    fn rfc_test() {
+        // the examples of the appendix A of RFC 7386
+        let cases = [
+            (json!({"a":"b"}), json!({"a":"c"}), json!({"a":"c"})),
+            (json!({"a":"b"}), json!({"b":"c"}), json!({"a":"b","b":"c"})),
+            (json!({"a":"b"}), json!({"a":null}), json!({})),
+            (json!({"a":"b","b":"c"}), json!({"a":null}), json!({"b":"c"})),
+            (json!({"a":["b"]}), json!({"a":"c"}), json!({"a":"c"})),
+            (json!({"a":"c"}), json!({"a":["b"]}), json!({"a":["b"]})),
+            (json!({"a":{"b":"c"}}), json!({"a":{"b":"d","c":null}}), json!({"a":{"b":"d"}})),
+            (json!({"a":[{"b":"c"}]}), json!({"a":[1]}), json!({"a":[1]})),
+            (json!(["a","b"]), json!(["c","d"]), json!(["c","d"])),
+            (json!({"a":"b"}), json!(["c"]), json!(["c"])),
+            (json!({"a":"foo"}), json!(null), json!(null)),
+            (json!({"a":"foo"}), json!("bar"), json!("bar")),
+            (json!({"e":null}), json!({"a":1}), json!({"e":null,"a":1})),
+            (json!([1,2]), json!({"a":"b","c":null}), json!({"a":"b"})),
+            (json!({}), json!({"a":{"bb":{"ccc":null}}}), json!({"a":{"bb":{}}})),
+        ];
+        for (mut doc, patch, expected) in cases {
+            merge_patch(&mut doc, &patch);
+            assert_eq!(doc, expected, "{}", patch);
+        }
+    }
+
+    #[test]
+    fn path_test() {
+        let mut doc = json!({"services":[
+            {"name":"a","config":{"port":80,"tls":{"on":true}}},
+            {"name":"b"},
+            {"name":"c","config":{"debug":true}}
+        ]});
+        let defaults = json!({"retries":3,"tls":{"on":false,"cert":null},"debug":null});
+        assert_eq!(parse_json_path("$.services[*].config").unwrap().merge_patch(&mut doc, &defaults), 2);
+        assert_eq!(doc, json!({"services":[
+            {"name":"a","config":{"port":80,"retries":3,"tls":{"on":false}}},
+            {"name":"b"},
+            {"name":"c","config":{"retries":3,"tls":{"on":false}}}
+        ]}));
+        assert_eq!(parse_json_path("$.missing").unwrap().merge_patch(&mut doc, &defaults), 0);
+    }
+
+    #[test]
+    fn deep_merge_test() {
+        let doc = json!({"a":{"b":1,"c":[1,2],"d":null},"e":[{"id":1,"v":1},{"id":2,"v":2},3]});
+        let source = json!({"a":{"b":{"x":1},"c":[3],"d":5,"f":null},"e":[{"id":2,"w":2},{"id":3},{"v":4},3]});
+        let merge = |options: MergeOptions| {
+            let mut doc = doc.clone();
+            deep_merge(&mut doc, &source, &options);
+            doc
+        };
+        assert_eq!(merge(MergeOptions::new()), json!({
+            "a":{"b":{"x":1},"c":[3],"d":5,"f":null},
+            "e":[{"id":2,"w":2},{"id":3},{"v":4},3]
+        }));
+        assert_eq!(merge(MergeOptions::new().arrays(ArrayMerge::Concat)), json!({
+            "a":{"b":{"x":1},"c":[1,2,3],"d":5,"f":null},
+            "e":[{"id":1,"v":1},{"id":2,"v":2},3,{"id":2,"w":2},{"id":3},{"v":4},3]
+        }));
+        assert_eq!(merge(MergeOptions::new().arrays(ArrayMerge::ByKey(String::from("id")))), json!({
+            "a":{"b":{"x":1},"c":[1,2,3],"d":5,"f":null},
+            "e":[{"id":1,"v":1},{"id":2,"v":2,"w":2},3,{"id":3},{"v":4},3]
+        }));
+
+        let mut doc = json!({"a":1,"b":2});
+        deep_merge(&mut doc, &json!({"a":null,"c":null}), &MergeOptions::new().null_removes());
+        assert_eq!(doc, json!({"b":2}));
+        deep_merge(&mut doc, &json!([1]), &MergeOptions::new());
+        assert_eq!(doc, json!([1]));
+    }
+
+    #[test]
+    fn deep_merge_path_test() {
+        let mut doc = json!({"envs":[{"name":"dev","hosts":["a"]},{"name":"prod","hosts":["b"]}]});
+        let path = parse_json_path("$.envs[?(@.name == 'prod')]").unwrap();
+        let options = MergeOptions::new().arrays(ArrayMerge::Concat);
+        assert_eq!(path.deep_merge(&mut doc, &json!({"hosts":["c"],"tls":true}), &options), 1);
+        assert_eq!(doc, json!({"envs":[{"name":"dev","hosts":["a"]},{"name":"prod","hosts":["b","c"],"tls":true}]}));
+    }
+}
@@ -4,18 +4,90 @@
 //! - define the json path structure from the parsing [[JsonPath]]
 //! - transform json path into the [[PathInstance]]
 //!
+//! The [[PathInstance]] borrows both the path and the document,
+//! the [[JsonPathInst]] owns the compiled path and is applied to any document.
+//!
+use std::cell::OnceCell;
+use std::rc::Rc;
 use serde_json::Value;
 
 use crate::parser::model::{JsonPath, JsonPathIndex, Operand};
-use crate::path::index::{ArrayIndex, ArraySlice, Current, Filter, UnionIndex};
+use crate::path::index::{ArrayIndex, ArraySlice, Current, Filter, Logical, UnionIndex};
 use crate::path::top::*;
+use crate::path::json::as_index;
+pub use crate::path::definite::BoundPrefix;
+pub use crate::path::explain::{EmptyReport, NodeSummary};
+pub use crate::path::schema::SchemaIssue;
+pub use crate::path::lint::LintWarning;
+pub use crate::path::visit::VisitFlow;
+pub use crate::path::nodes::NodeList;
+pub use crate::path::cursor::{Cursor, Page};
+pub use crate::path::normalized::{NormalizedPath, PathSegment, escape_member_name};
+pub use crate::path::non_finite::NonFinite;
+pub use crate::path::options::{Options, Collation, FilterFunction};
+pub use crate::path::handle::MatchHandle;
+pub use crate::path::profile::{Profiler, SegmentStats, FilterStats};
+pub use crate::path::policy::QueryPolicy;
+pub use crate::path::compiled::JsonPathInst;
+pub use crate::path::patch::{PatchOp, PatchRecorder};
+pub use crate::path::merge::{merge_patch, deep_merge, ArrayMerge, MergeOptions};
+pub use crate::path::diff::{diff, Change};
+pub use crate::path::pointer::RelativePointer;
 
 /// The module is responsible for processing of the [[JsonPath]] elements
 mod top;
 /// The module is in charge of processing [[JsonPathIndex]] elements
-mod index;
+pub(crate) mod index;
 /// The module is a helper module providing the set of helping funcitons to process a json elements
-mod json;
+pub(crate) mod json;
+/// The module provides the fast lookup for the definite paths
+pub(crate) mod definite;
+/// The module describes the locations of the found elements
+pub(crate) mod normalized;
+/// The module explains why the queries find nothing
+mod explain;
+/// The module checks the queries against the json schemas
+mod schema;
+/// The module looks for the constructs of the queries that always yield nothing
+mod lint;
+/// The module traverses the documents keeping track of the locations of the found elements
+pub(crate) mod visit;
+/// The module combines the found elements as the sets
+mod nodes;
+/// The module splits the results into the pages
+mod cursor;
+/// The module decides how the filters compare the non-finite numbers
+pub(crate) mod non_finite;
+/// The module holds the settings the queries are evaluated with
+mod options;
+/// The module refers to the found elements by the owned locations
+mod handle;
+/// The module measures how long every segment of the query takes
+mod profile;
+/// The module restricts the queries coming from the untrusted sources
+mod policy;
+/// The module holds the function extensions of the filters like `length()`
+pub(crate) mod functions;
+/// The module compares the values of MongoDB Extended JSON in the filters
+mod extjson;
+/// The module turns the queries into the closures
+mod compiled;
+/// The module checks the queries for the states the evaluation can not make sense of
+mod validate;
+/// The module compiles the regexes of the filters once
+pub(crate) mod pattern;
+/// The module changes the documents at the found locations
+mod mutate;
+/// The module writes the changes down as the json patch
+mod patch;
+/// The module merges the json documents into each other
+mod merge;
+/// The module compares the json documents
+mod diff;
+/// The module evaluates the json pointers
+mod pointer;
+/// The module binds the values to the placeholders of the filters
+mod vars;
 
 /// The trait defining the behaviour of processing every separated element.
 /// type Data usually stands for json [[Value]]
@@ -30,33 +102,60 @@ pub trait Path<'a> {
 pub type PathInstance<'a> = Box<dyn Path<'a, Data=Value> + 'a>;
 
 
+/// The document root and the settings the instances are evaluated with
+#[derive(Clone)]
+pub(crate) struct Env<'a> {
+    pub(crate) root: &'a Value,
+    pub(crate) options: Options,
+    /// the first error the filters run into, if anyone listens
+    pub(crate) error: Option<Rc<OnceCell<String>>>,
+}
+
+impl<'a> Env<'a> {
+    pub(crate) fn new(root: &'a Value) -> Self {
+        Env { root, options: Options::default(), error: None }
+    }
+}
+
 /// The major method to process the top part of json part
 pub fn json_path_instance<'a>(json_path: &'a JsonPath, root: &'a Value) -> PathInstance<'a> {
+    instance(json_path, &Env::new(root))
+}
+
+/// The same as [json_path_instance] but with the settings
+pub(crate) fn instance<'a>(json_path: &'a JsonPath, env: &Env<'a>) -> PathInstance<'a> {
     match json_path {
-        JsonPath::Root => Box::new(RootPointer::new(root)),
+        JsonPath::Root => Box::new(RootPointer::new(env.root)),
         JsonPath::Field(key) => Box::new(ObjectField::new(key)),
-        JsonPath::Chain(chain) => Box::new(Chain::from(chain, root)),
+        JsonPath::Chain(chain) => Box::new(Chain::from(chain, env)),
         JsonPath::Wildcard => Box::new(Wildcard {}),
         JsonPath::Descent(key) => Box::new(DescentObjectField::new(key)),
-        JsonPath::Current(value) => Box::new(Current::from(&**value,root)),
-        JsonPath::Index(index) => process_index(index, root),
+        JsonPath::Descendants => Box::new(Descendants {}),
+        JsonPath::Current(value) => Box::new(Current::from(value, env)),
+        JsonPath::Index(index) => process_index(index, env),
         JsonPath::Empty => Box::new(IdentityPath {})
     }
 }
 /// The method processes the indexes(all expressions indie [])
-fn process_index<'a>(json_path_index: &'a JsonPathIndex, root: &'a Value) -> PathInstance<'a> {
+fn process_index<'a>(json_path_index: &'a JsonPathIndex, env: &Env<'a>) -> PathInstance<'a> {
     match json_path_index {
-        JsonPathIndex::Single(index) => Box::new(ArrayIndex::new(index.as_u64().unwrap() as usize)),
+        JsonPathIndex::Single(index) => match as_index(index) {
+            Some(index) => Box::new(ArrayIndex::new(index)),
+            None => Box::new(UnionIndex::new(vec![])),
+        },
         JsonPathIndex::Slice(s, e, step) => Box::new(ArraySlice::new(*s, *e, *step)),
         JsonPathIndex::UnionKeys(elems) => Box::new(UnionIndex::from_keys(elems)),
         JsonPathIndex::UnionIndex(elems) => Box::new(UnionIndex::from_indexes(elems)),
-        JsonPathIndex::Filter(l, op, r) => Box::new(Filter::new(l, r, op, root)),
+        JsonPathIndex::Filter(l, op, r) => Box::new(Filter::with_env(l, r, op, env.clone())),
+        JsonPathIndex::Logical(expr) => Box::new(Logical::new(expr, env)),
+        JsonPathIndex::Union(sels) => Box::new(UnionIndex::new(sels.iter().map(|s| instance(s, env)).collect())),
     }
 }
 /// The method processes the operand inside the filter expressions
-fn process_operand<'a>(op: &'a Operand, root: &'a Value) -> PathInstance<'a> {
+fn process_operand<'a>(op: &'a Operand, env: &Env<'a>) -> PathInstance<'a> {
     match op {
-        Operand::Static(v) => json_path_instance(&JsonPath::Root, v),
-        Operand::Dynamic(jp) => json_path_instance(jp, root)
+        Operand::Static(v) => instance(&JsonPath::Root, &Env { root: v, ..env.clone() }),
+        Operand::Dynamic(jp) => instance(jp, env),
+        Operand::Function(..) | Operand::Variable(_) => Box::new(UnionIndex::new(vec![])),
     }
 }
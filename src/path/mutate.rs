@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+use serde_json::Value;
+use crate::parser::model::JsonPath;
+use crate::path::definite::location;
+use crate::path::json::type_name;
+use crate::path::normalized::{NormalizedPath, PathSegment};
+use crate::path::patch::PatchOp;
+
+impl JsonPath<'_> {
+    /// finds the elements as the mutable references to change them in place, in the order `find` gives them.
+    /// Since the references can not overlap, when both the element and something inside of it are found
+    /// only the enclosing element is given, the same element found twice is given once.
+    pub fn find_mut<'a>(&self, doc: &'a mut Value) -> Vec<&'a mut Value> {
+        let paths = self.found_paths(doc);
+        let targets = paths.iter().enumerate().map(|(i, p)| (i, p.segments())).collect();
+        let mut found = vec![];
+        split(doc, targets, &mut found);
+        found.sort_by_key(|(i, _)| *i);
+        found.into_iter().map(|(_, v)| v).collect()
+    }
+
+    /// removes the found members of the objects and elements of the arrays returning how many are removed.
+    /// The elements found inside the removed ones are gone along with them and are not counted,
+    /// the root of the document can not be removed.
+    pub fn delete(&self, doc: &mut Value) -> usize {
+        delete(self, doc, None)
+    }
+
+    /// replaces every found element with what the closure gives for it returning how many are replaced.
+    /// The elements found inside the other found ones are replaced first,
+    /// so the closure gets the enclosing element with them replaced already.
+    pub fn replace_with<F>(&self, doc: &mut Value, f: F) -> usize where F: FnMut(Value) -> Value {
+        replace_with(self, doc, f, None)
+    }
+
+    /// inserts the value into every found object as the member `at` or into every found array at the position `at`
+    /// returning into how many elements it is inserted. The member with the same name is replaced,
+    /// the elements of the other types and the arrays shorter than the position are skipped.
+    pub fn insert<S: Into<PathSegment>>(&self, doc: &mut Value, at: S, value: Value) -> usize {
+        insert(self, doc, at.into(), value, None)
+    }
+
+    /// inserts the value like [JsonPath::insert] into the element the definite path(see [JsonPath::is_definite])
+    /// points out to creating the missing members along the way, e.g. `$.a.b` turns `{}` into `{"a":{"b":{...}}}`.
+    /// The missing member becomes the array if the array index follows it, the index equal to the length appends.
+    pub fn insert_creating<S: Into<PathSegment>>(&self, doc: &mut Value, at: S, value: Value) -> Result<(), String> {
+        insert_creating(self, doc, at.into(), value, None)
+    }
+
+    fn found_paths(&self, doc: &Value) -> Vec<NormalizedPath> {
+        let mut paths = vec![];
+        self.find_with(doc, |p, _| paths.push(p.clone()));
+        paths
+    }
+}
+
+/// the changes are written down as the json patch operations when `patch` is given
+pub(crate) fn delete(path: &JsonPath, doc: &mut Value, mut patch: Option<&mut Vec<PatchOp>>) -> usize {
+    let mut paths = path.found_paths(doc);
+    paths.retain(|p| !p.is_root());
+    paths.sort();
+    // the enclosing element goes right before everything inside of it
+    let mut outermost: Vec<NormalizedPath> = vec![];
+    for p in paths {
+        if !outermost.last().is_some_and(|prev| p.starts_with(prev)) {
+            outermost.push(p);
+        }
+    }
+    // the greater indexes go first so the removals do not shift the ones left to remove
+    let mut removed = 0;
+    for p in outermost.iter().rev() {
+        if remove(doc, p).is_some() {
+            removed += 1;
+            if let Some(patch) = patch.as_deref_mut() {
+                patch.push(PatchOp::Remove { path: p.to_pointer() });
+            }
+        }
+    }
+    removed
+}
+
+pub(crate) fn replace_with<F>(path: &JsonPath, doc: &mut Value, mut f: F, mut patch: Option<&mut Vec<PatchOp>>) -> usize
+    where F: FnMut(Value) -> Value {
+    let mut paths = path.found_paths(doc);
+    paths.sort();
+    paths.dedup();
+    let mut replaced = 0;
+    for p in paths.iter().rev() {
+        if let Some(v) = p.resolve_mut(doc) {
+            *v = f(std::mem::take(v));
+            replaced += 1;
+            if let Some(patch) = patch.as_deref_mut() {
+                patch.push(PatchOp::Replace { path: p.to_pointer(), value: v.clone() });
+            }
+        }
+    }
+    replaced
+}
+
+pub(crate) fn insert(path: &JsonPath, doc: &mut Value, at: PathSegment, value: Value, mut patch: Option<&mut Vec<PatchOp>>) -> usize {
+    let mut paths = path.found_paths(doc);
+    paths.sort();
+    paths.dedup();
+    // the elements inside go first so the positions of the enclosing arrays are not shifted yet
+    let mut inserted = 0;
+    for p in paths.iter().rev() {
+        if let Some(node) = p.resolve_mut(doc) {
+            if put(node, &at, value.clone(), p, patch.as_deref_mut()).is_ok() {
+                inserted += 1;
+            }
+        }
+    }
+    inserted
+}
+
+pub(crate) fn insert_creating(path: &JsonPath, doc: &mut Value, at: PathSegment, value: Value,
+                              mut patch: Option<&mut Vec<PatchOp>>) -> Result<(), String> {
+    let mut target = NormalizedPath::root();
+    location(path, &mut target).ok_or_else(|| String::from("the containers can be created only for the definite paths"))?;
+    let mut node = doc;
+    let mut current = NormalizedPath::root();
+    let segments = target.segments();
+    for (i, seg) in segments.iter().enumerate() {
+        let next = segments.get(i + 1).unwrap_or(&at);
+        let missing = match next {
+            PathSegment::Key(_) => Value::Object(Default::default()),
+            PathSegment::Index(_) => Value::Array(vec![]),
+        };
+        let created = |patch: Option<&mut Vec<PatchOp>>, missing: &Value| if let Some(patch) = patch {
+            patch.push(PatchOp::Add { path: current.join(seg.clone()).to_pointer(), value: missing.clone() })
+        };
+        node = match (node, seg) {
+            (Value::Object(fields), PathSegment::Key(key)) => {
+                if !fields.contains_key(key) {
+                    created(patch.as_deref_mut(), &missing);
+                }
+                fields.entry(key.as_str()).or_insert(missing)
+            }
+            (Value::Array(elems), PathSegment::Index(idx)) => {
+                if *idx > elems.len() {
+                    return Err(format!("the index {} is out of bounds at {} with the length {}", idx, current, elems.len()));
+                }
+                if *idx == elems.len() {
+                    created(patch.as_deref_mut(), &missing);
+                    elems.push(missing);
+                }
+                &mut elems[*idx]
+            }
+            (v, seg) => return Err(mismatch(v, seg, &current)),
+        };
+        current.push(seg.clone());
+    }
+    put(node, &at, value, &current, patch)
+}
+
+/// inserts the value into the object or the array the path points out to
+fn put(node: &mut Value, at: &PathSegment, value: Value, path: &NormalizedPath, patch: Option<&mut Vec<PatchOp>>) -> Result<(), String> {
+    let replaced = match (&mut *node, at) {
+        (Value::Object(fields), PathSegment::Key(key)) => fields.insert(key.clone(), value).is_some(),
+        (Value::Array(elems), PathSegment::Index(idx)) if *idx <= elems.len() => {
+            elems.insert(*idx, value);
+            false
+        }
+        (Value::Array(elems), PathSegment::Index(idx)) =>
+            return Err(format!("the position {} is beyond the end of the array at {} with the length {}", idx, path, elems.len())),
+        (v, seg) => return Err(mismatch(v, seg, path)),
+    };
+    if let Some(patch) = patch {
+        let value = NormalizedPath::new(vec![at.clone()]).resolve(node).cloned().unwrap_or_default();
+        let path = path.join(at.clone()).to_pointer();
+        patch.push(if replaced { PatchOp::Replace { path, value } } else { PatchOp::Add { path, value } });
+    }
+    Ok(())
+}
+
+fn mismatch(v: &Value, seg: &PathSegment, path: &NormalizedPath) -> String {
+    match seg {
+        PathSegment::Key(key) => format!("the member '{}' is requested at {} but the element is {}", key, path, type_name(v)),
+        PathSegment::Index(idx) => format!("the index {} is requested at {} but the element is {}", idx, path, type_name(v)),
+    }
+}
+
+/// removes the element from the enclosing object or array
+fn remove(doc: &mut Value, path: &NormalizedPath) -> Option<Value> {
+    match (path.parent()?.resolve_mut(doc)?, path.last()?) {
+        (Value::Object(fields), PathSegment::Key(key)) => fields.remove(key),
+        (Value::Array(elems), PathSegment::Index(idx)) if *idx < elems.len() => Some(elems.remove(*idx)),
+        _ => None
+    }
+}
+
+/// hands out the disjoint mutable references to the nodes the rests of the paths lead to,
+/// along with the positions of the paths among the found ones
+fn split<'a>(node: &'a mut Value, targets: Vec<(usize, &[PathSegment])>, found: &mut Vec<(usize, &'a mut Value)>) {
+    if let Some((i, _)) = targets.iter().find(|(_, rest)| rest.is_empty()) {
+        found.push((*i, node));
+        return;
+    }
+    let mut keys: HashMap<&str, Vec<(usize, &[PathSegment])>> = HashMap::new();
+    let mut idxs: HashMap<usize, Vec<(usize, &[PathSegment])>> = HashMap::new();
+    for (i, rest) in targets {
+        match rest.split_first() {
+            Some((PathSegment::Key(k), rest)) => keys.entry(k.as_str()).or_default().push((i, rest)),
+            Some((PathSegment::Index(idx), rest)) => idxs.entry(*idx).or_default().push((i, rest)),
+            None => (),
+        }
+    }
+    match node {
+        Value::Object(fields) => for (k, v) in fields.iter_mut() {
+            if let Some(targets) = keys.remove(k.as_str()) {
+                split(v, targets, found)
+            }
+        },
+        Value::Array(elems) => for (idx, v) in elems.iter_mut().enumerate() {
+            if let Some(targets) = idxs.remove(&idx) {
+                split(v, targets, found)
+            }
+        },
+        _ => ()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use crate::parser::parser::parse_json_path;
+
+    #[test]
+    fn find_mut_test() {
+        let mut doc = json!({"items":[{"version":1},{"version":2},{"name":"x"}]});
+        for v in parse_json_path("$.items[*].version").unwrap().find_mut(&mut doc) {
+            *v = json!(v.as_i64().unwrap() + 1);
+        }
+        assert_eq!(doc, json!({"items":[{"version":2},{"version":3},{"name":"x"}]}));
+
+        let path = parse_json_path("$.items[2,0,2].*").unwrap();
+        let found = path.find_mut(&mut doc);
+        assert_eq!(found.iter().map(|v| (**v).clone()).collect::<Vec<_>>(), vec![json!("x"), json!(2)]);
+
+        let mut doc = json!({"a":{"b":{"b":1}},"d":[{"b":2}]});
+        assert_eq!(parse_json_path("$..b").unwrap().count(&doc), 3);
+        let mut found = parse_json_path("$..b").unwrap().find_mut(&mut doc);
+        assert_eq!(found.iter().map(|v| (**v).clone()).collect::<Vec<_>>(), vec![json!({"b":1}), json!(2)]);
+        *found[0] = json!(null);
+        assert_eq!(doc, json!({"a":{"b":null},"d":[{"b":2}]}));
+
+        let mut doc = json!([1, 2]);
+        *parse_json_path("$").unwrap().find_mut(&mut doc)[0] = json!("root");
+        assert_eq!(doc, json!("root"));
+        assert!(parse_json_path("$.a").unwrap().find_mut(&mut doc).is_empty());
+    }
+
+    #[test]
+    fn delete_test() {
+        let delete = |q, mut doc| {
+            let removed = parse_json_path(q).unwrap().delete(&mut doc);
+            (removed, doc)
+        };
+        let doc = json!({"a":[0,1,2,3,4,5],"b":{"c":1,"d":2},"e":[{"f":1},{"f":2,"g":[1]}]});
+        assert_eq!(delete("$.a[1,3,4]", doc.clone()).1["a"], json!([0, 2, 5]));
+        assert_eq!(delete("$.a[4,1,3,1]", doc.clone()), delete("$.a[1,3,4]", doc.clone()));
+        assert_eq!(delete("$.a[?(@ > 1)]", doc.clone()), (4, json!({"b":{"c":1,"d":2},"e":[{"f":1},{"f":2,"g":[1]}], "a":[0,1]})));
+        assert_eq!(delete("$.a[::2]", doc.clone()).1["a"], json!([1, 3, 5]));
+        assert_eq!(delete("$.b.c", doc.clone()).1["b"], json!({"d":2}));
+        assert_eq!(delete("$.b.*", doc.clone()), (2, json!({"a":[0,1,2,3,4,5],"b":{},"e":[{"f":1},{"f":2,"g":[1]}]})));
+        assert_eq!(delete("$.e[?(@.f > 1)]", doc.clone()).1["e"], json!([{"f":1}]));
+        assert_eq!(delete("$..f", doc.clone()).1["e"], json!([{}, {"g":[1]}]));
+
+        let (removed, doc) = delete("$..g", json!({"g":{"g":1},"a":[{"g":2}]}));
+        assert_eq!((removed, doc), (2, json!({"a":[{}]})));
+        assert_eq!(delete("$", json!({"a":1})), (0, json!({"a":1})));
+        assert_eq!(delete("$.x", json!({"a":1})), (0, json!({"a":1})));
+    }
+
+    #[test]
+    fn replace_with_test() {
+        let mut doc = json!({"items":[{"price":8.951,"name":" A "},{"price":12.5,"name":"b"},{"name":"c"}]});
+        let round = |v: Value| json!((v.as_f64().unwrap() * 10.0).round() / 10.0);
+        assert_eq!(parse_json_path("$.items[*].price").unwrap().replace_with(&mut doc, round), 2);
+        let trim = |v: Value| json!(v.as_str().unwrap().trim().to_lowercase());
+        assert_eq!(parse_json_path("$..name").unwrap().replace_with(&mut doc, trim), 3);
+        assert_eq!(doc, json!({"items":[{"price":9.0,"name":"a"},{"price":12.5,"name":"b"},{"name":"c"}]}));
+
+        let mut doc = json!({"b":{"b":1,"c":2}});
+        let mut seen = vec![];
+        let replaced = parse_json_path("$..b").unwrap().replace_with(&mut doc, |v| {
+            seen.push(v.clone());
+            json!([v])
+        });
+        assert_eq!(replaced, 2);
+        assert_eq!(seen, vec![json!(1), json!({"b":[1],"c":2})]);
+        assert_eq!(doc, json!({"b":[{"b":[1],"c":2}]}));
+
+        let mut doc = json!([1, 2]);
+        assert_eq!(parse_json_path("$[0,0]").unwrap().replace_with(&mut doc, |v| json!(v.as_i64().unwrap() + 1)), 1);
+        assert_eq!(parse_json_path("$").unwrap().replace_with(&mut doc, |_| json!(null)), 1);
+        assert_eq!(doc, json!(null));
+        assert_eq!(parse_json_path("$.a").unwrap().replace_with(&mut doc, |v| v), 0);
+    }
+
+    #[test]
+    fn insert_test() {
+        let mut doc = json!({"users":[{"name":"a"},{"name":"b","active":false},"c"],"tags":[["x"],["y","z"],[]]});
+        assert_eq!(parse_json_path("$.users[*]").unwrap().insert(&mut doc, "active", json!(true)), 2);
+        assert_eq!(doc["users"], json!([{"name":"a","active":true},{"name":"b","active":true},"c"]));
+
+        assert_eq!(parse_json_path("$.tags[*]").unwrap().insert(&mut doc, 1, json!("new")), 2);
+        assert_eq!(doc["tags"], json!([["x","new"],["y","new","z"],[]]));
+        assert_eq!(parse_json_path("$.tags[*]").unwrap().insert(&mut doc, 0, json!(0)), 3);
+        assert_eq!(doc["tags"], json!([[0,"x","new"],[0,"y","new","z"],[0]]));
+
+        let mut doc = json!([[1],[2]]);
+        assert_eq!(parse_json_path("$[*]").unwrap().insert(&mut doc, 0, json!(0)), 2);
+        assert_eq!(parse_json_path("$").unwrap().insert(&mut doc, 0, json!([])), 1);
+        assert_eq!(doc, json!([[], [0, 1], [0, 2]]));
+        assert_eq!(parse_json_path("$.a").unwrap().insert(&mut doc, "b", json!(1)), 0);
+    }
+
+    #[test]
+    fn insert_creating_test() {
+        let mut doc = json!({});
+        let path = |q| parse_json_path(q).unwrap();
+        assert_eq!(path("$.a.b").insert_creating(&mut doc, "c", json!(1)), Ok(()));
+        assert_eq!(doc, json!({"a":{"b":{"c":1}}}));
+        assert_eq!(path("$.a.d").insert_creating(&mut doc, 0, json!("x")), Ok(()));
+        assert_eq!(path("$.a.d[1]").insert_creating(&mut doc, "e", json!(2)), Ok(()));
+        assert_eq!(path("$.a.b").insert_creating(&mut doc, "c", json!(3)), Ok(()));
+        assert_eq!(doc, json!({"a":{"b":{"c":3},"d":["x",{"e":2}]}}));
+
+        assert_eq!(path("$.a.d[5]").insert_creating(&mut doc, "e", json!(2)),
+                   Err(String::from("the index 5 is out of bounds at $['a']['d'] with the length 2")));
+        assert_eq!(path("$.a.b.c").insert_creating(&mut doc, "e", json!(2)),
+                   Err(String::from("the member 'e' is requested at $['a']['b']['c'] but the element is number")));
+        assert_eq!(path("$.a.d").insert_creating(&mut doc, "e", json!(2)),
+                   Err(String::from("the member 'e' is requested at $['a']['d'] but the element is array")));
+        assert_eq!(path("$.a.d").insert_creating(&mut doc, 3, json!(2)),
+                   Err(String::from("the position 3 is beyond the end of the array at $['a']['d'] with the length 2")));
+        assert!(path("$.a[*]").insert_creating(&mut doc, 0, json!(2)).is_err());
+    }
+}
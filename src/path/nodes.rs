@@ -0,0 +1,157 @@
+use std::iter::FromIterator;
+use std::collections::HashSet;
+use serde_json::Value;
+use crate::parser::model::JsonPath;
+use crate::path::normalized::NormalizedPath;
+
+/// The elements found in the document along with their locations.
+///
+/// The set operations compare the elements by identity, i.e. two equal values
+/// found in the different places of the document are different nodes,
+/// hence the lists being combined should be found in the same document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeList<'a> {
+    nodes: Vec<(NormalizedPath, &'a Value)>,
+}
+
+impl<'a> NodeList<'a> {
+    pub fn new(nodes: Vec<(NormalizedPath, &'a Value)>) -> Self {
+        NodeList { nodes }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, (NormalizedPath, &'a Value)> {
+        self.nodes.iter()
+    }
+
+    /// the locations of the found elements
+    pub fn paths(&self) -> Vec<&NormalizedPath> {
+        self.nodes.iter().map(|(p, _)| p).collect()
+    }
+
+    /// the locations of the found elements as the json pointers like `/store/book/2`
+    pub fn pointers(&self) -> Vec<String> {
+        self.nodes.iter().map(|(p, _)| p.to_pointer()).collect()
+    }
+
+    /// the found elements as `find` returns them
+    pub fn values(&self) -> Vec<&'a Value> {
+        self.nodes.iter().map(|(_, v)| *v).collect()
+    }
+
+    /// the nodes of this list followed by the nodes of the other one missing here
+    pub fn union(&self, other: &NodeList<'a>) -> NodeList<'a> {
+        let mut seen = self.identities();
+        let mut nodes = self.nodes.clone();
+        nodes.extend(other.nodes.iter().filter(|(_, v)| seen.insert(*v as *const Value)).cloned());
+        NodeList::new(nodes)
+    }
+
+    /// the nodes of this list present in the other one
+    pub fn intersect(&self, other: &NodeList<'a>) -> NodeList<'a> {
+        let other = other.identities();
+        self.filter(|v| other.contains(&(v as *const Value)))
+    }
+
+    /// the nodes of this list missing in the other one
+    pub fn difference(&self, other: &NodeList<'a>) -> NodeList<'a> {
+        let other = other.identities();
+        self.filter(|v| !other.contains(&(v as *const Value)))
+    }
+
+    /// the list without the repeated nodes, e.g. when the union names the same element twice
+    /// or the descent reaches it along the different ways, every node stays where it is met first
+    pub fn dedup(&self) -> NodeList<'a> {
+        let mut seen = HashSet::new();
+        NodeList::new(self.nodes.iter().filter(|(_, v)| seen.insert(*v as *const Value)).cloned().collect())
+    }
+
+    fn identities(&self) -> HashSet<*const Value> {
+        self.nodes.iter().map(|(_, v)| *v as *const Value).collect()
+    }
+
+    fn filter<F: Fn(&Value) -> bool>(&self, pred: F) -> NodeList<'a> {
+        NodeList::new(self.nodes.iter().filter(|(_, v)| pred(v)).cloned().collect())
+    }
+}
+
+impl<'a> IntoIterator for NodeList<'a> {
+    type Item = (NormalizedPath, &'a Value);
+    type IntoIter = std::vec::IntoIter<(NormalizedPath, &'a Value)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.nodes.into_iter()
+    }
+}
+
+impl<'a, 'b> IntoIterator for &'b NodeList<'a> {
+    type Item = &'b (NormalizedPath, &'a Value);
+    type IntoIter = std::slice::Iter<'b, (NormalizedPath, &'a Value)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.nodes.iter()
+    }
+}
+
+impl<'a> FromIterator<(NormalizedPath, &'a Value)> for NodeList<'a> {
+    fn from_iter<T: IntoIterator<Item=(NormalizedPath, &'a Value)>>(iter: T) -> Self {
+        NodeList::new(iter.into_iter().collect())
+    }
+}
+
+impl JsonPath<'_> {
+    /// finds the elements along with their locations
+    pub fn find_nodes<'a>(&self, doc: &'a Value) -> NodeList<'a> {
+        let mut nodes = vec![];
+        self.find_with(doc, |path, v| nodes.push((path.clone(), v)));
+        NodeList::new(nodes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::parser::parser::parse_json_path;
+
+    #[test]
+    fn set_operations_test() {
+        let doc = json!({"books":[
+            {"title":"a","price":5,"tags":["x"]},
+            {"title":"b","price":15},
+            {"title":"c","price":25,"tags":["x"]}]});
+        let cheap = parse_json_path("$.books[?(@.price < 20)]").unwrap().find_nodes(&doc);
+        let tagged = parse_json_path("$.books[?(@.tags)]").unwrap().find_nodes(&doc);
+        let titles = |nodes: crate::NodeList| nodes.into_iter().map(|(_, v)| v["title"].clone()).collect::<Vec<_>>();
+
+        assert_eq!(titles(cheap.union(&tagged)), vec![json!("a"), json!("b"), json!("c")]);
+        assert_eq!(titles(cheap.intersect(&tagged)), vec![json!("a")]);
+        assert_eq!(titles(cheap.difference(&tagged)), vec![json!("b")]);
+        assert_eq!(tagged.difference(&cheap).paths().iter().map(|p| p.to_string()).collect::<Vec<_>>(),
+                   vec!["$['books'][2]"]);
+    }
+
+    #[test]
+    fn identity_test() {
+        let doc = json!({"a":1,"b":1});
+        let a = parse_json_path("$.a").unwrap().find_nodes(&doc);
+        let b = parse_json_path("$.b").unwrap().find_nodes(&doc);
+        assert_eq!(a.union(&b).len(), 2);
+        assert!(a.intersect(&b).is_empty());
+        assert_eq!(a.union(&a), a);
+        assert_eq!((&a).into_iter().count(), 1);
+    }
+
+    #[test]
+    fn dedup_test() {
+        let doc = json!({"a":[1,1,{"b":1}]});
+        let nodes = parse_json_path("$.a[2,0,2,1,0]").unwrap().find_nodes(&doc).dedup();
+        assert_eq!(nodes.pointers(), vec!["/a/2", "/a/0", "/a/1"]);
+    }
+}
@@ -0,0 +1,92 @@
+use serde_json::Value;
+use crate::parser::model::{FilterSign, JsonPath};
+use crate::path::Options;
+
+/// How the filters compare the numbers with the non-finite values.
+///
+/// The json has no way to store NaN or infinities as the numbers,
+/// so the documents usually carry them as the strings `"NaN"`, `"Infinity"` and `"-Infinity"`.
+/// The policy applies when such a string is compared(`==`, `!=`, `<`, `<=`, `>`, `>=`) with a number,
+/// the strings compared with the strings stay the strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonFinite {
+    /// the value is not a number, so it neither equals nor is ordered with any number(the default behaviour)
+    #[default]
+    Missing,
+    /// the comparison is an error and the evaluation fails
+    Error,
+    /// the value is the float compared by IEEE 754, i.e. NaN is unordered and unequal to everything
+    /// and the infinities are beyond all the numbers.
+    Ieee,
+}
+
+fn parse(v: &Value) -> Option<f64> {
+    match v.as_str()? {
+        "NaN" => Some(f64::NAN),
+        "Infinity" => Some(f64::INFINITY),
+        "-Infinity" => Some(f64::NEG_INFINITY),
+        _ => None
+    }
+}
+
+/// compares the non-finite value with the number according to the policy.
+/// Returns None if there is nothing the policy applies to.
+pub(crate) fn compare(policy: NonFinite, op: &FilterSign, left: &[&Value], right: &[&Value]) -> Option<Result<bool, String>> {
+    if policy == NonFinite::Missing {
+        return None;
+    }
+    let (l, r) = match (left, right) {
+        ([l], [r]) => (*l, *r),
+        _ => return None
+    };
+    let (lf, rf) = match (l, r) {
+        (Value::Number(n), v) => (n.to_string().parse::<f64>().ok()?, parse(v)?),
+        (v, Value::Number(n)) => (parse(v)?, n.to_string().parse::<f64>().ok()?),
+        _ => return None
+    };
+    let res = match op {
+        FilterSign::Equal => lf == rf,
+        FilterSign::Unequal => lf != rf,
+        FilterSign::Less => lf < rf,
+        FilterSign::LeOrEq => lf <= rf,
+        FilterSign::Greater => lf > rf,
+        FilterSign::GrOrEq => lf >= rf,
+        _ => return None
+    };
+    match policy {
+        NonFinite::Error => Some(Err(format!("the non-finite value {} is compared with {}", l, r))),
+        _ => Some(Ok(res)),
+    }
+}
+
+impl JsonPath<'_> {
+    /// finds the elements like `find` but compares the non-finite values with the numbers as the policy says.
+    /// With [NonFinite::Error] the first such comparison fails the evaluation.
+    pub fn find_with_policy<'a>(&'a self, doc: &'a Value, policy: NonFinite) -> Result<Vec<&'a Value>, String> {
+        self.find_with_options(doc, &Options::new().non_finite(policy))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::parser::parser::parse_json_path;
+    use crate::path::non_finite::NonFinite;
+
+    #[test]
+    fn policy_test() {
+        let doc = json!({"m":[{"v":1.5},{"v":"NaN"},{"v":"Infinity"},{"v":"-Infinity"},{"v":"n/a"}]});
+        let find = |q: &str, policy| parse_json_path(q).unwrap().find_with_policy(&doc, policy).map(|r| r.into_iter().cloned().collect::<Vec<_>>());
+
+        assert_eq!(find("$.m[?(@.v > 1)].v", NonFinite::Missing), Ok(vec![json!(1.5)]));
+        assert_eq!(find("$.m[?(@.v > 1)].v", NonFinite::Ieee), Ok(vec![json!(1.5), json!("Infinity")]));
+        assert_eq!(find("$.m[?(@.v < 1)].v", NonFinite::Ieee), Ok(vec![json!("-Infinity")]));
+        assert_eq!(find("$.m[?(@.v != 1.5)].v", NonFinite::Ieee),
+                   Ok(vec![json!("NaN"), json!("Infinity"), json!("-Infinity"), json!("n/a")]));
+        assert_eq!(find("$.m[?(@.v == 'NaN')].v", NonFinite::Ieee), Ok(vec![json!("NaN")]));
+
+        assert_eq!(find("$.m[?(@.v > 1)].v", NonFinite::Error),
+                   Err(String::from("the non-finite value \"NaN\" is compared with 1")));
+        assert_eq!(find("$.m[?(@.v == 'n/a')].v", NonFinite::Error), Ok(vec![json!("n/a")]));
+    }
+}
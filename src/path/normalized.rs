@@ -0,0 +1,359 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use serde_json::Value;
+use crate::parser::model::{JsonPath, JsonPathIndex};
+use crate::parser::parser::unescape_char;
+
+/// The step of the normalized path: either the member name of the object or the index of the array element.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl From<&str> for PathSegment {
+    fn from(key: &str) -> Self {
+        PathSegment::Key(String::from(key))
+    }
+}
+
+impl From<String> for PathSegment {
+    fn from(key: String) -> Self {
+        PathSegment::Key(key)
+    }
+}
+
+impl From<usize> for PathSegment {
+    fn from(idx: usize) -> Self {
+        PathSegment::Index(idx)
+    }
+}
+
+/// The location of the single element in the json document
+/// that is rendered in the RFC 9535 canonical form like `$['store']['book'][2]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NormalizedPath {
+    segments: Vec<PathSegment>,
+}
+
+impl NormalizedPath {
+    /// the path pointing out to the root of the document
+    pub fn root() -> Self {
+        NormalizedPath::default()
+    }
+
+    pub fn new(segments: Vec<PathSegment>) -> Self {
+        NormalizedPath { segments }
+    }
+
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// the path to the enclosing array or object or None for the root
+    pub fn parent(&self) -> Option<NormalizedPath> {
+        self.segments.split_last().map(|(_, init)| NormalizedPath::new(init.to_vec()))
+    }
+
+    /// the last segment or None for the root
+    pub fn last(&self) -> Option<&PathSegment> {
+        self.segments.last()
+    }
+
+    /// appends the segment to the end of the path
+    pub fn push<S: Into<PathSegment>>(&mut self, segment: S) {
+        self.segments.push(segment.into())
+    }
+
+    /// removes the last segment returning it
+    pub fn pop(&mut self) -> Option<PathSegment> {
+        self.segments.pop()
+    }
+
+    /// keeps the first segments dropping the rest
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.segments.truncate(len)
+    }
+
+    /// creates a new path with the segment appended to the end
+    pub fn join<S: Into<PathSegment>>(&self, segment: S) -> NormalizedPath {
+        let mut path = self.clone();
+        path.push(segment);
+        path
+    }
+
+    /// checks whether the given path is the prefix of the current one(every path starts with itself)
+    pub fn starts_with(&self, prefix: &NormalizedPath) -> bool {
+        self.segments.starts_with(&prefix.segments)
+    }
+
+    /// finds the element the path points out to in the document
+    pub fn resolve<'a>(&self, doc: &'a Value) -> Option<&'a Value> {
+        self.segments.iter().try_fold(doc, |node, seg| match (node, seg) {
+            (Value::Object(fields), PathSegment::Key(key)) => fields.get(key),
+            (Value::Array(elems), PathSegment::Index(idx)) => elems.get(*idx),
+            _ => None
+        })
+    }
+
+    /// finds the element the path points out to in the document to change it
+    pub fn resolve_mut<'a>(&self, doc: &'a mut Value) -> Option<&'a mut Value> {
+        self.segments.iter().try_fold(doc, |node, seg| match (node, seg) {
+            (Value::Object(fields), PathSegment::Key(key)) => fields.get_mut(key),
+            (Value::Array(elems), PathSegment::Index(idx)) => elems.get_mut(*idx),
+            _ => None
+        })
+    }
+
+    /// renders the path into the RFC 6901 json pointer like `/store/book/2`
+    pub fn to_pointer(&self) -> String {
+        self.segments.iter().fold(String::new(), |mut res, s| {
+            res.push('/');
+            match s {
+                PathSegment::Key(k) => res.push_str(&k.replace('~', "~0").replace('/', "~1")),
+                PathSegment::Index(i) => res.push_str(&i.to_string()),
+            }
+            res
+        })
+    }
+
+    /// parses the RFC 6901 json pointer.
+    /// Since the pointer does not distinguish the keys and indexes,
+    /// the tokens consisting of digits(without leading zeros) are treated as indexes.
+    pub fn from_pointer(pointer: &str) -> Result<NormalizedPath, String> {
+        if pointer.is_empty() {
+            return Ok(NormalizedPath::root());
+        }
+        if !pointer.starts_with('/') {
+            return Err(format!("the json pointer should start with '/' but got '{}'", pointer));
+        }
+        let mut segments = vec![];
+        for token in pointer[1..].split('/') {
+            if let Some(pos) = token.match_indices('~').map(|(i, _)| i)
+                .find(|i| !matches!(token.as_bytes().get(i + 1), Some(b'0') | Some(b'1'))) {
+                return Err(format!("the invalid escape sequence in the token '{}' at {}", token, pos));
+            }
+            match token.parse::<usize>() {
+                Ok(idx) if is_index_token(token) => segments.push(PathSegment::Index(idx)),
+                _ => segments.push(PathSegment::Key(token.replace("~1", "/").replace("~0", "~"))),
+            }
+        }
+        Ok(NormalizedPath::new(segments))
+    }
+}
+
+/// the pointer token consisting of digits without leading zeros is read as the index
+pub(crate) fn is_index_token(token: &str) -> bool {
+    !token.is_empty() && token.bytes().all(|b| b.is_ascii_digit()) && (token == "0" || !token.starts_with('0'))
+}
+
+impl FromStr for NormalizedPath {
+    type Err = String;
+
+    /// parses the path in the RFC 9535 canonical form like `$['a'][0]['b']`
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let mut chars = path.char_indices().peekable();
+        match chars.next() {
+            Some((_, '$')) => (),
+            _ => return Err(String::from("the normalized path should start with '$'")),
+        }
+        let mut segments = vec![];
+        while let Some((pos, c)) = chars.next() {
+            if c != '[' {
+                return Err(format!("expected '[' at {} but got '{}'", pos, c));
+            }
+            match chars.next() {
+                Some((_, '\'')) => {
+                    let mut key = String::new();
+                    loop {
+                        match chars.next() {
+                            Some((_, '\'')) => break,
+                            Some((pos, '\\')) => key.push(unescape_char(&mut chars).ok_or_else(|| format!("invalid escape sequence at {}", pos))?),
+                            Some((_, c)) => key.push(c),
+                            None => return Err(String::from("unterminated member name")),
+                        }
+                    }
+                    segments.push(PathSegment::Key(key));
+                }
+                Some((start, d)) if d.is_ascii_digit() => {
+                    let mut end = start + 1;
+                    while let Some((i, d)) = chars.peek() {
+                        if !d.is_ascii_digit() {
+                            break;
+                        }
+                        end = i + 1;
+                        chars.next();
+                    }
+                    let digits = &path[start..end];
+                    if digits.len() > 1 && digits.starts_with('0') {
+                        return Err(format!("leading zeros in the index at {}", start));
+                    }
+                    segments.push(PathSegment::Index(digits.parse::<usize>().map_err(|e| e.to_string())?));
+                }
+                Some((pos, c)) => return Err(format!("unexpected '{}' at {}", c, pos)),
+                None => return Err(String::from("unexpected end of the normalized path")),
+            }
+            match chars.next() {
+                Some((_, ']')) => (),
+                Some((pos, c)) => return Err(format!("expected ']' at {} but got '{}'", pos, c)),
+                None => return Err(String::from("unexpected end of the normalized path")),
+            }
+        }
+        Ok(NormalizedPath::new(segments))
+    }
+}
+
+impl From<&NormalizedPath> for JsonPath<'static> {
+    /// builds the definite path from the normalized one
+    fn from(path: &NormalizedPath) -> Self {
+        let mut chain = vec![JsonPath::Root];
+        for s in path.segments.iter() {
+            chain.push(match s {
+                PathSegment::Key(k) => JsonPath::Field(k.clone().into()),
+                PathSegment::Index(i) => JsonPath::Index(JsonPathIndex::Single(Value::from(*i))),
+            })
+        }
+        JsonPath::Chain(chain)
+    }
+}
+
+impl From<NormalizedPath> for JsonPath<'static> {
+    fn from(path: NormalizedPath) -> Self {
+        JsonPath::from(&path)
+    }
+}
+
+/// escapes the member name according to the rules of the normalized paths
+/// so that it can be safely placed between the single quotes in the query like `$['<name>']`.
+pub fn escape_member_name(name: &str) -> String {
+    let mut res = String::with_capacity(name.len());
+    for c in name.chars() {
+        match c {
+            '\'' => res.push_str("\\'"),
+            '\\' => res.push_str("\\\\"),
+            '\u{8}' => res.push_str("\\b"),
+            '\u{c}' => res.push_str("\\f"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if (c as u32) < 0x20 => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+impl Display for PathSegment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathSegment::Key(k) => write!(f, "['{}']", escape_member_name(k)),
+            PathSegment::Index(i) => write!(f, "[{}]", i),
+        }
+    }
+}
+
+impl Display for NormalizedPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "$")?;
+        self.segments.iter().try_for_each(|s| write!(f, "{}", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::path::normalized::{escape_member_name, NormalizedPath, PathSegment};
+    use crate::parser::model::JsonPath;
+    use crate::parser::parser::parse_json_path;
+
+    #[test]
+    fn display_test() {
+        let mut path = NormalizedPath::root();
+        assert_eq!(path.to_string(), "$");
+        path.push("store");
+        path.push("book");
+        path.push(2);
+        assert_eq!(path.to_string(), "$['store']['book'][2]");
+        assert_eq!(NormalizedPath::root().join("it's\\\n\u{1}").to_string(), "$['it\\'s\\\\\\n\\u0001']");
+    }
+
+    #[test]
+    fn parent_test() {
+        let path = NormalizedPath::root().join("a").join(0).join("b");
+        let parent = path.parent().unwrap();
+        assert_eq!(parent, NormalizedPath::root().join("a").join(0));
+        assert_eq!(path.last(), Some(&PathSegment::from("b")));
+        assert!(path.starts_with(&parent));
+        assert!(path.starts_with(&path));
+        assert!(path.starts_with(&NormalizedPath::root()));
+        assert!(!parent.starts_with(&path));
+        assert!(!path.starts_with(&NormalizedPath::root().join("b")));
+        assert_eq!(NormalizedPath::root().parent(), None);
+    }
+
+    #[test]
+    fn resolve_mut_test() {
+        let mut doc = json!({"a":[{"b":1}]});
+        *NormalizedPath::root().join("a").join(0).join("b").resolve_mut(&mut doc).unwrap() = json!(2);
+        assert_eq!(doc, json!({"a":[{"b":2}]}));
+        assert!(NormalizedPath::root().join("a").join(1).resolve_mut(&mut doc).is_none());
+        assert!(NormalizedPath::root().join(0).resolve_mut(&mut doc).is_none());
+    }
+
+    #[test]
+    fn pointer_test() {
+        let path = NormalizedPath::root().join("a/b").join("m~n").join(10).join("");
+        assert_eq!(path.to_pointer(), "/a~1b/m~0n/10/");
+        assert_eq!(NormalizedPath::from_pointer(&path.to_pointer()), Ok(path));
+        assert_eq!(NormalizedPath::from_pointer(""), Ok(NormalizedPath::root()));
+        assert_eq!(NormalizedPath::from_pointer("/01"), Ok(NormalizedPath::root().join("01")));
+        assert_eq!(NormalizedPath::from_pointer("/~01"), Ok(NormalizedPath::root().join("~1")));
+        assert!(NormalizedPath::from_pointer("a").is_err());
+        assert!(NormalizedPath::from_pointer("/a~2").is_err());
+        assert!(NormalizedPath::from_pointer("/a~").is_err());
+    }
+
+    #[test]
+    fn parse_test() {
+        let path = NormalizedPath::root().join("a").join(0).join("it's\\\n\u{1}").join("\u{1F600}");
+        assert_eq!(path.to_string().parse::<NormalizedPath>(), Ok(path));
+        assert_eq!("$".parse::<NormalizedPath>(), Ok(NormalizedPath::root()));
+        assert_eq!("$['\\ud83d\\ude00']".parse::<NormalizedPath>(), Ok(NormalizedPath::root().join("\u{1F600}")));
+        assert!("".parse::<NormalizedPath>().is_err());
+        assert!("$.a".parse::<NormalizedPath>().is_err());
+        assert!("$['a'".parse::<NormalizedPath>().is_err());
+        assert!("$['a]".parse::<NormalizedPath>().is_err());
+        assert!("$[01]".parse::<NormalizedPath>().is_err());
+        assert!("$[-1]".parse::<NormalizedPath>().is_err());
+        assert!("$['\\x']".parse::<NormalizedPath>().is_err());
+        assert!("$['\\ud83d']".parse::<NormalizedPath>().is_err());
+    }
+
+    #[test]
+    fn escape_round_trip_test() {
+        for key in ["it's", "\"quoted\"", "back\\slash", "line\nfeed\r\t", "\u{1}\u{1f}\u{8}\u{c}", "\u{e9}\u{1F600}", "[a.b]*"] {
+            let query = format!("$['{}']", escape_member_name(key));
+            assert_eq!(parse_json_path(&query).unwrap(), JsonPath::Chain(vec![JsonPath::Root, JsonPath::field(key)]), "{}", query);
+            let path = NormalizedPath::root().join(key);
+            assert_eq!(path.to_string(), query);
+            assert_eq!(query.parse::<NormalizedPath>(), Ok(path));
+            let doc = json!({ key: 1 });
+            assert_eq!(parse_json_path(&query).unwrap().find_one(&doc), Some(&json!(1)));
+        }
+        assert_eq!(parse_json_path("$[\"\\\"\\'\\\\\\n\\u00e9\"]").unwrap(),
+                   JsonPath::Chain(vec![JsonPath::Root, JsonPath::field("\"'\\\n\u{e9}")]));
+    }
+
+    #[test]
+    fn query_test() {
+        let json = json!({"a":[{"b":1},{"b":2}]});
+        let path: NormalizedPath = "$['a'][1]['b']".parse().unwrap();
+        let query = JsonPath::from(&path);
+        assert_eq!(query, parse_json_path("$['a'][1]['b']").unwrap());
+        assert_eq!(query.find_one(&json), Some(&json!(2)));
+    }
+}
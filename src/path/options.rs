@@ -0,0 +1,353 @@
+use std::cell::OnceCell;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Formatter};
+use std::rc::Rc;
+use std::sync::Arc;
+use serde_json::Value;
+use crate::parser::model::{FilterSign, JsonPath};
+use crate::path::{definite, Env, functions, instance, NonFinite, Path};
+use crate::path::top::Chain;
+
+/// The order of the strings in the filters, e.g. the one following the rules of the language.
+///
+/// The locale-aware orders come from `icu_collator` with the feature `icu`(see [Options::locale]),
+/// its collators being the collations as they are. Any function `Fn(&str, &str) -> Ordering` is the collation as well.
+pub trait Collation: Send + Sync {
+    fn compare(&self, left: &str, right: &str) -> Ordering;
+}
+
+impl<F> Collation for F where F: Fn(&str, &str) -> Ordering + Send + Sync {
+    fn compare(&self, left: &str, right: &str) -> Ordering {
+        self(left, right)
+    }
+}
+
+#[cfg(feature = "icu")]
+impl Collation for icu_collator::CollatorBorrowed<'static> {
+    fn compare(&self, left: &str, right: &str) -> Ordering {
+        icu_collator::CollatorBorrowed::compare(self, left, right)
+    }
+}
+
+/// The function extension of the filters the application adds to the built-in ones like `length()`.
+///
+/// It gets the elements every argument gives and returns the value standing for the call, `None` is nothing.
+/// Any function `Fn(&[Vec<&Value>]) -> Option<Value>` is the function extension as well.
+pub trait FilterFunction: Send + Sync {
+    fn call(&self, args: &[Vec<&Value>]) -> Option<Value>;
+}
+
+impl<F> FilterFunction for F where F: Fn(&[Vec<&Value>]) -> Option<Value> + Send + Sync {
+    fn call(&self, args: &[Vec<&Value>]) -> Option<Value> {
+        self(args)
+    }
+}
+
+/// The settings the queries are evaluated with.
+#[derive(Clone, Default)]
+pub struct Options {
+    pub(crate) non_finite: NonFinite,
+    pub(crate) collation: Option<Arc<dyn Collation>>,
+    pub(crate) extended_json: bool,
+    pub(crate) functions: HashMap<String, Arc<dyn FilterFunction>>,
+    pub(crate) strict: bool,
+    pub(crate) leaf_to_null: bool,
+    pub(crate) dedup: bool,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Options::default()
+    }
+
+    /// how the non-finite values are compared with the numbers
+    pub fn non_finite(mut self, policy: NonFinite) -> Self {
+        self.non_finite = policy;
+        self
+    }
+
+    /// the order the strings are compared with, without it the strings are compared by their code points
+    pub fn collation<C: Collation + 'static>(mut self, collation: C) -> Self {
+        self.collation = Some(Arc::new(collation));
+        self
+    }
+
+    /// the collation of the locale like `de` or `sv-SE`, the strings are compared by the Unicode Collation Algorithm
+    /// with the tailoring of the locale
+    #[cfg(feature = "icu")]
+    pub fn locale(self, tag: &str) -> Result<Self, String> {
+        let locale = icu_locale_core::Locale::try_from_str(tag)
+            .map_err(|e| format!("the locale '{}' is invalid: {}", tag, e))?;
+        let collator = icu_collator::Collator::try_new((&locale).into(), Default::default())
+            .map_err(|e| format!("the collation of the locale '{}' is not available: {}", tag, e))?;
+        Ok(self.collation(collator))
+    }
+
+    /// compares the wrappers of MongoDB Extended JSON like `{"$date": ...}`, `{"$oid": ...}` or `{"$numberLong": ...}`
+    /// by the values they stand for, so the BSON documents exported as the canonical Extended JSON
+    /// are queried without losing the types of their values
+    pub fn extended_json(mut self) -> Self {
+        self.extended_json = true;
+        self
+    }
+
+    /// adds the function the filters can call like `[?(double(@.price) > 10)]`,
+    /// the names of the built-in functions(`length`, `count` and `value`) can not be taken
+    pub fn function<F: FilterFunction + 'static>(mut self, name: &str, function: F) -> Self {
+        self.functions.insert(String::from(name), Arc::new(function));
+        self
+    }
+
+    /// the missing members and the indexes applied to the elements being not arrays(or out of their bounds)
+    /// fail the query with the error telling where it happened instead of selecting nothing.
+    /// The filters stay lenient, so `[?(@.a)]` still checks whether the member exists.
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// the member the query ends with gives null in every found object missing it instead of nothing,
+    /// like `PATH_LEAF_TO_NULL` of Jayway: `$.user.phone` finds null for `{"user":{}}` but nothing for `{}`.
+    /// In the strict mode the missing final member is not the error then.
+    pub fn leaf_to_null(mut self) -> Self {
+        self.leaf_to_null = true;
+        self
+    }
+
+    /// finds every element once, the one found several times(e.g. by `$[0,0]` or `$['a','a']`)
+    /// stays where it is met first. The elements are told apart by their places in the document, not by the values.
+    pub fn dedup(mut self) -> Self {
+        self.dedup = true;
+        self
+    }
+}
+
+impl Debug for Options {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Options")
+            .field("non_finite", &self.non_finite)
+            .field("collation", &self.collation.is_some())
+            .field("extended_json", &self.extended_json)
+            .field("functions", &self.functions.keys().collect::<Vec<_>>())
+            .field("strict", &self.strict)
+            .field("leaf_to_null", &self.leaf_to_null)
+            .field("dedup", &self.dedup)
+            .finish()
+    }
+}
+
+static NULL: Value = Value::Null;
+
+/// compares two strings with the collation if there is any
+pub(crate) fn collate(options: &Options, op: &FilterSign, left: &[&Value], right: &[&Value]) -> Option<bool> {
+    let collation = options.collation.as_ref()?;
+    let ord = match (left, right) {
+        ([Value::String(l)], [Value::String(r)]) => collation.compare(l, r),
+        _ => return None
+    };
+    match op {
+        FilterSign::Equal => Some(ord == Ordering::Equal),
+        FilterSign::Unequal => Some(ord != Ordering::Equal),
+        FilterSign::Less => Some(ord == Ordering::Less),
+        FilterSign::LeOrEq => Some(ord != Ordering::Greater),
+        FilterSign::Greater => Some(ord == Ordering::Greater),
+        FilterSign::GrOrEq => Some(ord != Ordering::Less),
+        _ => None
+    }
+}
+
+impl JsonPath<'_> {
+    /// finds the elements like `find` but with the settings
+    pub fn find_with_options<'a>(&'a self, doc: &'a Value, options: &Options) -> Result<Vec<&'a Value>, String> {
+        self.validate()?;
+        if let Some(name) = functions::unknown(self, options) {
+            return Err(format!("the function '{}' is unknown", name));
+        }
+        let leaf = if options.leaf_to_null { leaf(self) } else { None };
+        let error = Rc::new(OnceCell::new());
+        let env = Env { root: doc, options: options.clone(), error: Some(error.clone()) };
+        if options.strict {
+            match leaf {
+                Some((prefix, _)) => definite::check_strict(&JsonPath::Chain(prefix.to_vec()), &env)?,
+                None => definite::check_strict(self, &env)?,
+            }
+        }
+        // the nulls standing for the missing members are all the same static value,
+        // so the objects they are missing in are deduplicated instead
+        let res = match leaf {
+            Some((prefix, key)) => dedup(Chain::from(prefix, &env).find(doc), options).into_iter()
+                .filter_map(|v| v.as_object().map(|fields| fields.get(key).unwrap_or(&NULL)))
+                .collect(),
+            None => dedup(instance(self, &env).find(doc), options),
+        };
+        match error.get() {
+            Some(e) => Err(e.clone()),
+            None => Ok(res)
+        }
+    }
+}
+
+/// leaves the first occurrence of every element if the options say so
+fn dedup<'a>(mut found: Vec<&'a Value>, options: &Options) -> Vec<&'a Value> {
+    if options.dedup {
+        let mut seen = HashSet::new();
+        found.retain(|v| seen.insert(*v as *const Value));
+    }
+    found
+}
+
+/// the steps before the final member and the name of the member if the query ends with one
+fn leaf<'a>(path: &'a JsonPath<'a>) -> Option<(&'a [JsonPath<'a>], &'a str)> {
+    match path {
+        JsonPath::Chain(elems) => match elems.split_last() {
+            Some((JsonPath::Field(key), prefix)) if !prefix.is_empty() => Some((prefix, key.as_ref())),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+    use serde_json::json;
+    use crate::parser::parser::parse_json_path;
+    use serde_json::Value;
+    use crate::path::{NonFinite, Options};
+
+    /// orders the letters ignoring the case and the accents of the few latin letters
+    fn folded(left: &str, right: &str) -> Ordering {
+        let fold = |s: &str| s.chars().map(|c| match c {
+            'é' | 'è' | 'É' => 'e',
+            'ö' | 'Ö' => 'o',
+            c => c.to_ascii_lowercase()
+        }).collect::<String>();
+        fold(left).cmp(&fold(right))
+    }
+
+    #[test]
+    fn collation_test() {
+        let doc = json!({"names":["zoe","Émile","eva","Öre","adam"]});
+        let find = |q: &str, options: &Options| parse_json_path(q).unwrap().find_with_options(&doc, options).unwrap()
+            .into_iter().cloned().collect::<Vec<_>>();
+
+        assert_eq!(find("$.names[?(@ < 'f')]", &Options::new()), vec![json!("eva"), json!("adam")]);
+        let options = Options::new().collation(folded);
+        assert_eq!(find("$.names[?(@ < 'f')]", &options), vec![json!("Émile"), json!("eva"), json!("adam")]);
+        assert_eq!(find("$.names[?(@ == 'ore')]", &options), vec![json!("Öre")]);
+        assert_eq!(find("$.names[?(@ >= 'p')]", &options), vec![json!("zoe")]);
+
+        let options = options.non_finite(NonFinite::Error);
+        assert!(parse_json_path("$[?(@.v > 1)]").unwrap().find_with_options(&json!([{"v":"NaN"}]), &options).is_err());
+    }
+
+    #[test]
+    fn function_test() {
+        let doc = json!({"items":[{"id":1,"price":3,"tags":["a","b"]},{"id":2,"price":7,"tags":["b"]},{"id":3,"price":"x"}]});
+        let options = Options::new()
+            .function("double", |args: &[Vec<&Value>]| match args {
+                [v] => v.first().and_then(|v| v.as_f64()).map(|n| json!(n * 2.0)),
+                _ => None
+            })
+            .function("has", |args: &[Vec<&Value>]| match args {
+                [found, tag] => Some(Value::Bool(found.iter().any(|v| tag.contains(v)))),
+                _ => None
+            })
+            .function("length", |_: &[Vec<&Value>]| Some(json!(100)));
+        let find = |q: &str| parse_json_path(q).unwrap().find_with_options(&doc, &options)
+            .map(|found| found.into_iter().cloned().collect::<Vec<_>>());
+
+        assert_eq!(find("$.items[?(double(@.price) > 10)].id"), Ok(vec![json!(2)]));
+        assert_eq!(find("$.items[?(double(double(@.price)) == 12)].id"), Ok(vec![json!(1)]));
+        assert_eq!(find("$.items[?(has(@.tags[*], 'a'))].id"), Ok(vec![json!(1)]));
+        assert_eq!(find("$.items[?(!has(@.tags[*], 'a'))].id"), Ok(vec![json!(2), json!(3)]));
+        assert_eq!(find("$.items[?(has(@.tags[*], 'b') == true && length(@.tags) == 1)].id"), Ok(vec![json!(2)]));
+        assert_eq!(find("$.items[?(triple(@.price) > 10)].id"), Err(String::from("the function 'triple' is unknown")));
+
+        let path = parse_json_path("$.items[?(double(@.price) > 10)].id").unwrap();
+        assert!(path.find_nodes(&doc).is_empty());
+        assert_eq!(path.try_find(&doc), Err(String::from("the function 'double' is unknown")));
+        assert!(format!("{:?}", options).contains("\"double\""));
+    }
+
+    #[test]
+    fn strict_test() {
+        let doc = json!({"users":[{"name":"a","tags":["x"]},{"name":"b","tags":{}}],"count":2});
+        let find = |q: &str, options: &Options| parse_json_path(q).unwrap().find_with_options(&doc, options)
+            .map(|found| found.into_iter().cloned().collect::<Vec<_>>());
+        let strict = Options::new().strict();
+
+        assert_eq!(find("$.users[*].name", &strict), Ok(vec![json!("a"), json!("b")]));
+        assert_eq!(find("$.users[?(@.age)].name", &strict), Ok(vec![]));
+        assert_eq!(find("$.users[1].tags.*", &strict), Ok(vec![]));
+        assert_eq!(find("$.usres[0]", &Options::new()), Ok(vec![]));
+        assert_eq!(find("$.usres[0]", &strict),
+                   Err(String::from("the member 'usres' is not found at $, did you mean 'users'?")));
+        assert_eq!(find("$.users[*].tags[0]", &strict),
+                   Err(String::from("the index 0 is requested at $['users'][1]['tags'] but the element is object")));
+        assert_eq!(find("$.users[2]", &strict),
+                   Err(String::from("the index 2 is out of bounds at $['users'] with the length 2")));
+        assert_eq!(find("$.count.value", &strict),
+                   Err(String::from("the member 'value' is requested at $['count'] but the element is number")));
+        assert_eq!(find("$..name.first", &strict),
+                   Err(String::from("the member 'first' is requested at $['users'][0]['name'] but the element is string")));
+        assert!(format!("{:?}", strict).contains("strict: true"));
+    }
+
+    #[test]
+    fn strict_filters_test() {
+        let doc = json!({"a":[{"p":10,"n":"B"},{"p":5,"n":"0"}]});
+        let find = |q: &str, options: &Options| parse_json_path(q).unwrap().find_with_options(&doc, options)
+            .map(|found| found.into_iter().cloned().collect::<Vec<_>>());
+
+        let double = Options::new().function("double", |args: &[Vec<&Value>]| match args {
+            [v] => v.first().and_then(|v| v.as_f64()).map(|n| json!(n * 2.0)),
+            _ => None
+        });
+        assert_eq!(find("$.a[?(double(@.p) > 15)].p", &double.clone().strict()), Ok(vec![json!(10)]));
+        assert_eq!(find("$.a[?(double(@.p) > 15)].missing", &double.strict()),
+                   Err(String::from("the member 'missing' is not found at $['a'][0]")));
+
+        let folded = Options::new().collation(|l: &str, r: &str| l.to_lowercase().cmp(&r.to_lowercase()));
+        assert_eq!(find("$.a[?(@.n > 'a')].missing", &Options::new().strict()), Ok(vec![]));
+        assert_eq!(find("$.a[?(@.n > 'a')].missing", &folded.strict()),
+                   Err(String::from("the member 'missing' is not found at $['a'][0]")));
+    }
+
+    #[test]
+    fn leaf_to_null_test() {
+        let doc = json!({"users":[{"name":"a","phone":"1"},{"name":"b"},{"name":"c","phone":null},5],"admin":{}});
+        let options = Options::new().leaf_to_null();
+        let find = |q: &str, options: &Options| parse_json_path(q).unwrap().find_with_options(&doc, options)
+            .map(|found| found.into_iter().cloned().collect::<Vec<_>>());
+
+        assert_eq!(find("$.users[*].phone", &Options::new()), Ok(vec![json!("1"), json!(null)]));
+        assert_eq!(find("$.users[*].phone", &options), Ok(vec![json!("1"), json!(null), json!(null)]));
+        assert_eq!(find("$.admin.phone", &options), Ok(vec![json!(null)]));
+        assert_eq!(find("$.root.phone", &options), Ok(vec![]));
+        assert_eq!(find("$.users[?(@.phone == null)].name", &options), Ok(vec![json!("c")]));
+        assert_eq!(find("$.users[1]", &options), Ok(vec![json!({"name":"b"})]));
+
+        let strict = options.strict();
+        assert_eq!(find("$.admin.phone", &strict), Ok(vec![json!(null)]));
+        assert_eq!(find("$.amdin.phone", &strict),
+                   Err(String::from("the member 'amdin' is not found at $, did you mean 'admin'?")));
+    }
+
+    #[test]
+    fn dedup_test() {
+        let doc = json!({"a":[{"b":1},{"b":1}],"c":{"b":2}});
+        let options = Options::new().dedup();
+        let find = |q: &str, options: &Options| parse_json_path(q).unwrap().find_with_options(&doc, options)
+            .map(|found| found.into_iter().cloned().collect::<Vec<_>>());
+
+        assert_eq!(find("$.a[0,1,0]", &Options::new()).unwrap().len(), 3);
+        assert_eq!(find("$.a[0,1,0]", &options), Ok(vec![json!({"b":1}), json!({"b":1})]));
+        assert_eq!(find("$.a[1,0,1]", &options).unwrap().len(), 2);
+        assert_eq!(find("$['c','c'].b", &options), Ok(vec![json!(2)]));
+        assert_eq!(find("$..a[0,0].b", &Options::new()), Ok(vec![json!(1), json!(1)]));
+        assert_eq!(find("$..a[0,0].b", &options), Ok(vec![json!(1)]));
+        assert_eq!(find("$.a[0,0,1].x", &options.leaf_to_null()), Ok(vec![json!(null), json!(null)]));
+    }
+}
@@ -0,0 +1,194 @@
+use serde_json::{json, Value};
+use crate::parser::model::JsonPath;
+use crate::path::{merge, mutate};
+use crate::path::merge::MergeOptions;
+use crate::path::normalized::PathSegment;
+
+/// The operation of RFC 6902 json patch, the targets are the json pointers like `/store/book/2`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchOp {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+}
+
+impl PatchOp {
+    /// the json pointer the operation targets
+    pub fn path(&self) -> &str {
+        match self {
+            PatchOp::Add { path, .. } | PatchOp::Remove { path } | PatchOp::Replace { path, .. } => path,
+        }
+    }
+
+    /// the operation as it goes in the patch document like `{"op":"remove","path":"/a/0"}`
+    pub fn to_value(&self) -> Value {
+        match self {
+            PatchOp::Add { path, value } => json!({"op": "add", "path": path, "value": value}),
+            PatchOp::Remove { path } => json!({"op": "remove", "path": path}),
+            PatchOp::Replace { path, value } => json!({"op": "replace", "path": path, "value": value}),
+        }
+    }
+}
+
+/// Changes the document by the paths like [JsonPath::delete] or [JsonPath::insert] do,
+/// writing the changes down as the RFC 6902 json patch so they can be audited or replayed elsewhere.
+/// Applied in the order they are recorded, the operations turn the original document into the changed one.
+/// # Examples:
+/// ```
+/// use serde_json::json;
+/// use jsonpath_rust::{JsonPath, PatchRecorder};
+///
+/// let mut doc = json!({"users":[{"name":"a"},{"name":"b","tmp":1}]});
+/// let mut recorder = PatchRecorder::new(&mut doc);
+/// recorder.delete(&JsonPath::parse("$..tmp").unwrap());
+/// recorder.insert(&JsonPath::parse("$.users[0]").unwrap(), "active", json!(true));
+/// assert_eq!(recorder.to_value(), json!([
+///     {"op":"remove","path":"/users/1/tmp"},
+///     {"op":"add","path":"/users/0/active","value":true}
+/// ]));
+/// assert_eq!(doc, json!({"users":[{"name":"a","active":true},{"name":"b"}]}));
+/// ```
+#[derive(Debug)]
+pub struct PatchRecorder<'a> {
+    doc: &'a mut Value,
+    ops: Vec<PatchOp>,
+}
+
+impl<'a> PatchRecorder<'a> {
+    pub fn new(doc: &'a mut Value) -> Self {
+        PatchRecorder { doc, ops: vec![] }
+    }
+
+    /// the document with the changes made so far
+    pub fn doc(&self) -> &Value {
+        self.doc
+    }
+
+    /// see [JsonPath::delete]
+    pub fn delete(&mut self, path: &JsonPath) -> usize {
+        mutate::delete(path, self.doc, Some(&mut self.ops))
+    }
+
+    /// see [JsonPath::replace_with]
+    pub fn replace_with<F>(&mut self, path: &JsonPath, f: F) -> usize where F: FnMut(Value) -> Value {
+        mutate::replace_with(path, self.doc, f, Some(&mut self.ops))
+    }
+
+    /// see [JsonPath::insert]
+    pub fn insert<S: Into<PathSegment>>(&mut self, path: &JsonPath, at: S, value: Value) -> usize {
+        mutate::insert(path, self.doc, at.into(), value, Some(&mut self.ops))
+    }
+
+    /// see [JsonPath::insert_creating]
+    pub fn insert_creating<S: Into<PathSegment>>(&mut self, path: &JsonPath, at: S, value: Value) -> Result<(), String> {
+        mutate::insert_creating(path, self.doc, at.into(), value, Some(&mut self.ops))
+    }
+
+    /// see [JsonPath::merge_patch], the patched elements are written down as replaced
+    pub fn merge_patch(&mut self, path: &JsonPath, patch: &Value) -> usize {
+        mutate::replace_with(path, self.doc, merge::merged(patch), Some(&mut self.ops))
+    }
+
+    /// see [JsonPath::deep_merge], the merged elements are written down as replaced
+    pub fn deep_merge(&mut self, path: &JsonPath, source: &Value, options: &MergeOptions) -> usize {
+        mutate::replace_with(path, self.doc, merge::deep_merged(source, options), Some(&mut self.ops))
+    }
+
+    /// the operations recorded so far
+    pub fn ops(&self) -> &[PatchOp] {
+        &self.ops
+    }
+
+    /// the recorded operations as the patch document
+    pub fn to_value(&self) -> Value {
+        Value::Array(self.ops.iter().map(PatchOp::to_value).collect())
+    }
+
+    pub fn into_ops(self) -> Vec<PatchOp> {
+        self.ops
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use crate::parser::parser::parse_json_path;
+    use crate::path::normalized::{NormalizedPath, PathSegment};
+    use crate::path::patch::{PatchOp, PatchRecorder};
+    use crate::path::merge::MergeOptions;
+
+    /// applies the operations the way the json patch tools do
+    fn replay(doc: &mut Value, ops: &[PatchOp]) {
+        for op in ops {
+            let path = NormalizedPath::from_pointer(op.path()).unwrap();
+            if path.is_root() {
+                if let PatchOp::Replace { value, .. } = op {
+                    *doc = value.clone();
+                }
+                continue;
+            }
+            let parent = path.parent().unwrap().resolve_mut(doc).unwrap();
+            match (op, parent, path.last().unwrap()) {
+                (PatchOp::Remove { .. }, Value::Object(fields), PathSegment::Key(k)) => { fields.remove(k).unwrap(); }
+                (PatchOp::Remove { .. }, Value::Array(elems), PathSegment::Index(i)) => { elems.remove(*i); }
+                (PatchOp::Add { value, .. } | PatchOp::Replace { value, .. }, Value::Object(fields), PathSegment::Key(k)) => {
+                    fields.insert(k.clone(), value.clone());
+                }
+                (PatchOp::Add { value, .. }, Value::Array(elems), PathSegment::Index(i)) => elems.insert(*i, value.clone()),
+                (PatchOp::Replace { value, .. }, Value::Array(elems), PathSegment::Index(i)) => elems[*i] = value.clone(),
+                (op, parent, _) => panic!("{:?} does not fit {}", op, parent),
+            }
+        }
+    }
+
+    #[test]
+    fn record_test() {
+        let original = json!({"a":[0,1,2,3],"b":{"c":{"c":1},"d":[[]]},"e":"x"});
+        let mut doc = original.clone();
+        let mut recorder = PatchRecorder::new(&mut doc);
+        assert_eq!(recorder.delete(&parse_json_path("$.a[1,3]").unwrap()), 2);
+        assert_eq!(recorder.replace_with(&parse_json_path("$..c").unwrap(), |v| json!([v])), 2);
+        assert_eq!(recorder.insert(&parse_json_path("$.b.d[*]").unwrap(), 0, json!("y")), 1);
+        assert_eq!(recorder.insert(&parse_json_path("$.b").unwrap(), "e", json!(5)), 1);
+        assert_eq!(recorder.insert(&parse_json_path("$.b").unwrap(), "e", json!(6)), 1);
+        assert_eq!(recorder.insert_creating(&parse_json_path("$.f[0].g").unwrap(), "h", json!(true)), Ok(()));
+        assert_eq!(recorder.doc()["f"], json!([{"g":{"h":true}}]));
+
+        assert_eq!(recorder.ops(), &[
+            PatchOp::Remove { path: String::from("/a/3") },
+            PatchOp::Remove { path: String::from("/a/1") },
+            PatchOp::Replace { path: String::from("/b/c/c"), value: json!([1]) },
+            PatchOp::Replace { path: String::from("/b/c"), value: json!([{"c":[1]}]) },
+            PatchOp::Add { path: String::from("/b/d/0/0"), value: json!("y") },
+            PatchOp::Add { path: String::from("/b/e"), value: json!(5) },
+            PatchOp::Replace { path: String::from("/b/e"), value: json!(6) },
+            PatchOp::Add { path: String::from("/f"), value: json!([]) },
+            PatchOp::Add { path: String::from("/f/0"), value: json!({}) },
+            PatchOp::Add { path: String::from("/f/0/g"), value: json!({}) },
+            PatchOp::Add { path: String::from("/f/0/g/h"), value: json!(true) },
+        ][..]);
+        assert_eq!(recorder.to_value()[0], json!({"op":"remove","path":"/a/3"}));
+        let ops = recorder.into_ops();
+
+        let mut replayed = original;
+        replay(&mut replayed, &ops);
+        assert_eq!(replayed, doc);
+    }
+
+    #[test]
+    fn plain_changes_test() {
+        let mut recorded = json!({"a":[{"b":1},{"b":2}]});
+        let mut plain = recorded.clone();
+        let path = parse_json_path("$.a[?(@.b > 1)]").unwrap();
+        PatchRecorder::new(&mut recorded).delete(&path);
+        path.delete(&mut plain);
+        assert_eq!(recorded, plain);
+
+        let path = parse_json_path("$.a[*]").unwrap();
+        let mut recorder = PatchRecorder::new(&mut recorded);
+        assert_eq!(recorder.merge_patch(&path, &json!({"c":2})), 1);
+        assert_eq!(recorder.ops(), &[PatchOp::Replace { path: String::from("/a/0"), value: json!({"b":1,"c":2}) }][..]);
+        assert_eq!(recorder.deep_merge(&path, &json!({"d":[1]}), &MergeOptions::new()), 1);
+        assert_eq!(recorder.ops()[1], PatchOp::Replace { path: String::from("/a/0"), value: json!({"b":1,"c":2,"d":[1]}) });
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// the compiled regex of the filters
+#[cfg(feature = "regex")]
+pub(crate) type Pattern = regex::Regex;
+
+/// the placeholder of the regex when the feature `regex` is off, it is never built
+#[cfg(not(feature = "regex"))]
+#[derive(Debug, Clone)]
+pub(crate) struct Pattern(());
+
+/// the number of the patterns kept compiled, the cache starts over when it is full
+const CACHE_SIZE: usize = 1024;
+
+/// compiles the pattern once for all the queries and the evaluations using it
+pub(crate) fn compile(pattern: &str) -> Result<Pattern, String> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Pattern>>> = OnceLock::new();
+    let mut cache = CACHE.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(p) = cache.get(pattern) {
+        return Ok(p.clone());
+    }
+    let compiled = build(pattern)?;
+    if cache.len() >= CACHE_SIZE {
+        cache.clear();
+    }
+    cache.insert(String::from(pattern), compiled.clone());
+    Ok(compiled)
+}
+
+#[cfg(feature = "regex")]
+fn build(pattern: &str) -> Result<Pattern, String> {
+    regex::Regex::new(pattern).map_err(|e| format!("the regex '{}' is invalid: {}", pattern, e))
+}
+
+#[cfg(not(feature = "regex"))]
+fn build(pattern: &str) -> Result<Pattern, String> {
+    Err(format!("the regex '{}' needs the feature `regex`", pattern))
+}
+
+/// checks whether the string contains the match of the pattern
+#[cfg(feature = "regex")]
+pub(crate) fn is_match(pattern: &Pattern, s: &str) -> bool {
+    pattern.is_match(s)
+}
+
+#[cfg(not(feature = "regex"))]
+pub(crate) fn is_match(_: &Pattern, _: &str) -> bool {
+    false
+}
+
+/// escapes the characters having the special meaning in the regexes
+#[cfg(feature = "regex")]
+pub(crate) fn escape(s: &str) -> String {
+    regex::escape(s)
+}
+
+#[cfg(not(feature = "regex"))]
+pub(crate) fn escape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        if "\\.+*?()|[]{}^$#&-~".contains(c) {
+            res.push('\\');
+        }
+        res.push(c);
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::path::pattern::{compile, escape};
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn compile_test() {
+        use crate::path::pattern::is_match;
+        let p = compile("(?i)^foo").unwrap();
+        assert!(is_match(&p, "FOObar"));
+        assert!(!is_match(&p, "bar"));
+        assert!(is_match(&compile(&format!("^{}$", escape("a.b*"))).unwrap(), "a.b*"));
+        assert_eq!(compile("(").unwrap_err().lines().next(), Some("the regex '(' is invalid: regex parse error:"));
+    }
+
+    #[cfg(not(feature = "regex"))]
+    #[test]
+    fn disabled_test() {
+        assert_eq!(compile("^a").unwrap_err(), "the regex '^a' needs the feature `regex`");
+        assert_eq!(escape("a.b"), "a\\.b");
+    }
+}
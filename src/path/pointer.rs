@@ -0,0 +1,361 @@
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use serde_json::Value;
+use crate::parser::errors::JsonPathError;
+use crate::parser::model::{JsonPath, JsonPathIndex};
+use crate::path::definite;
+use crate::path::normalized::{is_index_token, NormalizedPath, PathSegment};
+
+impl JsonPath<'static> {
+    /// builds the path from the RFC 6901 json pointer like `/store/book/0`,
+    /// so the pointer is evaluated by the same engine and serves the mutations like [JsonPath::delete] as well.
+    /// The tokens consisting of digits(without leading zeros) find both the member with this name in the objects
+    /// and the element with this index in the arrays(`['0',0]`), the empty pointer stands for the whole document.
+    /// [JsonPath::to_pointer] turns the path back into the pointer.
+    /// # Examples:
+    /// ```
+    /// use serde_json::json;
+    /// use jsonpath_rust::JsonPath;
+    ///
+    /// let path = JsonPath::from_pointer("/store/book/0/a~1b").unwrap();
+    /// assert_eq!(path, JsonPath::parse("$.store.book['0',0]['a/b']").unwrap());
+    /// let json = json!({"store":{"book":[{"a/b":1}]}});
+    /// assert_eq!(path.find_owned(&json), vec![json!(1)]);
+    /// let json = json!({"store":{"book":{"0":{"a/b":2}}}});
+    /// assert_eq!(path.find_owned(&json), vec![json!(2)]);
+    /// ```
+    pub fn from_pointer(pointer: &str) -> Result<Self, String> {
+        let mut chain = vec![JsonPath::Root];
+        for segment in NormalizedPath::from_pointer(pointer)?.segments() {
+            chain.push(match segment {
+                PathSegment::Key(key) => JsonPath::Field(key.clone().into()),
+                PathSegment::Index(idx) => JsonPath::Index(JsonPathIndex::Union(vec![
+                    JsonPath::Field(idx.to_string().into()),
+                    JsonPath::Index(JsonPathIndex::Single(Value::from(*idx))),
+                ])),
+            })
+        }
+        Ok(JsonPath::Chain(chain))
+    }
+
+    /// parses either the jsonpath query(`$.a.b`) or the json pointer(`/a/b`),
+    /// the pointers are told apart by starting with `/` or being empty.
+    pub fn parse_any(query: &str) -> Result<Self, JsonPathError> {
+        if query.is_empty() || query.starts_with('/') {
+            JsonPath::from_pointer(query).map_err(JsonPathError::Invalid)
+        } else {
+            JsonPath::from_str(query).map_err(JsonPathError::Parse)
+        }
+    }
+}
+
+/// The relative json pointer like `1/name` or `0#`: it climbs up the given number of levels
+/// from the element it is anchored at, optionally moves along the array and then either
+/// follows the json pointer or, with `#`, takes the member name or the index of the element it has got to.
+/// # Examples:
+/// ```
+/// use serde_json::json;
+/// use jsonpath_rust::{JsonPath, RelativePointer};
+///
+/// let json = json!({"users":[{"name":"a","role":"admin"},{"name":"b","role":"user"}]});
+/// let names: RelativePointer = "1/name".parse().unwrap();
+/// let path = JsonPath::parse("$.users[?(@.role == 'admin')].role").unwrap();
+/// let found = path.find_relative(&json, &names);
+/// assert_eq!(found.len(), 1);
+/// assert_eq!(found[0].as_ref(), &json!("a"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RelativePointer {
+    up: usize,
+    shift: i64,
+    target: Option<NormalizedPath>,
+}
+
+impl RelativePointer {
+    /// how many levels the pointer climbs up
+    pub fn up(&self) -> usize {
+        self.up
+    }
+
+    /// how far the pointer moves along the array after climbing up
+    pub fn shift(&self) -> i64 {
+        self.shift
+    }
+
+    /// the pointer ends with `#` asking for the name or the index rather than the value
+    pub fn is_name(&self) -> bool {
+        self.target.is_none()
+    }
+
+    /// the location the pointer gets to from the element at the given location,
+    /// for `#` it is the element whose name or index is taken
+    pub fn location(&self, doc: &Value, at: &NormalizedPath) -> Option<NormalizedPath> {
+        let segments = at.segments();
+        let mut location = NormalizedPath::new(segments[..segments.len().checked_sub(self.up)?].to_vec());
+        if self.shift != 0 {
+            let idx = match location.pop() {
+                Some(PathSegment::Index(idx)) => i64::try_from(idx).ok()?.checked_add(self.shift)?,
+                _ => return None,
+            };
+            location.push(usize::try_from(idx).ok()?);
+        }
+        let mut node = location.resolve(doc)?;
+        for segment in self.target.iter().flat_map(|t| t.segments()) {
+            let (next, segment) = match (node, segment) {
+                (Value::Object(fields), PathSegment::Key(key)) => (fields.get(key)?, segment.clone()),
+                (Value::Object(fields), PathSegment::Index(idx)) => {
+                    let key = idx.to_string();
+                    (fields.get(&key)?, PathSegment::Key(key))
+                }
+                (Value::Array(elems), PathSegment::Index(idx)) => (elems.get(*idx)?, segment.clone()),
+                _ => return None,
+            };
+            node = next;
+            location.push(segment);
+        }
+        Some(location)
+    }
+
+    /// evaluates the pointer anchored at the element at the given location,
+    /// `#` gives the member name as the string or the index as the number
+    pub fn resolve<'a>(&self, doc: &'a Value, at: &NormalizedPath) -> Option<Cow<'a, Value>> {
+        let location = self.location(doc, at)?;
+        match (&self.target, location.last()) {
+            (Some(_), _) => location.resolve(doc).map(Cow::Borrowed),
+            (None, Some(PathSegment::Key(key))) => Some(Cow::Owned(Value::from(key.as_str()))),
+            (None, Some(PathSegment::Index(idx))) => Some(Cow::Owned(Value::from(*idx))),
+            (None, None) => None,
+        }
+    }
+}
+
+impl FromStr for RelativePointer {
+    type Err = String;
+
+    fn from_str(pointer: &str) -> Result<Self, Self::Err> {
+        let digits = |s: &str| s.bytes().take_while(u8::is_ascii_digit).count();
+        let number = |s: &str| match s.parse::<usize>() {
+            Ok(n) if s == "0" || !s.starts_with('0') => Ok(n),
+            _ => Err(format!("expected the number without leading zeros in the relative pointer '{}'", pointer)),
+        };
+        let len = digits(pointer);
+        let up = number(&pointer[..len])?;
+        let mut rest = &pointer[len..];
+        let mut shift = 0;
+        if let Some(sign @ ('+' | '-')) = rest.chars().next() {
+            let len = digits(&rest[1..]) + 1;
+            let n = i64::try_from(number(&rest[1..len])?)
+                .map_err(|_| format!("the shift is out of the range in the relative pointer '{}'", pointer))?;
+            shift = if sign == '-' { -n } else { n };
+            rest = &rest[len..];
+        }
+        let target = match rest {
+            "#" => None,
+            _ => Some(NormalizedPath::from_pointer(rest)?),
+        };
+        Ok(RelativePointer { up, shift, target })
+    }
+}
+
+impl Display for RelativePointer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.up)?;
+        if self.shift != 0 {
+            write!(f, "{:+}", self.shift)?;
+        }
+        match &self.target {
+            Some(target) => write!(f, "{}", target.to_pointer()),
+            None => write!(f, "#"),
+        }
+    }
+}
+
+impl JsonPath<'_> {
+    /// renders the singular path, consisting of the member names and the non-negative indexes only,
+    /// into the RFC 6901 json pointer, e.g. `$.store.book[0]` into `/store/book/0`.
+    /// The pointer finds the member names looking like the indexes(`$['0']`) as well as the indexes,
+    /// so they are written the same way along with the selectors `['0',0]` [JsonPath::from_pointer] gives for them.
+    /// The paths with the wildcards, the filters, the slices, the other unions, the descents or the negative indexes
+    /// can not be written losslessly and give the error.
+    pub fn to_pointer(&self) -> Result<String, String> {
+        let elems = match self {
+            JsonPath::Chain(elems) => elems.as_slice(),
+            path => std::slice::from_ref(path),
+        };
+        if !matches!(elems.first(), Some(JsonPath::Root)) {
+            return Err(String::from("the path should start with '$' to be turned into the json pointer"));
+        }
+        let mut location = NormalizedPath::root();
+        for (pos, elem) in elems.iter().enumerate().skip(1) {
+            match pointer_token(elem) {
+                Some(idx) => location.push(idx),
+                None => definite::location(elem, &mut location).ok_or_else(|| format!(
+                    "the path is not singular: the segment {} is neither the member name nor the non-negative index", pos
+                ))?,
+            }
+        }
+        Ok(location.to_pointer())
+    }
+
+    /// evaluates the relative pointer anchored at every found element,
+    /// the elements the pointer leads nowhere from are skipped
+    pub fn find_relative<'a>(&self, doc: &'a Value, pointer: &RelativePointer) -> Vec<Cow<'a, Value>> {
+        let mut res = vec![];
+        self.find_with(doc, |path, _| res.extend(pointer.resolve(doc, path)));
+        res
+    }
+}
+
+/// the selectors `['<n>',<n>]` the digit token of the json pointer is turned into
+fn pointer_token(elem: &JsonPath) -> Option<usize> {
+    match elem {
+        JsonPath::Index(JsonPathIndex::Union(selectors)) => match selectors.as_slice() {
+            [JsonPath::Field(key), JsonPath::Index(JsonPathIndex::Single(idx))] if is_index_token(key) =>
+                idx.as_u64().filter(|idx| idx.to_string() == *key).and_then(|idx| usize::try_from(idx).ok()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use serde_json::json;
+    use crate::parser::errors::JsonPathError;
+    use crate::parser::model::JsonPath;
+    use crate::path::normalized::NormalizedPath;
+    use crate::path::pointer::RelativePointer;
+
+    #[test]
+    fn rfc_test() {
+        // the examples of the section 5 of RFC 6901
+        let doc = json!({
+            "foo": ["bar", "baz"], "": 0, "a/b": 1, "c%d": 2, "e^f": 3,
+            "g|h": 4, "i\\j": 5, "k\"l": 6, " ": 7, "m~n": 8
+        });
+        let cases = [
+            ("", doc.clone()), ("/foo", json!(["bar", "baz"])), ("/foo/0", json!("bar")), ("/", json!(0)),
+            ("/a~1b", json!(1)), ("/c%d", json!(2)), ("/e^f", json!(3)), ("/g|h", json!(4)),
+            ("/i\\j", json!(5)), ("/k\"l", json!(6)), ("/ ", json!(7)), ("/m~0n", json!(8)),
+        ];
+        for (pointer, expected) in cases {
+            let path = JsonPath::from_pointer(pointer).unwrap();
+            assert_eq!(path.find_owned(&doc), vec![expected], "{}", pointer);
+        }
+        assert_eq!(JsonPath::from_pointer("/foo/2").unwrap().count(&doc), 0);
+        assert_eq!(JsonPath::from_pointer("/01").unwrap(), JsonPath::parse("$['01']").unwrap());
+        assert!(JsonPath::from_pointer("foo").is_err());
+        assert!(JsonPath::from_pointer("/m~2n").is_err());
+    }
+
+    #[test]
+    fn numeric_member_test() {
+        let doc = json!({"0":"member zero","items":{"1":["a","b"]},"list":[{"0":"x"}]});
+        let find = |pointer: &str| JsonPath::from_pointer(pointer).unwrap().find_owned(&doc);
+        assert_eq!(find("/0"), vec![json!("member zero")]);
+        assert_eq!(find("/items/1/0"), vec![json!("a")]);
+        assert_eq!(find("/list/0/0"), vec![json!("x")]);
+        assert_eq!(find("/1"), Vec::<serde_json::Value>::new());
+
+        let mut doc = doc.clone();
+        assert_eq!(JsonPath::from_pointer("/items/1/1").unwrap().delete(&mut doc), 1);
+        assert_eq!(JsonPath::from_pointer("/0").unwrap().delete(&mut doc), 1);
+        assert_eq!(doc, json!({"items":{"1":["a"]},"list":[{"0":"x"}]}));
+    }
+
+    #[test]
+    fn mutate_test() {
+        let mut doc = json!({"a":{"b":[1,2,3]}});
+        assert_eq!(JsonPath::from_pointer("/a/b/1").unwrap().delete(&mut doc), 1);
+        assert_eq!(JsonPath::from_pointer("/a").unwrap().insert(&mut doc, "c", json!(true)), 1);
+        assert_eq!(doc, json!({"a":{"b":[1,3],"c":true}}));
+    }
+
+    #[test]
+    fn to_pointer_test() {
+        for (query, pointer) in [
+            ("$", ""), ("$.a.b", "/a/b"), ("$['a/b'][0]['m~n']", "/a~1b/0/m~0n"), ("$[''][3]", "//3"), ("$['01']", "/01"),
+            ("$.a['0']", "/a/0"), ("$.a['0',0]", "/a/0"),
+        ] {
+            let path = JsonPath::parse(query).unwrap();
+            assert_eq!(path.to_pointer().as_deref(), Ok(pointer), "{}", query);
+            assert_eq!(JsonPath::from_pointer(pointer).unwrap().to_pointer().as_deref(), Ok(pointer), "{}", query);
+        }
+        for query in ["$.a[*]", "$..a", "$.a[-1]", "$.a[0:2]", "$.a[0,1]", "$['a','b']", "$.a[?(@.b)]", "$.a['0',1]", "$.a[0,'0']"] {
+            assert!(JsonPath::parse(query).unwrap().to_pointer().is_err(), "{}", query);
+        }
+        assert_eq!(
+            JsonPath::parse("$.a[*]").unwrap().to_pointer(),
+            Err(String::from("the path is not singular: the segment 2 is neither the member name nor the non-negative index"))
+        );
+    }
+
+    #[test]
+    fn parse_any_test() {
+        assert_eq!(JsonPath::parse_any("/a/0").unwrap(), JsonPath::parse("$.a['0',0]").unwrap());
+        assert_eq!(JsonPath::parse_any("$.a[0]").unwrap(), JsonPath::parse("$.a[0]").unwrap());
+        assert_eq!(JsonPath::parse_any("").unwrap(), JsonPath::from_pointer("").unwrap());
+        assert!(matches!(JsonPath::parse_any("/a~"), Err(JsonPathError::Invalid(_))));
+        assert!(matches!(JsonPath::parse_any("$.a["), Err(JsonPathError::Parse(_))));
+    }
+
+    #[test]
+    fn relative_test() {
+        // the examples of the section 5.1 of the relative json pointer draft
+        let doc = json!({"foo": ["bar", "baz"], "highly": {"nested": {"objects": true}}});
+        let resolve = |pointer: &str, at: &str| {
+            let pointer: RelativePointer = pointer.parse().unwrap();
+            pointer.resolve(&doc, &at.parse::<NormalizedPath>().unwrap()).map(Cow::into_owned)
+        };
+        let baz = "$['foo'][1]";
+        assert_eq!(resolve("0", baz), Some(json!("baz")));
+        assert_eq!(resolve("1/0", baz), Some(json!("bar")));
+        assert_eq!(resolve("0-1", baz), Some(json!("bar")));
+        assert_eq!(resolve("2/highly/nested/objects", baz), Some(json!(true)));
+        assert_eq!(resolve("0#", baz), Some(json!(1)));
+        assert_eq!(resolve("0-1#", baz), Some(json!(0)));
+        assert_eq!(resolve("1#", baz), Some(json!("foo")));
+
+        let nested = "$['highly']['nested']";
+        assert_eq!(resolve("0/objects", nested), Some(json!(true)));
+        assert_eq!(resolve("1/nested/objects", nested), Some(json!(true)));
+        assert_eq!(resolve("2/foo/0", nested), Some(json!("bar")));
+        assert_eq!(resolve("0#", nested), Some(json!("nested")));
+        assert_eq!(resolve("1#", nested), Some(json!("highly")));
+
+        assert_eq!(resolve("0+1", baz), None);
+        assert_eq!(resolve("0-2", baz), None);
+        assert_eq!(resolve("3", baz), None);
+        assert_eq!(resolve("2#", baz), None);
+        assert_eq!(resolve("0+1", nested), None);
+        assert_eq!(resolve("0+9223372036854775807", baz), None);
+        assert_eq!(resolve("0-9223372036854775807", baz), None);
+    }
+
+    #[test]
+    fn parse_relative_test() {
+        for pointer in ["0", "1/a~1b/0", "0#", "2-3/x", "10+1#", "0/"] {
+            assert_eq!(pointer.parse::<RelativePointer>().unwrap().to_string(), pointer);
+        }
+        let pointer: RelativePointer = "2-3#".parse().unwrap();
+        assert_eq!((pointer.up(), pointer.shift(), pointer.is_name()), (2, -3, true));
+        for pointer in ["", "01", "/a", "0+", "0+01", "1a", "0##", "0#/a", "-1", "0-18446744073709551615", "0+9223372036854775808"] {
+            assert!(pointer.parse::<RelativePointer>().is_err(), "{}", pointer);
+        }
+    }
+
+    #[test]
+    fn find_relative_test() {
+        let doc = json!({"a":{"0":"key","items":[{"id":1},{"id":2}]}});
+        let path = JsonPath::parse("$.a.items[*].id").unwrap();
+        let ids: Vec<_> = path.find_relative(&doc, &"1#".parse().unwrap()).into_iter().map(Cow::into_owned).collect();
+        assert_eq!(ids, vec![json!(0), json!(1)]);
+        let keys = path.find_relative(&doc, &"3/0".parse().unwrap());
+        assert_eq!(keys, vec![Cow::Borrowed(&json!("key")), Cow::Borrowed(&json!("key"))]);
+        let location = "2/0".parse::<RelativePointer>().unwrap().location(&doc, &"$['a']['items'][0]['id']".parse().unwrap());
+        assert_eq!(location, Some("$['a']['items'][0]".parse().unwrap()));
+    }
+}
@@ -0,0 +1,152 @@
+use crate::parser::model::{FilterSign, JsonPath, JsonPathIndex, Operand};
+use crate::parser::parser::parse_json_path;
+
+/// The restrictions for the queries coming from the untrusted sources, like the users of the public api.
+///
+/// The query is checked before it gets evaluated, so the expensive constructs are rejected up front
+/// regardless of the document. The paths inside the filters are checked as well.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryPolicy {
+    deny_descent: bool,
+    deny_regex: bool,
+    max_fan_out: Option<usize>,
+    max_filter_depth: Option<usize>,
+}
+
+impl QueryPolicy {
+    /// the policy allowing everything
+    pub fn new() -> Self {
+        QueryPolicy::default()
+    }
+
+    /// rejects the recursive descent `..`
+    pub fn deny_descent(mut self) -> Self {
+        self.deny_descent = true;
+        self
+    }
+
+    /// rejects the regular expressions `~=`
+    pub fn deny_regex(mut self) -> Self {
+        self.deny_regex = true;
+        self
+    }
+
+    /// limits the number of the segments selecting many elements(wildcards, slices, unions, filters and descents),
+    /// since every one of them multiplies the number of the elements the rest of the query walks through
+    pub fn max_fan_out(mut self, segments: usize) -> Self {
+        self.max_fan_out = Some(segments);
+        self
+    }
+
+    /// limits how deep the filters can be nested into each other
+    pub fn max_filter_depth(mut self, depth: usize) -> Self {
+        self.max_filter_depth = Some(depth);
+        self
+    }
+
+    /// checks the query against the policy
+    pub fn check(&self, path: &JsonPath) -> Result<(), String> {
+        let mut fan_out = 0;
+        self.path(path, 0, &mut fan_out)?;
+        match self.max_fan_out {
+            Some(max) if fan_out > max =>
+                Err(format!("the query has {} segments selecting many elements but at most {} are allowed", fan_out, max)),
+            _ => Ok(())
+        }
+    }
+
+    /// parses the query and checks it against the policy
+    pub fn parse<'a>(&self, query: &'a str) -> Result<JsonPath<'a>, String> {
+        let path = parse_json_path(query).map_err(|e| e.to_string())?;
+        self.check(&path)?;
+        Ok(path)
+    }
+
+    fn path(&self, path: &JsonPath, depth: usize, fan_out: &mut usize) -> Result<(), String> {
+        match path {
+            JsonPath::Chain(elems) => elems.iter().try_for_each(|p| self.path(p, depth, fan_out)),
+            JsonPath::Current(tail) => self.path(tail, depth, fan_out),
+            JsonPath::Descent(key) => {
+                if self.deny_descent {
+                    return Err(format!("the recursive descent '..{}' is not allowed", key));
+                }
+                *fan_out += 1;
+                Ok(())
+            }
+            JsonPath::Descendants => {
+                if self.deny_descent {
+                    return Err(String::from("the recursive descent '..' is not allowed"));
+                }
+                *fan_out += 1;
+                Ok(())
+            }
+            JsonPath::Wildcard => {
+                *fan_out += 1;
+                Ok(())
+            }
+            JsonPath::Index(JsonPathIndex::Slice(..) | JsonPathIndex::UnionIndex(_) | JsonPathIndex::UnionKeys(_)) => {
+                *fan_out += 1;
+                Ok(())
+            }
+            JsonPath::Index(JsonPathIndex::Filter(l, op, r)) => self.filter(&[(l, op, r)], depth, fan_out),
+            JsonPath::Index(JsonPathIndex::Logical(expr)) => self.filter(&expr.atoms(), depth, fan_out),
+            JsonPath::Index(JsonPathIndex::Union(sels)) => {
+                *fan_out += 1;
+                sels.iter().try_for_each(|p| match p {
+                    JsonPath::Index(JsonPathIndex::Filter(l, op, r)) => self.filter(&[(l, op, r)], depth, fan_out),
+                    JsonPath::Index(JsonPathIndex::Logical(expr)) => self.filter(&expr.atoms(), depth, fan_out),
+                    _ => Ok(())
+                })
+            }
+            _ => Ok(())
+        }
+    }
+
+    fn filter(&self, atoms: &[(&Operand, &FilterSign, &Operand)], depth: usize, fan_out: &mut usize) -> Result<(), String> {
+        *fan_out += 1;
+        let depth = depth + 1;
+        if let Some(max) = self.max_filter_depth {
+            if depth > max {
+                return Err(format!("the filters are nested {} deep but at most {} are allowed", depth, max));
+            }
+        }
+        for (l, op, r) in atoms {
+            if self.deny_regex && **op == FilterSign::Regex {
+                return Err(String::from("the regular expressions are not allowed"));
+            }
+            for op in [l, r] {
+                self.operand(op, depth, fan_out)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn operand(&self, op: &Operand, depth: usize, fan_out: &mut usize) -> Result<(), String> {
+        match op {
+            Operand::Dynamic(p) => self.path(p, depth, fan_out),
+            Operand::Function(_, args) => args.iter().try_for_each(|a| self.operand(a, depth, fan_out)),
+            Operand::Static(_) | Operand::Variable(_) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::path::policy::QueryPolicy;
+
+    #[test]
+    fn policy_test() {
+        let strict = QueryPolicy::new().deny_descent().deny_regex().max_fan_out(2).max_filter_depth(1);
+        assert!(strict.parse("$.a[*].b[?(@.c > 1)]").is_ok());
+        assert_eq!(strict.parse("$..a").unwrap_err(), "the recursive descent '..a' is not allowed");
+        assert_eq!(strict.parse("$..[0]").unwrap_err(), "the recursive descent '..' is not allowed");
+        assert_eq!(strict.parse("$.a[?(@.b[?(@..c)])]").unwrap_err(), "the filters are nested 2 deep but at most 1 are allowed");
+        assert_eq!(QueryPolicy::new().deny_descent().parse("$.a[?(@..c)]").unwrap_err(), "the recursive descent '..c' is not allowed");
+        assert_eq!(QueryPolicy::new().deny_descent().parse("$.a[?(count(@..c) > 1)]").unwrap_err(), "the recursive descent '..c' is not allowed");
+        assert_eq!(strict.parse("$.a[?(@.b ~= 'x+')]").unwrap_err(), "the regular expressions are not allowed");
+        assert_eq!(strict.parse("$.a[*][*][0:2]").unwrap_err(), "the query has 3 segments selecting many elements but at most 2 are allowed");
+        assert!(strict.parse("$.a[").is_err());
+
+        assert!(QueryPolicy::new().parse("$..a[?(@.b ~= 'x+')][*][*]").is_ok());
+    }
+}
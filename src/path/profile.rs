@@ -0,0 +1,172 @@
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+use serde_json::Value;
+use crate::parser::model::{JsonPath, JsonPathIndex};
+use crate::path::Env;
+use crate::path::index::{Filter, Logical};
+use crate::path::json_path_instance;
+
+/// The time the parts of the filter expression have taken.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FilterStats {
+    /// the number of the elements the filter has been checked against
+    pub checks: usize,
+    /// the time of evaluating the left operand
+    pub left: Duration,
+    /// the number of the elements the left operand has produced
+    pub left_nodes: usize,
+    /// the time of evaluating the right operand
+    pub right: Duration,
+    /// the number of the elements the right operand has produced
+    pub right_nodes: usize,
+    /// the time of comparing the operands
+    pub compare: Duration,
+}
+
+/// The statistics of the segment of the query gathered over all the evaluations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentStats {
+    pub segment: JsonPath<'static>,
+    /// the number of the elements the segment has been applied to
+    pub input: usize,
+    /// the number of the elements the segment has produced
+    pub output: usize,
+    pub time: Duration,
+    /// the details if the segment is the filter
+    pub filter: Option<FilterStats>,
+}
+
+/// The profiler evaluating the query segment by segment and summing up the time
+/// and the number of the elements every segment takes over many evaluations,
+/// so the slow part of the complex query stands out in the report(see [Display]).
+///
+/// The profiling is opt-in: the query evaluated with `find` is not measured at all.
+pub struct Profiler<'p> {
+    path: &'p JsonPath<'p>,
+    segments: Vec<SegmentStats>,
+    runs: usize,
+}
+
+impl<'p> Profiler<'p> {
+    pub fn new(path: &'p JsonPath<'p>) -> Self {
+        let segments = split(path).iter().map(|s| SegmentStats {
+            segment: s.clone().into_owned(),
+            input: 0,
+            output: 0,
+            time: Duration::ZERO,
+            filter: match s {
+                JsonPath::Index(JsonPathIndex::Filter(..) | JsonPathIndex::Logical(_)) => Some(FilterStats::default()),
+                _ => None
+            },
+        }).collect();
+        Profiler { path, segments, runs: 0 }
+    }
+
+    /// finds the elements like `find` does recording the statistics
+    pub fn find<'a>(&mut self, doc: &'a Value) -> Vec<&'a Value> where 'p: 'a {
+        self.runs += 1;
+        let mut current = vec![doc];
+        for (segment, stats) in split(self.path).iter().zip(self.segments.iter_mut()) {
+            let start = Instant::now();
+            let next: Vec<&Value> = match (segment, stats.filter.as_mut()) {
+                (JsonPath::Index(JsonPathIndex::Filter(l, op, r)), Some(fs)) =>
+                    select(&current, &Logical::Atom(Box::new(Filter::new(l, r, op, doc))), fs),
+                (JsonPath::Index(JsonPathIndex::Logical(expr)), Some(fs)) =>
+                    select(&current, &Logical::new(expr, &Env::new(doc)), fs),
+                _ => {
+                    let instance = json_path_instance(segment, doc);
+                    current.iter().flat_map(|v| instance.find(v)).collect()
+                }
+            };
+            stats.time += start.elapsed();
+            stats.input += current.len();
+            stats.output += next.len();
+            current = next;
+        }
+        current
+    }
+
+    /// the number of the evaluations
+    pub fn runs(&self) -> usize {
+        self.runs
+    }
+
+    pub fn segments(&self) -> &[SegmentStats] {
+        &self.segments
+    }
+
+    /// the slowest segment so far
+    pub fn slowest(&self) -> Option<&SegmentStats> {
+        self.segments.iter().max_by_key(|s| s.time)
+    }
+
+    /// forgets the statistics gathered so far
+    pub fn reset(&mut self) {
+        *self = Profiler::new(self.path);
+    }
+}
+
+fn split<'a, 'p>(path: &'a JsonPath<'p>) -> &'a [JsonPath<'p>] {
+    match path {
+        JsonPath::Chain(elems) => elems.as_slice(),
+        single => std::slice::from_ref(single),
+    }
+}
+
+/// the children of the elements satisfying the filter
+fn select<'a>(current: &[&'a Value], filter: &Logical<'a>, stats: &mut FilterStats) -> Vec<&'a Value> {
+    current.iter().flat_map(|v| match v {
+        Value::Array(elems) => elems.iter().collect::<Vec<_>>(),
+        Value::Object(fields) => fields.values().collect(),
+        _ => vec![]
+    }).filter(|el| filter.check_timed(el, stats)).collect()
+}
+
+impl Display for Profiler<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let total: Duration = self.segments.iter().map(|s| s.time).sum();
+        writeln!(f, "the profile of {} run(s), {:?} in total:", self.runs, total)?;
+        for (idx, s) in self.segments.iter().enumerate() {
+            let share = if total.is_zero() { 0.0 } else { s.time.as_secs_f64() * 100.0 / total.as_secs_f64() };
+            writeln!(f, " #{} {:?}: {:?} ({:.1}%), {} in, {} out", idx + 1, s.segment, s.time, share, s.input, s.output)?;
+            if let Some(fs) = &s.filter {
+                writeln!(f, "    {} check(s): left {:?} ({} nodes), right {:?} ({} nodes), compare {:?}",
+                         fs.checks, fs.left, fs.left_nodes, fs.right, fs.right_nodes, fs.compare)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::parser::parser::parse_json_path;
+    use crate::path::json_path_instance;
+    use crate::path::profile::Profiler;
+
+    #[test]
+    fn profiler_test() {
+        let docs = [json!({"items":[{"p":1},{"p":5},{"p":10}]}), json!({"items":[{"p":7}]}), json!({"other":1})];
+        let path = parse_json_path("$.items[?(@.p > 3)].p").unwrap();
+        let mut profiler = Profiler::new(&path);
+        for doc in docs.iter() {
+            assert_eq!(profiler.find(doc), json_path_instance(&path, doc).find(doc));
+        }
+        assert_eq!(profiler.runs(), 3);
+        let counts: Vec<(usize, usize)> = profiler.segments().iter().map(|s| (s.input, s.output)).collect();
+        assert_eq!(counts, vec![(3, 3), (3, 2), (2, 3), (3, 3)]);
+
+        let filter = profiler.segments()[2].filter.as_ref().unwrap();
+        assert_eq!((filter.checks, filter.left_nodes, filter.right_nodes), (4, 4, 4));
+        assert!(profiler.slowest().is_some());
+
+        let report = profiler.to_string();
+        assert!(report.starts_with("the profile of 3 run(s)"), "{}", report);
+        assert!(report.contains("4 check(s)"), "{}", report);
+
+        profiler.reset();
+        assert_eq!(profiler.runs(), 0);
+        assert_eq!(profiler.segments()[0].input, 0);
+    }
+}
@@ -0,0 +1,516 @@
+use serde_json::{Map, Value};
+use crate::path::pattern;
+use crate::parser::model::{FilterExpression, FilterSign, JsonPath, JsonPathIndex, Operand};
+
+/// the comparisons every matching element has to satisfy,
+/// the ones under `||` and `!` can fail without failing the whole expression
+fn conjuncts<'e, 'a>(expr: &'e FilterExpression<'a>) -> Vec<(&'e Operand<'a>, &'e FilterSign, &'e Operand<'a>)> {
+    match expr {
+        FilterExpression::And(l, r) => {
+            let mut atoms = conjuncts(l);
+            atoms.extend(conjuncts(r));
+            atoms
+        }
+        FilterExpression::Atom(..) => expr.atoms(),
+        FilterExpression::Or(..) | FilterExpression::Not(_) => vec![],
+    }
+}
+
+/// the schema accepting everything
+static ANY: Value = Value::Bool(true);
+
+/// The selector that can never match any document valid against the schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaIssue {
+    /// the position of the segment in the query
+    pub position: usize,
+    /// the segment that can never match
+    pub segment: JsonPath<'static>,
+    /// the explanation
+    pub message: String,
+}
+
+impl JsonPath<'_> {
+    /// checks the query against the json schema and reports the selectors that can never match,
+    /// namely the unknown properties of the closed objects(`additionalProperties: false`)
+    /// or the indexes and fields applied to the elements of the different types.
+    /// The local references(`#/...`) and the combinators `anyOf`, `oneOf`, `allOf` are taken into account.
+    /// The check is conservative: if the schema does not restrict the element, the selector is considered fine.
+    pub fn check_schema(&self, schema: &Value) -> Vec<SchemaIssue> {
+        let mut walker = SchemaWalker::new(schema);
+        walker.walk(self, vec![schema]);
+        walker.issues
+    }
+
+    /// derives the json schema of the result(the array of the found elements)
+    /// from the json schema of the incoming documents.
+    /// The subschemas the derived schema refers to(`#/...`) are carried over so that the references stay resolvable:
+    /// the definitions(`$defs` and `definitions`) keep their places and the rest go to `$defs`,
+    /// the other definitions are left out.
+    pub fn output_schema(&self, schema: &Value) -> Value {
+        let mut walker = SchemaWalker::new(schema);
+        let found = walker.walk(self, vec![schema]);
+
+        let mut res = Map::new();
+        res.insert(String::from("type"), Value::from("array"));
+        if !walker.issues.is_empty() || found.is_empty() {
+            res.insert(String::from("maxItems"), Value::from(0));
+            return Value::Object(res);
+        }
+
+        let mut items: Vec<&Value> = vec![];
+        for s in found.into_iter().flat_map(|s| walker.resolve(s, 0)) {
+            if !items.contains(&s) {
+                items.push(s)
+            }
+        }
+        let items = if items.iter().any(|s| **s == Value::Bool(true) || **s == Value::Object(Map::new())) {
+            Value::Bool(true)
+        } else if items.len() == 1 {
+            items[0].clone()
+        } else {
+            let mut any_of = Map::new();
+            any_of.insert(String::from("anyOf"), Value::Array(items.into_iter().cloned().collect()));
+            Value::Object(any_of)
+        };
+        let mut targets = vec![];
+        references(schema, &items, &mut targets);
+        let places = places(&targets);
+        res.insert(String::from("items"), relocate(&items, &places));
+        if self.is_definite() {
+            res.insert(String::from("maxItems"), Value::from(1));
+        }
+        for ((_, target), (defs, name)) in targets.iter().zip(places.iter().map(|(_, place)| place)) {
+            let defs = res.entry(defs.clone()).or_insert_with(|| Value::Object(Map::new()));
+            if let Value::Object(defs) = defs {
+                defs.insert(name.clone(), relocate(target, &places));
+            }
+        }
+        Value::Object(res)
+    }
+}
+
+/// The walker moves through the schema along with the query
+/// keeping the set of the subschemas describing the current elements.
+pub(crate) struct SchemaWalker<'a> {
+    root: &'a Value,
+    pub(crate) issues: Vec<SchemaIssue>,
+}
+
+impl<'a> SchemaWalker<'a> {
+    pub(crate) fn new(root: &'a Value) -> Self {
+        SchemaWalker { root, issues: vec![] }
+    }
+
+    /// returns the subschemas describing the elements found by the path
+    pub(crate) fn walk(&mut self, path: &JsonPath, current: Vec<&'a Value>) -> Vec<&'a Value> {
+        let segments = match path {
+            JsonPath::Chain(elems) => elems.as_slice(),
+            single => std::slice::from_ref(single),
+        };
+        let mut current = current;
+        for (position, segment) in segments.iter().enumerate() {
+            match self.step(segment, &current) {
+                Ok(next) => current = next,
+                Err(message) => {
+                    self.issues.push(SchemaIssue { position, segment: segment.clone().into_owned(), message });
+                    return vec![];
+                }
+            }
+        }
+        current
+    }
+
+    fn step(&mut self, segment: &JsonPath, current: &[&'a Value]) -> Result<Vec<&'a Value>, String> {
+        let schemas: Vec<&'a Value> = current.iter().flat_map(|s| self.resolve(s, 0)).collect();
+        match segment {
+            JsonPath::Root => Ok(vec![self.root]),
+            JsonPath::Empty => Ok(schemas),
+            JsonPath::Current(tail) => Ok(self.walk(tail, schemas)),
+            JsonPath::Chain(_) => Ok(self.walk(segment, schemas)),
+            JsonPath::Descent(_) | JsonPath::Descendants => Ok(vec![&ANY]),
+            JsonPath::Field(key) => collect(&schemas, |s| field(s, key)),
+            JsonPath::Index(JsonPathIndex::UnionKeys(keys)) =>
+                collect(&schemas, |s| {
+                    let found: Vec<&Value> = keys.iter().flat_map(|k| field(s, k).unwrap_or_default()).collect();
+                    if found.is_empty() { field(s, &keys[0]) } else { Ok(found) }
+                }),
+            JsonPath::Index(JsonPathIndex::Single(idx)) => collect(&schemas, |s| item(s, idx.as_u64())),
+            JsonPath::Index(JsonPathIndex::UnionIndex(_))
+            | JsonPath::Index(JsonPathIndex::Slice(..)) => collect(&schemas, |s| item(s, None)),
+            JsonPath::Wildcard => collect(&schemas, children),
+            JsonPath::Index(JsonPathIndex::Filter(left, _, right)) => {
+                let elems = collect(&schemas, children)?;
+                for op in [left, right] {
+                    self.check_operand(op, &elems)?;
+                }
+                Ok(elems)
+            }
+            JsonPath::Index(JsonPathIndex::Logical(expr)) => {
+                let elems = collect(&schemas, children)?;
+                for (left, _, right) in conjuncts(expr) {
+                    for op in [left, right] {
+                        self.check_operand(op, &elems)?;
+                    }
+                }
+                Ok(elems)
+            }
+            JsonPath::Index(JsonPathIndex::Union(sels)) => {
+                let mut found = vec![];
+                for sel in sels {
+                    found.extend(self.step(sel, current)?);
+                }
+                Ok(found)
+            }
+        }
+    }
+
+    /// checks the relative paths of the operand, the arguments of the functions included.
+    fn check_operand(&mut self, op: &Operand, elems: &[&'a Value]) -> Result<(), String> {
+        match op {
+            Operand::Dynamic(path) => self.check_path(path, elems),
+            Operand::Function(_, args) => args.iter().try_for_each(|a| self.check_operand(a, elems)),
+            Operand::Static(_) | Operand::Variable(_) => Ok(()),
+        }
+    }
+
+    /// checks the relative paths inside the filters against the schemas of the filtered elements.
+    fn check_path(&mut self, path: &JsonPath, elems: &[&'a Value]) -> Result<(), String> {
+        let relative = match path {
+            JsonPath::Chain(chain) => match chain.first() {
+                Some(JsonPath::Current(tail)) => Some(tail),
+                _ => None
+            },
+            _ => None
+        };
+        if let Some(tail) = relative {
+            let mut nested = SchemaWalker::new(self.root);
+            nested.walk(tail, elems.to_vec());
+            if let Some(issue) = nested.issues.into_iter().next() {
+                return Err(format!("the filter operand can never match: {:?} {}", issue.segment, issue.message));
+            }
+        }
+        Ok(())
+    }
+
+    /// follows the references and expands the combinators
+    pub(crate) fn resolve(&self, schema: &'a Value, depth: usize) -> Vec<&'a Value> {
+        if depth > 32 {
+            return vec![&ANY];
+        }
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            return match reference.strip_prefix('#').and_then(|p| self.root.pointer(p)) {
+                Some(s) => self.resolve(s, depth + 1),
+                None => vec![&ANY]
+            };
+        }
+        for comb in ["anyOf", "oneOf", "allOf"] {
+            if let Some(branches) = schema.get(comb).and_then(Value::as_array) {
+                return branches.iter().flat_map(|b| self.resolve(b, depth + 1)).collect();
+            }
+        }
+        vec![schema]
+    }
+}
+
+/// gathers the local references(`#/...`) the schema makes along with their targets,
+/// the references the targets make included
+fn references<'a>(root: &'a Value, schema: &Value, found: &mut Vec<(String, &'a Value)>) {
+    match schema {
+        Value::Object(fields) => {
+            if let Some(Value::String(reference)) = fields.get("$ref") {
+                let target = reference.strip_prefix('#').and_then(|p| root.pointer(p));
+                if let (Some(target), false) = (target, found.iter().any(|(r, _)| r == reference)) {
+                    found.push((reference.clone(), target));
+                    references(root, target, found);
+                }
+            }
+            fields.values().for_each(|v| references(root, v, found));
+        }
+        Value::Array(elems) => elems.iter().for_each(|v| references(root, v, found)),
+        _ => ()
+    }
+}
+
+/// the place of every reference target in the derived schema: the definitions keep their places,
+/// the rest go to `$defs` named after their pointers
+fn places(targets: &[(String, &Value)]) -> Vec<(String, (String, String))> {
+    let unescape = |t: &str| t.replace("~1", "/").replace("~0", "~");
+    let kept = |reference: &str| match reference.strip_prefix("#/").map(|p| p.split('/').collect::<Vec<_>>()).as_deref() {
+        Some([defs, name]) if *defs == "$defs" || *defs == "definitions" => Some((unescape(defs), unescape(name))),
+        _ => None
+    };
+    let mut taken: Vec<String> = targets.iter()
+        .filter_map(|(r, _)| kept(r).filter(|(defs, _)| defs == "$defs").map(|(_, name)| name))
+        .collect();
+    targets.iter().map(|(reference, _)| {
+        let place = kept(reference).unwrap_or_else(|| {
+            let tokens: Vec<String> = reference.trim_start_matches('#').split('/').skip(1).map(unescape).collect();
+            let mut name = if tokens.is_empty() { String::from("root") } else { tokens.join(".") };
+            while taken.contains(&name) {
+                name.push('_');
+            }
+            taken.push(name.clone());
+            (String::from("$defs"), name)
+        });
+        (reference.clone(), place)
+    }).collect()
+}
+
+/// the copy of the schema with the references pointing out to the places of their targets
+fn relocate(schema: &Value, places: &[(String, (String, String))]) -> Value {
+    let escape = |t: &str| t.replace('~', "~0").replace('/', "~1");
+    match schema {
+        Value::Object(fields) => Value::Object(fields.iter().map(|(k, v)| {
+            let moved = match (k.as_str(), v) {
+                ("$ref", Value::String(r)) => places.iter().find(|(reference, _)| reference == r)
+                    .map(|(_, (defs, name))| Value::from(format!("#/{}/{}", escape(defs), escape(name)))),
+                _ => None
+            };
+            (k.clone(), moved.unwrap_or_else(|| relocate(v, places)))
+        }).collect()),
+        Value::Array(elems) => Value::Array(elems.iter().map(|v| relocate(v, places)).collect()),
+        v => v.clone()
+    }
+}
+
+fn collect<'a, F>(schemas: &[&'a Value], f: F) -> Result<Vec<&'a Value>, String>
+    where F: Fn(&'a Value) -> Result<Vec<&'a Value>, String> {
+    let mut res = vec![];
+    let mut error = None;
+    for s in schemas.iter() {
+        match f(s) {
+            Ok(mut found) => res.append(&mut found),
+            Err(e) => error = error.or(Some(e))
+        }
+    }
+    match error {
+        Some(e) if res.is_empty() => Err(e),
+        _ => Ok(res)
+    }
+}
+
+/// checks whether the schema allows the given json type
+pub(crate) fn allows(schema: &Value, tp: &str) -> bool {
+    match schema {
+        Value::Bool(b) => *b,
+        Value::Object(fields) => match fields.get("type") {
+            Some(Value::String(t)) => t == tp || (tp == "number" && t == "integer"),
+            Some(Value::Array(types)) => types.iter().any(|t| t == tp || (tp == "number" && t == "integer")),
+            _ => true
+        },
+        _ => true
+    }
+}
+
+fn types(schema: &Value) -> String {
+    match schema {
+        Value::Bool(false) => String::from("nothing"),
+        _ => match schema.get("type") {
+            Some(Value::String(t)) => t.clone(),
+            Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect::<Vec<_>>().join(" or "),
+            _ => String::from("anything")
+        }
+    }
+}
+
+fn field<'a>(schema: &'a Value, key: &str) -> Result<Vec<&'a Value>, String> {
+    if !allows(schema, "object") {
+        return Err(format!("the member '{}' is requested but the element is {}", key, types(schema)));
+    }
+    if let Some(s) = schema.get("properties").and_then(|p| p.get(key)) {
+        return Ok(vec![s]);
+    }
+    let patterns: Vec<&Value> = schema.get("patternProperties")
+        .and_then(Value::as_object)
+        .map(|pp| pp.iter()
+            .filter(|(p, _)| pattern::compile(p).map(|r| pattern::is_match(&r, key)).unwrap_or(true))
+            .map(|(_, s)| s)
+            .collect())
+        .unwrap_or_default();
+    if !patterns.is_empty() {
+        return Ok(patterns);
+    }
+    match schema.get("additionalProperties") {
+        Some(Value::Bool(false)) => Err(format!("the member '{}' is unknown", key)),
+        Some(s) => Ok(vec![s]),
+        None => Ok(vec![&ANY])
+    }
+}
+
+fn item(schema: &Value, idx: Option<u64>) -> Result<Vec<&Value>, String> {
+    if !allows(schema, "array") {
+        return Err(format!("the index is requested but the element is {}", types(schema)));
+    }
+    if let (Some(idx), Some(max)) = (idx, schema.get("maxItems").and_then(Value::as_u64)) {
+        if idx >= max {
+            return Err(format!("the index {} exceeds the maximum number of items {}", idx, max));
+        }
+    }
+    let prefix = schema.get("prefixItems").and_then(Value::as_array)
+        .or_else(|| schema.get("items").and_then(Value::as_array));
+    match (prefix, idx) {
+        (Some(prefix), Some(idx)) if (idx as usize) < prefix.len() => Ok(vec![&prefix[idx as usize]]),
+        (Some(prefix), None) => {
+            let mut res: Vec<&Value> = prefix.iter().collect();
+            res.push(schema.get("items").filter(|i| !i.is_array()).unwrap_or(&ANY));
+            Ok(res)
+        }
+        _ => match schema.get("items").filter(|i| !i.is_array()) {
+            Some(Value::Bool(false)) => Err(String::from("the array can not have the items at this position")),
+            Some(s) => Ok(vec![s]),
+            None => Ok(vec![&ANY])
+        }
+    }
+}
+
+fn children(schema: &Value) -> Result<Vec<&Value>, String> {
+    let obj = allows(schema, "object");
+    let arr = allows(schema, "array");
+    if !obj && !arr {
+        return Err(format!("the children are requested but the element is {}", types(schema)));
+    }
+    let restricted = schema.get("type").is_some();
+    let mut res = vec![];
+    if obj {
+        if let Some(props) = schema.get("properties").and_then(Value::as_object) {
+            res.extend(props.values());
+        }
+        if let Some(props) = schema.get("patternProperties").and_then(Value::as_object) {
+            res.extend(props.values());
+        }
+        match schema.get("additionalProperties") {
+            Some(Value::Bool(false)) => (),
+            Some(s) => res.push(s),
+            None => res.push(&ANY)
+        }
+    }
+    if arr && (restricted || res.is_empty()) {
+        res.append(&mut item(schema, None)?);
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use crate::parser::parser::parse_json_path;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "additionalProperties": false,
+            "properties": {
+                "store": {
+                    "type": "object",
+                    "properties": {
+                        "book": {"type": "array", "items": {"$ref": "#/$defs/book"}},
+                        "name": {"type": "string"}
+                    }
+                },
+                "pair": {"type": "array", "prefixItems": [{"type": "string"}, {"type": "number"}], "maxItems": 2}
+            },
+            "$defs": {
+                "book": {
+                    "type": "object",
+                    "additionalProperties": false,
+                    "properties": {"title": {"type": "string"}, "price": {"type": "number"}}
+                }
+            }
+        })
+    }
+
+    fn issues(path: &str) -> Vec<(usize, String)> {
+        parse_json_path(path).unwrap().check_schema(&schema())
+            .into_iter()
+            .map(|i| (i.position, i.message))
+            .collect()
+    }
+
+    #[test]
+    fn valid_test() {
+        assert!(issues("$.store.book[*].title").is_empty());
+        assert!(issues("$.store.book[0].price").is_empty());
+        assert!(issues("$.store.book[?(@.price > 10)].title").is_empty());
+        assert!(issues("$.store.unknown").is_empty());
+        assert!(issues("$..anything").is_empty());
+        assert!(issues("$.pair[1]").is_empty());
+        assert!(issues("$.*").is_empty());
+    }
+
+    #[test]
+    fn invalid_test() {
+        assert_eq!(issues("$.stor.book"), vec![(1, String::from("the member 'stor' is unknown"))]);
+        assert_eq!(issues("$.store.book[*].isbn"), vec![(4, String::from("the member 'isbn' is unknown"))]);
+        assert_eq!(issues("$.store.name[0]"), vec![(3, String::from("the index is requested but the element is string"))]);
+        assert_eq!(issues("$.store.book.title"), vec![(3, String::from("the member 'title' is requested but the element is array"))]);
+        assert_eq!(issues("$.pair[2]"), vec![(2, String::from("the index 2 exceeds the maximum number of items 2"))]);
+        assert_eq!(issues("$.pair[0].a"), vec![(3, String::from("the member 'a' is requested but the element is string"))]);
+        assert_eq!(issues("$.store.book[?(@.isbn)]"),
+                   vec![(3, String::from("the filter operand can never match: Field(\"isbn\") the member 'isbn' is unknown"))]);
+        assert_eq!(issues("$.store.book[?(length(@.isbn) > 10)]"),
+                   vec![(3, String::from("the filter operand can never match: Field(\"isbn\") the member 'isbn' is unknown"))]);
+    }
+
+    #[test]
+    fn combinators_test() {
+        let schema = json!({"anyOf": [
+            {"type": "object", "properties": {"a": {"type": "string"}}, "additionalProperties": false},
+            {"type": "array"}
+        ]});
+        let check = |p: &str| parse_json_path(p).unwrap().check_schema(&schema).len();
+        assert_eq!(check("$.a"), 0);
+        assert_eq!(check("$[0]"), 0);
+        assert_eq!(check("$.b"), 1);
+        assert_eq!(check("$.a.b"), 1);
+    }
+
+    #[test]
+    fn output_schema_test() {
+        let output = |p: &str| parse_json_path(p).unwrap().output_schema(&schema());
+        let defs = schema()["$defs"].clone();
+
+        assert_eq!(output("$.store.book[*].title"), json!({"type":"array","items":{"type":"string"}}));
+        assert_eq!(output("$.store.name"), json!({"type":"array","items":{"type":"string"},"maxItems":1}));
+        assert_eq!(output("$.store.book[?(@.price < 10)]"), json!({"type":"array","items":defs["book"]}));
+        assert_eq!(output("$.store.book"),
+                   json!({"type":"array","items":{"type": "array", "items": {"$ref": "#/$defs/book"}},"maxItems":1,"$defs":defs}));
+        assert_eq!(output("$.pair[*]"), json!({"type":"array","items":true}));
+        assert_eq!(output("$.pair[:1]"), json!({"type":"array","items":true}));
+        assert_eq!(output("$.store.book[*]['title','price']"),
+                   json!({"type":"array","items":{"anyOf":[{"type":"string"},{"type":"number"}]}}));
+        assert_eq!(output("$.store.book[*].isbn"), json!({"type":"array","maxItems":0}));
+    }
+
+    #[test]
+    fn output_references_test() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "point": {"type": "object", "properties": {"x": {"type": "number"}}},
+                "path": {"type": "array", "items": {"$ref": "#/properties/point"}},
+                "tree": {"type": "array", "items": {"$ref": "#/definitions/node"}}
+            },
+            "definitions": {
+                "node": {"type": "object", "properties": {"children": {"type": "array", "items": {"$ref": "#/definitions/node"}}}},
+                "unused": {"type": "string"}
+            },
+            "$defs": {"other": {"type": "null"}}
+        });
+        let output = |p: &str| parse_json_path(p).unwrap().output_schema(&schema);
+
+        assert_eq!(output("$.path"), json!({
+            "type": "array",
+            "items": {"type": "array", "items": {"$ref": "#/$defs/properties.point"}},
+            "maxItems": 1,
+            "$defs": {"properties.point": schema["properties"]["point"]}
+        }));
+        assert_eq!(output("$.tree"), json!({
+            "type": "array",
+            "items": {"type": "array", "items": {"$ref": "#/definitions/node"}},
+            "maxItems": 1,
+            "definitions": {"node": schema["definitions"]["node"]}
+        }));
+        assert_eq!(output("$.properties"), json!({"type":"array","items":true,"maxItems":1}));
+    }
+}
@@ -1,6 +1,8 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 use serde_json::{Value};
 use serde_json::value::Value::{Array, Object};
-use crate::path::{PathInstance, Path, json_path_instance};
+use crate::path::{PathInstance, Path, Env, instance};
 use crate::parser::model::*;
 
 /// to process the element [*]
@@ -40,17 +42,6 @@ impl<'a> Path<'a> for IdentityPath {
     }
 }
 
-
-pub(crate) struct EmptyPath {}
-
-impl<'a> Path<'a> for EmptyPath {
-    type Data = Value;
-
-    fn find(&self, _data: &'a Self::Data) -> Vec<&'a Self::Data> {
-        vec![]
-    }
-}
-
 /// process $ element
 pub(crate) struct RootPointer<'a, T> {
     root: &'a T
@@ -72,11 +63,11 @@ impl<'a> Path<'a> for RootPointer<'a, Value> {
 
 /// process object fields like ['key'] or .key
 pub(crate) struct ObjectField<'a> {
-    key: &'a String,
+    key: &'a str,
 }
 
 impl<'a> ObjectField<'a> {
-    pub(crate) fn new(key: &'a String) -> ObjectField<'a> {
+    pub(crate) fn new(key: &'a str) -> ObjectField<'a> {
         ObjectField { key }
     }
 }
@@ -100,7 +91,7 @@ impl<'a> Path<'a> for ObjectField<'a> {
 
 /// processes decent object like ..
 pub(crate) struct DescentObjectField<'a> {
-    key: &'a String,
+    key: &'a str,
 }
 
 impl<'a> Path<'a> for DescentObjectField<'a> {
@@ -131,11 +122,32 @@ impl<'a> Path<'a> for DescentObjectField<'a> {
 }
 
 impl<'a> DescentObjectField<'a> {
-    pub fn new(key: &'a String) -> Self {
+    pub fn new(key: &'a str) -> Self {
         DescentObjectField { key }
     }
 }
 
+/// the element along with all its descendants in the document order, the parents go before their children
+pub(crate) struct Descendants {}
+
+impl<'a> Path<'a> for Descendants {
+    type Data = Value;
+
+    fn find(&self, data: &'a Self::Data) -> Vec<&'a Self::Data> {
+        fn collect<'a>(data: &'a Value, res: &mut Vec<&'a Value>) {
+            res.push(data);
+            match data {
+                Value::Object(elems) => elems.values().for_each(|v| collect(v, res)),
+                Value::Array(elems) => elems.iter().for_each(|v| collect(v, res)),
+                _ => ()
+            }
+        }
+        let mut res = vec![];
+        collect(data, &mut res);
+        res
+    }
+}
+
 /// the top method of the processing representing the chain of other operators
 pub(crate) struct Chain<'a> {
     chain: Vec<PathInstance<'a>>,
@@ -145,8 +157,8 @@ impl<'a> Chain<'a> {
     pub fn new(chain: Vec<PathInstance<'a>>) -> Self {
         Chain { chain }
     }
-    pub fn from(chain: &'a [JsonPath], root: &'a Value) -> Self {
-        Chain::new(chain.iter().map(|p| json_path_instance(p, root)).collect())
+    pub(crate) fn from(chain: &'a [JsonPath], env: &Env<'a>) -> Self {
+        Chain::new(chain.iter().map(|p| instance(p, env)).collect())
     }
 }
 
@@ -155,17 +167,36 @@ impl<'a> Path<'a> for Chain<'a> {
 
     fn find(&self, data: &'a Self::Data) -> Vec<&'a Self::Data> {
         self.chain.iter().fold(vec![data], |inter_res, path| {
-            inter_res.iter().flat_map(|d| path.find(d)).collect()
+            let mut distinct = HashSet::new();
+            if inter_res.len() < 2 || inter_res.iter().all(|d| distinct.insert(*d as *const Value)) {
+                return inter_res.iter().flat_map(|d| path.find(d)).collect();
+            }
+            // the overlapping descents yield the same nodes several times,
+            // so every distinct node is walked only once and the repeated ones copy what it has found
+            let mut res = vec![];
+            let mut walked: HashMap<*const Value, Range<usize>> = HashMap::new();
+            for d in inter_res {
+                match walked.get(&(d as *const Value)) {
+                    Some(found) => res.extend_from_within(found.clone()),
+                    None => {
+                        let start = res.len();
+                        res.extend(path.find(d));
+                        walked.insert(d as *const Value, start..res.len());
+                    }
+                }
+            }
+            res
         })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::path::top::{Path, ObjectField, RootPointer, json_path_instance};
+    use crate::path::top::{Path, ObjectField, RootPointer};
+    use crate::path::json_path_instance;
     use serde_json::Value;
     use serde_json::json;
-    use crate::parser::model::{JsonPath, JsonPathIndex, Operand, FilterSign};
+    use crate::parser::model::{JsonPath, JsonPathIndex};
 
     #[test]
     fn object_test() {
@@ -198,8 +229,6 @@ mod tests {
         let field3 = JsonPath::field("f");
         let field4 = JsonPath::field("array");
         let field5 = JsonPath::field("object");
-        let field6 = JsonPath::field("field1");
-        let field7 = JsonPath::field("field2");
 
         let root = JsonPath::Root;
         let path_inst = json_path_instance(&root, &json);
@@ -220,14 +249,13 @@ mod tests {
 
 
         let index1 = JsonPath::Index(JsonPathIndex::Single(json!(3)));
-        let index2 = JsonPath::Index(JsonPathIndex::Single(json!(2)));
         let chain = vec![root.clone(), field1.clone(), field2.clone(), field4.clone(), index1.clone()];
         let chain = JsonPath::Chain(chain);
         let path_inst = json_path_instance(&chain, &json);
         let exp_json = json!(3);
         assert_eq!(path_inst.find(&json), vec![&exp_json]);
 
-        let index = JsonPath::Index(JsonPathIndex::Slice(1, -1, 2));
+        let index = JsonPath::Index(JsonPathIndex::Slice(Some(1), Some(-1), 2));
         let chain = vec![root.clone(), field1.clone(), field2.clone(), field4.clone(), index.clone()];
         let chain = JsonPath::Chain(chain);
         let path_inst = json_path_instance(&chain, &json);
@@ -251,9 +279,7 @@ mod tests {
         assert_eq!(path_inst.find(&json), vec![&json!(1), &json!(2)]);
 
 
-        let op6 = Operand::Dynamic(Box::new(field6));
-        let op7 = Operand::Dynamic(Box::new(field7));
-        let union = JsonPath::Index(JsonPathIndex::UnionKeys(vec![String::from("field1"), String::from("field2")]));
+        let union = JsonPath::Index(JsonPathIndex::UnionKeys(vec!["field1".into(), "field2".into()]));
         let chain = vec![root.clone(), field1.clone(), field2.clone(), field5.clone(), union.clone()];
         let chain = JsonPath::Chain(chain);
         let path_inst = json_path_instance(&chain, &json);
@@ -277,7 +303,7 @@ mod tests {
                      }
             }
         });
-        let key = JsonPath::Descent(String::from("key1"));
+        let key = JsonPath::descent("key1");
         let root = JsonPath::Root;
         let chain = vec![root, key];
         let chain = JsonPath::Chain(chain);
@@ -293,6 +319,26 @@ mod tests {
         assert_eq!(path_inst.find(&json), expected_res)
     }
 
+    #[test]
+    fn overlapping_descent_test() {
+        let json = json!({"a":{"b":1,"a":{"b":2,"a":{"b":3}}}});
+        let path = crate::parser::parser::parse_json_path("$..a..b").unwrap();
+        let path_inst = json_path_instance(&path, &json);
+        let res: Vec<Value> = path_inst.find(&json).into_iter().cloned().collect();
+        assert_eq!(res, vec![json!(1), json!(2), json!(3), json!(2), json!(3), json!(3)]);
+
+        let path = crate::parser::parser::parse_json_path("$..a..a..b").unwrap();
+        let path_inst = json_path_instance(&path, &json);
+        let res: Vec<Value> = path_inst.find(&json).into_iter().cloned().collect();
+        assert_eq!(res, vec![json!(2), json!(3), json!(3), json!(3)]);
+
+        // the repeated nodes apart from each other copy what the first walk has found
+        let json = json!({"x":[{"y":[1,2]},{"y":[3]}]});
+        let path = crate::parser::parser::parse_json_path("$.x[0,1,0].y[*]").unwrap();
+        let res: Vec<Value> = json_path_instance(&path, &json).find(&json).into_iter().cloned().collect();
+        assert_eq!(res, vec![json!(1), json!(2), json!(3), json!(1), json!(2)]);
+    }
+
     #[test]
     fn wildcard_test() {
         let json =
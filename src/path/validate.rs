@@ -0,0 +1,124 @@
+use serde_json::Value;
+use crate::parser::model::{FilterSign, JsonPath, JsonPathIndex, Operand};
+use crate::path::json::as_index;
+use crate::path::pattern;
+use crate::path::Options;
+
+impl JsonPath<'_> {
+    /// checks the query for the states the evaluation can not make sense of,
+    /// like the indexes being not the positions in the array, the invalid regex or the variables nobody has bound.
+    /// The zero step of the slice is valid and selects nothing.
+    /// The parser never gives them but the queries built by hand or decoded from the bytes can have them,
+    /// `find` selects nothing by them while [JsonPath::try_find] reports them.
+    pub fn validate(&self) -> Result<(), String> {
+        self.check_states()?;
+        match self.variables().first() {
+            Some(name) => Err(format!("the variable '${}' is not bound", name)),
+            None => Ok(()),
+        }
+    }
+
+    /// the same as [JsonPath::validate] but the variables can be bound later
+    pub(crate) fn check_states(&self) -> Result<(), String> {
+        match self {
+            JsonPath::Index(JsonPathIndex::Single(idx)) => index(idx),
+            JsonPath::Index(JsonPathIndex::UnionIndex(idxs)) => idxs.iter().try_for_each(index),
+            JsonPath::Index(JsonPathIndex::Filter(l, op, r)) => atom(l, op, r),
+            JsonPath::Index(JsonPathIndex::Logical(expr)) => expr.atoms().into_iter().try_for_each(|(l, op, r)| atom(l, op, r)),
+            JsonPath::Index(JsonPathIndex::Union(sels)) => sels.iter().try_for_each(JsonPath::check_states),
+            JsonPath::Current(tail) => tail.check_states(),
+            JsonPath::Chain(elems) => elems.iter().try_for_each(JsonPath::check_states),
+            _ => Ok(())
+        }
+    }
+
+    /// finds the elements like `find` but fails on the invalid queries(see [JsonPath::validate])
+    /// instead of selecting nothing
+    pub fn try_find<'a>(&'a self, doc: &'a Value) -> Result<Vec<&'a Value>, String> {
+        self.find_with_options(doc, &Options::new())
+    }
+}
+
+fn index(idx: &Value) -> Result<(), String> {
+    as_index(idx).map(|_| ()).ok_or_else(|| format!("the index {} is not the integer", idx))
+}
+
+fn atom(left: &Operand, op: &FilterSign, right: &Operand) -> Result<(), String> {
+    match (op, right) {
+        (FilterSign::Regex, Operand::Static(Value::String(p))) => pattern::compile(p).and_then(|_| operand(left)),
+        _ => operand(left).and_then(|_| operand(right)),
+    }
+}
+
+fn operand(op: &Operand) -> Result<(), String> {
+    match op {
+        Operand::Static(_) | Operand::Variable(_) => Ok(()),
+        Operand::Dynamic(path) => path.check_states(),
+        Operand::Function(_, args) => args.iter().try_for_each(operand),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::parser::model::{FilterSign, JsonPath, JsonPathIndex, Operand};
+    use crate::parser::parser::parse_json_path;
+    use crate::path::json_path_instance;
+
+    #[test]
+    fn invalid_states_test() {
+        let doc = json!({"a":[1,2,3]});
+        let index = |idx| JsonPath::Chain(vec![JsonPath::Root, JsonPath::field("a"), JsonPath::Index(idx)]);
+
+        for path in [index(JsonPathIndex::Single(json!(0.5))), index(JsonPathIndex::Single(json!("0"))),
+            index(JsonPathIndex::UnionIndex(vec![json!(-0.5), json!(1.5)])),
+            index(JsonPathIndex::Filter(Operand::Dynamic(Box::new(index(JsonPathIndex::Single(json!(null))))),
+                                        FilterSign::Exists, Operand::Static(json!(null))))] {
+            assert!(path.validate().is_err(), "{:?}", path);
+            assert!(path.try_find(&doc).is_err());
+            assert!(json_path_instance(&path, &doc).find(&doc).is_empty(), "{:?}", path);
+            assert!(path.find_nodes(&doc).is_empty());
+            assert!(path.compile()(&doc).is_empty());
+            assert_eq!(path.find_raw(r#"{"a":[1,2,3]}"#).unwrap().len(), 0);
+        }
+
+        assert_eq!(index(JsonPathIndex::Single(json!(2))).try_find(&doc), Ok(vec![&json!(3)]));
+        assert_eq!(index(JsonPathIndex::Slice(Some(0), Some(2), 0)).try_find(&doc), Ok(vec![]));
+    }
+
+    #[test]
+    fn unbound_variable_test() {
+        let doc = json!({"items":[{"id":1},{"id":2}]});
+        let path = parse_json_path("$.items[?(@.id == $id)]").unwrap();
+        assert_eq!(path.validate(), Err(String::from("the variable '$id' is not bound")));
+        assert!(path.try_find(&doc).is_err());
+        assert!(parse_json_path("$.items[?(@.id == 1 || length($name) > 1)]").unwrap().validate().is_err());
+        assert_eq!(path.bind_var("id", 2).try_find(&doc), Ok(vec![&json!({"id":2})]));
+    }
+
+    #[test]
+    fn extreme_bounds_test() {
+        let doc = json!([1, 2, 3]);
+        let find = |q: &str| parse_json_path(q).unwrap().try_find(&doc).unwrap().into_iter().cloned().collect::<Vec<_>>();
+        assert_eq!(find("$[-99999999999:]"), vec![json!(1), json!(2), json!(3)]);
+        assert_eq!(find("$[1:99999999999]"), vec![json!(2), json!(3)]);
+        assert_eq!(find("$[99999999999:-99999999999:-1]"), vec![json!(3), json!(2), json!(1)]);
+        assert_eq!(find("$[-3:]"), vec![json!(1), json!(2), json!(3)]);
+        assert_eq!(find("$[0:3:99999999999999999999999]"), vec![json!(1)]);
+        let huge = parse_json_path("$[99999999999999999999]").unwrap();
+        assert!(huge.try_find(&doc).is_err());
+        assert!(json_path_instance(&huge, &doc).find(&doc).is_empty());
+        assert!(find("$[0:2:0]").is_empty());
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn invalid_regex_test() {
+        let doc = json!({"a":["ab","b"]});
+        let path = parse_json_path("$.a[?(@ =~ /(a/)]").unwrap();
+        assert!(path.validate().unwrap_err().starts_with("the regex '(a' is invalid"));
+        assert!(path.try_find(&doc).is_err());
+        assert!(json_path_instance(&path, &doc).find(&doc).is_empty());
+        assert_eq!(parse_json_path("$.a[?(@ =~ /^A/i)]").unwrap().try_find(&doc), Ok(vec![&json!("ab")]));
+    }
+}
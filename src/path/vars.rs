@@ -0,0 +1,124 @@
+use serde_json::Value;
+use crate::parser::model::{FilterExpression, JsonPath, JsonPathIndex, Operand};
+use crate::path::JsonPathInst;
+
+impl<'a> JsonPath<'a> {
+    /// gives the copy of the path with the placeholder `$name` in the filters replaced by the value,
+    /// so the values coming from the users go into the query with no formatting and no escaping
+    /// and the same parsed path serves the different values. The placeholders left unbound find nothing.
+    /// # Examples:
+    /// ```
+    /// use serde_json::json;
+    /// use jsonpath_rust::JsonPath;
+    ///
+    /// let by_id = JsonPath::parse("$.items[?(@.id == $id)].name").unwrap();
+    /// let json = json!({"items":[{"id":42,"name":"a"},{"id":"42' || true","name":"b"}]});
+    /// assert_eq!(by_id.bind_var("id", 42).find_owned(&json), vec![json!("a")]);
+    /// assert_eq!(by_id.bind_var("id", "42' || true").find_owned(&json), vec![json!("b")]);
+    /// assert!(by_id.find_owned(&json).is_empty());
+    /// ```
+    pub fn bind_var<V: Into<Value>>(&self, name: &str, value: V) -> JsonPath<'a> {
+        let mut path = self.clone();
+        let value = value.into();
+        path.visit_variables(&mut |op| match op {
+            Operand::Variable(n) if n == name => *op = Operand::Static(value.clone()),
+            _ => (),
+        });
+        path
+    }
+
+    /// the names of the placeholders the path has yet to be bound(without `$`)
+    pub fn variables(&self) -> Vec<String> {
+        let mut names: Vec<String> = vec![];
+        self.clone().visit_variables(&mut |op| match op {
+            Operand::Variable(n) if !names.iter().any(|name| name == n) => names.push(n.to_string()),
+            _ => (),
+        });
+        names
+    }
+
+    fn visit_variables<F: FnMut(&mut Operand<'a>)>(&mut self, f: &mut F) {
+        fn expr<'a, F: FnMut(&mut Operand<'a>)>(e: &mut FilterExpression<'a>, f: &mut F) {
+            match e {
+                FilterExpression::Atom(l, _, r) => {
+                    operand(l, f);
+                    operand(r, f);
+                }
+                FilterExpression::And(l, r) | FilterExpression::Or(l, r) => {
+                    expr(l, f);
+                    expr(r, f);
+                }
+                FilterExpression::Not(e) => expr(e, f),
+            }
+        }
+        fn operand<'a, F: FnMut(&mut Operand<'a>)>(op: &mut Operand<'a>, f: &mut F) {
+            match op {
+                Operand::Dynamic(path) => path.visit_variables(f),
+                Operand::Function(_, args) => args.iter_mut().for_each(|a| operand(a, f)),
+                Operand::Variable(_) => f(op),
+                Operand::Static(_) => (),
+            }
+        }
+        match self {
+            JsonPath::Chain(elems) => elems.iter_mut().for_each(|p| p.visit_variables(f)),
+            JsonPath::Current(tail) => tail.visit_variables(f),
+            JsonPath::Index(JsonPathIndex::Filter(l, _, r)) => {
+                operand(l, f);
+                operand(r, f);
+            }
+            JsonPath::Index(JsonPathIndex::Logical(e)) => expr(e, f),
+            JsonPath::Index(JsonPathIndex::Union(sels)) => sels.iter_mut().for_each(|p| p.visit_variables(f)),
+            _ => (),
+        }
+    }
+}
+
+impl JsonPathInst {
+    /// compiles the copy of the path with the placeholder bound to the value(see [JsonPath::bind_var])
+    pub fn bind_var<V: Into<Value>>(&self, name: &str, value: V) -> JsonPathInst {
+        JsonPathInst::new(self.path().bind_var(name, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::JsonPathQuery;
+    use crate::parser::model::{JsonPath, Operand};
+    use crate::parser::parser::parse_json_path;
+    use crate::path::JsonPathInst;
+
+    #[test]
+    fn bind_test() {
+        let doc = json!({"items":[{"id":1,"tags":["a"],"n":"x"},{"id":2,"tags":["b","c"],"n":"y"}],"min":2});
+        let path = parse_json_path("$.items[?(@.id >= $min && (length(@.tags) == $len || $flag))].n").unwrap();
+        assert_eq!(path.variables(), vec!["min", "len", "flag"]);
+        assert!(path.find_owned(&doc).is_empty());
+
+        let bound = path.bind_var("min", 1).bind_var("len", 2);
+        assert_eq!(bound.variables(), vec!["flag"]);
+        assert_eq!(bound.find_owned(&doc), vec![json!("y")]);
+        assert_eq!(bound.bind_var("flag", true).find_owned(&doc), vec![json!("x"), json!("y")]);
+        assert_eq!(bound, parse_json_path("$.items[?(@.id >= 1 && (length(@.tags) == 2 || $flag))].n").unwrap());
+        assert_eq!(path.bind_var("other", 1), path);
+
+        let tags = parse_json_path("$.items[?($tag in @.tags)].id").unwrap();
+        assert_eq!(tags.bind_var("tag", json!("c")).find_owned(&doc), vec![json!(2)]);
+        assert_eq!(tags.to_string(), "$.items[?($tag in @.tags)].id");
+
+        let inst: JsonPathInst = "$.items[?(@.n == $n)].id".parse().unwrap();
+        assert_eq!(inst.bind_var("n", "x").find(&doc), vec![&json!(1)]);
+        assert_eq!(inst.bind_var("n", "y").find(&doc), vec![&json!(2)]);
+    }
+
+    #[test]
+    fn parse_test() {
+        let path = parse_json_path("$[?(@.a == $_a1)]").unwrap();
+        assert_eq!(path.variables(), vec!["_a1"]);
+        assert!(parse_json_path("$[?(@.a == $1)]").is_err());
+        assert_eq!(parse_json_path("$[?(@.a == $.b)]").unwrap().variables(), Vec::<String>::new());
+        assert_eq!(JsonPath::parse("$.a[?(@ == $x)]").unwrap().into_owned(), parse_json_path("$.a[?(@ == $x)]").unwrap());
+        assert!(matches!(Operand::Variable("x".into()), Operand::Variable(_)));
+        assert!(json!({"a":[1]}).path("$.a[?(@ == $x)]").is_err());
+    }
+}
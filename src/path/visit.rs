@@ -0,0 +1,931 @@
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::ops::ControlFlow;
+use serde_json::Value;
+use serde_core::de::DeserializeOwned;
+use crate::parser::model::{FilterExpression, FilterSign, JsonPath, JsonPathIndex, Operand};
+use crate::path::{Env, Options};
+use crate::path::functions::{self, Found};
+use crate::path::index::{compare, lengths, ArraySlice};
+use crate::path::json::{as_index, position};
+use crate::path::normalized::{NormalizedPath, PathSegment};
+use crate::path::pattern::{self, Pattern};
+
+/// The node of the document the traversal moves through:
+/// the element of [Value], the element of the json text or the node of the other json-like tree.
+pub(crate) trait Node<'a>: Copy {
+    /// the member of the object
+    fn member(self, key: &str) -> Option<Self>;
+    /// the number of the elements of the array, None for the other nodes
+    fn array_len(self) -> Option<usize>;
+    /// the element of the array
+    fn element(self, idx: usize) -> Option<Self>;
+    /// the members of the object or the elements of the array in their order
+    fn children<F: FnMut(Key<'a>, Self)>(self, f: F);
+    /// the elements of the array at the positions, in the order of the positions
+    fn elements<F: FnMut(usize, Self)>(self, idxs: &[usize], mut f: F) {
+        for &idx in idxs {
+            if let Some(v) = self.element(idx) {
+                f(idx, v)
+            }
+        }
+    }
+    /// the nodes the operand of the filter finds, as the filters compare them
+    fn found(nodes: Vec<Self>) -> Found<'a>;
+}
+
+impl<'a> Node<'a> for &'a Value {
+    fn member(self, key: &str) -> Option<Self> {
+        self.as_object()?.get(key)
+    }
+
+    fn array_len(self) -> Option<usize> {
+        self.as_array().map(Vec::len)
+    }
+
+    fn element(self, idx: usize) -> Option<Self> {
+        self.as_array()?.get(idx)
+    }
+
+    fn children<F: FnMut(Key<'a>, Self)>(self, mut f: F) {
+        match self {
+            Value::Array(elems) => elems.iter().enumerate().for_each(|(idx, v)| f(Key::Index(idx), v)),
+            Value::Object(fields) => fields.iter().for_each(|(key, v)| f(Key::Name(Cow::Borrowed(key)), v)),
+            _ => ()
+        }
+    }
+
+    fn found(nodes: Vec<Self>) -> Found<'a> {
+        Found::Nodes(nodes)
+    }
+}
+
+/// the name of the member or the index of the element the child is reached by
+#[derive(Debug, Clone)]
+pub(crate) enum Key<'a> {
+    Name(Cow<'a, str>),
+    Index(usize),
+}
+
+impl From<&Key<'_>> for PathSegment {
+    fn from(key: &Key<'_>) -> Self {
+        match key {
+            Key::Name(name) => PathSegment::Key(name.to_string()),
+            Key::Index(idx) => PathSegment::Index(*idx),
+        }
+    }
+}
+
+impl From<PathSegment> for Key<'_> {
+    fn from(segment: PathSegment) -> Self {
+        match segment {
+            PathSegment::Key(name) => Key::Name(Cow::Owned(name)),
+            PathSegment::Index(idx) => Key::Index(idx),
+        }
+    }
+}
+
+/// The segment of the query prepared for the traversal.
+#[derive(Debug)]
+pub(crate) enum Step<'p> {
+    Root,
+    Identity,
+    Wildcard,
+    Field(Cow<'p, str>),
+    Descent(Cow<'p, str>),
+    Descendants,
+    Index(Option<i64>),
+    Slice(ArraySlice),
+    Keys(Vec<Cow<'p, str>>),
+    Indexes(Vec<i64>),
+    Filter(Test<'p>),
+    /// the selectors of the different kinds selecting one after another
+    Union(Vec<Step<'p>>),
+}
+
+/// The expression of the filter, its operands are evaluated by the same traversal.
+#[derive(Debug)]
+pub(crate) enum Test<'p> {
+    Atom(Box<Atom<'p>>),
+    And(Box<Test<'p>>, Box<Test<'p>>),
+    Or(Box<Test<'p>>, Box<Test<'p>>),
+    Not(Box<Test<'p>>),
+}
+
+#[derive(Debug)]
+pub(crate) struct Atom<'p> {
+    left: Arg<'p>,
+    op: FilterSign,
+    right: Arg<'p>,
+    /// the regex given by the literal compiled along with the query
+    pattern: Option<Pattern>,
+    /// the function standing alone like `[?(has(@.tags, 'a'))]` matches unless it gives nothing or `false`
+    test: bool,
+}
+
+/// The operand of the filter.
+#[derive(Debug)]
+enum Arg<'p> {
+    Literal(Cow<'p, Value>),
+    /// the path from the candidate or from the root, the ones from the root are kept aside in the slot
+    /// and evaluated once for all the candidates.
+    /// The path ending with `.length` like `@.tags.length` gives the lengths of the arrays and strings it finds.
+    Path { steps: Vec<Step<'p>>, kept: Option<usize>, length: bool },
+    /// the function extension over the operands
+    Function(Cow<'p, str>, Vec<Arg<'p>>),
+    /// the variable nobody has bound
+    Nothing,
+}
+
+/// The query prepared once and walked over any number of documents.
+#[derive(Debug)]
+pub(crate) struct Plan<'p> {
+    pub(crate) steps: Vec<Step<'p>>,
+    /// the number of the operands kept aside
+    kept: usize,
+}
+
+impl<'p> Plan<'p> {
+    pub(crate) fn new(path: &'p JsonPath<'_>) -> Self {
+        let mut kept = 0;
+        let mut steps = vec![];
+        compile(path, &mut kept, &mut steps);
+        Plan { steps, kept }
+    }
+
+    pub(crate) fn into_owned(self) -> Plan<'static> {
+        Plan { steps: self.steps.into_iter().map(Step::into_owned).collect(), kept: self.kept }
+    }
+
+    /// checks whether the plan finds every location at most once walking the document from the top down,
+    /// so the traversal can seek to the location of the found node(see [Walk::seek])
+    pub(crate) fn seekable(&self) -> bool {
+        fn distinct<T: PartialEq>(items: &[T]) -> bool {
+            items.iter().enumerate().all(|(i, item)| !items[..i].contains(item))
+        }
+        let descents = self.steps.iter().filter(|s| matches!(s, Step::Descent(_) | Step::Descendants)).count();
+        descents <= 1 && self.steps.iter().enumerate().all(|(pos, step)| match step {
+            Step::Root => pos == 0,
+            Step::Union(_) => false,
+            Step::Keys(names) => distinct(names),
+            // the negative indexes can point out to the same elements as the other ones
+            Step::Indexes(idxs) => distinct(idxs) && (idxs.iter().all(|i| *i >= 0) || idxs.iter().all(|i| *i < 0)),
+            _ => true,
+        })
+    }
+}
+
+fn compile<'p>(path: &'p JsonPath<'_>, kept: &mut usize, steps: &mut Vec<Step<'p>>) {
+    match path {
+        JsonPath::Root => steps.push(Step::Root),
+        JsonPath::Empty => steps.push(Step::Identity),
+        JsonPath::Wildcard => steps.push(Step::Wildcard),
+        JsonPath::Field(key) => steps.push(Step::Field(Cow::Borrowed(key))),
+        JsonPath::Descent(key) => steps.push(Step::Descent(Cow::Borrowed(key))),
+        JsonPath::Descendants => steps.push(Step::Descendants),
+        JsonPath::Current(tail) => compile(tail, kept, steps),
+        JsonPath::Chain(elems) => elems.iter().for_each(|e| compile(e, kept, steps)),
+        JsonPath::Index(JsonPathIndex::Single(idx)) => steps.push(Step::Index(as_index(idx))),
+        JsonPath::Index(JsonPathIndex::Slice(s, e, step)) => steps.push(Step::Slice(ArraySlice::new(*s, *e, *step))),
+        JsonPath::Index(JsonPathIndex::UnionKeys(keys)) => steps.push(Step::Keys(keys.iter().map(|k| Cow::Borrowed(k.as_ref())).collect())),
+        JsonPath::Index(JsonPathIndex::UnionIndex(idxs)) => steps.push(Step::Indexes(idxs.iter().filter_map(as_index).collect())),
+        JsonPath::Index(JsonPathIndex::Filter(l, op, r)) => steps.push(Step::Filter(atom(l, op, r, kept))),
+        JsonPath::Index(JsonPathIndex::Logical(expr)) => steps.push(Step::Filter(test(expr, kept))),
+        JsonPath::Index(JsonPathIndex::Union(sels)) => {
+            let mut union = vec![];
+            sels.iter().for_each(|s| compile(s, kept, &mut union));
+            steps.push(Step::Union(union))
+        }
+    }
+}
+
+fn test<'p>(expr: &'p FilterExpression<'_>, kept: &mut usize) -> Test<'p> {
+    match expr {
+        FilterExpression::Atom(l, op, r) => atom(l, op, r, kept),
+        FilterExpression::And(l, r) => Test::And(Box::new(test(l, kept)), Box::new(test(r, kept))),
+        FilterExpression::Or(l, r) => Test::Or(Box::new(test(l, kept)), Box::new(test(r, kept))),
+        FilterExpression::Not(expr) => Test::Not(Box::new(test(expr, kept))),
+    }
+}
+
+fn atom<'p>(left: &'p Operand<'_>, op: &FilterSign, right: &'p Operand<'_>, kept: &mut usize) -> Test<'p> {
+    let pattern = match (op, right) {
+        (FilterSign::Regex, Operand::Static(Value::String(p))) => pattern::compile(p).ok(),
+        _ => None
+    };
+    Test::Atom(Box::new(Atom {
+        test: matches!((op, left), (FilterSign::Exists, Operand::Function(..))),
+        left: arg(left, kept),
+        op: op.clone(),
+        right: arg(right, kept),
+        pattern,
+    }))
+}
+
+fn arg<'p>(op: &'p Operand<'_>, kept: &mut usize) -> Arg<'p> {
+    match op {
+        Operand::Static(v) => Arg::Literal(Cow::Borrowed(v)),
+        Operand::Variable(_) => Arg::Nothing,
+        Operand::Function(name, args) => Arg::Function(Cow::Borrowed(name), args.iter().map(|a| arg(a, kept)).collect()),
+        Operand::Dynamic(path) => {
+            let mut steps = vec![];
+            compile(path, kept, &mut steps);
+            let length = matches!(steps.last(), Some(Step::Field(key)) if key == "length");
+            if length {
+                steps.pop();
+            }
+            let from_root = matches!(steps.first(), Some(Step::Root));
+            if from_root {
+                *kept += 1;
+            }
+            Arg::Path { steps, kept: from_root.then(|| *kept - 1), length }
+        }
+    }
+}
+
+impl Step<'_> {
+    fn into_owned(self) -> Step<'static> {
+        match self {
+            Step::Root => Step::Root,
+            Step::Identity => Step::Identity,
+            Step::Wildcard => Step::Wildcard,
+            Step::Field(key) => Step::Field(Cow::Owned(key.into_owned())),
+            Step::Descent(key) => Step::Descent(Cow::Owned(key.into_owned())),
+            Step::Descendants => Step::Descendants,
+            Step::Index(idx) => Step::Index(idx),
+            Step::Slice(slice) => Step::Slice(slice),
+            Step::Keys(keys) => Step::Keys(keys.into_iter().map(|k| Cow::Owned(k.into_owned())).collect()),
+            Step::Indexes(idxs) => Step::Indexes(idxs),
+            Step::Filter(test) => Step::Filter(test.into_owned()),
+            Step::Union(sels) => Step::Union(sels.into_iter().map(Step::into_owned).collect()),
+        }
+    }
+
+    /// checks whether the step jumps to the root or holds the filter looking at the root
+    pub(crate) fn refers_root(&self) -> bool {
+        match self {
+            Step::Root => true,
+            Step::Filter(test) => test.refers_root(),
+            Step::Union(sels) => sels.iter().any(Step::refers_root),
+            _ => false
+        }
+    }
+}
+
+impl Test<'_> {
+    fn into_owned(self) -> Test<'static> {
+        match self {
+            Test::Atom(atom) => {
+                let Atom { left, op, right, pattern, test } = *atom;
+                Test::Atom(Box::new(Atom { left: left.into_owned(), op, right: right.into_owned(), pattern, test }))
+            }
+            Test::And(l, r) => Test::And(Box::new(l.into_owned()), Box::new(r.into_owned())),
+            Test::Or(l, r) => Test::Or(Box::new(l.into_owned()), Box::new(r.into_owned())),
+            Test::Not(expr) => Test::Not(Box::new(expr.into_owned())),
+        }
+    }
+
+    fn refers_root(&self) -> bool {
+        match self {
+            Test::Atom(atom) => atom.left.refers_root() || atom.right.refers_root(),
+            Test::And(l, r) | Test::Or(l, r) => l.refers_root() || r.refers_root(),
+            Test::Not(expr) => expr.refers_root(),
+        }
+    }
+}
+
+impl Arg<'_> {
+    fn into_owned(self) -> Arg<'static> {
+        match self {
+            Arg::Literal(v) => Arg::Literal(Cow::Owned(v.into_owned())),
+            Arg::Path { steps, kept, length } => Arg::Path { steps: steps.into_iter().map(Step::into_owned).collect(), kept, length },
+            Arg::Function(name, args) => Arg::Function(Cow::Owned(name.into_owned()), args.into_iter().map(Arg::into_owned).collect()),
+            Arg::Nothing => Arg::Nothing,
+        }
+    }
+
+    fn refers_root(&self) -> bool {
+        match self {
+            Arg::Literal(_) | Arg::Nothing => false,
+            Arg::Path { steps, .. } => steps.iter().any(Step::refers_root),
+            Arg::Function(_, args) => args.iter().any(Arg::refers_root),
+        }
+    }
+}
+
+/// The state of the evaluation over one document the filters share:
+/// the root, the options and the operands kept aside.
+pub(crate) struct Ctx<'a, 'o, N> {
+    root: N,
+    options: Cow<'o, Options>,
+    /// the first error the filters run into, if anyone listens
+    error: Option<&'o OnceCell<String>>,
+    kept: Vec<OnceCell<Found<'a>>>,
+}
+
+impl<'a, 'o, N: Node<'a>> Ctx<'a, 'o, N> {
+    pub(crate) fn new(root: N, plan: &Plan, options: Cow<'o, Options>, error: Option<&'o OnceCell<String>>) -> Self {
+        Ctx { root, options, error, kept: (0..plan.kept).map(|_| OnceCell::new()).collect() }
+    }
+
+    /// checks whether the candidate satisfies the expression evaluating the right side only if it is needed
+    fn test(&self, test: &Test, node: N) -> bool {
+        match test {
+            Test::Atom(atom) => {
+                let (left, right) = (self.arg(&atom.left, node), self.arg(&atom.right, node));
+                compare(&self.options, self.error, &atom.op, atom.pattern.as_ref(), atom.test, left.values(), right.values())
+            }
+            Test::And(l, r) => self.test(l, node) && self.test(r, node),
+            Test::Or(l, r) => self.test(l, node) || self.test(r, node),
+            Test::Not(expr) => !self.test(expr, node),
+        }
+    }
+
+    fn arg<'s>(&'s self, arg: &'s Arg, node: N) -> Found<'s> {
+        match arg {
+            Arg::Literal(v) => Found::Nodes(vec![v.as_ref()]),
+            Arg::Nothing => Found::Nodes(vec![]),
+            Arg::Path { steps, kept: Some(slot), length } =>
+                Found::Nodes(self.kept[*slot].get_or_init(|| self.path(steps, node, *length)).values()),
+            Arg::Path { steps, kept: None, length } => self.path(steps, node, *length),
+            Arg::Function(name, args) => {
+                let args: Vec<Found<'s>> = args.iter().map(|a| self.arg(a, node)).collect();
+                match self.options.functions.get(name.as_ref()) {
+                    Some(f) if functions::signature(name).is_none() => {
+                        let args: Vec<Vec<&Value>> = args.iter().map(Found::values).collect();
+                        Found::Values(f.call(&args).into_iter().collect())
+                    }
+                    _ => functions::call(name, args),
+                }
+            }
+        }
+    }
+
+    fn path(&self, steps: &[Step], node: N, length: bool) -> Found<'a> {
+        let mut walk = Walk::new(node, false);
+        let mut nodes = vec![];
+        while let Some(v) = walk.next(steps, self) {
+            nodes.push(v);
+        }
+        let found = N::found(nodes);
+        if length { Found::Values(lengths(&found.values())) } else { found }
+    }
+}
+
+/// what is left to do with the node
+#[derive(Clone, Copy)]
+enum Pending {
+    /// the node goes through the step
+    Run,
+    /// the node and its descendants are searched for the member the step names, or the step is skipped for them
+    Descent,
+    /// the node is the candidate of the filter
+    Check,
+}
+
+/// The pending work of the traversal.
+enum Frame<'k, N> {
+    /// the node reached from the parent at the given depth by the key, at the position of the step
+    Node { pending: Pending, node: N, pos: usize, depth: usize, key: Option<Key<'k>> },
+    /// the location the traversal goes back to once the jump to the root is over
+    Restore(NormalizedPath),
+}
+
+/// The traversal with the explicit stack: every query, filter operand and adapter walks the documents with it,
+/// so all of them give the nodes in the same order and none of them overflows the stack.
+pub(crate) struct Walk<'k, N> {
+    stack: Vec<Frame<'k, N>>,
+    /// the locations are kept only if anyone needs them
+    tracked: bool,
+    path: NormalizedPath,
+    /// the location the traversal seeks to, the nodes off the way to it are skipped
+    target: Option<NormalizedPath>,
+}
+
+impl<'k, N: Copy> Walk<'k, N> {
+    pub(crate) fn new(node: N, tracked: bool) -> Self {
+        Walk {
+            stack: vec![Frame::Node { pending: Pending::Run, node, pos: 0, depth: 0, key: None }],
+            tracked,
+            path: NormalizedPath::root(),
+            target: None,
+        }
+    }
+
+    /// the location of the node given last relative to the start, the root if the locations are not tracked
+    pub(crate) fn location(&self) -> &NormalizedPath {
+        &self.path
+    }
+
+    fn push(&mut self, pending: Pending, node: N, pos: usize, depth: usize, key: Option<Key<'k>>) {
+        let key = if self.tracked { key } else { None };
+        self.stack.push(Frame::Node { pending, node, pos, depth, key })
+    }
+
+    /// schedules the children so they are popped in their order
+    fn children<'a>(&mut self, pending: Pending, node: N, pos: usize, depth: usize)
+        where N: Node<'a>, 'a: 'k {
+        let start = self.stack.len();
+        node.children(|key, v| self.push(pending, v, pos, depth, Some(key)));
+        self.stack[start..].reverse();
+    }
+
+    fn elements<'a>(&mut self, node: N, idxs: &[usize], pos: usize, depth: usize)
+        where N: Node<'a>, 'a: 'k {
+        let start = self.stack.len();
+        node.elements(idxs, |idx, v| self.push(Pending::Run, v, pos, depth, Some(Key::Index(idx))));
+        self.stack[start..].reverse();
+    }
+
+    /// the next found node
+    pub(crate) fn next<'a, 'o>(&mut self, steps: &'k [Step<'k>], ctx: &Ctx<'a, 'o, N>) -> Option<N>
+        where N: Node<'a>, 'a: 'k {
+        while let Some(frame) = self.stack.pop() {
+            let (pending, node, pos, depth, key) = match frame {
+                Frame::Node { pending, node, pos, depth, key } => (pending, node, pos, depth, key),
+                Frame::Restore(path) => {
+                    self.path = path;
+                    continue;
+                }
+            };
+            if self.tracked {
+                self.path.truncate(depth);
+                if let Some(key) = &key {
+                    self.path.push(PathSegment::from(key));
+                }
+                if matches!(&self.target, Some(target) if !target.starts_with(&self.path)) {
+                    continue;
+                }
+            }
+            let here = depth + usize::from(key.is_some());
+            let pos = match pending {
+                Pending::Run => pos,
+                Pending::Descent => {
+                    self.children(Pending::Descent, node, pos, here);
+                    match &steps[pos] {
+                        Step::Descent(name) => if let Some(v) = node.member(name) {
+                            self.push(Pending::Run, v, pos + 1, here, Some(Key::Name(Cow::Borrowed(name))))
+                        },
+                        Step::Descendants => self.push(Pending::Run, node, pos + 1, depth, key),
+                        _ => (),
+                    }
+                    continue;
+                }
+                Pending::Check => match &steps[pos] {
+                    Step::Filter(test) if ctx.test(test, node) => pos + 1,
+                    _ => continue,
+                },
+            };
+            let step = match steps.get(pos) {
+                Some(step) => step,
+                None => return Some(node),
+            };
+            let next = pos + 1;
+            match step {
+                Step::Root => {
+                    if self.tracked && !self.path.is_root() {
+                        self.stack.push(Frame::Restore(self.path.clone()));
+                    }
+                    self.push(Pending::Run, ctx.root, next, 0, None)
+                }
+                Step::Identity => self.push(Pending::Run, node, next, depth, key),
+                Step::Wildcard => self.children(Pending::Run, node, next, here),
+                Step::Field(name) => if let Some(v) = node.member(name) {
+                    self.push(Pending::Run, v, next, here, Some(Key::Name(Cow::Borrowed(name))))
+                },
+                Step::Descent(_) | Step::Descendants => self.push(Pending::Descent, node, pos, depth, key),
+                Step::Filter(_) => self.children(Pending::Check, node, pos, here),
+                step => self.select(step, node, next, here, ctx),
+            }
+        }
+        None
+    }
+
+    /// skips the found nodes up to the one at the location walking only the nodes on the way to it,
+    /// false if the traversal does not get there. The plan should be [Plan::seekable] and the locations tracked.
+    pub(crate) fn seek<'a, 'o>(&mut self, steps: &'k [Step<'k>], ctx: &Ctx<'a, 'o, N>, target: &NormalizedPath) -> bool
+        where N: Node<'a>, 'a: 'k {
+        self.target = Some(target.clone());
+        let mut found = false;
+        while self.next(steps, ctx).is_some() {
+            if self.path == *target {
+                found = true;
+                break;
+            }
+        }
+        self.target = None;
+        found
+    }
+
+    /// schedules the nodes the selector picks from the node so they are popped in their order,
+    /// the filter tests the children right away
+    fn select<'a, 'o>(&mut self, step: &'k Step<'k>, node: N, next: usize, here: usize, ctx: &Ctx<'a, 'o, N>)
+        where N: Node<'a>, 'a: 'k {
+        match step {
+            Step::Wildcard => self.children(Pending::Run, node, next, here),
+            Step::Field(name) => if let Some(v) = node.member(name) {
+                self.push(Pending::Run, v, next, here, Some(Key::Name(Cow::Borrowed(name))))
+            },
+            Step::Index(idx) => if let Some(idx) = idx.and_then(|idx| position(idx, node.array_len()?)) {
+                self.elements(node, &[idx], next, here)
+            },
+            Step::Slice(slice) => if let Some(len) = node.array_len() {
+                self.elements(node, &slice.indexes(len), next, here)
+            },
+            Step::Keys(names) => {
+                let start = self.stack.len();
+                for name in names.iter() {
+                    if let Some(v) = node.member(name) {
+                        self.push(Pending::Run, v, next, here, Some(Key::Name(Cow::Borrowed(name))))
+                    }
+                }
+                self.stack[start..].reverse();
+            }
+            Step::Indexes(idxs) => if let Some(len) = node.array_len() {
+                let idxs: Vec<usize> = idxs.iter().filter_map(|idx| position(*idx, len)).collect();
+                self.elements(node, &idxs, next, here)
+            },
+            Step::Filter(test) => {
+                let start = self.stack.len();
+                node.children(|key, v| if ctx.test(test, v) {
+                    self.push(Pending::Run, v, next, here, Some(key))
+                });
+                self.stack[start..].reverse();
+            }
+            // the nodes of the last selector are pushed first to be popped last
+            Step::Union(sels) => sels.iter().rev().for_each(|sel| self.select(sel, node, next, here, ctx)),
+            Step::Root | Step::Identity | Step::Descent(_) | Step::Descendants => (),
+        }
+    }
+}
+
+/// The nodes the query finds one by one, the document is walked only as far as the taken nodes need.
+pub(crate) struct Matches<'a, 'k, N> {
+    steps: &'k [Step<'k>],
+    ctx: Ctx<'a, 'k, N>,
+    walk: Walk<'k, N>,
+}
+
+impl<'a: 'k, 'k, N: Node<'a>> Matches<'a, 'k, N> {
+    /// walks the steps from the node, the locations are relative to the node and kept only if they are tracked
+    pub(crate) fn new(steps: &'k [Step<'k>], ctx: Ctx<'a, 'k, N>, node: N, tracked: bool) -> Self {
+        Matches { steps, ctx, walk: Walk::new(node, tracked) }
+    }
+
+    /// the location of the node given last
+    pub(crate) fn location(&self) -> &NormalizedPath {
+        self.walk.location()
+    }
+
+    /// skips the nodes up to the one at the location, see [Walk::seek]
+    pub(crate) fn seek(&mut self, target: &NormalizedPath) -> bool {
+        self.walk.seek(self.steps, &self.ctx, target)
+    }
+}
+
+impl<'a: 'k, 'k, N: Node<'a>> Iterator for Matches<'a, 'k, N> {
+    type Item = N;
+
+    fn next(&mut self) -> Option<N> {
+        self.walk.next(self.steps, &self.ctx)
+    }
+}
+
+/// traverses the document in the same order the query engine does,
+/// passing every found element along with its location to the visitor.
+pub(crate) fn visit<'a, F>(path: &JsonPath, doc: &'a Value, f: F) -> ControlFlow<()>
+    where F: FnMut(&NormalizedPath, &'a Value) -> ControlFlow<()> {
+    visit_at(path, &Env::new(doc), doc, f)
+}
+
+/// traverses the subtree of the document the same way, `@` stands for the node and `$` for the root of the env.
+/// The filters follow the options of the env. The locations are relative to the node.
+pub(crate) fn visit_at<'a, F>(path: &JsonPath, env: &Env<'a>, node: &'a Value, mut f: F) -> ControlFlow<()>
+    where F: FnMut(&NormalizedPath, &'a Value) -> ControlFlow<()> {
+    let plan = Plan::new(path);
+    let ctx = Ctx::new(env.root, &plan, Cow::Borrowed(&env.options), env.error.as_deref());
+    let mut matches = Matches::new(&plan.steps, ctx, node, true);
+    while let Some(v) = matches.next() {
+        f(matches.location(), v)?;
+    }
+    ControlFlow::Continue(())
+}
+
+/// the same as [visit_at] for the visitors taking no locations, so the locations are not built
+pub(crate) fn visit_values_at<'a, F>(path: &JsonPath, env: &Env<'a>, node: &'a Value, mut f: F) -> ControlFlow<()>
+    where F: FnMut(&'a Value) -> ControlFlow<()> {
+    let plan = Plan::new(path);
+    let ctx = Ctx::new(env.root, &plan, Cow::Borrowed(&env.options), env.error.as_deref());
+    for v in Matches::new(&plan.steps, ctx, node, false) {
+        f(v)?;
+    }
+    ControlFlow::Continue(())
+}
+
+/// The value returned by the callback of [JsonPath::find_with]
+/// deciding whether the traversal goes on.
+pub trait VisitFlow {
+    /// what `find_with` returns for the callback
+    type Output;
+    fn flow(self) -> ControlFlow<()>;
+    fn finish(flow: ControlFlow<()>) -> Self::Output;
+}
+
+impl VisitFlow for () {
+    type Output = ();
+    fn flow(self) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+    fn finish(_: ControlFlow<()>) {}
+}
+
+impl VisitFlow for ControlFlow<()> {
+    type Output = ControlFlow<()>;
+    fn flow(self) -> ControlFlow<()> {
+        self
+    }
+    fn finish(flow: ControlFlow<()>) -> ControlFlow<()> {
+        flow
+    }
+}
+
+impl JsonPath<'_> {
+    /// invokes the callback for every element found in the document, along with its location,
+    /// in the order `find` would return them but without collecting the results.
+    /// The callback returning `ControlFlow::Break` stops the traversal immediately,
+    /// then `find_with` returns `ControlFlow::Break` as well.
+    pub fn find_with<'a, F, R>(&self, doc: &'a Value, mut f: F) -> R::Output
+        where F: FnMut(&NormalizedPath, &'a Value) -> R, R: VisitFlow {
+        R::finish(visit(self, doc, |path, v| f(path, v).flow()))
+    }
+
+    /// the same as [JsonPath::find_with] for the callbacks taking no locations, they are not built then
+    pub(crate) fn find_values_with<'a, F, R>(&self, doc: &'a Value, mut f: F) -> R::Output
+        where F: FnMut(&'a Value) -> R, R: VisitFlow {
+        R::finish(visit_values_at(self, &Env::new(doc), doc, |v| f(v).flow()))
+    }
+
+    /// finds the first element in the order `find` gives them and stops the traversal right there
+    pub fn find_first<'a>(&self, doc: &'a Value) -> Option<&'a Value> {
+        let mut first = None;
+        let _ = self.find_values_with(doc, |v| {
+            first = Some(v);
+            ControlFlow::Break(())
+        });
+        first
+    }
+
+    /// checks whether the path finds anything stopping the traversal at the first found element
+    pub fn exists(&self, doc: &Value) -> bool {
+        self.find_first(doc).is_some()
+    }
+
+    /// counts the found elements without collecting them
+    pub fn count(&self, doc: &Value) -> usize {
+        let mut count = 0;
+        self.find_values_with(doc, |_| count += 1);
+        count
+    }
+
+    /// finds the only element failing when there is none or there are several ones,
+    /// the traversal stops at the second element.
+    pub fn find_unique<'a>(&self, doc: &'a Value) -> Result<&'a Value, String> {
+        let mut found: Vec<(NormalizedPath, &'a Value)> = vec![];
+        let _ = self.find_with(doc, |p, v| {
+            found.push((p.clone(), v));
+            if found.len() > 1 { ControlFlow::Break(()) } else { ControlFlow::Continue(()) }
+        });
+        match found.as_slice() {
+            [(_, v)] => Ok(*v),
+            [] => Err(String::from("the path finds nothing")),
+            [(first, _), (second, _), ..] => Err(format!("the path finds more than one element: {} and {}", first, second)),
+        }
+    }
+
+    /// clears the buffer and fills it with the found elements,
+    /// so the same buffer serves many documents without allocating the results anew.
+    pub fn find_into<'a>(&self, doc: &'a Value, buf: &mut Vec<&'a Value>) {
+        buf.clear();
+        self.find_values_with(doc, |v| buf.push(v));
+    }
+
+    /// finds the copies of the elements so they outlive the document
+    pub fn find_owned(&self, doc: &Value) -> Vec<Value> {
+        let mut owned = vec![];
+        self.find_owned_into(doc, &mut owned);
+        owned
+    }
+
+    /// the same as [JsonPath::find_into] but with the copies of the found elements
+    pub fn find_owned_into(&self, doc: &Value, buf: &mut Vec<Value>) {
+        buf.clear();
+        self.find_values_with(doc, |v| buf.push(v.clone()));
+    }
+
+    /// deserializes every found element into `T`, e.g. the user struct,
+    /// stopping at the first element that does not fit and telling where it is
+    /// # Examples:
+    /// ```
+    /// use serde_json::json;
+    /// use jsonpath_rust::JsonPath;
+    ///
+    /// let json = json!({"books":[{"price":8},{"price":12},{"price":"free"}]});
+    /// let path = JsonPath::parse("$.books[*].price").unwrap();
+    /// let err = path.find_as::<u64>(&json).unwrap_err();
+    /// assert!(err.starts_with("the element at $['books'][2]['price'] is not valid"));
+    /// ```
+    pub fn find_as<T: DeserializeOwned>(&self, doc: &Value) -> Result<Vec<T>, String> {
+        let mut res = vec![];
+        let mut error = None;
+        let _ = self.find_with(doc, |p, v| match T::deserialize(v) {
+            Ok(t) => {
+                res.push(t);
+                ControlFlow::Continue(())
+            }
+            Err(e) => {
+                error = Some(format!("the element at {} is not valid: {}", p, e));
+                ControlFlow::Break(())
+            }
+        });
+        error.map_or(Ok(res), Err)
+    }
+
+    /// the same as [JsonPath::find_into] but with the locations of the found elements
+    pub fn find_paths_into(&self, doc: &Value, buf: &mut Vec<NormalizedPath>) {
+        buf.clear();
+        self.find_with(doc, |p, _| buf.push(p.clone()));
+    }
+
+    /// finds the locations of the elements as the RFC 6901 json pointers like `/store/book/2/title`
+    pub fn find_pointers(&self, doc: &Value) -> Vec<String> {
+        let mut pointers = vec![];
+        self.find_with(doc, |p, _| pointers.push(p.to_pointer()));
+        pointers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+use std::ops::ControlFlow;
+    use serde_json::{json, Value};
+    use crate::parser::parser::parse_json_path;
+    use crate::path::json_path_instance;
+
+    fn check(query: &str, doc: &Value) {
+        let path = parse_json_path(query).unwrap();
+        let expected = json_path_instance(&path, doc).find(doc);
+        let mut found = vec![];
+        path.find_with(doc, |p, v| found.push((p.to_string(), v)));
+        assert_eq!(found.iter().map(|(_, v)| *v).collect::<Vec<_>>(), expected, "{}", query);
+        let mut values = vec![];
+        path.find_values_with(doc, |v| values.push(v));
+        assert_eq!(values, expected, "{}", query);
+        for (p, v) in found {
+            let located = parse_json_path(&p).unwrap();
+            assert_eq!(json_path_instance(&located, doc).find(doc), vec![v], "{} -> {}", query, p);
+        }
+    }
+
+    #[test]
+    fn same_as_find_test() {
+        let doc = json!({"store":{"book":[
+            {"category":"reference","author":"Nigel Rees","price":8.95},
+            {"category":"fiction","author":"Evelyn Waugh","price":12.99,"isbn":"0-553"},
+            {"category":"fiction","author":"Herman Melville","price":22.99}],
+            "bicycle":{"color":"red","price":19.95}}});
+        for query in ["$", "$.store.book[*].author", "$..author", "$..price", "$.store.*",
+            "$.store.book[1:]", "$.store.book[-1:]", "$.store.book[0,2].price",
+            "$.store.bicycle['color','price']", "$.store.book[?(@.price < 10)].author",
+            "$..book[?(@.isbn)]", "$.store.*[?(@.price > 20)]", "$.store.book[?(@.price > $.store.bicycle.price)]",
+            "$.store.book[2,0:2,?@.isbn].price", "$.store[*,'bicycle']"] {
+            check(query, &doc)
+        }
+    }
+
+    #[test]
+    fn break_test() {
+        let doc = json!({"records":[{"id":1,"valid":true},{"id":2,"valid":false},{"id":3,"valid":false}]});
+        let path = parse_json_path("$.records[*]").unwrap();
+        let mut visited = 0;
+        let mut invalid = None;
+        let res = path.find_with(&doc, |p, v| {
+            visited += 1;
+            if v["valid"] == json!(false) {
+                invalid = Some(p.clone());
+                return ControlFlow::Break(());
+            }
+            ControlFlow::Continue(())
+        });
+        assert_eq!(res, ControlFlow::Break(()));
+        assert_eq!(visited, 2);
+        assert_eq!(invalid.unwrap().to_string(), "$['records'][1]");
+
+        let res = parse_json_path("$..id").unwrap().find_with(&doc, |_, _| ControlFlow::Continue(()));
+        assert_eq!(res, ControlFlow::Continue(()));
+    }
+
+    #[test]
+    fn paths_test() {
+        let doc = json!({"a":{"b":[{"c":1},{"c":2}]}});
+        let mut paths = vec![];
+        parse_json_path("$..c").unwrap().find_with(&doc, |p, _| paths.push(p.to_string()));
+        assert_eq!(paths, vec!["$['a']['b'][0]['c']", "$['a']['b'][1]['c']"]);
+    }
+
+    #[test]
+    fn first_test() {
+        let doc = json!({"a":[{"b":1},{"b":2}],"c":{"b":3}});
+        let path = |q| parse_json_path(q).unwrap();
+        assert_eq!(path("$..b").find_first(&doc), Some(&json!(1)));
+        assert_eq!(path("$.c.*").find_first(&doc), Some(&json!(3)));
+        assert_eq!(path("$.a[?(@.b > 1)]").find_first(&doc), Some(&json!({"b":2})));
+        assert_eq!(path("$.d").find_first(&doc), None);
+
+        assert_eq!(path("$.a[?(@.b > 1)].b").find_unique(&doc), Ok(&json!(2)));
+        assert_eq!(path("$.d").find_unique(&doc), Err(String::from("the path finds nothing")));
+        assert_eq!(path("$..b").find_unique(&doc),
+                   Err(String::from("the path finds more than one element: $['a'][0]['b'] and $['a'][1]['b']")));
+    }
+
+    #[test]
+    fn exists_test() {
+        let doc = json!({"a":[{"b":1},{"b":null}],"c":{}});
+        let path = |q| parse_json_path(q).unwrap();
+        assert!(path("$..b").exists(&doc));
+        assert!(path("$.a[1].b").exists(&doc));
+        assert!(path("$.c").exists(&doc));
+        assert!(!path("$.c.*").exists(&doc));
+        assert!(!path("$.a[?(@.b > 1)]").exists(&doc));
+        assert!(!path("$.a[5]").exists(&doc));
+    }
+
+    #[test]
+    fn count_test() {
+        let doc = json!({"a":[{"b":1},{"b":2},{"c":3}],"b":{"b":4}});
+        let path = |q| parse_json_path(q).unwrap();
+        for q in ["$..b", "$.a[*]", "$.a[?(@.b)]", "$.a[?(@.b > 5)]", "$.*", "$"] {
+            assert_eq!(path(q).count(&doc), json_path_instance(&path(q), &doc).find(&doc).len(), "{}", q);
+        }
+        assert_eq!(path("$..b").count(&doc), 4);
+    }
+
+    #[test]
+    fn pointers_test() {
+        let doc = json!({"a":{"b":[{"c":1},{"c":2}]},"x/y":{"c~":3}});
+        assert_eq!(parse_json_path("$..c").unwrap().find_pointers(&doc), vec!["/a/b/0/c", "/a/b/1/c"]);
+        assert_eq!(parse_json_path("$['x/y']['c~']").unwrap().find_pointers(&doc), vec!["/x~1y/c~0"]);
+        assert_eq!(parse_json_path("$").unwrap().find_pointers(&doc), vec![""]);
+        assert!(parse_json_path("$.z").unwrap().find_pointers(&doc).is_empty());
+    }
+
+    #[test]
+    fn find_as_test() {
+        use serde_core::Deserialize;
+        #[derive(Debug, PartialEq)]
+        struct Book(String, u64);
+        impl<'de> Deserialize<'de> for Book {
+            fn deserialize<D: serde_core::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+                let v = Value::deserialize(d)?;
+                match (v["title"].as_str(), v["price"].as_u64()) {
+                    (Some(title), Some(price)) => Ok(Book(title.to_string(), price)),
+                    _ => Err(serde_core::de::Error::custom("the book needs the title and the price")),
+                }
+            }
+        }
+        let doc = json!({"books":[{"title":"a","price":8},{"title":"b","price":12},{"title":"c"}]});
+        let books = parse_json_path("$.books[?(@.price)]").unwrap().find_as::<Book>(&doc);
+        assert_eq!(books, Ok(vec![Book(String::from("a"), 8), Book(String::from("b"), 12)]));
+        assert_eq!(parse_json_path("$.books[*]").unwrap().find_as::<Book>(&doc),
+                   Err(String::from("the element at $['books'][2] is not valid: the book needs the title and the price")));
+        assert_eq!(parse_json_path("$.books[*].title").unwrap().find_as::<String>(&doc), Ok(vec![String::from("a"), String::from("b"), String::from("c")]));
+        assert_eq!(parse_json_path("$.none").unwrap().find_as::<u64>(&doc), Ok(vec![]));
+    }
+
+    #[test]
+    fn find_into_test() {
+        let path = parse_json_path("$.a[*]").unwrap();
+        let docs = [json!({"a":[1,2,3]}), json!({"a":[4]}), json!({"b":1})];
+        let mut buf = Vec::with_capacity(8);
+        let mut owned = vec![];
+        let mut paths = vec![];
+        let mut found = vec![];
+        for doc in docs.iter() {
+            path.find_into(doc, &mut buf);
+            path.find_owned_into(doc, &mut owned);
+            path.find_paths_into(doc, &mut paths);
+            assert_eq!(buf.iter().map(|v| (*v).clone()).collect::<Vec<_>>(), owned);
+            assert_eq!(buf.len(), paths.len());
+            found.push(buf.len());
+        }
+        assert_eq!(found, vec![3, 1, 0]);
+        assert_eq!(buf.capacity(), 8);
+        path.find_paths_into(&docs[0], &mut paths);
+        assert_eq!(paths.last().unwrap().to_string(), "$['a'][2]");
+
+        let owned = {
+            let doc = json!({"a":[{"b":1},[2]]});
+            path.find_owned(&doc)
+        };
+        assert_eq!(owned, vec![json!({"b":1}), json!([2])]);
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::BTreeMap;
+use std::io::BufRead;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::thread;
+use serde_json::Value;
+use crate::parser::model::JsonPath;
+use crate::parser::parser::parse_json_path;
+use crate::path::json_path_instance;
+
+/// The result of evaluating the query against one record of the input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    /// the number of the line in the input, starting from 1
+    pub line: usize,
+    /// the found elements or the error if the line is not a valid json
+    pub result: Result<Vec<Value>, String>,
+}
+
+/// The pipeline evaluating the query against every record of the JSON Lines input
+/// on the pool of the worker threads and emitting the results in the order of the input.
+///
+/// At most `capacity` records are read but not yet emitted at any moment,
+/// therefore the memory stays bounded regardless of the size of the input.
+///
+/// # Example
+/// ```
+/// use jsonpath_rust::NdjsonPipeline;
+/// use serde_json::json;
+///
+/// let input = "{\"a\":1}\n{\"a\":2}\n";
+/// let mut found = vec![];
+/// NdjsonPipeline::new("$.a").unwrap()
+///     .workers(2)
+///     .run(input.as_bytes(), |rec| found.push(rec.result.unwrap()))
+///     .unwrap();
+/// assert_eq!(found, vec![vec![json!(1)], vec![json!(2)]]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct NdjsonPipeline {
+    path: JsonPath<'static>,
+    workers: usize,
+    capacity: usize,
+    json5: bool,
+}
+
+impl NdjsonPipeline {
+    /// creates the pipeline for the given query using all available cores
+    pub fn new(path: &str) -> Result<Self, String> {
+        parse_json_path(path).map(NdjsonPipeline::from_path).map_err(|e| e.to_string())
+    }
+
+    /// creates the pipeline for the parsed query using all available cores
+    pub fn from_path(path: JsonPath<'_>) -> Self {
+        let workers = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        NdjsonPipeline { path: path.into_owned(), workers, capacity: workers * 16, json5: false }
+    }
+
+    /// sets the number of the worker threads (at least 1)
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// sets the maximum number of the records in flight (at least 1)
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// reads every line as the JSON5 document
+    #[cfg(feature = "json5")]
+    pub fn json5(mut self) -> Self {
+        self.json5 = true;
+        self
+    }
+
+    /// reads the input line by line, skipping the blank lines, and passes the results to the sink
+    /// in the order of the input. Returns the error if the input can not be read.
+    pub fn run<R, F>(&self, input: R, mut sink: F) -> Result<(), String>
+        where R: BufRead + Send, F: FnMut(Record) {
+        let (job_tx, job_rx) = sync_channel::<(usize, usize, String)>(self.capacity);
+        let (res_tx, res_rx) = sync_channel::<(usize, Record)>(self.capacity);
+        let (token_tx, token_rx) = sync_channel::<()>(self.capacity);
+        for _ in 0..self.capacity {
+            token_tx.send(()).map_err(|e| e.to_string())?;
+        }
+        let job_rx = Arc::new(Mutex::new(job_rx));
+
+        thread::scope(|s| {
+            let reader = s.spawn(move || {
+                let records = input.lines().enumerate().filter(|(_, l)| l.as_ref().map_or(true, |l| !l.trim().is_empty()));
+                for (seq, (idx, text)) in records.enumerate() {
+                    let text = text.map_err(|e| e.to_string())?;
+                    if token_rx.recv().is_err() || job_tx.send((seq, idx + 1, text)).is_err() {
+                        break;
+                    }
+                }
+                Ok(())
+            });
+            for _ in 0..self.workers {
+                let job_rx = Arc::clone(&job_rx);
+                let res_tx = res_tx.clone();
+                s.spawn(move || work(&self.path, self.json5, &job_rx, |rec| res_tx.send(rec).is_ok()));
+            }
+            drop(res_tx);
+
+            // the records arrive out of order and wait here until all the preceding ones are emitted
+            let mut pending = BTreeMap::new();
+            let mut next = 0;
+            for (seq, rec) in res_rx.iter() {
+                pending.insert(seq, rec);
+                while let Some(rec) = pending.remove(&next) {
+                    sink(rec);
+                    next += 1;
+                    let _ = token_tx.send(());
+                }
+            }
+            reader.join().map_err(|_| String::from("the reader thread has panicked"))?
+        })
+    }
+}
+
+fn work<F: FnMut((usize, Record)) -> bool>(path: &JsonPath, json5: bool, jobs: &Mutex<Receiver<(usize, usize, String)>>, mut emit: F) {
+    loop {
+        let job = match jobs.lock() {
+            Ok(rx) => rx.recv(),
+            Err(_) => return,
+        };
+        let (seq, line, text) = match job {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        let result = parse(&text, json5)
+            .map(|v| json_path_instance(path, &v).find(&v).into_iter().cloned().collect());
+        if !emit((seq, Record { line, result })) {
+            return;
+        }
+    }
+}
+
+#[cfg(feature = "json5")]
+fn parse(text: &str, json5: bool) -> Result<Value, String> {
+    if json5 { crate::from_json5(text) } else { serde_json::from_str(text).map_err(|e| e.to_string()) }
+}
+
+#[cfg(not(feature = "json5"))]
+fn parse(text: &str, _json5: bool) -> Result<Value, String> {
+    serde_json::from_str(text).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::pipeline::NdjsonPipeline;
+
+    #[test]
+    fn ordered_test() {
+        let input: String = (0..500).map(|i| format!("{{\"id\":{},\"tags\":[{}]}}\n\n", i, i % 3)).collect();
+        let mut lines = vec![];
+        let mut ids = vec![];
+        NdjsonPipeline::new("$.id").unwrap()
+            .workers(4)
+            .capacity(3)
+            .run(input.as_bytes(), |rec| {
+                lines.push(rec.line);
+                ids.extend(rec.result.unwrap());
+            })
+            .unwrap();
+        assert_eq!(ids, (0..500).map(|i| json!(i)).collect::<Vec<_>>());
+        assert_eq!(lines, (0..500).map(|i| i * 2 + 1).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn invalid_record_test() {
+        let input = "{\"a\":1}\nnot a json\n{\"b\":2}\n";
+        let mut results = vec![];
+        NdjsonPipeline::new("$.a").unwrap()
+            .run(input.as_bytes(), |rec| results.push(rec))
+            .unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].result, Ok(vec![json!(1)]));
+        assert!(results[1].result.is_err());
+        assert_eq!(results[2].result, Ok(vec![]));
+    }
+
+    #[cfg(feature = "json5")]
+    #[test]
+    fn json5_test() {
+        let input = "{a: 1, /* x */}\n{a: 'two',}\n";
+        let mut results = vec![];
+        NdjsonPipeline::new("$.a").unwrap().workers(2).json5().run(input.as_bytes(), |r| results.push(r.result)).unwrap();
+        assert_eq!(results, vec![Ok(vec![json!(1)]), Ok(vec![json!("two")])]);
+    }
+}
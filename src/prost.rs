@@ -0,0 +1,174 @@
+use std::borrow::Cow;
+use prost_types::value::Kind;
+use prost_types::{Struct, Value as Proto};
+use serde_json::{Map, Number, Value};
+use crate::parser::model::JsonPath;
+use crate::path::Options;
+use crate::path::functions::Found;
+use crate::path::normalized::{NormalizedPath, PathSegment};
+use crate::path::visit::{Ctx, Key, Matches, Node, Plan};
+use crate::tree::JsonTree;
+
+/// The value of `google.protobuf.Value`, the value without the kind is taken as null
+/// as well as the numbers that are not finite since the json has no such numbers.
+impl JsonTree for Proto {
+    fn to_json(&self) -> Value {
+        match &self.kind {
+            None | Some(Kind::NullValue(_)) => Value::Null,
+            Some(Kind::NumberValue(n)) => Number::from_f64(*n).map(Value::Number).unwrap_or(Value::Null),
+            Some(Kind::StringValue(s)) => Value::String(s.clone()),
+            Some(Kind::BoolValue(b)) => Value::Bool(*b),
+            Some(Kind::StructValue(s)) => struct_to_json(s),
+            Some(Kind::ListValue(list)) => Value::Array(list.values.iter().map(Proto::to_json).collect()),
+        }
+    }
+
+    fn child(&self, segment: &PathSegment) -> Option<&Self> {
+        match (&self.kind, segment) {
+            (Some(Kind::StructValue(s)), PathSegment::Key(key)) => s.fields.get(key),
+            (Some(Kind::ListValue(list)), PathSegment::Index(idx)) => list.values.get(*idx),
+            _ => None
+        }
+    }
+
+    fn children(&self) -> Vec<(PathSegment, &Self)> {
+        match &self.kind {
+            Some(Kind::StructValue(s)) => s.fields.iter().map(|(key, v)| (PathSegment::Key(key.clone()), v)).collect(),
+            Some(Kind::ListValue(list)) => list.values.iter().enumerate().map(|(idx, v)| (PathSegment::Index(idx), v)).collect(),
+            _ => vec![]
+        }
+    }
+}
+
+fn struct_to_json(s: &Struct) -> Value {
+    Value::Object(s.fields.iter().map(|(k, v)| (k.clone(), v.to_json())).collect::<Map<_, _>>())
+}
+
+/// The node of `google.protobuf.Struct` the traversal moves through,
+/// the root is the struct itself and the nodes below are the values.
+#[derive(Clone, Copy)]
+enum ProtoNode<'a> {
+    Struct(&'a Struct),
+    Value(&'a Proto),
+}
+
+impl<'a> Node<'a> for ProtoNode<'a> {
+    fn member(self, key: &str) -> Option<Self> {
+        match self {
+            ProtoNode::Struct(s) => s.fields.get(key),
+            ProtoNode::Value(v) => v.child(&PathSegment::Key(key.to_string())),
+        }.map(ProtoNode::Value)
+    }
+
+    fn array_len(self) -> Option<usize> {
+        match self {
+            ProtoNode::Value(Proto { kind: Some(Kind::ListValue(list)) }) => Some(list.values.len()),
+            _ => None
+        }
+    }
+
+    fn element(self, idx: usize) -> Option<Self> {
+        match self {
+            ProtoNode::Value(v) => v.child(&PathSegment::Index(idx)).map(ProtoNode::Value),
+            ProtoNode::Struct(_) => None
+        }
+    }
+
+    fn children<F: FnMut(Key<'a>, Self)>(self, mut f: F) {
+        match self {
+            ProtoNode::Struct(s) => s.fields.iter().for_each(|(key, v)| f(Key::Name(Cow::Borrowed(key)), ProtoNode::Value(v))),
+            ProtoNode::Value(v) => v.children().into_iter().for_each(|(seg, v)| f(Key::from(seg), ProtoNode::Value(v))),
+        }
+    }
+
+    fn found(nodes: Vec<Self>) -> Found<'a> {
+        Found::Values(nodes.into_iter().map(|n| match n {
+            ProtoNode::Struct(s) => struct_to_json(s),
+            ProtoNode::Value(v) => v.to_json(),
+        }).collect())
+    }
+}
+
+impl JsonPath<'_> {
+    /// finds the values of `google.protobuf.Struct` along with their locations, the struct is walked in place.
+    /// The struct itself is not a value, so the query selecting the root finds nothing.
+    /// The single `prost_types::Value` is queried by [JsonPath::find_tree].
+    /// # Examples:
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use prost_types::{Struct, Value};
+    /// use jsonpath_rust::JsonPath;
+    ///
+    /// let item = |id: f64| Value::from(BTreeMap::from([(String::from("id"), Value::from(id))]));
+    /// let payload = Struct {
+    ///     fields: BTreeMap::from([(String::from("items"), Value::from(vec![item(1.0), item(2.0)]))]),
+    /// };
+    /// let found = JsonPath::parse("$.items[?(@.id > 1)].id").unwrap().find_struct(&payload);
+    /// assert_eq!(found[0].0.to_string(), "$['items'][1]['id']");
+    /// assert_eq!(found[0].1, &Value::from(2.0));
+    /// ```
+    pub fn find_struct<'a>(&self, doc: &'a Struct) -> Vec<(NormalizedPath, &'a Proto)> {
+        let plan = Plan::new(self);
+        let ctx = Ctx::new(ProtoNode::Struct(doc), &plan, Cow::Owned(Options::default()), None);
+        let mut matches = Matches::new(&plan.steps, ctx, ProtoNode::Struct(doc), true);
+        let mut found = vec![];
+        while let Some(node) = matches.next() {
+            if let ProtoNode::Value(v) = node {
+                found.push((matches.location().clone(), v));
+            }
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use prost_types::value::Kind;
+    use prost_types::{Struct, Value};
+    use serde_json::json;
+    use crate::parser::parser::parse_json_path;
+    use crate::tree::JsonTree;
+
+    fn payload() -> Struct {
+        let item = |id: f64, name: &str| Value::from(BTreeMap::from([
+            (String::from("id"), Value::from(id)),
+            (String::from("name"), Value::from(name)),
+        ]));
+        Struct {
+            fields: BTreeMap::from([
+                (String::from("items"), Value::from(vec![item(1.0, "a"), item(2.0, "b")])),
+                (String::from("ok"), Value::from(true)),
+                (String::from("none"), Value::from(Kind::NullValue(0))),
+                (String::from("limit"), Value::from(2.0)),
+            ]),
+        }
+    }
+
+    #[test]
+    fn find_struct_test() {
+        let doc = payload();
+        let res = parse_json_path("$.items[?(@.id >= $.limit)].name").unwrap().find_struct(&doc);
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].0.to_string(), "$['items'][1]['name']");
+        assert_eq!(res[0].1, &Value::from("b"));
+
+        let res = parse_json_path("$..name").unwrap().find_struct(&doc);
+        assert_eq!(res.iter().map(|(p, _)| p.to_string()).collect::<Vec<_>>(), vec!["$['items'][0]['name']", "$['items'][1]['name']"]);
+        assert_eq!(parse_json_path("$.items[-1].id").unwrap().find_struct(&doc)[0].1, &Value::from(2.0));
+        assert_eq!(parse_json_path("$.none").unwrap().find_struct(&doc)[0].1.kind, Some(Kind::NullValue(0)));
+        assert!(parse_json_path("$[0]").unwrap().find_struct(&doc).is_empty());
+        assert!(parse_json_path("$").unwrap().find_struct(&doc).is_empty());
+        // the values are found in place
+        assert!(std::ptr::eq(parse_json_path("$.ok").unwrap().find_struct(&doc)[0].1, &doc.fields["ok"]));
+    }
+
+    #[test]
+    fn find_tree_value_test() {
+        let doc = Value::from(payload().fields);
+        let res = parse_json_path("$.items[?(@.name == 'a')].id").unwrap().find_tree(&doc);
+        assert_eq!(res[0].1, &Value::from(1.0));
+        assert_eq!(doc.to_json(), json!({"items":[{"id":1.0,"name":"a"},{"id":2.0,"name":"b"}],"ok":true,"none":null,"limit":2.0}));
+        assert_eq!(Value { kind: None }.to_json(), json!(null));
+    }
+}
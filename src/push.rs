@@ -0,0 +1,322 @@
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use serde_json::{Map, Value};
+use crate::parser::model::JsonPath;
+use crate::path::Options;
+use crate::path::normalized::{NormalizedPath, PathSegment};
+use crate::path::visit::{Ctx, Matches, Plan, Step};
+
+/// The evaluator fed with the events of the json document by the caller,
+/// like the ones the SAX parsers produce, and reporting the found elements as soon as they are complete.
+///
+/// The fields, wildcards, single indexes and descents are followed while the events go,
+/// so nothing is kept but the found elements. For the other segments(filters, slices, unions)
+/// the candidate element is collected and the rest of the query is evaluated against it once it ends.
+/// The filters referring to `$` make the evaluator collect the whole document first.
+/// The collected elements go through the same traversal [JsonPath::find_with] does.
+///
+/// The elements are reported in the order they end in the document.
+pub struct PushEvaluator<F: FnMut(NormalizedPath, Value)> {
+    plan: Plan<'static>,
+    on_match: F,
+    frames: Vec<Frame>,
+    captures: Vec<Capture>,
+    path: NormalizedPath,
+    whole: bool,
+    done: bool,
+}
+
+/// the array or object being read
+struct Frame {
+    states: Vec<usize>,
+    array: bool,
+    next: usize,
+    key: Option<String>,
+}
+
+/// the element being collected to evaluate the segments starting from `rest` against it
+struct Capture {
+    path: NormalizedPath,
+    rest: usize,
+    stack: Vec<(Value, Option<String>)>,
+    result: Option<Value>,
+}
+
+impl<F: FnMut(NormalizedPath, Value)> PushEvaluator<F> {
+    pub fn new(path: &JsonPath, on_match: F) -> Self {
+        let plan = Plan::new(path).into_owned();
+        let whole = plan.steps.iter().skip_while(|s| matches!(s, Step::Root)).any(Step::refers_root);
+        PushEvaluator { plan, on_match, frames: vec![], captures: vec![], path: NormalizedPath::root(), whole, done: false }
+    }
+
+    pub fn start_object(&mut self) -> Result<(), String> {
+        self.start(false)
+    }
+
+    pub fn end_object(&mut self) -> Result<(), String> {
+        self.end(false)
+    }
+
+    pub fn start_array(&mut self) -> Result<(), String> {
+        self.start(true)
+    }
+
+    pub fn end_array(&mut self) -> Result<(), String> {
+        self.end(true)
+    }
+
+    /// the name of the next member of the object
+    pub fn key(&mut self, key: &str) -> Result<(), String> {
+        match self.frames.last_mut() {
+            Some(Frame { array: false, key: k @ None, .. }) => {
+                *k = Some(key.to_string());
+                for c in self.captures.iter_mut() {
+                    if let Some((_, k)) = c.stack.last_mut() {
+                        *k = Some(key.to_string());
+                    }
+                }
+                Ok(())
+            }
+            _ => Err(format!("the key '{}' is not expected here", key)),
+        }
+    }
+
+    /// the scalar element: null, boolean, number or string
+    pub fn value<V: Into<Value>>(&mut self, value: V) -> Result<(), String> {
+        let value = value.into();
+        if value.is_array() || value.is_object() {
+            return Err(String::from("the arrays and objects come as the start and end events"));
+        }
+        let states = self.enter()?;
+        self.capture_at(&states);
+        for c in self.captures.iter_mut() {
+            c.push(value.clone());
+        }
+        self.leave();
+        self.complete();
+        Ok(())
+    }
+
+    /// checks the document has been read up to the end
+    pub fn finish(self) -> Result<(), String> {
+        if self.done { Ok(()) } else { Err(String::from("the document is incomplete")) }
+    }
+
+    fn start(&mut self, array: bool) -> Result<(), String> {
+        let states = self.enter()?;
+        let states = self.capture_at(&states);
+        for c in self.captures.iter_mut() {
+            c.stack.push((if array { Value::Array(vec![]) } else { Value::Object(Map::new()) }, None));
+        }
+        self.frames.push(Frame { states, array, next: 0, key: None });
+        Ok(())
+    }
+
+    fn end(&mut self, array: bool) -> Result<(), String> {
+        match self.frames.last() {
+            Some(f) if f.array == array && (array || f.key.is_none()) => (),
+            _ => return Err(format!("the end of the {} is not expected here", if array { "array" } else { "object" })),
+        }
+        self.frames.pop();
+        for c in self.captures.iter_mut() {
+            if let Some((v, _)) = c.stack.pop() {
+                c.push(v);
+            }
+        }
+        self.leave();
+        self.complete();
+        Ok(())
+    }
+
+    /// moves to the next element returning the states it is reached with
+    fn enter(&mut self) -> Result<Vec<usize>, String> {
+        if self.done {
+            return Err(String::from("the document has already ended"));
+        }
+        let (states, seg) = match self.frames.last_mut() {
+            None => return Ok(if self.whole { vec![] } else { self.closure(vec![0]) }),
+            Some(f) if f.array => {
+                f.next += 1;
+                (f.states.clone(), PathSegment::Index(f.next - 1))
+            }
+            Some(f) => match f.key.take() {
+                Some(key) => (f.states.clone(), PathSegment::Key(key)),
+                None => return Err(String::from("the member has no key")),
+            }
+        };
+        let mut next = vec![];
+        for s in states {
+            match (&self.plan.steps[s], &seg) {
+                (Step::Field(k), PathSegment::Key(key)) if k == key => next.push(s + 1),
+                (Step::Wildcard, _) => next.push(s + 1),
+                (Step::Index(Some(i)), PathSegment::Index(idx)) if usize::try_from(*i) == Ok(*idx) => next.push(s + 1),
+                (Step::Descent(k), seg) => {
+                    next.push(s);
+                    if matches!(seg, PathSegment::Key(key) if k == key) {
+                        next.push(s + 1);
+                    }
+                }
+                _ => ()
+            }
+        }
+        self.path.push(seg);
+        Ok(self.closure(next))
+    }
+
+    fn leave(&mut self) {
+        match self.frames.last() {
+            Some(_) => {
+                self.path.pop();
+            }
+            None => self.done = true,
+        }
+    }
+
+    /// skips the segments not moving through the document
+    fn closure(&self, states: Vec<usize>) -> Vec<usize> {
+        let mut res: Vec<usize> = vec![];
+        for mut s in states {
+            while matches!(self.plan.steps.get(s), Some(Step::Root | Step::Identity)) {
+                s += 1;
+            }
+            if !res.contains(&s) {
+                res.push(s);
+            }
+        }
+        res
+    }
+
+    /// starts collecting the current element if it is found or the rest of the query needs it,
+    /// returns the states to follow inside of it
+    fn capture_at(&mut self, states: &[usize]) -> Vec<usize> {
+        if self.frames.is_empty() && self.whole {
+            self.captures.push(Capture { path: NormalizedPath::root(), rest: 0, stack: vec![], result: None });
+        }
+        let mut follow = vec![];
+        for &s in states {
+            match self.plan.steps.get(s) {
+                Some(Step::Field(_) | Step::Wildcard | Step::Descent(_)) => follow.push(s),
+                // the negative indexes need the length of the array, so the array is captured
+                Some(Step::Index(Some(i))) if *i >= 0 => follow.push(s),
+                _ => self.captures.push(Capture { path: self.path.clone(), rest: s, stack: vec![], result: None }),
+            }
+        }
+        follow
+    }
+
+    /// reports the elements the completed captures hold
+    fn complete(&mut self) {
+        while let Some(Capture { result: Some(_), .. }) = self.captures.last() {
+            let Capture { path, rest, result, .. } = self.captures.pop().unwrap();
+            let value = result.unwrap();
+            if rest == self.plan.steps.len() {
+                (self.on_match)(path, value);
+            } else {
+                let ctx = Ctx::new(&value, &self.plan, Cow::Owned(Options::default()), None);
+                let mut matches = Matches::new(&self.plan.steps[rest..], ctx, &value, true);
+                while let Some(v) = matches.next() {
+                    let mut found = path.clone();
+                    matches.location().segments().iter().for_each(|s| found.push(s.clone()));
+                    (self.on_match)(found, v.clone());
+                }
+            }
+        }
+    }
+}
+
+impl Capture {
+    /// puts the completed element into its parent or keeps it as the result
+    fn push(&mut self, value: Value) {
+        match self.stack.last_mut() {
+            Some((Value::Array(elems), _)) => elems.push(value),
+            Some((Value::Object(fields), key)) if key.is_some() => {
+                fields.insert(key.take().unwrap(), value);
+            }
+            _ => self.result = Some(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use crate::parser::parser::parse_json_path;
+    use crate::path::normalized::NormalizedPath;
+    use crate::push::PushEvaluator;
+
+    fn feed<F: FnMut(NormalizedPath, Value)>(ev: &mut PushEvaluator<F>, v: &Value) {
+        match v {
+            Value::Array(elems) => {
+                ev.start_array().unwrap();
+                elems.iter().for_each(|e| feed(ev, e));
+                ev.end_array().unwrap();
+            }
+            Value::Object(fields) => {
+                ev.start_object().unwrap();
+                for (k, v) in fields {
+                    ev.key(k).unwrap();
+                    feed(ev, v);
+                }
+                ev.end_object().unwrap();
+            }
+            v => ev.value(v.clone()).unwrap(),
+        }
+    }
+
+    #[test]
+    fn same_as_find_test() {
+        let doc = json!({"store": {"book": [
+            {"category": "reference", "author": "Nigel Rees", "price": 8.95},
+            {"category": "fiction", "author": "Evelyn Waugh", "price": 12.99, "isbn": "0-553"},
+            {"category": "fiction", "author": "Herman Melville", "price": 22.99, "a": {"a": 1}}],
+            "bicycle": {"color": "red", "price": 19.95}}, "expensive": 10, "a": [1]});
+        for query in ["$", "$.store.book[*].author", "$..author", "$..price", "$.store.*", "$..a",
+            "$.store.book[1:]", "$.store.book[-1:].price", "$.store.book[0,2].price", "$.store['bicycle','book']",
+            "$.store.book[?(@.price < 10)].author", "$..book[?(@.isbn)]", "$.store.book[?(@.price > $.expensive)].price",
+            "$.store.book[1].author", "$.missing", "$..book[*]..a"] {
+            let path = parse_json_path(query).unwrap();
+            let mut expected: Vec<(String, Value)> = path.find_nodes(&doc).into_iter().map(|(p, v)| (p.to_string(), v.clone())).collect();
+            let mut found = vec![];
+            let mut ev = PushEvaluator::new(&path, |p, v| found.push((p.to_string(), v)));
+            feed(&mut ev, &doc);
+            ev.finish().unwrap();
+            expected.sort_by(|a, b| a.0.cmp(&b.0));
+            found.sort_by(|a, b| a.0.cmp(&b.0));
+            assert_eq!(found, expected, "{}", query);
+        }
+    }
+
+    #[test]
+    fn incremental_test() {
+        let path = parse_json_path("$.items[*].id").unwrap();
+        let mut found = vec![];
+        {
+            let mut ev = PushEvaluator::new(&path, |p, v| found.push((p.to_string(), v)));
+            ev.start_object().unwrap();
+            ev.key("items").unwrap();
+            ev.start_array().unwrap();
+            ev.start_object().unwrap();
+            ev.key("id").unwrap();
+            ev.value(1).unwrap();
+            ev.end_object().unwrap();
+            ev.start_object().unwrap();
+            ev.key("id").unwrap();
+            ev.value("x").unwrap();
+            assert!(ev.end_array().is_err());
+            assert!(ev.value(2).is_err());
+            ev.end_object().unwrap();
+            ev.end_array().unwrap();
+            assert!(ev.value(()).is_err());
+            ev.end_object().unwrap();
+            assert!(ev.start_array().is_err());
+            ev.finish().unwrap();
+        }
+        assert_eq!(found, vec![(String::from("$['items'][0]['id']"), json!(1)), (String::from("$['items'][1]['id']"), json!("x"))]);
+
+        let mut ev = PushEvaluator::new(&path, |_, _| ());
+        assert!(ev.key("a").is_err());
+        ev.start_array().unwrap();
+        assert!(ev.value(json!([])).is_err());
+        assert!(ev.finish().is_err());
+    }
+}
@@ -0,0 +1,513 @@
+use std::borrow::Cow;
+use std::ops::Range;
+use serde_json::Value;
+use crate::parser::model::JsonPath;
+use crate::parser::parser::unescape;
+use crate::path::Options;
+use crate::path::functions::Found;
+use crate::path::normalized::NormalizedPath;
+use crate::path::visit::{Ctx, Key, Matches, Node, Plan};
+
+/// The json type of the element found in the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawKind {
+    Null,
+    Bool,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+/// The element found in the json text without parsing it into [Value].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawMatch<'a> {
+    /// the text of the element
+    pub raw: &'a str,
+    /// the byte range of the element in the input
+    pub span: Range<usize>,
+    /// the location of the element in the document
+    pub path: NormalizedPath,
+}
+
+impl<'a> RawMatch<'a> {
+    pub fn kind(&self) -> RawKind {
+        kind(self.raw.as_bytes()[0])
+    }
+
+    /// the content of the string borrowed from the input unless it has the escape sequences
+    pub fn as_str(&self) -> Option<Cow<'a, str>> {
+        match self.kind() {
+            RawKind::String => Some(unquote(self.raw)),
+            _ => None
+        }
+    }
+
+    /// the text of the number as it is in the input
+    pub fn as_number(&self) -> Option<&'a str> {
+        match self.kind() {
+            RawKind::Number => Some(self.raw),
+            _ => None
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.raw {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.raw == "null"
+    }
+
+    /// the line and the column(in chars) the element starts at in the input, both counting from 1
+    pub fn line_col(&self, text: &str) -> (usize, usize) {
+        let before = &text[..self.span.start];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        (before.matches('\n').count() + 1, before[line_start..].chars().count() + 1)
+    }
+
+    /// parses the element into [Value]
+    pub fn to_value(&self) -> Result<Value, String> {
+        serde_json::from_str(self.raw).map_err(|e| e.to_string())
+    }
+}
+
+fn kind(first: u8) -> RawKind {
+    match first {
+        b'n' => RawKind::Null,
+        b't' | b'f' => RawKind::Bool,
+        b'"' => RawKind::String,
+        b'[' => RawKind::Array,
+        b'{' => RawKind::Object,
+        _ => RawKind::Number,
+    }
+}
+
+fn unquote(raw: &str) -> Cow<'_, str> {
+    let inner = &raw[1..raw.len() - 1];
+    if inner.contains('\\') { Cow::Owned(unescape(inner)) } else { Cow::Borrowed(inner) }
+}
+
+/// how deep the arrays and objects can be nested
+const MAX_DEPTH: usize = 128;
+
+/// The scanner skipping through the json text to find the boundaries of the elements.
+struct Scanner<'a> {
+    text: &'a str,
+}
+
+type Span = Range<usize>;
+
+impl<'a> Scanner<'a> {
+    fn byte(&self, pos: usize) -> Option<u8> {
+        self.text.as_bytes().get(pos).copied()
+    }
+
+    fn ws(&self, mut pos: usize) -> usize {
+        while matches!(self.byte(pos), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            pos += 1;
+        }
+        pos
+    }
+
+    fn err<T>(&self, msg: &str, pos: usize) -> Result<T, String> {
+        Err(format!("{} at {}", msg, pos))
+    }
+
+    fn expect(&self, pos: usize, b: u8) -> Result<usize, String> {
+        if self.byte(pos) == Some(b) { Ok(pos + 1) } else { self.err(&format!("expected '{}'", b as char), pos) }
+    }
+
+    /// the span of the element starting at the position
+    fn value(&self, pos: usize) -> Result<Span, String> {
+        self.nested(pos, 0)
+    }
+
+    /// the span of the element nested into the given number of arrays and objects,
+    /// the elements deeper than [MAX_DEPTH] are refused like serde_json does
+    fn nested(&self, pos: usize, depth: usize) -> Result<Span, String> {
+        if depth > MAX_DEPTH {
+            return self.err("recursion limit exceeded", pos);
+        }
+        let end = match self.byte(pos) {
+            Some(b'"') => self.string(pos)?,
+            Some(b'[') => self.compound(pos, b']', |s, p| s.nested(p, depth + 1).map(|v| v.end))?,
+            Some(b'{') => self.compound(pos, b'}', |s, p| {
+                let key = s.string(p)?;
+                let p = s.expect(s.ws(key), b':')?;
+                s.nested(s.ws(p), depth + 1).map(|v| v.end)
+            })?,
+            Some(b't') => self.literal(pos, "true")?,
+            Some(b'f') => self.literal(pos, "false")?,
+            Some(b'n') => self.literal(pos, "null")?,
+            Some(b'-' | b'0'..=b'9') => self.number(pos)?,
+            _ => return self.err("expected a value", pos),
+        };
+        Ok(pos..end)
+    }
+
+    /// the end of the number following the json grammar: no leading zeros and the digits after `.` and `e`
+    fn number(&self, pos: usize) -> Result<usize, String> {
+        let digits = |from: usize| (from..).find(|p| !matches!(self.byte(*p), Some(b'0'..=b'9'))).unwrap_or(from);
+        let mut p = if self.byte(pos) == Some(b'-') { pos + 1 } else { pos };
+        p = match self.byte(p) {
+            Some(b'0') => p + 1,
+            Some(b'1'..=b'9') => digits(p),
+            _ => return self.err("invalid number", p),
+        };
+        if self.byte(p) == Some(b'.') {
+            p = match digits(p + 1) {
+                end if end > p + 1 => end,
+                end => return self.err("invalid number", end),
+            };
+        }
+        if matches!(self.byte(p), Some(b'e' | b'E')) {
+            let sign = if matches!(self.byte(p + 1), Some(b'+' | b'-')) { p + 2 } else { p + 1 };
+            p = match digits(sign) {
+                end if end > sign => end,
+                end => return self.err("invalid number", end),
+            };
+        }
+        match self.byte(p) {
+            Some(b'0'..=b'9' | b'.' | b'e' | b'E' | b'+' | b'-') => self.err("invalid number", p),
+            _ => Ok(p),
+        }
+    }
+
+    fn string(&self, pos: usize) -> Result<usize, String> {
+        let mut p = self.expect(pos, b'"')?;
+        loop {
+            match self.byte(p) {
+                Some(b'"') => return Ok(p + 1),
+                Some(b'\\') => p = self.escape(p + 1)?,
+                Some(0x00..=0x1f) => return self.err("control character (\\u0000-\\u001F) found while parsing a string", p),
+                Some(_) => p += 1,
+                None => return self.err("EOF while parsing a string", pos),
+            }
+        }
+    }
+
+    /// the end of the escape sequence after the backslash, the surrogates of `\\uXXXX` have to come in pairs
+    fn escape(&self, pos: usize) -> Result<usize, String> {
+        match self.byte(pos) {
+            Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => Ok(pos + 1),
+            Some(b'u') => match self.hex(pos + 1)? {
+                0xDC00..=0xDFFF => self.err("lone leading surrogate in hex escape", pos + 1),
+                0xD800..=0xDBFF => {
+                    if self.byte(pos + 5) != Some(b'\\') || self.byte(pos + 6) != Some(b'u') {
+                        return self.err("unexpected end of hex escape", pos + 5);
+                    }
+                    match self.hex(pos + 7)? {
+                        0xDC00..=0xDFFF => Ok(pos + 11),
+                        _ => self.err("lone leading surrogate in hex escape", pos + 7),
+                    }
+                }
+                _ => Ok(pos + 5),
+            },
+            None => self.err("EOF while parsing a string", pos),
+            Some(_) => self.err("invalid escape", pos),
+        }
+    }
+
+    /// the code unit of the four hex digits at the position
+    fn hex(&self, pos: usize) -> Result<u16, String> {
+        match self.text.get(pos..pos + 4).filter(|d| d.bytes().all(|b| b.is_ascii_hexdigit())) {
+            Some(digits) => Ok(u16::from_str_radix(digits, 16).unwrap_or_default()),
+            None => self.err("invalid escape", pos),
+        }
+    }
+
+    fn literal(&self, pos: usize, lit: &str) -> Result<usize, String> {
+        if self.text[pos..].starts_with(lit) { Ok(pos + lit.len()) } else { self.err("expected a literal", pos) }
+    }
+
+    fn compound<F>(&self, pos: usize, close: u8, mut item: F) -> Result<usize, String>
+        where F: FnMut(&Self, usize) -> Result<usize, String> {
+        let mut p = self.ws(pos + 1);
+        if self.byte(p) == Some(close) {
+            return Ok(p + 1);
+        }
+        loop {
+            p = self.ws(item(self, p)?);
+            match self.byte(p) {
+                Some(b',') => p = self.ws(p + 1),
+                Some(b) if b == close => return Ok(p + 1),
+                _ => return self.err(&format!("expected ',' or '{}'", close as char), p),
+            }
+        }
+    }
+
+    fn elements(&self, node: &Span) -> Result<Vec<Span>, String> {
+        let mut elems = vec![];
+        self.compound(node.start, b']', |s, p| {
+            let v = s.value(p)?;
+            let end = v.end;
+            elems.push(v);
+            Ok(end)
+        })?;
+        Ok(elems)
+    }
+
+    /// the members of the object ordered by the keys like [Value] keeps them, the last duplicate wins
+    fn members(&self, node: &Span) -> Result<Vec<(Cow<'a, str>, Span)>, String> {
+        let mut members = vec![];
+        self.compound(node.start, b'}', |s, p| {
+            let key_end = s.string(p)?;
+            let key = unquote(&s.text[p..key_end]);
+            let v = s.value(s.ws(s.expect(s.ws(key_end), b':')?))?;
+            let end = v.end;
+            members.push((key, v));
+            Ok(end)
+        })?;
+        members.reverse();
+        members.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+        members.dedup_by(|(k1, _), (k2, _)| k1 == k2);
+        Ok(members)
+    }
+
+    fn member(&self, node: &Span, key: &str) -> Result<Option<Span>, String> {
+        match self.byte(node.start) {
+            Some(b'{') => Ok(self.members(node)?.into_iter().find(|(k, _)| k == key).map(|(_, v)| v)),
+            _ => Ok(None)
+        }
+    }
+
+    fn parse(&self, span: &Span) -> Result<Value, String> {
+        serde_json::from_str(&self.text[span.clone()]).map_err(|e| e.to_string())
+    }
+}
+
+/// The element of the json text the traversal moves through, the text is checked to be valid json beforehand.
+#[derive(Clone, Copy)]
+struct RawNode<'a> {
+    text: &'a str,
+    start: usize,
+    end: usize,
+}
+
+impl<'a> RawNode<'a> {
+    fn scanner(self) -> Scanner<'a> {
+        Scanner { text: self.text }
+    }
+
+    fn span(self) -> Span {
+        self.start..self.end
+    }
+
+    fn at(self, span: Span) -> Self {
+        RawNode { text: self.text, start: span.start, end: span.end }
+    }
+
+    fn spans(self) -> Option<Vec<Span>> {
+        match self.scanner().byte(self.start) {
+            Some(b'[') => self.scanner().elements(&self.span()).ok(),
+            _ => None
+        }
+    }
+}
+
+impl<'a> Node<'a> for RawNode<'a> {
+    fn member(self, key: &str) -> Option<Self> {
+        self.scanner().member(&self.span(), key).ok().flatten().map(|v| self.at(v))
+    }
+
+    fn array_len(self) -> Option<usize> {
+        self.spans().map(|elems| elems.len())
+    }
+
+    fn element(self, idx: usize) -> Option<Self> {
+        self.spans()?.into_iter().nth(idx).map(|v| self.at(v))
+    }
+
+    fn children<F: FnMut(Key<'a>, Self)>(self, mut f: F) {
+        let s = self.scanner();
+        match s.byte(self.start) {
+            Some(b'[') => s.elements(&self.span()).unwrap_or_default().into_iter().enumerate()
+                .for_each(|(idx, v)| f(Key::Index(idx), self.at(v))),
+            Some(b'{') => s.members(&self.span()).unwrap_or_default().into_iter()
+                .for_each(|(key, v)| f(Key::Name(key), self.at(v))),
+            _ => ()
+        }
+    }
+
+    /// the array is scanned once for all the positions
+    fn elements<F: FnMut(usize, Self)>(self, idxs: &[usize], mut f: F) {
+        if let Some(elems) = self.spans() {
+            for &idx in idxs {
+                if let Some(v) = elems.get(idx) {
+                    f(idx, self.at(v.clone()))
+                }
+            }
+        }
+    }
+
+    /// the operands of the filters are parsed, only they are
+    fn found(nodes: Vec<Self>) -> Found<'a> {
+        Found::Values(nodes.into_iter().filter_map(|v| v.scanner().parse(&v.span()).ok()).collect())
+    }
+}
+
+impl<'p> JsonPath<'p> {
+    /// evaluates the query straight over the json text without building [Value] for it
+    /// and returns the slices of the text the found elements occupy.
+    /// Only the elements the operands of the filters find are parsed.
+    pub fn find_raw<'a>(&'p self, text: &'a str) -> Result<Vec<RawMatch<'a>>, String> {
+        let scanner = Scanner { text };
+        let start = scanner.ws(0);
+        let root = scanner.value(start)?;
+        let rest = scanner.ws(root.end);
+        if rest != text.len() {
+            return scanner.err("unexpected trailing characters", rest);
+        }
+        let root = RawNode { text, start: root.start, end: root.end };
+        let plan = Plan::new(self);
+        let ctx = Ctx::new(root, &plan, Cow::Owned(Options::default()), None);
+        let mut matches = Matches::new(&plan.steps, ctx, root, true);
+        let mut found = vec![];
+        while let Some(v) = matches.next() {
+            found.push(RawMatch { raw: &text[v.span()], span: v.span(), path: matches.location().clone() });
+        }
+        Ok(found)
+    }
+
+    /// rewrites the found elements right in the text leaving the rest of it(spaces, order of the members) intact.
+    /// The function gets every found element and returns the new text for it or None to keep it.
+    /// The elements found inside of the replaced ones are skipped.
+    pub fn replace_raw<F>(&'p self, text: &str, mut f: F) -> Result<String, String>
+        where F: FnMut(&RawMatch) -> Option<String> {
+        let mut found = self.find_raw(text)?;
+        found.sort_by_key(|m| (m.span.start, std::cmp::Reverse(m.span.end)));
+        let mut res = String::with_capacity(text.len());
+        let mut pos = 0;
+        for m in found.iter() {
+            if m.span.start < pos {
+                continue;
+            }
+            if let Some(new) = f(m) {
+                res.push_str(&text[pos..m.span.start]);
+                res.push_str(&new);
+                pos = m.span.end;
+            }
+        }
+        res.push_str(&text[pos..]);
+        Ok(res)
+    }
+
+    /// the same as [JsonPath::find_raw] for the bytes being utf-8 json text
+    pub fn find_raw_bytes<'a>(&'p self, bytes: &'a [u8]) -> Result<Vec<RawMatch<'a>>, String> {
+        std::str::from_utf8(bytes).map_err(|e| e.to_string()).and_then(|text| self.find_raw(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use serde_json::Value;
+    use crate::parser::parser::parse_json_path;
+    use crate::path::json_path_instance;
+    use crate::raw::RawKind;
+
+    const DOC: &str = r#" {"store": {"book": [
+        {"category": "reference", "author": "Nigel \"N\" Rees", "title": "Sayings", "price": 8.95},
+        {"category": "fiction", "author": "Evelyn Waugh", "title": "Sword", "price": 12.99, "isbn": "0-553"},
+        {"category": "fiction", "author": "Herman Melville", "title": "Moby Dick", "price": 22.99e0}],
+        "bicycle": {"color": "red", "price": 19.95, "tags": [true, null, -1]}}, "expensive": 10} "#;
+
+    #[test]
+    fn same_as_find_test() {
+        let doc: Value = serde_json::from_str(DOC).unwrap();
+        for query in ["$", "$.store.book[*].author", "$..author", "$..price", "$.store.*",
+            "$.store.book[1:]", "$.store.book[-1:]", "$.store.book[0,2].price", "$.store.bicycle.tags[*]",
+            "$.store.bicycle['color','price']", "$.store.book[?(@.price < 10)].author",
+            "$..book[?(@.isbn)].title", "$.store.book[?(@.price > $.expensive)].price"] {
+            let path = parse_json_path(query).unwrap();
+            let expected = json_path_instance(&path, &doc).find(&doc);
+            let found: Vec<Value> = path.find_raw(DOC).unwrap().iter().map(|m| m.to_value().unwrap()).collect();
+            assert_eq!(found.iter().collect::<Vec<_>>(), expected, "{}", query);
+        }
+    }
+
+    #[test]
+    fn borrowed_test() {
+        let path = parse_json_path("$.store.book[*].author").unwrap();
+        let found = path.find_raw(DOC).unwrap();
+        assert!(matches!(found[1].as_str(), Some(Cow::Borrowed("Evelyn Waugh"))));
+        assert_eq!(found[0].as_str(), Some(Cow::Owned(String::from("Nigel \"N\" Rees"))));
+        assert_eq!(&DOC[found[1].span.clone()], "\"Evelyn Waugh\"");
+
+        let found = parse_json_path("$..price").unwrap().find_raw_bytes(DOC.as_bytes()).unwrap();
+        assert_eq!(found.iter().map(|m| m.as_number().unwrap()).collect::<Vec<_>>(), vec!["19.95", "8.95", "12.99", "22.99e0"]);
+
+        let found = parse_json_path("$.store.bicycle.tags[*]").unwrap().find_raw(DOC).unwrap();
+        assert_eq!(found.iter().map(|m| m.kind()).collect::<Vec<_>>(), vec![RawKind::Bool, RawKind::Null, RawKind::Number]);
+        assert_eq!(found[0].as_bool(), Some(true));
+        assert!(found[1].is_null());
+    }
+
+    #[test]
+    fn malformed_test() {
+        let path = parse_json_path("$.a").unwrap();
+        assert!(path.find_raw(r#"{"a":1"#).is_err());
+        assert!(path.find_raw(r#"{"a":1} x"#).is_err());
+        assert!(path.find_raw(r#"{"a" 1}"#).is_err());
+        assert!(path.find_raw(r#"{"a":"1}"#).is_err());
+    }
+
+    #[test]
+    fn invalid_json_test() {
+        let path = parse_json_path("$.a").unwrap();
+        let error = |text: &str| path.find_raw(text).unwrap_err();
+        assert_eq!(error(r#"{"a":"\x"}"#), "invalid escape at 7");
+        assert_eq!(error(r#"{"a":"\u12"}"#), "invalid escape at 8");
+        assert_eq!(error(r#"{"a":"\ud83d"}"#), "unexpected end of hex escape at 12");
+        assert_eq!(error(r#"{"a":"\ud83d\u0041"}"#), "lone leading surrogate in hex escape at 14");
+        assert_eq!(error(r#"{"a":"\ude00"}"#), "lone leading surrogate in hex escape at 8");
+        assert_eq!(error("{\"a\":\"\t\"}"), "control character (\\u0000-\\u001F) found while parsing a string at 6");
+        assert_eq!(error(r#"{"\x":1}"#), "invalid escape at 3");
+        for number in ["01", "-01", "1.", "1.e5", "1e", "1e+", "-", "1..2", "1e5.5", "1-"] {
+            assert!(error(&format!(r#"{{"a":{}}}"#, number)).starts_with("invalid number"), "{}", number);
+        }
+        // the raw scanner accepts what serde_json does
+        for text in [r#""\ud83d\ude00\"\\\/\b\f\n\r\t""#, "-0", "0.5e-3", "1E+2", "-12.5E10", "[0,-0.0]"] {
+            assert!(serde_json::from_str::<serde_json::Value>(text).is_ok(), "{}", text);
+            assert!(parse_json_path("$").unwrap().find_raw(text).is_ok(), "{}", text);
+        }
+        assert_eq!(path.find_raw(r#"{"a":"\ud83d\ude00"}"#).unwrap()[0].as_str().as_deref(), Some("\u{1F600}"));
+    }
+
+    #[test]
+    fn spans_test() {
+        let text = "{\n  \"user\": {\"name\": \"Ann\", \"card\": \"4111\"},\n  \"cards\": [\"5500\", \"3400\"]\n}";
+        let path = parse_json_path("$..card").unwrap();
+        let found = path.find_raw(text).unwrap();
+        assert_eq!(found[0].path.to_string(), "$['user']['card']");
+        assert_eq!(found[0].line_col(text), (2, 35));
+
+        let found = parse_json_path("$.cards[*]").unwrap().find_raw(text).unwrap();
+        assert_eq!(found.iter().map(|m| (m.path.to_string(), m.line_col(text))).collect::<Vec<_>>(),
+                   vec![(String::from("$['cards'][0]"), (3, 13)), (String::from("$['cards'][1]"), (3, 21))]);
+
+        let redacted = parse_json_path("$.user.card").unwrap().replace_raw(text, |_| Some(String::from("\"****\""))).unwrap();
+        assert_eq!(redacted, text.replace("4111", "****"));
+
+        let replaced = parse_json_path("$[*]").unwrap()
+            .replace_raw(r#"{"a": {"b": 1}, "c": [1, 2]}"#, |m| if m.kind() == RawKind::Array { Some(String::from("[]")) } else { None }).unwrap();
+        assert_eq!(replaced, r#"{"a": {"b": 1}, "c": []}"#);
+    }
+
+    #[test]
+    fn depth_test() {
+        let path = parse_json_path("$..a").unwrap();
+        let deep = format!("{}{}", "[".repeat(200_000), "]".repeat(200_000));
+        assert!(path.find_raw(&deep).unwrap_err().starts_with("recursion limit exceeded"));
+        let deep = format!("{}{}", "{\"a\":".repeat(200_000), "}".repeat(200_000));
+        assert!(path.find_raw_bytes(deep.as_bytes()).unwrap_err().starts_with("recursion limit exceeded"));
+
+        let nested = format!("{}1{}", "[".repeat(128), "]".repeat(128));
+        assert_eq!(parse_json_path("$..[0]").unwrap().find_raw(&nested).unwrap().len(), 128);
+    }
+}
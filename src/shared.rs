@@ -0,0 +1,276 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use serde_json::{Number, Value};
+use crate::parser::model::JsonPath;
+use crate::path::definite::location;
+use crate::path::normalized::{NormalizedPath, PathSegment};
+use crate::tree::JsonTree;
+
+/// The json document whose arrays and objects are shared between its versions.
+///
+/// Cloning the document is cheap and the updates copy only the nodes on the way
+/// to the changed element, so the new version shares all the untouched subtrees with the old one.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SharedValue {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(Arc<str>),
+    Array(Arc<Vec<SharedValue>>),
+    Object(Arc<BTreeMap<String, SharedValue>>),
+}
+
+impl SharedValue {
+    /// finds the element at the location
+    pub fn get(&self, path: &NormalizedPath) -> Option<&SharedValue> {
+        self.get_at(path)
+    }
+
+    /// returns the new version of the document with the element at the location replaced.
+    /// The missing member is added if its parent exists.
+    pub fn set<V: Into<SharedValue>>(&self, path: &NormalizedPath, value: V) -> Result<SharedValue, String> {
+        let mut doc = self.clone();
+        set(&mut doc, path.segments(), value.into()).map_err(|e| format!("{} at {}", e, path))?;
+        Ok(doc)
+    }
+
+    /// checks whether both are the same shared array or object rather than just equal
+    pub fn ptr_eq(&self, other: &SharedValue) -> bool {
+        match (self, other) {
+            (SharedValue::Array(a), SharedValue::Array(b)) => Arc::ptr_eq(a, b),
+            (SharedValue::Object(a), SharedValue::Object(b)) => Arc::ptr_eq(a, b),
+            _ => false
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        Value::from(self)
+    }
+}
+
+fn set(node: &mut SharedValue, segments: &[PathSegment], value: SharedValue) -> Result<(), String> {
+    let (seg, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => {
+            *node = value;
+            return Ok(());
+        }
+    };
+    match (node, seg) {
+        (SharedValue::Object(fields), PathSegment::Key(key)) => {
+            let fields = Arc::make_mut(fields);
+            match fields.get_mut(key) {
+                Some(child) => set(child, rest, value),
+                None if rest.is_empty() => {
+                    fields.insert(key.clone(), value);
+                    Ok(())
+                }
+                None => Err(format!("the member '{}' is not found", key)),
+            }
+        }
+        (SharedValue::Array(elems), PathSegment::Index(idx)) => match Arc::make_mut(elems).get_mut(*idx) {
+            Some(child) => set(child, rest, value),
+            None => Err(format!("the index {} is out of bounds", idx)),
+        },
+        (_, seg) => Err(format!("the segment {} can not be applied to the scalar", seg)),
+    }
+}
+
+/// The document producing the new versions of itself that share the unchanged parts with the old ones,
+/// like [SharedValue] or the documents built on the persistent maps and vectors.
+/// [JsonPath::update_shared] works with any of them, the queries walk the document itself as [JsonTree].
+pub trait PersistentValue: JsonTree + Clone {
+    /// returns the new version with the element at the location replaced
+    fn set_at(&self, path: &NormalizedPath, value: Self) -> Result<Self, String>;
+}
+
+impl PersistentValue for SharedValue {
+    fn set_at(&self, path: &NormalizedPath, value: Self) -> Result<Self, String> {
+        self.set(path, value)
+    }
+}
+
+impl JsonTree for SharedValue {
+    fn to_json(&self) -> Value {
+        self.to_value()
+    }
+
+    fn child(&self, segment: &PathSegment) -> Option<&Self> {
+        match (self, segment) {
+            (SharedValue::Object(fields), PathSegment::Key(key)) => fields.get(key),
+            (SharedValue::Array(elems), PathSegment::Index(idx)) => elems.get(*idx),
+            _ => None
+        }
+    }
+
+    fn children(&self) -> Vec<(PathSegment, &Self)> {
+        match self {
+            SharedValue::Array(elems) => elems.iter().enumerate().map(|(idx, v)| (PathSegment::Index(idx), v)).collect(),
+            SharedValue::Object(fields) => fields.iter().map(|(key, v)| (PathSegment::Key(key.clone()), v)).collect(),
+            _ => vec![]
+        }
+    }
+}
+
+impl From<Value> for SharedValue {
+    fn from(v: Value) -> Self {
+        match v {
+            Value::Null => SharedValue::Null,
+            Value::Bool(b) => SharedValue::Bool(b),
+            Value::Number(n) => SharedValue::Number(n),
+            Value::String(s) => SharedValue::String(Arc::from(s)),
+            Value::Array(elems) => SharedValue::Array(Arc::new(elems.into_iter().map(SharedValue::from).collect())),
+            Value::Object(fields) => SharedValue::Object(Arc::new(
+                fields.into_iter().map(|(k, v)| (k, SharedValue::from(v))).collect())),
+        }
+    }
+}
+
+impl From<&SharedValue> for Value {
+    fn from(v: &SharedValue) -> Self {
+        match v {
+            SharedValue::Null => Value::Null,
+            SharedValue::Bool(b) => Value::Bool(*b),
+            SharedValue::Number(n) => Value::Number(n.clone()),
+            SharedValue::String(s) => Value::String(s.to_string()),
+            SharedValue::Array(elems) => Value::Array(elems.iter().map(Value::from).collect()),
+            SharedValue::Object(fields) => Value::Object(fields.iter().map(|(k, v)| (k.clone(), Value::from(v))).collect()),
+        }
+    }
+}
+
+impl JsonPath<'_> {
+    /// replaces every found element with what the function gives for it
+    /// and returns the new version of the document sharing the untouched subtrees with the old one.
+    /// The function gets the element of the document itself, so nothing is copied into [Value].
+    ///
+    /// The definite paths(see [JsonPath::is_definite]) go straight to the element,
+    /// the other queries walk the document(see [JsonPath::find_tree]) to find the locations.
+    /// Like [JsonPath::replace_with] the elements found inside the other found ones are replaced first,
+    /// so the function gets the enclosing element with them replaced already.
+    pub fn update_shared<D, F>(&self, doc: &D, mut f: F) -> D
+        where D: PersistentValue, F: FnMut(&D) -> D {
+        let mut path = NormalizedPath::root();
+        let mut locations = if location(self, &mut path).is_some() {
+            vec![path]
+        } else {
+            self.find_tree(doc).into_iter().map(|(p, _)| p).collect()
+        };
+        locations.sort();
+        locations.dedup();
+
+        let mut res = doc.clone();
+        for path in locations.iter().rev() {
+            // the definite path may point out to nothing
+            if let Some(old) = res.get_at(path) {
+                if let Ok(updated) = res.set_at(path, f(old)) {
+                    res = updated;
+                }
+            }
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::parser::parser::parse_json_path;
+    use crate::path::normalized::NormalizedPath;
+    use crate::path::normalized::PathSegment;
+    use crate::shared::{SharedValue, PersistentValue};
+    use crate::tree::JsonTree;
+
+    fn path(p: &str) -> NormalizedPath {
+        p.parse().unwrap()
+    }
+
+    #[test]
+    fn set_test() {
+        let v1 = SharedValue::from(json!({"a":{"b":1},"c":[1,2,{"d":3}]}));
+        let v2 = v1.set(&path("$['c'][2]['d']"), json!(4)).unwrap();
+
+        assert_eq!(v1.to_value(), json!({"a":{"b":1},"c":[1,2,{"d":3}]}));
+        assert_eq!(v2.to_value(), json!({"a":{"b":1},"c":[1,2,{"d":4}]}));
+        assert!(v1.get(&path("$['a']")).unwrap().ptr_eq(v2.get(&path("$['a']")).unwrap()));
+        assert!(!v1.get(&path("$['c']")).unwrap().ptr_eq(v2.get(&path("$['c']")).unwrap()));
+
+        let v3 = v2.set(&path("$['a']['e']"), json!("new")).unwrap();
+        assert_eq!(v3.get(&path("$['a']['e']")), Some(&SharedValue::from(json!("new"))));
+        assert!(v3.get(&path("$['c']")).unwrap().ptr_eq(v2.get(&path("$['c']")).unwrap()));
+
+        assert!(v1.set(&path("$['x']['y']"), json!(1)).is_err());
+        assert!(v1.set(&path("$['c'][5]"), json!(1)).is_err());
+        assert!(v1.set(&path("$['a']['b']['c']"), json!(1)).is_err());
+    }
+
+    #[test]
+    fn find_tree_test() {
+        let doc = SharedValue::from(json!({"users":[{"name":"a","age":30},{"name":"b","age":40}],"min":35}));
+        let found = parse_json_path("$.users[?(@.age > $.min)]").unwrap().find_tree(&doc);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0.to_string(), "$['users'][1]");
+        assert!(std::ptr::eq(found[0].1, doc.get(&path("$['users'][1]")).unwrap()));
+
+        let names: Vec<String> = parse_json_path("$..name").unwrap().find_tree(&doc).into_iter().map(|(p, _)| p.to_string()).collect();
+        assert_eq!(names, vec!["$['users'][0]['name']", "$['users'][1]['name']"]);
+    }
+
+    /// the document keeping the whole history of the updates of the flat object
+    #[derive(Clone, Debug, PartialEq)]
+    struct Journal(Vec<(String, serde_json::Value)>, Option<serde_json::Value>);
+
+    impl PersistentValue for Journal {
+        fn set_at(&self, path: &NormalizedPath, value: Self) -> Result<Self, String> {
+            let mut next = self.clone();
+            next.0.push((path.to_string(), value.to_json()));
+            Ok(next)
+        }
+    }
+
+    impl JsonTree for Journal {
+        fn to_json(&self) -> serde_json::Value {
+            self.1.clone().unwrap_or(json!(null))
+        }
+
+        fn child(&self, _: &PathSegment) -> Option<&Self> {
+            None
+        }
+
+        fn children(&self) -> Vec<(PathSegment, &Self)> {
+            vec![]
+        }
+    }
+
+    #[test]
+    fn custom_backend_test() {
+        let doc = Journal(vec![], Some(json!({"a":1})));
+        let next = parse_json_path("$").unwrap().update_shared(&doc, |_| Journal(vec![], Some(json!(2))));
+        assert_eq!(next.0, vec![(String::from("$"), json!(2))]);
+    }
+
+    #[test]
+    fn update_shared_test() {
+        let v1 = SharedValue::from(json!({"users":[{"name":"a","age":30},{"name":"b","age":40}],"meta":{"v":1}}));
+
+        let v2 = parse_json_path("$.users[?(@.age > 35)].age").unwrap().update_shared(&v1, |v| match v {
+            SharedValue::Number(n) => SharedValue::from(json!(n.as_i64().unwrap() + 1)),
+            v => v.clone(),
+        });
+        assert_eq!(v2.to_value(), json!({"users":[{"name":"a","age":30},{"name":"b","age":41}],"meta":{"v":1}}));
+        assert!(v1.get(&path("$['meta']")).unwrap().ptr_eq(v2.get(&path("$['meta']")).unwrap()));
+        assert!(v1.get(&path("$['users'][0]")).unwrap().ptr_eq(v2.get(&path("$['users'][0]")).unwrap()));
+
+        let v3 = parse_json_path("$.meta.v").unwrap().update_shared(&v2, |_| SharedValue::from(json!(2)));
+        assert_eq!(v3.get(&path("$['meta']['v']")), Some(&SharedValue::from(json!(2))));
+        assert!(v3.get(&path("$['users']")).unwrap().ptr_eq(v2.get(&path("$['users']")).unwrap()));
+
+        let v4 = parse_json_path("$.missing").unwrap().update_shared(&v3, |_| SharedValue::from(json!(0)));
+        assert!(v4.ptr_eq(&v3));
+
+        let nested = SharedValue::from(json!({"a":{"a":1},"b":[2]}));
+        let wrapped = parse_json_path("$..a").unwrap().update_shared(&nested, |v| SharedValue::from(json!({"w": v.to_value()})));
+        assert_eq!(wrapped.to_value(), json!({"a":{"w":{"a":{"w":1}}},"b":[2]}));
+        assert!(wrapped.get(&path("$['b']")).unwrap().ptr_eq(nested.get(&path("$['b']")).unwrap()));
+    }
+}
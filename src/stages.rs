@@ -0,0 +1,295 @@
+use std::ops::ControlFlow;
+use std::str::FromStr;
+use serde_json::Value;
+use crate::parser::model::{cmp_values, JsonPath, SharedPath};
+use crate::path::json::same;
+use crate::path::visit::visit_values_at;
+use crate::path::{Env, Options};
+
+/// The processing step applied to the found elements one after another.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stage {
+    /// replaces every element with the elements the path finds in it, `@` is the element and `$` is the document
+    Map(SharedPath),
+    /// orders the elements by the first element the path finds in each of them, the elements without it go first.
+    /// The strings are ordered by the collation of the options if there is any(see [StagedQuery::find_with_options])
+    SortBy(SharedPath),
+    /// keeps the first elements
+    Limit(usize),
+    /// drops the first elements
+    Skip(usize),
+    Reverse,
+    /// drops the elements equal to the ones before them
+    Unique,
+}
+
+/// The query followed by the stages post-processing the found elements,
+/// like `$.store.book[*] | sort_by(@.price) | map(@.title) | limit(2)`.
+/// The stages only rearrange the elements of the document, so the results still borrow from it.
+/// # Examples:
+/// ```
+/// use serde_json::json;
+/// use jsonpath_rust::StagedQuery;
+///
+/// let doc = json!({"book":[{"title":"a","price":10},{"title":"b","price":5},{"title":"c","price":7}]});
+/// let query: StagedQuery = "$.book[*] | sort_by(@.price) | map(@.title) | limit(2)".parse().unwrap();
+/// assert_eq!(query.find(&doc), vec![&json!("b"), &json!("c")]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct StagedQuery {
+    path: SharedPath,
+    stages: Vec<Stage>,
+}
+
+impl StagedQuery {
+    pub fn new<P: Into<SharedPath>>(path: P) -> Self {
+        StagedQuery { path: path.into(), stages: vec![] }
+    }
+
+    pub fn path(&self) -> &JsonPath<'static> {
+        &self.path
+    }
+
+    pub fn stages(&self) -> &[Stage] {
+        &self.stages
+    }
+
+    pub fn stage(mut self, stage: Stage) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    pub fn map<P: Into<SharedPath>>(self, path: P) -> Self {
+        self.stage(Stage::Map(path.into()))
+    }
+
+    pub fn sort_by<P: Into<SharedPath>>(self, path: P) -> Self {
+        self.stage(Stage::SortBy(path.into()))
+    }
+
+    pub fn limit(self, n: usize) -> Self {
+        self.stage(Stage::Limit(n))
+    }
+
+    pub fn skip(self, n: usize) -> Self {
+        self.stage(Stage::Skip(n))
+    }
+
+    pub fn reverse(self) -> Self {
+        self.stage(Stage::Reverse)
+    }
+
+    pub fn unique(self) -> Self {
+        self.stage(Stage::Unique)
+    }
+
+    /// finds the elements and runs them through the stages
+    pub fn find<'a>(&self, doc: &'a Value) -> Vec<&'a Value> {
+        let mut res = vec![];
+        self.path.find_values_with(doc, |v| res.push(v));
+        let options = Options::new();
+        self.stages.iter().fold(res, |nodes, stage| stage.apply(doc, nodes, &options))
+    }
+
+    /// finds the elements with the options(see [JsonPath::find_with_options]) and runs them through the stages,
+    /// `sort_by` orders the strings by the collation of the options
+    pub fn find_with_options<'a>(&'a self, doc: &'a Value, options: &Options) -> Result<Vec<&'a Value>, String> {
+        let res = self.path.find_with_options(doc, options)?;
+        Ok(self.stages.iter().fold(res, |nodes, stage| stage.apply(doc, nodes, options)))
+    }
+}
+
+impl Stage {
+    fn apply<'a>(&self, doc: &'a Value, mut nodes: Vec<&'a Value>, options: &Options) -> Vec<&'a Value> {
+        let env = Env { root: doc, options: options.clone(), error: None };
+        match self {
+            Stage::Map(path) => {
+                let mut res = vec![];
+                for node in nodes {
+                    let _ = visit_values_at(path, &env, node, |v| {
+                        res.push(v);
+                        ControlFlow::Continue(())
+                    });
+                }
+                res
+            }
+            Stage::SortBy(path) => {
+                let mut keyed: Vec<(Option<&Value>, &Value)> = nodes.into_iter().map(|node| {
+                    let mut key = None;
+                    let _ = visit_values_at(path, &env, node, |v| {
+                        key = Some(v);
+                        ControlFlow::Break(())
+                    });
+                    (key, node)
+                }).collect();
+                keyed.sort_by(|(l, _), (r, _)| match (l, r, &options.collation) {
+                    (Some(Value::String(l)), Some(Value::String(r)), Some(collation)) => collation.compare(l, r),
+                    (Some(l), Some(r), _) => cmp_values(l, r),
+                    (l, r, _) => l.is_some().cmp(&r.is_some()),
+                });
+                keyed.into_iter().map(|(_, node)| node).collect()
+            }
+            Stage::Limit(n) => {
+                nodes.truncate(*n);
+                nodes
+            }
+            Stage::Skip(n) => nodes.into_iter().skip(*n).collect(),
+            Stage::Reverse => {
+                nodes.reverse();
+                nodes
+            }
+            Stage::Unique => {
+                let mut res: Vec<&Value> = vec![];
+                for node in nodes {
+                    if !res.iter().any(|v| same(v, node)) {
+                        res.push(node)
+                    }
+                }
+                res
+            }
+        }
+    }
+}
+
+impl FromStr for StagedQuery {
+    type Err = String;
+
+    /// parses the query followed by the stages separated by `|`:
+    /// `map(path)`, `sort_by(path)`, `limit(n)`, `skip(n)`, `reverse` and `unique`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = split(s).into_iter();
+        let path = parts.next().map(str::trim).unwrap_or_default();
+        let mut query = StagedQuery::new(SharedPath::parse(path)?);
+        for part in parts {
+            query = query.stage(part.parse()?);
+        }
+        Ok(query)
+    }
+}
+
+impl FromStr for Stage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (name, arg) = match s.find('(') {
+            Some(open) if s.ends_with(')') => (s[..open].trim(), Some(s[open + 1..s.len() - 1].trim())),
+            Some(_) => return Err(format!("the stage '{}' misses the closing parenthesis", s)),
+            None => (s, None),
+        };
+        let count = |arg: Option<&str>| arg.and_then(|a| a.parse::<usize>().ok())
+            .ok_or_else(|| format!("the stage '{}' expects the number", name));
+        let path = |arg: Option<&str>| arg.filter(|a| !a.is_empty())
+            .ok_or_else(|| format!("the stage '{}' expects the path", name))
+            .and_then(SharedPath::parse);
+        match (name, arg) {
+            ("map", arg) => path(arg).map(Stage::Map),
+            ("sort_by", arg) => path(arg).map(Stage::SortBy),
+            ("limit", arg) => count(arg).map(Stage::Limit),
+            ("skip", arg) => count(arg).map(Stage::Skip),
+            ("reverse", None) | ("reverse", Some("")) => Ok(Stage::Reverse),
+            ("unique", None) | ("unique", Some("")) => Ok(Stage::Unique),
+            _ => Err(format!("the stage '{}' is unknown", s)),
+        }
+    }
+}
+
+/// splits the text by `|` standing outside of the quotes, brackets and parentheses
+fn split(text: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let (mut depth, mut quote, mut escaped, mut start) = (0, None, false, 0);
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(q), c) if q == c => quote = None,
+            (Some(_), _) => (),
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '[' | '(') => depth += 1,
+            (None, ']' | ')') => depth -= 1,
+            (None, '|') if depth == 0 => {
+                parts.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => ()
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::parser::model::SharedPath;
+    use crate::path::Options;
+    use crate::stages::{split, Stage, StagedQuery};
+
+    fn doc() -> serde_json::Value {
+        json!({"max": 9, "book":[
+            {"title":"a","price":10,"tags":["x","y"]},
+            {"title":"b","price":5,"tags":["y"]},
+            {"title":"c","tags":["z"]},
+            {"title":"d","price":7}
+        ]})
+    }
+
+    #[test]
+    fn stages_test() {
+        let doc = doc();
+        let find = |q: &str| q.parse::<StagedQuery>().unwrap().find(&doc).into_iter().cloned().collect::<Vec<_>>();
+
+        assert_eq!(find("$.book[*] | sort_by(@.price) | map(@.title)"), vec![json!("c"), json!("b"), json!("d"), json!("a")]);
+        assert_eq!(find("$.book[*].tags | map(@[*]) | unique"), vec![json!("x"), json!("y"), json!("z")]);
+        assert_eq!(find("$.book[*].title | reverse | skip(1) | limit(2)"), vec![json!("c"), json!("b")]);
+        assert_eq!(find("$.book[?(@.price)] | map($.max)"), vec![json!(9), json!(9), json!(9)]);
+        assert_eq!(find("$.book[?(@.title == 'a')] | limit(0)"), Vec::<serde_json::Value>::new());
+    }
+
+    #[test]
+    fn builder_test() {
+        let doc = doc();
+        let query = StagedQuery::new(SharedPath::parse("$.book[*]").unwrap())
+            .sort_by(SharedPath::parse("@.title").unwrap())
+            .reverse()
+            .map(SharedPath::parse("@.price").unwrap())
+            .limit(2);
+        assert_eq!(query.find(&doc), vec![&json!(7), &json!(5)]);
+        assert_eq!(query.stages()[1], Stage::Reverse);
+    }
+
+    #[test]
+    fn collation_test() {
+        let doc = json!({"names":[{"n":"bob"},{"n":"Alice"},{"n":"carl"},{}]});
+        let query: StagedQuery = "$.names[*] | sort_by(@.n) | map(@.n)".parse().unwrap();
+        assert_eq!(query.find(&doc), vec![&json!("Alice"), &json!("bob"), &json!("carl")]);
+        let folded = Options::new().collation(|l: &str, r: &str| l.to_lowercase().cmp(&r.to_lowercase()));
+        assert_eq!(query.find_with_options(&doc, &folded), Ok(vec![&json!("Alice"), &json!("bob"), &json!("carl")]));
+
+        let doc = json!({"names":["bob","Carl","alice"]});
+        let query: StagedQuery = "$.names[*] | sort_by(@)".parse().unwrap();
+        assert_eq!(query.find(&doc), vec![&json!("Carl"), &json!("alice"), &json!("bob")]);
+        assert_eq!(query.find_with_options(&doc, &folded), Ok(vec![&json!("alice"), &json!("bob"), &json!("Carl")]));
+    }
+
+    #[cfg(feature = "icu")]
+    #[test]
+    fn locale_test() {
+        let doc = json!(["zebra", "Äpfel", "apple", "Zoo", "öl"]);
+        let query: StagedQuery = "$[*] | sort_by(@)".parse().unwrap();
+        let options = Options::new().locale("de").unwrap();
+        assert_eq!(query.find_with_options(&doc, &options).unwrap(),
+                   vec![&json!("Äpfel"), &json!("apple"), &json!("öl"), &json!("zebra"), &json!("Zoo")]);
+        assert!(Options::new().locale("not a locale").is_err());
+    }
+
+    #[test]
+    fn parse_test() {
+        assert_eq!(split("$.a[?(@.b == 'x|y')] | map(@.c) | limit(1)"), vec!["$.a[?(@.b == 'x|y')] ", " map(@.c) ", " limit(1)"]);
+        assert!("$.a | limit(x)".parse::<StagedQuery>().is_err());
+        assert!("$.a | map()".parse::<StagedQuery>().is_err());
+        assert!("$.a | group_by(@.b)".parse::<StagedQuery>().is_err());
+        assert!("$.a | map(@.b".parse::<StagedQuery>().is_err());
+        assert_eq!("$.a | unique | reverse()".parse::<StagedQuery>().unwrap().stages(), &[Stage::Unique, Stage::Reverse]);
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use serde_json::Value;
+use crate::parser::model::JsonPath;
+use crate::path::json_path_instance;
+
+struct Shared {
+    docs: VecDeque<Value>,
+    capacity: usize,
+    sender_alive: bool,
+    stream_alive: bool,
+    sender_waker: Option<Waker>,
+    stream_waker: Option<Waker>,
+}
+
+impl Shared {
+    fn wake_sender(&mut self) {
+        if let Some(w) = self.sender_waker.take() { w.wake() }
+    }
+    fn wake_stream(&mut self) {
+        if let Some(w) = self.stream_waker.take() { w.wake() }
+    }
+}
+
+fn lock(shared: &Mutex<Shared>) -> std::sync::MutexGuard<'_, Shared> {
+    shared.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The asynchronous stream of the elements found by the query in the documents
+/// coming from the paired [`MatchSender`].
+///
+/// The documents are buffered up to the given capacity and evaluated only when
+/// the consumer asks for the next match, so the slow consumer suspends the producer.
+/// With the feature `futures-core` the stream is `futures_core::Stream`, without it
+/// `next` gives the future for `while let Some(m) = stream.next().await`.
+pub struct MatchStream {
+    path: JsonPath<'static>,
+    shared: Arc<Mutex<Shared>>,
+    matches: VecDeque<Value>,
+}
+
+/// The producing half of the [`MatchStream`].
+pub struct MatchSender {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl MatchStream {
+    /// creates the stream for the query and the sender feeding it with documents.
+    /// At most `capacity` (at least 1) documents wait for evaluation at any moment.
+    pub fn channel(path: JsonPath<'_>, capacity: usize) -> (MatchSender, MatchStream) {
+        let shared = Arc::new(Mutex::new(Shared {
+            docs: VecDeque::new(),
+            capacity: capacity.max(1),
+            sender_alive: true,
+            stream_alive: true,
+            sender_waker: None,
+            stream_waker: None,
+        }));
+        (MatchSender { shared: Arc::clone(&shared) }, MatchStream { path: path.into_owned(), shared, matches: VecDeque::new() })
+    }
+
+    /// attempts to pull out the next match, registering the current task for wakeup if none is ready.
+    /// Returns `Ready(None)` once the sender is dropped and all the documents are processed.
+    pub fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Value>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(m) = this.matches.pop_front() {
+                return Poll::Ready(Some(m));
+            }
+            let doc = {
+                let mut shared = lock(&this.shared);
+                match shared.docs.pop_front() {
+                    Some(doc) => {
+                        shared.wake_sender();
+                        doc
+                    }
+                    None if !shared.sender_alive => return Poll::Ready(None),
+                    None => {
+                        shared.stream_waker = Some(cx.waker().clone());
+                        return Poll::Pending;
+                    }
+                }
+            };
+            this.matches.extend(json_path_instance(&this.path, &doc).find(&doc).into_iter().cloned());
+        }
+    }
+
+    /// returns the future resolving to the next match
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Next<'_> {
+        Next { stream: self }
+    }
+}
+
+#[cfg(feature = "futures-core")]
+impl futures_core::Stream for MatchStream {
+    type Item = Value;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Value>> {
+        MatchStream::poll_next(self, cx)
+    }
+}
+
+impl Drop for MatchStream {
+    fn drop(&mut self) {
+        let mut shared = lock(&self.shared);
+        shared.stream_alive = false;
+        shared.wake_sender();
+    }
+}
+
+impl MatchSender {
+    /// returns the future that waits for the room in the buffer and puts the document there.
+    /// The future resolves to the error holding the document if the stream is dropped.
+    pub fn send(&self, doc: Value) -> SendDoc<'_> {
+        SendDoc { sender: self, doc: Some(doc) }
+    }
+
+    /// puts the document into the buffer if there is room for it, otherwise gives it back
+    pub fn try_send(&self, doc: Value) -> Result<(), Value> {
+        let mut shared = lock(&self.shared);
+        if !shared.stream_alive || shared.docs.len() >= shared.capacity {
+            return Err(doc);
+        }
+        shared.docs.push_back(doc);
+        shared.wake_stream();
+        Ok(())
+    }
+}
+
+impl Drop for MatchSender {
+    fn drop(&mut self) {
+        let mut shared = lock(&self.shared);
+        shared.sender_alive = false;
+        shared.wake_stream();
+    }
+}
+
+/// The future returned by [`MatchStream::next`].
+pub struct Next<'a> {
+    stream: &'a mut MatchStream,
+}
+
+impl<'a> Future for Next<'a> {
+    type Output = Option<Value>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut *self.stream).poll_next(cx)
+    }
+}
+
+/// The future returned by [`MatchSender::send`].
+pub struct SendDoc<'a> {
+    sender: &'a MatchSender,
+    doc: Option<Value>,
+}
+
+impl<'a> Future for SendDoc<'a> {
+    type Output = Result<(), Value>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let doc = match self.doc.take() {
+            Some(doc) => doc,
+            None => return Poll::Ready(Ok(())),
+        };
+        let mut shared = lock(&self.sender.shared);
+        if !shared.stream_alive {
+            return Poll::Ready(Err(doc));
+        }
+        if shared.docs.len() < shared.capacity {
+            shared.docs.push_back(doc);
+            shared.wake_stream();
+            return Poll::Ready(Ok(()));
+        }
+        shared.sender_waker = Some(cx.waker().clone());
+        drop(shared);
+        self.doc = Some(doc);
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::{pin, Pin};
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::thread::{self, Thread};
+    use serde_json::json;
+    use crate::parser::parser::parse_json_path;
+    use crate::stream::MatchStream;
+
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark()
+        }
+    }
+
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut fut = pin!(fut);
+        loop {
+            match fut.as_mut().poll(&mut cx) {
+                Poll::Ready(v) => return v,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn stream_test() {
+        let (tx, mut stream) = MatchStream::channel(parse_json_path("$.items[*]").unwrap(), 2);
+        let producer = thread::spawn(move || {
+            for i in 0..50 {
+                block_on(tx.send(json!({"items":[i, i]}))).unwrap();
+            }
+        });
+        let mut found = vec![];
+        while let Some(m) = block_on(stream.next()) {
+            found.push(m);
+        }
+        producer.join().unwrap();
+        assert_eq!(found, (0..50).flat_map(|i| vec![json!(i), json!(i)]).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn backpressure_test() {
+        let (tx, mut stream) = MatchStream::channel(parse_json_path("$.a").unwrap(), 1);
+        let mut cx = Context::from_waker(Waker::noop());
+
+        assert_eq!(tx.try_send(json!({"a":1})), Ok(()));
+        assert_eq!(tx.try_send(json!({"a":2})), Err(json!({"a":2})));
+        let mut send = Box::pin(tx.send(json!({"a":2})));
+        assert!(send.as_mut().poll(&mut cx).is_pending());
+
+        assert_eq!(Pin::new(&mut stream).poll_next(&mut cx), Poll::Ready(Some(json!(1))));
+        assert_eq!(send.as_mut().poll(&mut cx), Poll::Ready(Ok(())));
+        drop(send);
+        assert_eq!(Pin::new(&mut stream).poll_next(&mut cx), Poll::Ready(Some(json!(2))));
+        assert!(Pin::new(&mut stream).poll_next(&mut cx).is_pending());
+
+        drop(tx);
+        assert_eq!(Pin::new(&mut stream).poll_next(&mut cx), Poll::Ready(None));
+    }
+
+    #[cfg(feature = "futures-core")]
+    #[test]
+    fn futures_stream_test() {
+        fn poll<S: futures_core::Stream + Unpin>(stream: &mut S, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+            Pin::new(stream).poll_next(cx)
+        }
+        let (tx, mut stream) = MatchStream::channel(parse_json_path("$.a").unwrap(), 1);
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(poll(&mut stream, &mut cx).is_pending());
+        assert_eq!(tx.try_send(json!({"a":1})), Ok(()));
+        drop(tx);
+        assert_eq!(poll(&mut stream, &mut cx), Poll::Ready(Some(json!(1))));
+        assert_eq!(poll(&mut stream, &mut cx), Poll::Ready(None));
+    }
+
+    #[test]
+    fn dropped_stream_test() {
+        let (tx, stream) = MatchStream::channel(parse_json_path("$.a").unwrap(), 1);
+        drop(stream);
+        assert_eq!(block_on(tx.send(json!(1))), Err(json!(1)));
+    }
+}
@@ -0,0 +1,183 @@
+use std::borrow::Cow;
+use serde_json::Value;
+use crate::parser::model::JsonPath;
+use crate::path::Options;
+use crate::path::functions::Found;
+use crate::path::normalized::{NormalizedPath, PathSegment};
+use crate::path::visit::{Ctx, Key, Matches, Node, Plan};
+
+/// The read-only json-like document of the other library,
+/// like `prost_types::Struct` carrying the dynamic payloads of gRPC or the tree of a config format.
+///
+/// [JsonPath::find_tree] walks the document itself and gives back the references to its nodes,
+/// only the nodes the operands of the filters find are turned into [Value] to be compared.
+pub trait JsonTree {
+    /// the node as [Value], the filters compare the nodes their operands find this way
+    fn to_json(&self) -> Value;
+    /// the member of the object or the element of the array
+    fn child(&self, segment: &PathSegment) -> Option<&Self>;
+    /// the members of the object along with their names or the elements of the array along with their indexes,
+    /// in their order; nothing for the other nodes
+    fn children(&self) -> Vec<(PathSegment, &Self)>;
+
+    /// finds the node at the location
+    fn get_at(&self, path: &NormalizedPath) -> Option<&Self> {
+        path.segments().iter().try_fold(self, |node, seg| node.child(seg))
+    }
+}
+
+impl JsonTree for Value {
+    fn to_json(&self) -> Value {
+        self.clone()
+    }
+
+    fn child(&self, segment: &PathSegment) -> Option<&Self> {
+        match (self, segment) {
+            (Value::Object(fields), PathSegment::Key(key)) => fields.get(key),
+            (Value::Array(elems), PathSegment::Index(idx)) => elems.get(*idx),
+            _ => None
+        }
+    }
+
+    fn children(&self) -> Vec<(PathSegment, &Self)> {
+        match self {
+            Value::Array(elems) => elems.iter().enumerate().map(|(idx, v)| (PathSegment::Index(idx), v)).collect(),
+            Value::Object(fields) => fields.iter().map(|(key, v)| (PathSegment::Key(key.clone()), v)).collect(),
+            _ => vec![]
+        }
+    }
+}
+
+/// The node of [JsonTree] the traversal moves through.
+struct Tree<'a, T>(&'a T);
+
+impl<T> Clone for Tree<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Tree<'_, T> {}
+
+impl<'a, T: JsonTree> Node<'a> for Tree<'a, T> {
+    fn member(self, key: &str) -> Option<Self> {
+        self.0.child(&PathSegment::Key(key.to_string())).map(Tree)
+    }
+
+    /// the tree tells the array by the indexes of its children, so the empty array passes for the scalar
+    fn array_len(self) -> Option<usize> {
+        let children = self.0.children();
+        match children.first() {
+            Some((PathSegment::Index(_), _)) => Some(children.len()),
+            _ => None
+        }
+    }
+
+    fn element(self, idx: usize) -> Option<Self> {
+        self.0.child(&PathSegment::Index(idx)).map(Tree)
+    }
+
+    fn children<F: FnMut(Key<'a>, Self)>(self, mut f: F) {
+        self.0.children().into_iter().for_each(|(seg, v)| f(Key::from(seg), Tree(v)))
+    }
+
+    fn found(nodes: Vec<Self>) -> Found<'a> {
+        Found::Values(nodes.into_iter().map(|v| v.0.to_json()).collect())
+    }
+}
+
+impl JsonPath<'_> {
+    /// finds the nodes of the json-like document along with their locations
+    pub fn find_tree<'a, D: JsonTree>(&self, doc: &'a D) -> Vec<(NormalizedPath, &'a D)> {
+        self.find_tree_with_options(doc, &Options::default())
+    }
+
+    /// the same as [JsonPath::find_tree] but the filters follow the settings
+    pub fn find_tree_with_options<'a, D: JsonTree>(&self, doc: &'a D, options: &Options) -> Vec<(NormalizedPath, &'a D)> {
+        let plan = Plan::new(self);
+        let ctx = Ctx::new(Tree(doc), &plan, Cow::Borrowed(options), None);
+        let mut matches = Matches::new(&plan.steps, ctx, Tree(doc), true);
+        let mut found = vec![];
+        while let Some(Tree(node)) = matches.next() {
+            found.push((matches.location().clone(), node));
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use serde_json::{json, Value};
+    use crate::parser::parser::parse_json_path;
+    use crate::path::normalized::PathSegment;
+    use crate::tree::JsonTree;
+
+    /// the mirror of `prost_types::Value`
+    #[derive(Debug, PartialEq)]
+    enum Kind {
+        Null,
+        Number(f64),
+        String(String),
+        Bool(bool),
+        Struct(BTreeMap<String, Kind>),
+        List(Vec<Kind>),
+    }
+
+    impl JsonTree for Kind {
+        fn to_json(&self) -> Value {
+            match self {
+                Kind::Null => Value::Null,
+                Kind::Number(n) => json!(n),
+                Kind::String(s) => json!(s),
+                Kind::Bool(b) => json!(b),
+                Kind::Struct(fields) => Value::Object(fields.iter().map(|(k, v)| (k.clone(), v.to_json())).collect()),
+                Kind::List(elems) => Value::Array(elems.iter().map(Kind::to_json).collect()),
+            }
+        }
+
+        fn child(&self, segment: &PathSegment) -> Option<&Self> {
+            match (self, segment) {
+                (Kind::Struct(fields), PathSegment::Key(key)) => fields.get(key),
+                (Kind::List(elems), PathSegment::Index(idx)) => elems.get(*idx),
+                _ => None
+            }
+        }
+
+        fn children(&self) -> Vec<(PathSegment, &Self)> {
+            match self {
+                Kind::Struct(fields) => fields.iter().map(|(k, v)| (PathSegment::Key(k.clone()), v)).collect(),
+                Kind::List(elems) => elems.iter().enumerate().map(|(i, v)| (PathSegment::Index(i), v)).collect(),
+                _ => vec![]
+            }
+        }
+    }
+
+    #[test]
+    fn find_tree_test() {
+        let item = |id: f64, name: &str| Kind::Struct(BTreeMap::from([
+            (String::from("id"), Kind::Number(id)),
+            (String::from("name"), Kind::String(String::from(name))),
+        ]));
+        let doc = Kind::Struct(BTreeMap::from([
+            (String::from("items"), Kind::List(vec![item(1.0, "a"), item(2.0, "b")])),
+            (String::from("ok"), Kind::Bool(true)),
+            (String::from("none"), Kind::Null),
+        ]));
+
+        let res = parse_json_path("$.items[?(@.id > 1)].name").unwrap().find_tree(&doc);
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].0.to_string(), "$['items'][1]['name']");
+        assert_eq!(res[0].1, &Kind::String(String::from("b")));
+
+        let res = parse_json_path("$..name").unwrap().find_tree(&doc);
+        assert_eq!(res.iter().map(|(p, _)| p.to_string()).collect::<Vec<_>>(), vec!["$['items'][0]['name']", "$['items'][1]['name']"]);
+        assert_eq!(parse_json_path("$.items[-1].id").unwrap().find_tree(&doc)[0].1, &Kind::Number(2.0));
+        assert!(parse_json_path("$.ok.x").unwrap().find_tree(&doc).is_empty());
+        assert_eq!(parse_json_path("$.none").unwrap().find_tree(&doc)[0].1, &Kind::Null);
+
+        let json = json!({"a":[1,2]});
+        let res = parse_json_path("$.a[*]").unwrap().find_tree(&json);
+        assert!(std::ptr::eq(res[1].1, &json["a"][1]));
+    }
+}
@@ -0,0 +1,100 @@
+//! Runs the cases of the [JSONPath Compliance Test Suite](https://github.com/jsonpath-standard/jsonpath-compliance-test-suite)
+//! and reports how many of them pass in every category(the part of the name before the first comma, like `basic` or `filter`).
+//!
+//! The whole `basic` and `filter` groups are kept in `tests/cts/sample.json` along with the other cases
+//! the engine is known to pass, all of them have to pass.
+//! The whole suite is run when the variable `JSONPATH_CTS` points out to its `cts.json`:
+//! ```text
+//! JSONPATH_CTS=../jsonpath-compliance-test-suite/cts.json cargo test --test cts -- --nocapture
+//! ```
+
+use std::collections::BTreeMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::str::FromStr;
+use serde_json::Value;
+use jsonpath_rust::{JsonPath, JsonPathInst, Options};
+
+/// the passed cases and the names of the failed ones by the category
+type Report = BTreeMap<String, (usize, Vec<String>)>;
+
+fn run(suite: &Value) -> Report {
+    let mut report = Report::new();
+    for case in suite["tests"].as_array().expect("the suite has the tests") {
+        let name = case["name"].as_str().unwrap_or_default();
+        let category = name.split(',').next().unwrap_or_default().trim().to_string();
+        let entry = report.entry(category).or_default();
+        match catch_unwind(AssertUnwindSafe(|| check(case))) {
+            Ok(Ok(())) => entry.0 += 1,
+            Ok(Err(e)) => entry.1.push(format!("{}: {}", name, e)),
+            Err(_) => entry.1.push(format!("{}: the engine panicked", name)),
+        }
+    }
+    report
+}
+
+/// checks the case against every engine, they all should give the expected elements in the same order
+fn check(case: &Value) -> Result<(), String> {
+    let selector = case["selector"].as_str().ok_or("the case has no selector")?;
+    let parsed = JsonPath::parse_strict(selector).map_err(|e| e.to_string()).and_then(|p| p.validate().map(|_| p));
+    if case["invalid_selector"] == Value::Bool(true) {
+        return match parsed {
+            Ok(_) => Err(String::from("the invalid selector is accepted")),
+            Err(_) => Ok(()),
+        };
+    }
+    let path = parsed?;
+    let doc = &case["document"];
+    let found: Vec<Value> = (doc >> &path).into_iter().cloned().collect();
+
+    let inst = JsonPathInst::from_str(selector).map_err(|e| e.to_string())?;
+    let text = doc.to_string();
+    let others: Vec<(&str, Vec<Value>)> = vec![
+        ("options", path.find_with_options(doc, &Options::new())?.into_iter().cloned().collect()),
+        ("compiled", inst.find(doc).into_iter().cloned().collect()),
+        ("lazy", inst.iter(doc).cloned().collect()),
+        ("raw", path.find_raw(&text)?.iter().map(|m| serde_json::from_str(m.raw).unwrap_or_default()).collect()),
+    ];
+    if let Some((engine, res)) = others.iter().find(|(_, res)| *res != found) {
+        return Err(format!("the {} engine gives {:?} but the default one gives {:?}", engine, res, found));
+    }
+
+    let expected: Vec<&Value> = match (&case["result"], &case["results"]) {
+        (Value::Array(_), _) => vec![&case["result"]],
+        (_, Value::Array(results)) => results.iter().collect(),
+        _ => return Err(String::from("the case has no result")),
+    };
+    let found = Value::Array(found);
+    if expected.iter().any(|e| **e == found) {
+        Ok(())
+    } else {
+        Err(format!("expected {} but got {}", expected[0], found))
+    }
+}
+
+fn print(report: &Report) {
+    for (category, (passed, failed)) in report {
+        println!("{:<40} {:>5} passed {:>5} failed", category, passed, failed.len());
+        failed.iter().for_each(|f| println!("    {}", f));
+    }
+}
+
+#[test]
+fn sample_test() {
+    let suite: Value = serde_json::from_str(include_str!("cts/sample.json")).unwrap();
+    let report = run(&suite);
+    print(&report);
+    assert_eq!(report["basic"].0, 45);
+    assert_eq!(report["filter"].0, 186);
+    let failed: Vec<&String> = report.values().flat_map(|(_, failed)| failed).collect();
+    assert!(failed.is_empty(), "{:#?}", failed);
+}
+
+#[test]
+fn suite_test() {
+    let file = match std::env::var("JSONPATH_CTS") {
+        Ok(file) => file,
+        Err(_) => return println!("JSONPATH_CTS is not set, the compliance test suite is skipped"),
+    };
+    let suite: Value = serde_json::from_str(&std::fs::read_to_string(&file).unwrap()).unwrap();
+    print(&run(&suite));
+}